@@ -1,46 +1,36 @@
-function __fish_bind_test1
-    set -l args
-    set -l use_keys no
-    for i in (commandline -poc)
-        switch $i
-            case -k --k --ke --key
-                set use_keys yes
-
-            case "-*"
-
-            case "*"
-                set -a args $i
-        end
-    end
+# Print an optspec for argparse covering all of bind's options.
+function __fish_bind_optspecs
+    string join \n a/all e/erase f/function-names h/help k/key K/key-names L/list-modes \
+        M/mode= m/sets-mode= p/preset s/silent u/user ignore inherits= timeout=
+end
 
-    switch $use_keys
-        case yes
-            switch (count $args)
-                case 1
-                    return 0
-            end
-    end
-    return 1
+# Complete key names for the sequence position when --key is given.
+# With --erase, every positional argument is a key.
+function __fish_bind_test1
+    set -l cmd (commandline -poc)
+    set -e cmd[1]
+    argparse (__fish_bind_optspecs) -- $cmd 2>/dev/null
+    or return 1
+    set -q _flag_key
+    or return 1
+    # The listing modes take no further arguments.
+    set -q _flag_function_names; or set -q _flag_key_names; or set -q _flag_list_modes
+    and return 1
+    set -q _flag_erase
+    and return 0
+    not set -q argv[1]
 end
 
+# Complete input function names for every position after the sequence.
 function __fish_bind_test2
-    set -l args
-    for i in (commandline -poc)
-        switch $i
-            case "-*"
-
-            case "*"
-                set -a args $i
-        end
-    end
-
-    switch (count $args)
-        case 2
-            return 0
-    end
-
-    return 1
-
+    set -l cmd (commandline -poc)
+    set -e cmd[1]
+    argparse (__fish_bind_optspecs) -- $cmd 2>/dev/null
+    or return 1
+    set -q _flag_erase; or set -q _flag_function_names; or set -q _flag_key_names
+    or set -q _flag_list_modes
+    and return 1
+    set -q argv[1]
 end
 
 complete -c bind -f
@@ -53,6 +43,12 @@ complete -c bind -s K -l key-names -d 'Print names of available keys'
 complete -c bind -s M -l mode -d 'Specify the bind mode that the bind is used in' -xa '(bind -L)'
 complete -c bind -s m -l sets-mode -d 'Change current mode after bind is executed' -xa '(bind -L)'
 complete -c bind -s L -l list-modes -d 'Display a list of defined bind modes'
+complete -c bind -l inherits -d 'Fall back to the bindings of another mode' -xa '(bind -L)'
+complete -c bind -l timeout -d 'Milliseconds to wait for the rest of the sequence' -x
+complete -c bind -s s -l silent -d 'Do not print an error for unknown key names'
+complete -c bind -l ignore -d 'Silently drop input starting with SEQUENCE'
+complete -c bind -l preset -d 'Operate on preset bindings'
+complete -c bind -l user -d 'Operate on user bindings'
 
 complete -c bind -n __fish_bind_test1 -a '(bind --key-names)' -d 'Key name' -x
 complete -c bind -n __fish_bind_test2 -a '(bind --function-names)' -d 'Function name' -x
@@ -2,4 +2,5 @@ complete -c command -n 'test (count (commandline -opc)) -eq 1' -s h -l help -d '
 complete -c command -n 'test (count (commandline -opc)) -eq 1' -s a -l all -d 'Print all external commands by the given name'
 complete -c command -n 'test (count (commandline -opc)) -eq 1' -s q -l quiet -l query -d 'Do not print anything, only set exit status'
 complete -c command -n 'test (count (commandline -opc)) -eq 1' -s s -l search -d 'Print the file that would be executed'
+complete -c command -n 'test (count (commandline -opc)) -eq 1' -l resolve-tilde -d 'Expand a leading ~ before searching'
 complete -c command -xa "(__fish_complete_subcommand)"
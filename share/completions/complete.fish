@@ -8,12 +8,16 @@ complete -c complete -s o -l old-option -d "Old style long option to complete" -
 complete -c complete -s f -l no-files -d "Don't use file completion"
 complete -c complete -s F -l force-files -d "Always use file completion"
 complete -c complete -s r -l require-parameter -d "Require parameter"
+complete -c complete -l short-option-requires-argument -d "Require parameter in the same token, like -n5"
 complete -c complete -s x -l exclusive -d "Require parameter and don't use file completion"
+complete -c complete -l only -d "Replace all other completions when the condition is true"
+complete -c complete -l position -x -d "Only complete the Nth non-option argument, or with N- also later ones"
 complete -c complete -s a -l arguments -d "Space-separated list of possible arguments" -x
 complete -c complete -s d -l description -d "Description of completion" -x
 complete -c complete -s e -l erase -d "Remove completion"
 complete -c complete -s h -l help -d "Display help and exit"
 complete -c complete -s C -l do-complete -d "Print completions for a commandline specified as a parameter"
+complete -c complete -l escape -d "Print completions escaped for use as fish input" -n '__fish_seen_argument -s C -l do-complete'
 complete -c complete -s n -l condition -d "Completion only used if command has zero exit status" -x
 complete -c complete -s w -l wraps -d "Inherit completions from specified command" -xa '(__fish_complete_command)'
 complete -c complete -s k -l keep-order -d "Keep order of arguments instead of sorting alphabetically"
@@ -0,0 +1,2 @@
+complete -c emit -s h -l help -d "Display help and exit"
+complete -c emit -l async -d "Run the handlers once the current command is done"
@@ -1,2 +1,4 @@
 complete -c exec -n 'test (count (commandline -opc)) -eq 1' -s h -l help -d 'Display help and exit'
+complete -c exec -n 'not string match -rqv -- "^-|^[0-9]+\$" (commandline -opc)[2..-1]' -s k -l keep-fd -x -d 'Pass file descriptor on to the command'
+complete -c exec -n 'not string match -rqv -- "^-|^[0-9]+\$" (commandline -opc)[2..-1]' -s f -l force -d 'Exit if the command cannot be executed'
 complete -c exec -xa "(__fish_complete_subcommand)"
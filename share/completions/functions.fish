@@ -8,6 +8,7 @@ function __fish_maybe_list_all_functions
 end
 
 complete -c functions -s e -l erase -d "Erase function" -x -a "(__fish_maybe_list_all_functions)"
+complete -c functions -l all-autoloaded -d "With --erase, erase autoloaded functions so they reload"
 complete -c functions -xa "(functions -na)" -d Function
 complete -c functions -s a -l all -d "Show hidden functions"
 complete -c functions -s h -l help -d "Display help and exit"
@@ -1,5 +1,5 @@
 # Note that when a completion file is sourced a new block scope is created so `set -l` works.
-set -l __fish_history_all_commands search delete save merge clear
+set -l __fish_history_all_commands search delete save merge clear deduplicate jobs
 
 complete -c history -s h -l help -d "Display help and exit"
 
@@ -16,11 +16,13 @@ complete -c history -n '__fish_seen_subcommand_from search delete' \
     -s C -l case-sensitive -d "Match items in a case-sensitive manner"
 
 # Note that these options are only valid with the "search" subcommand.
-complete -c history -n '__fish_seen_subcommand_from search' \
-    -s n -l max -d "Limit output to the first 'n' matches" -x
 complete -c history -n '__fish_seen_subcommand_from search' \
     -s z -l null -d "Terminate entries with NUL character"
-complete -c history -n '__fish_seen_subcommand_from search' \
+
+# Note that these options are only valid with the "search" and "jobs" subcommands.
+complete -c history -n '__fish_seen_subcommand_from search jobs' \
+    -s n -l max -d "Limit output to the first 'n' matches" -x
+complete -c history -n '__fish_seen_subcommand_from search jobs' \
     -s R -l reverse -d "Output the oldest results first" -x
 
 # We don't include a completion for the "save" subcommand because it should not be used
@@ -31,5 +33,9 @@ complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_c
     -a delete -d "Deletes commands from history matching the strings"
 complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
     -a merge -d "Incorporate history changes from other sessions"
+complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
+    -a deduplicate -d "Remove repeated commands from history"
+complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
+    -a jobs -d "Show recently completed jobs"
 complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
     -a clear -d "Clears history file"
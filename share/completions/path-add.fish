@@ -0,0 +1,9 @@
+complete -c path-add -s h -l help -d "Display help and exit"
+complete -c path-add -s p -l prepend -d "Add directories to the front"
+complete -c path-add -s a -l append -d "Add directories to the end"
+complete -c path-add -s m -l move -d "Move directories that are already there"
+complete -c path-add -s g -l global -d "Make variable scope global"
+complete -c path-add -s l -l local -d "Make variable scope local"
+complete -c path-add -s U -l universal -d "Make variable scope universal"
+complete -c path-add -n "__fish_is_nth_token 1" -f -a "(set -n)" -d Variable
+complete -c path-add -n "not __fish_is_nth_token 1" -f -a "(__fish_complete_directories)"
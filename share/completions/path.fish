@@ -0,0 +1,30 @@
+# Completion for builtin path
+# This follows a strict command-then-options approach, so we can just test the number of tokens
+set -l subcommands basename dirname extension change-extension normalize resolve is filter sort
+complete -c path -n "test (count (commandline -opc)) -le 2" -s h -l help -d "Display help and exit"
+complete -f -c path -n "test (count (commandline -opc)) -lt 2" -a basename -d "Print the last component"
+complete -f -c path -n "test (count (commandline -opc)) -lt 2" -a dirname -d "Print all but the last component"
+complete -f -c path -n "test (count (commandline -opc)) -lt 2" -a extension -d "Print the extension"
+complete -f -c path -n "test (count (commandline -opc)) -lt 2" -a change-extension -d "Replace the extension"
+complete -f -c path -n "test (count (commandline -opc)) -lt 2" -a normalize -d "Remove redundant components"
+complete -f -c path -n "test (count (commandline -opc)) -lt 2" -a resolve -d "Make absolute and resolve symlinks"
+complete -f -c path -n "test (count (commandline -opc)) -lt 2" -a is -d "Check for existence, type and permissions"
+complete -f -c path -n "test (count (commandline -opc)) -lt 2" -a filter -d "Print paths with the given type and permissions"
+complete -f -c path -n "test (count (commandline -opc)) -lt 2" -a sort -d "Print paths in sorted order"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] $subcommands" -s z -l null-in -d "Read NUL-separated paths from stdin"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] $subcommands; and not contains -- (commandline -opc)[2] is" -s Z -l null-out -d "Separate results with NUL"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] $subcommands; and not contains -- (commandline -opc)[2] is" -s q -l quiet -d "Do not print output"
+complete -x -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s t -l type -a "file dir link block char fifo socket" -d "Check for file type"
+complete -x -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s p -l perm -a "read write exec suid sgid user group" -d "Check for permission"
+complete -x -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s m -l mode -d "Check for exact octal mode"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s f -d "Check for regular files"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s d -d "Check for directories"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s l -d "Check for symlinks"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s r -d "Check for read permission"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s w -d "Check for write permission"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s x -d "Check for execute permission"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] is filter" -s v -l invert -d "Check for paths that don't match"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] sort" -s n -l natural -d "Compare numbers by value"
+complete -x -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] sort" -s k -l key -a "basename mtime" -d "Sort by basename or modification time"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] sort" -s r -l reverse -d "Reverse the order"
+complete -f -c path -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] sort" -s u -l unique -d "Only print the first of equal paths"
@@ -14,7 +14,11 @@ complete -c read -s s -l silent -d "Mask input with ●"
 complete -c read -s n -l nchars -d "Read the specified number of characters" -x
 complete -c read -s a -l list -l array -d "Store the results as an array"
 complete -c read -s R -l right-prompt -d "Set right-hand prompt command" -x
+complete -c read -l right-prompt-str -d "Set right-hand prompt using provided string" -x
 complete -c read -s z -l null -d "Use NUL character as line terminator"
+complete -c read -l until -d "Use this character as line terminator" -x
 complete -c read -s L -l line -d "Read each line into its own variable"
 complete -c read -s d -l delimiter -d "Set string to use as delimiter" -x
 complete -c read -s t -l tokenize -d "Use shell tokenization rules when splitting"
+complete -c read -l validate -d "Ask again until this code accepts the input" -x
+complete -c read -l max-tries -d "Give up after this many rejected inputs" -x
@@ -82,6 +82,7 @@ complete -c set -n __fish_is_first_token -s p -l prepend -d "Prepend value to a
 complete -c set -n __fish_is_first_token -s S -l show -d "Show variable"
 complete -c set -n __fish_is_first_token -l path -d "Make variable as a path variable"
 complete -c set -n __fish_is_first_token -l unpath -d "Make variable not as a path variable"
+complete -c set -n __fish_is_first_token -l dedupe -d "Remove duplicate elements from the value"
 
 #TODO: add CPP code to generate list of read-only variables and exclude them from the following completions
 
@@ -112,4 +113,4 @@ complete -c set -n __fish_set_is_color -s o -l bold -d 'Make font bold'
 
 # Locale completions
 complete -c set -n '__fish_set_is_locale; and not __fish_seen_argument -s e -l erase' -x -a '(command -sq locale; and locale -a)' -d Locale
-complete -c set -s L -l long -d 'Do not truncate long lines'
+complete -c set -s L -l long -d 'Do not truncate long lines, print queried variables'
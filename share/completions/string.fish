@@ -2,7 +2,7 @@
 # This follows a strict command-then-options approach, so we can just test the number of tokens
 complete -f -c string
 complete -f -c string -n "test (count (commandline -opc)) -le 2" -s h -l help -d "Display help and exit"
-complete -f -c string -n "test (count (commandline -opc)) -ge 2; and not contains -- (commandline -opc)[2] escape collect pad" -s q -l quiet -d "Do not print output"
+complete -f -c string -n "test (count (commandline -opc)) -ge 2; and not contains -- (commandline -opc)[2] escape collect pad hex distance" -s q -l quiet -d "Do not print output"
 complete -f -c string -n "test (count (commandline -opc)) -lt 2" -a lower
 complete -f -c string -n "test (count (commandline -opc)) -lt 2" -a upper
 complete -f -c string -n "test (count (commandline -opc)) -lt 2" -a length
@@ -17,6 +17,8 @@ complete -x -c string -n 'test (count (commandline -opc)) -ge 2; and string matc
 complete -f -c string -n 'test (count (commandline -opc)) -ge 2; and string match -qr split0\?\$ -- (commandline -opc)[2]' -s r -l right -d "Split right-to-left"
 complete -f -c string -n 'test (count (commandline -opc)) -ge 2; and string match -qr split0\?\$ -- (commandline -opc)[2]' -s n -l no-empty -d "Empty results excluded"
 complete -f -c string -n "test (count (commandline -opc)) -lt 2" -a collect
+complete -f -c string -n "test (count (commandline -opc)) -lt 2" -a distance
+complete -x -c string -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] distance" -l algorithm -a "levenshtein jaro-winkler" -d "How to measure the difference"
 complete -f -c string -n 'test (count (commandline -opc)) -ge 2; and string match -qr collect\$ -- (commandline -opc)[2]' -s N -l no-trim-newlines -d "Don't trim trailing newlines"
 
 complete -f -c string -n "test (count (commandline -opc)) -lt 2" -a join
@@ -33,12 +35,16 @@ complete -x -c string -n "test (count (commandline -opc)) -ge 2; and contains --
  var 'For use as a variable name' \
  regex 'For string match -r, string replace -r' \
  url 'For use as a URL')"
+complete -f -c string -n "test (count (commandline -opc)) -lt 2" -a hex
+complete -f -c string -n "test (count (commandline -opc)) -eq 2; and contains -- (commandline -opc)[2] hex" -a "encode\t'Convert to hex' decode\t'Convert from hex'"
+complete -f -c string -n "test (count (commandline -opc)) -ge 3; and contains -- (commandline -opc)[2] hex; and contains -- (commandline -opc)[3] encode" -s u -l upper -d "Use uppercase hex digits"
 complete -f -c string -n "test (count (commandline -opc)) -lt 2" -a match
 complete -f -c string -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] match" -s n -l index -d "Report index, length of match"
 complete -f -c string -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] match" -s v -l invert -d "Report only non-matches"
 complete -f -c string -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] match" -s e -l entire -d "Show entire matching lines"
 complete -f -c string -n "test (count (commandline -opc)) -lt 2" -a replace
 complete -f -c string -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] replace" -s f -l filter -d "Report only actual replacements"
+complete -f -c string -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] replace" -l validate-refs -d "Check group references in the replacement"
 # All replace options are also valid for match
 complete -f -c string -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] match replace" -s a -l all -d "Report every match"
 complete -f -c string -n "test (count (commandline -opc)) -ge 2; and contains -- (commandline -opc)[2] match replace" -s i -l ignore-case -d "Case insensitive"
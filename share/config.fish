@@ -97,7 +97,9 @@ else if not contains -- $__fish_data_dir/completions $fish_complete_path
 end
 
 # Add a handler for when fish_user_path changes, so we can apply the same changes to PATH
-function __fish_reconstruct_path -d "Update PATH when fish_user_paths changes" --on-variable fish_user_paths
+# $fish_user_paths_order decides whether they go before ("prepend", the default) or after
+# ("append") the other entries.
+function __fish_reconstruct_path -d "Update PATH when fish_user_paths changes" --on-variable fish_user_paths --on-variable fish_user_paths_order
     set -l local_path $PATH
 
     for x in $__fish_added_user_paths
@@ -109,13 +111,23 @@ function __fish_reconstruct_path -d "Update PATH when fish_user_paths changes" -
     if set -q fish_user_paths
         # Explicitly split on ":" because $fish_user_paths might not be a path variable,
         # but $PATH definitely is.
-        for x in (string split ":" -- $fish_user_paths[-1..1])
-            if set -l idx (contains --index -- $x $local_path)
-                set -e local_path[$idx]
-            else
-                set -ga __fish_added_user_paths $x
+        if test "$fish_user_paths_order" = append
+            # Entries that are already in $PATH stay where they are.
+            for x in (string split ":" -- $fish_user_paths)
+                if not contains -- $x $local_path
+                    set -ga __fish_added_user_paths $x
+                    set -a local_path $x
+                end
+            end
+        else
+            for x in (string split ":" -- $fish_user_paths[-1..1])
+                if set -l idx (contains --index -- $x $local_path)
+                    set -e local_path[$idx]
+                else
+                    set -ga __fish_added_user_paths $x
+                end
+                set -p local_path $x
             end
-            set -p local_path $x
         end
     end
 
@@ -236,6 +248,15 @@ function fg
 end
 
 function kill
+    # With remove-percent-self, "%" is not expanded in arguments, so point to the alternatives.
+    if status test-feature remove-percent-self
+        for arg in $argv
+            if string match -qr '^%\d+$' -- $arg
+                printf (_ "kill: '%s' is not expanded. Use 'kill (jobs -p %s)', or 'fg %s' to bring the job to the foreground.\n") $arg $arg $arg >&2
+                return 2
+            end
+        end
+    end
     command kill (__fish_expand_pid_args $argv)
 end
 
@@ -0,0 +1,5 @@
+function __fish_complete_jobs --description "Print a list of job specs and their commands"
+    # Without a terminal or in a command substitution, jobs prints no header, just
+    # "ID<tab>GROUP<tab>[CPU%<tab>]STATE<tab>COMMAND" for each job.
+    jobs 2>/dev/null | string replace -rf '^(\d+)\t-?\d+\t(?:\d+%\t)?[^\t]*\t' '%$1\t'
+end
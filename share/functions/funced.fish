@@ -11,6 +11,15 @@ function __funced_md5
     return 1
 end
 
+# Check that the first token of an editor command (which may contain arguments,
+# like "code --wait") is something we can run.
+function __funced_editor_exists
+    set -l editor_cmd
+    string join -- ' ' $argv | read -lat editor_cmd
+    test "$editor_cmd[1]" = fish
+    or type -q -- $editor_cmd[1]
+end
+
 function funced --description 'Edit function definition'
     set -l options h/help 'e/editor=' i/interactive s/save
     argparse -n funced --max-args=1 $options -- $argv
@@ -28,18 +37,30 @@ function funced --description 'Edit function definition'
 
     set -l funcname $argv[1]
 
-    # Check VISUAL first since theoretically EDITOR could be ed.
+    # The editor is picked in this order:
+    # --interactive, --editor, $VISUAL, $EDITOR and finally the built-in editor.
+    # Check VISUAL before EDITOR since theoretically EDITOR could be ed.
     set -l editor
     if set -q _flag_interactive
         set editor fish
     else if set -q _flag_editor
         set editor $_flag_editor
-    else if set -q VISUAL
-        set editor $VISUAL
-    else if set -q EDITOR
-        set editor $EDITOR
+        if not __funced_editor_exists $editor
+            printf (_ "%s: Editor command '%s' could not be found\n") funced "$editor" >&2
+            return 1
+        end
     else
-        set editor fish
+        for var in VISUAL EDITOR
+            string length -q -- $$var
+            or continue
+            if __funced_editor_exists $$var
+                set editor $$var
+                break
+            end
+            printf (_ "%s: The value for \$%s '%s' could not be used because the command could not be found\n") funced $var "$$var" >&2
+        end
+        set -q editor[1]
+        or set editor fish
     end
 
     set -l init
@@ -50,14 +71,6 @@ function funced --description 'Edit function definition'
             set init function $funcname\n\nend
     end
 
-    # Break editor up to get its first command (i.e. discard flags)
-    set -l editor_cmd
-    echo $editor | read -ta editor_cmd
-    if not type -q -f "$editor_cmd[1]"
-        echo (_ "funced: The value for \$EDITOR '$editor' could not be used because the command '$editor_cmd[1]' could not be found")
-        set editor fish
-    end
-
     if test "$editor" = fish
         if functions -q -- $funcname
             functions --no-details -- $funcname | fish_indent --no-indent | read -z init
@@ -95,7 +108,7 @@ function funced --description 'Edit function definition'
     while true
         set -l checksum (__funced_md5 "$tmpname")
 
-        if not eval $editor $tmpname
+        if not eval $editor (string escape -- $tmpname)
             echo (_ "Editing failed or was cancelled")
         else
             # Verify the checksum (if present) to detect potential problems
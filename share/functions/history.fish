@@ -68,7 +68,7 @@ function history --description "display or manipulate interactive command histor
     # command. This allows the flags to appear before or after the subcommand.
     if not set -q hist_cmd[1]
         and set -q argv[1]
-        if contains $argv[1] search delete merge save clear
+        if contains $argv[1] search delete merge save clear deduplicate jobs
             set hist_cmd $argv[1]
             set -e argv[1]
         end
@@ -175,6 +175,20 @@ function history --description "display or manipulate interactive command histor
 
             builtin history merge -- $argv
 
+        case deduplicate # remove repeated commands from the history
+            __fish_unexpected_hist_args $argv
+            and return 1
+
+            set -l removed (builtin history deduplicate -- $argv)
+            or return
+            printf (_ "Removed %d duplicate history entries\n") $removed
+
+        case jobs # show recently completed jobs from the job journal
+            __fish_unexpected_hist_args $argv
+            and return 1
+
+            builtin history jobs $max_count $_flag_reverse
+
         case clear # clear the interactive command history
             __fish_unexpected_hist_args $argv
             and return 1
@@ -13,17 +13,11 @@ function isatty -d "Tests if a file descriptor is a tty"
         return 1
     end
 
-    set -l fd
-    switch "$argv"
-        case stdin ''
-            set fd 0
-        case stdout
-            set fd 1
-        case stderr
-            set fd 2
-        case '*'
-            set fd $argv[1]
+    # Like `test -t`, an invalid file descriptor is simply not a tty.
+    if set -q argv[1]; and not contains -- $argv[1] stdin stdout stderr
+        string match -qr '^\d+$' -- $argv[1]
+        or return 1
     end
 
-    test -t "$fd"
+    status is-tty $argv
 end
@@ -1113,7 +1113,8 @@ class ast_t::populator_t {
             const auto &peek = peek_token();
             if ((flags_ & parse_flag_leave_unterminated) &&
                 (peek.tok_error == tokenizer_error_t::unterminated_quote ||
-                 peek.tok_error == tokenizer_error_t::unterminated_subshell)) {
+                 peek.tok_error == tokenizer_error_t::unterminated_subshell ||
+                 peek.tok_error == tokenizer_error_t::unterminated_dollar_subshell)) {
                 return;
             }
 
@@ -1142,7 +1143,8 @@ class ast_t::populator_t {
 
             if ((flags_ & parse_flag_leave_unterminated) &&
                 (peek.tok_error == tokenizer_error_t::unterminated_quote ||
-                 peek.tok_error == tokenizer_error_t::unterminated_subshell)) {
+                 peek.tok_error == tokenizer_error_t::unterminated_subshell ||
+                 peek.tok_error == tokenizer_error_t::unterminated_dollar_subshell)) {
                 return;
             }
 
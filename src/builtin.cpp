@@ -50,6 +50,8 @@
 #include "builtin_history.h"
 #include "builtin_jobs.h"
 #include "builtin_math.h"
+#include "builtin_path.h"
+#include "builtin_path_add.h"
 #include "builtin_printf.h"
 #include "builtin_pwd.h"
 #include "builtin_random.h"
@@ -114,6 +116,61 @@ void builtin_wperror(const wchar_t *s, io_streams_t &streams) {
     }
 }
 
+// How many bytes we read() at once.
+// Bash uses 128 here, so we do too (see READ_CHUNK_SIZE).
+// This should be about the size of a line.
+#define ARG_CHUNK_SIZE 128
+
+bool arg_iterator_t::args_from_stdin(const io_streams_t &streams) {
+    return streams.stdin_is_directly_redirected;
+}
+
+bool arg_iterator_t::get_arg_stdin() {
+    assert(args_from_stdin(streams_) && "should not be reading from stdin");
+    assert(streams_.stdin_fd >= 0 && "should have a valid fd");
+    // Read in chunks from fd until buffer has a separator (or the end if split_ is unset).
+    size_t pos;
+    while (!split_ || (pos = buffer_.find(sep_, searched_)) == std::string::npos) {
+        searched_ = buffer_.size();
+        char buf[ARG_CHUNK_SIZE];
+        long n = read_blocked(streams_.stdin_fd, buf, ARG_CHUNK_SIZE);
+        if (n == 0) {
+            // If we still have buffer contents, flush them,
+            // in case there was no trailing sep.
+            if (buffer_.empty()) return false;
+            storage_ = str2wcstring(buffer_);
+            buffer_.clear();
+            return true;
+        }
+        if (n == -1) {
+            // Some error happened. We can't do anything about it,
+            // so ignore it.
+            // (read_blocked already retries for EAGAIN and EINTR)
+            storage_ = str2wcstring(buffer_);
+            buffer_.clear();
+            return false;
+        }
+        buffer_.append(buf, n);
+    }
+
+    // Split the buffer on the sep and return the first part.
+    storage_ = str2wcstring(buffer_, pos);
+    buffer_.erase(0, pos + 1);
+    searched_ = 0;
+    return true;
+}
+
+const wcstring *arg_iterator_t::nextstr() {
+    if (args_from_stdin(streams_)) {
+        return get_arg_stdin() ? &storage_ : nullptr;
+    }
+    if (argv_ && argv_[argidx_]) {
+        storage_ = argv_[argidx_++];
+        return &storage_;
+    }
+    return nullptr;
+}
+
 static const wchar_t *const short_options = L"+:h";
 static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
                                               {nullptr, 0, nullptr, 0}};
@@ -153,20 +210,40 @@ int parse_help_only_cmd_opts(struct help_only_cmd_opts_t &opts, int *optind, int
 ///    builtin or function name to get up help for
 ///
 /// Process and print help for the specified builtin or function.
+///
+/// In an interactive session with the output going to the terminal, this shows the help in a
+/// pager. Otherwise, or if \p plain is set, the plain text goes to the builtin's output (or error)
+/// stream, so that e.g. `set --help | grep` works.
 void builtin_print_help(parser_t &parser, const io_streams_t &streams, const wchar_t *name,
-                        wcstring *error_message) {
+                        wcstring *error_message, bool plain) {
     // This won't ever work if no_exec is set.
     if (no_exec()) return;
     const wcstring name_esc = escape_string(name, ESCAPE_ALL);
     wcstring cmd = format_string(L"__fish_print_help %ls ", name_esc.c_str());
-    io_chain_t ios;
     if (error_message) {
         cmd.append(escape_string(*error_message, ESCAPE_ALL));
-        // If it's an error, redirect the output of __fish_print_help to stderr
-        ios.push_back(std::make_shared<io_fd_t>(STDOUT_FILENO, STDERR_FILENO));
     }
-    auto res = parser.eval(cmd, ios);
-    if (res.status.exit_code() == 2) {
+
+    output_stream_t &target = error_message ? streams.err : streams.out;
+    bool redirected = error_message ? streams.err_is_redirected : streams.out_is_redirected;
+    int status;
+    if (plain || redirected || !is_interactive_session()) {
+        // Capture the output. As this is not a tty, __fish_print_help won't start a pager.
+        wcstring_list_t lines;
+        status = exec_subshell(cmd, parser, lines, false /* don't apply exit status */);
+        for (const wcstring &line : lines) {
+            target.append(line);
+            target.push_back(L'\n');
+        }
+    } else {
+        io_chain_t ios;
+        if (error_message) {
+            // If it's an error, redirect the output of __fish_print_help to stderr
+            ios.push_back(std::make_shared<io_fd_t>(STDOUT_FILENO, STDERR_FILENO));
+        }
+        status = parser.eval(cmd, ios).status.exit_code();
+    }
+    if (status == 2) {
         streams.err.append_format(BUILTIN_ERR_MISSING_HELP, name_esc.c_str(), name_esc.c_str());
     }
 }
@@ -397,6 +474,8 @@ static const builtin_data_t builtin_datas[] = {
     {L"math", &builtin_math, N_(L"Evaluate math expressions")},
     {L"not", &builtin_generic, N_(L"Negate exit status of job")},
     {L"or", &builtin_generic, N_(L"Execute command if previous command failed")},
+    {L"path", &builtin_path, N_(L"Handle paths")},
+    {L"path-add", &builtin_path_add, N_(L"Add directories to a path variable")},
     {L"printf", &builtin_printf, N_(L"Prints formatted text")},
     {L"pwd", &builtin_pwd, N_(L"Print the working directory")},
     {L"random", &builtin_random, N_(L"Generate random number")},
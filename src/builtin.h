@@ -93,7 +93,7 @@ const wchar_t *builtin_get_desc(const wcstring &name);
 wcstring builtin_help_get(parser_t &parser, const wchar_t *cmd);
 
 void builtin_print_help(parser_t &parser, const io_streams_t &streams, const wchar_t *name,
-                        wcstring *error_message = nullptr);
+                        wcstring *error_message = nullptr, bool plain = false);
 int builtin_count_args(const wchar_t *const *argv);
 
 void builtin_unknown_option(parser_t &parser, io_streams_t &streams, const wchar_t *cmd,
@@ -106,6 +106,42 @@ void builtin_print_error_trailer(parser_t &parser, output_stream_t &b, const wch
 
 void builtin_wperror(const wchar_t *s, io_streams_t &streams);
 
+/// A helper type for extracting arguments from either argv or stdin, for builtins like `string`
+/// that take their arguments from stdin if it is redirected.
+class arg_iterator_t {
+    // The list of arguments passed to the builtin.
+    const wchar_t *const *argv_;
+    // If using argv, index of the next argument to return.
+    int argidx_;
+    // If not using argv, a string to store bytes that have been read but not yet returned.
+    std::string buffer_;
+    // How much of buffer_ is known not to contain a separator, so we don't search it again.
+    size_t searched_ = 0;
+    // If set, when reading from a stream, split on sep_.
+    const bool split_;
+    // The separator between arguments on stdin.
+    const char sep_;
+    // Backing storage for the next() string.
+    wcstring storage_;
+    const io_streams_t &streams_;
+
+    /// Reads the next argument from stdin, returning true if an argument was produced and false if
+    /// not. On true, the string is stored in storage_.
+    bool get_arg_stdin();
+
+   public:
+    arg_iterator_t(const wchar_t *const *argv, int argidx, const io_streams_t &streams,
+                   bool split = true, char sep = '\n')
+        : argv_(argv), argidx_(argidx), split_(split), sep_(sep), streams_(streams) {}
+
+    /// \return the next argument, or nullptr if there are none left.
+    const wcstring *nextstr();
+
+    /// \return whether arguments are read from stdin, which is the case if we are the second or
+    /// later process in a pipeline.
+    static bool args_from_stdin(const io_streams_t &streams);
+};
+
 struct help_only_cmd_opts_t {
     bool print_help = false;
 };
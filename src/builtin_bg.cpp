@@ -25,7 +25,7 @@ static int send_to_bg(parser_t &parser, io_streams_t &streams, job_t *j) {
             _(L"%ls: Can't put job %d, '%ls' to background because it is not under job control\n"),
             L"bg", j->job_id(), j->command_wcstr());
         builtin_print_help(parser, streams, L"bg", &error_message);
-        return STATUS_CMD_ERROR;
+        return STATUS_INVALID_STATE;
     }
 
     streams.err.append_format(_(L"Send job %d '%ls' to background\n"), j->job_id(),
@@ -55,7 +55,8 @@ maybe_t<int> builtin_bg(parser_t &parser, io_streams_t &streams, const wchar_t *
         // No jobs were specified so use the most recent (i.e., last) job.
         job_t *job = nullptr;
         for (const auto &j : parser.jobs()) {
-            if (j->is_stopped() && j->wants_job_control() && (!j->is_completed())) {
+            if (j->is_stopped() && j->wants_job_control() && (!j->is_completed()) &&
+                !j->is_hidden()) {
                 job = j.get();
                 break;
             }
@@ -63,7 +64,7 @@ maybe_t<int> builtin_bg(parser_t &parser, io_streams_t &streams, const wchar_t *
 
         if (!job) {
             streams.err.append_format(_(L"%ls: There are no suitable jobs\n"), cmd);
-            retval = STATUS_CMD_ERROR;
+            retval = STATUS_NOT_FOUND;
         } else {
             retval = send_to_bg(parser, streams, job);
         }
@@ -89,13 +90,17 @@ maybe_t<int> builtin_bg(parser_t &parser, io_streams_t &streams, const wchar_t *
     if (retval != STATUS_CMD_OK) return retval;
 
     // Background all existing jobs that match the pids.
-    // Non-existent jobs aren't an error, but information about them is useful.
+    // A job that can't be backgrounded doesn't stop the others. The status is that of the first
+    // failure.
     for (auto p : pids) {
+        int job_status;
         if (job_t *j = parser.job_get_from_pid(p)) {
-            retval |= send_to_bg(parser, streams, j);
+            job_status = send_to_bg(parser, streams, j);
         } else {
             streams.err.append_format(_(L"%ls: Could not find job '%d'\n"), cmd, p);
+            job_status = STATUS_NOT_FOUND;
         }
+        if (retval == STATUS_CMD_OK) retval = job_status;
     }
 
     return retval;
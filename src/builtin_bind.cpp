@@ -25,6 +25,7 @@ struct bind_cmd_opts_t {
     bool list_modes = false;
     bool print_help = false;
     bool silent = false;
+    bool ignore = false;
     bool use_terminfo = false;
     bool have_user = false;
     bool user = false;
@@ -33,6 +34,8 @@ struct bind_cmd_opts_t {
     int mode = BIND_INSERT;
     const wchar_t *bind_mode = DEFAULT_BIND_MODE;
     const wchar_t *sets_bind_mode = L"";
+    const wchar_t *inherits = nullptr;
+    int timeout_ms = 0;
 };
 
 namespace {
@@ -54,12 +57,15 @@ class builtin_bind_t {
     void key_names(bool all, io_streams_t &streams);
     void function_names(io_streams_t &streams);
     bool add(const wcstring &seq, const wchar_t *const *cmds, size_t cmds_len, const wchar_t *mode,
-             const wchar_t *sets_mode, bool terminfo, bool user, io_streams_t &streams);
+             const wchar_t *sets_mode, bool terminfo, bool user, int timeout_ms,
+             io_streams_t &streams);
     bool erase(const wchar_t *const *seq, bool all, const wchar_t *mode, bool use_terminfo,
                bool user, io_streams_t &streams);
     bool get_terminfo_sequence(const wcstring &seq, wcstring *out_seq, io_streams_t &streams) const;
     bool insert(int optind, int argc, const wchar_t **argv, io_streams_t &streams);
     void list_modes(io_streams_t &streams);
+    void list_mode_parents(const wchar_t *bind_mode, io_streams_t &streams);
+    bool set_mode_parent(int optind, int argc, const wchar_t **argv, io_streams_t &streams);
     bool list_one(const wcstring &seq, const wcstring &bind_mode, bool user, io_streams_t &streams);
     bool list_one(const wcstring &seq, const wcstring &bind_mode, bool user, bool preset,
                   io_streams_t &streams);
@@ -71,8 +77,9 @@ bool builtin_bind_t::list_one(const wcstring &seq, const wcstring &bind_mode, bo
                               io_streams_t &streams) {
     wcstring_list_t ecmds;
     wcstring sets_mode;
+    int timeout_ms = 0;
 
-    if (!input_mappings_->get(seq, bind_mode, &ecmds, user, &sets_mode)) {
+    if (!input_mappings_->get(seq, bind_mode, &ecmds, user, &sets_mode, &timeout_ms)) {
         return false;
     }
 
@@ -92,6 +99,12 @@ bool builtin_bind_t::list_one(const wcstring &seq, const wcstring &bind_mode, bo
         streams.out.append(L" -m ");
         streams.out.append(esets_mode);
     }
+    if (timeout_ms > 0) {
+        streams.out.append_format(L" --timeout %d", timeout_ms);
+    }
+    if (ecmds.empty()) {
+        streams.out.append(L" --ignore");
+    }
 
     // Append the name.
     wcstring tname;
@@ -142,6 +155,18 @@ void builtin_bind_t::list(const wchar_t *bind_mode, bool user, io_streams_t &str
     }
 }
 
+/// List the modes that inherit from another one.
+void builtin_bind_t::list_mode_parents(const wchar_t *bind_mode, io_streams_t &streams) {
+    for (const auto &mode_parent : input_mappings_->get_mode_parents()) {
+        if (bind_mode && bind_mode != mode_parent.first) {
+            continue;
+        }
+        streams.out.append_format(L"bind -M %ls --inherits %ls\n",
+                                  escape_string(mode_parent.first, ESCAPE_ALL).c_str(),
+                                  escape_string(mode_parent.second, ESCAPE_ALL).c_str());
+    }
+}
+
 /// Print terminfo key binding names to string buffer used for standard output.
 ///
 /// \param all if set, all terminfo key binding names will be printed. If not set, only ones that
@@ -190,17 +215,17 @@ bool builtin_bind_t::get_terminfo_sequence(const wcstring &seq, wcstring *out_se
 /// Add specified key binding.
 bool builtin_bind_t::add(const wcstring &seq, const wchar_t *const *cmds, size_t cmds_len,
                          const wchar_t *mode, const wchar_t *sets_mode, bool terminfo, bool user,
-                         io_streams_t &streams) {
+                         int timeout_ms, io_streams_t &streams) {
     if (terminfo) {
         wcstring seq2;
         if (get_terminfo_sequence(seq, &seq2, streams)) {
-            input_mappings_->add(seq2, cmds, cmds_len, mode, sets_mode, user);
+            input_mappings_->add(seq2, cmds, cmds_len, mode, sets_mode, user, timeout_ms);
         } else {
             return true;
         }
 
     } else {
-        input_mappings_->add(seq, cmds, cmds_len, mode, sets_mode, user);
+        input_mappings_->add(seq, cmds, cmds_len, mode, sets_mode, user, timeout_ms);
     }
 
     return false;
@@ -264,6 +289,12 @@ bool builtin_bind_t::insert(int optind, int argc, const wchar_t **argv, io_strea
         }
     }
 
+    // An ignored sequence has no commands.
+    if (opts->ignore && arg_count != 1) {
+        streams.err.append_format(BUILTIN_ERR_ARG_COUNT1, cmd, 1, arg_count);
+        return true;
+    }
+
     if (arg_count == 0) {
         // We don't overload this with user and def because we want them to be grouped.
         // First the presets, then the users (because of scrolling).
@@ -272,6 +303,13 @@ bool builtin_bind_t::insert(int optind, int argc, const wchar_t **argv, io_strea
         }
         if (opts->user) {
             list(opts->bind_mode_given ? opts->bind_mode : nullptr, true, streams);
+            list_mode_parents(opts->bind_mode_given ? opts->bind_mode : nullptr, streams);
+        }
+    } else if (opts->ignore) {
+        // A sequence without commands just swallows that input.
+        if (add(argv[optind], argv + (optind + 1), 0, opts->bind_mode, opts->sets_bind_mode,
+                opts->use_terminfo, opts->user, opts->timeout_ms, streams)) {
+            return true;
         }
     } else if (arg_count == 1) {
         wcstring seq;
@@ -300,7 +338,7 @@ bool builtin_bind_t::insert(int optind, int argc, const wchar_t **argv, io_strea
     } else {
         // Actually insert!
         if (add(argv[optind], argv + (optind + 1), argc - (optind + 1), opts->bind_mode,
-                opts->sets_bind_mode, opts->use_terminfo, opts->user, streams)) {
+                opts->sets_bind_mode, opts->use_terminfo, opts->user, opts->timeout_ms, streams)) {
             return true;
         }
     }
@@ -308,6 +346,23 @@ bool builtin_bind_t::insert(int optind, int argc, const wchar_t **argv, io_strea
     return false;
 }
 
+/// Make the mode given with -M inherit from the one given with --inherits.
+bool builtin_bind_t::set_mode_parent(int optind, int argc, const wchar_t **argv,
+                                     io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    if (optind != argc) {
+        streams.err.append_format(BUILTIN_ERR_ARG_COUNT1, cmd, 0, argc - optind);
+        return true;
+    }
+    if (!input_mappings_->set_mode_parent(opts->bind_mode, opts->inherits)) {
+        streams.err.append_format(_(L"%ls: Mode '%ls' can not inherit from '%ls', which inherits "
+                                    L"from it\n"),
+                                  cmd, opts->bind_mode, opts->inherits);
+        return true;
+    }
+    return false;
+}
+
 /// List all current bind modes.
 void builtin_bind_t::list_modes(io_streams_t &streams) {
     // List all known modes, even if they are only in preset bindings.
@@ -334,9 +389,11 @@ static int parse_cmd_opts(bind_cmd_opts_t &opts, int *optind,  //!OCLINT(high nc
     const wchar_t *cmd = argv[0];
     static const wchar_t *const short_options = L":aehkKfM:Lm:s";
     static const struct woption long_options[] = {{L"all", no_argument, nullptr, 'a'},
+                                                  {L"inherits", required_argument, nullptr, 1},
                                                   {L"erase", no_argument, nullptr, 'e'},
                                                   {L"function-names", no_argument, nullptr, 'f'},
                                                   {L"help", no_argument, nullptr, 'h'},
+                                                  {L"ignore", no_argument, nullptr, 3},
                                                   {L"key", no_argument, nullptr, 'k'},
                                                   {L"key-names", no_argument, nullptr, 'K'},
                                                   {L"list-modes", no_argument, nullptr, 'L'},
@@ -344,6 +401,7 @@ static int parse_cmd_opts(bind_cmd_opts_t &opts, int *optind,  //!OCLINT(high nc
                                                   {L"preset", no_argument, nullptr, 'p'},
                                                   {L"sets-mode", required_argument, nullptr, 'm'},
                                                   {L"silent", no_argument, nullptr, 's'},
+                                                  {L"timeout", required_argument, nullptr, 2},
                                                   {L"user", no_argument, nullptr, 'u'},
                                                   {nullptr, 0, nullptr, 0}};
 
@@ -396,6 +454,31 @@ static int parse_cmd_opts(bind_cmd_opts_t &opts, int *optind,  //!OCLINT(high nc
                 opts.sets_bind_mode = w.woptarg;
                 break;
             }
+            case 1: {
+                // An empty parent removes the inheritance.
+                if (*w.woptarg && !valid_var_name(w.woptarg)) {
+                    streams.err.append_format(BUILTIN_ERR_BIND_MODE, cmd, w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                opts.inherits = w.woptarg;
+                break;
+            }
+            case 2: {
+                // The same bounds as fish_escape_delay_ms.
+                int timeout = fish_wcstoi(w.woptarg);
+                if (errno || timeout < 10 || timeout >= 5000) {
+                    streams.err.append_format(_(L"%ls: Invalid timeout '%ls', expected a number "
+                                                L"of milliseconds from 10 to 4999\n"),
+                                              cmd, w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                opts.timeout_ms = timeout;
+                break;
+            }
+            case 3: {
+                opts.ignore = true;
+                break;
+            }
             case L'p': {
                 opts.have_preset = true;
                 opts.preset = true;
@@ -451,6 +534,14 @@ maybe_t<int> builtin_bind_t::builtin_bind(parser_t &parser, io_streams_t &stream
         return STATUS_CMD_OK;
     }
 
+    if (opts.inherits) {
+        if (opts.mode != BIND_INSERT) {
+            streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
+            return STATUS_INVALID_ARGS;
+        }
+        return set_mode_parent(optind, argc, argv, streams) ? STATUS_CMD_ERROR : STATUS_CMD_OK;
+    }
+
     // Default to user mode
     if (!opts.have_preset && !opts.have_user) opts.user = true;
     switch (opts.mode) {
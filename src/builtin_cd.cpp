@@ -12,6 +12,7 @@
 #include "common.h"
 #include "env.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "io.h"
 #include "parser.h"
 #include "path.h"
@@ -54,7 +55,7 @@ maybe_t<int> builtin_cd(parser_t &parser, io_streams_t &streams, const wchar_t *
 
         if (!parser.is_interactive()) streams.err.append(parser.current_line());
 
-        return STATUS_CMD_ERROR;
+        return STATUS_NOT_FOUND;
     }
 
     errno = 0;
@@ -85,7 +86,9 @@ maybe_t<int> builtin_cd(parser_t &parser, io_streams_t &streams, const wchar_t *
             break;
         }
 
-        parser.libdata().cwd_fd = std::make_shared<const autoclose_fd_t>(std::move(dir_fd));
+        // Keep it out of the range of user redirections.
+        parser.libdata().cwd_fd =
+            std::make_shared<const autoclose_fd_t>(heightenize_fd(std::move(dir_fd), true));
         std::vector<event_t> evts;
         parser.vars().set_one(L"PWD", ENV_EXPORT | ENV_GLOBAL, std::move(norm_dir), &evts);
         for (const auto &evt : evts) {
@@ -114,5 +117,7 @@ maybe_t<int> builtin_cd(parser_t &parser, io_streams_t &streams, const wchar_t *
         streams.err.append(parser.current_line());
     }
 
+    if (best_errno == ENOENT) return STATUS_NOT_FOUND;
+    if (best_errno == EACCES) return STATUS_PERMISSION_DENIED;
     return STATUS_CMD_ERROR;
 }
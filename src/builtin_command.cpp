@@ -9,6 +9,7 @@
 
 #include "builtin.h"
 #include "common.h"
+#include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "io.h"
 #include "parser.h"
@@ -21,12 +22,14 @@ struct command_cmd_opts_t {
     bool find_path = false;
     bool quiet = false;
     bool all_paths = false;
+    bool resolve_tilde = false;
 };
 static const wchar_t *const short_options = L":ahqsv";
 static const struct woption long_options[] = {
-    {L"help", no_argument, nullptr, 'h'},   {L"all", no_argument, nullptr, 'a'},
-    {L"quiet", no_argument, nullptr, 'q'},  {L"query", no_argument, nullptr, 'q'},
-    {L"search", no_argument, nullptr, 's'}, {nullptr, 0, nullptr, 0}};
+    {L"help", no_argument, nullptr, 'h'},      {L"all", no_argument, nullptr, 'a'},
+    {L"quiet", no_argument, nullptr, 'q'},     {L"query", no_argument, nullptr, 'q'},
+    {L"search", no_argument, nullptr, 's'},    {L"resolve-tilde", no_argument, nullptr, 1},
+    {nullptr, 0, nullptr, 0}};
 
 static int parse_cmd_opts(command_cmd_opts_t &opts, int *optind, int argc, const wchar_t **argv,
                           parser_t &parser, io_streams_t &streams) {
@@ -52,6 +55,10 @@ static int parse_cmd_opts(command_cmd_opts_t &opts, int *optind, int argc, const
                 opts.find_path = true;
                 break;
             }
+            case 1: {
+                opts.resolve_tilde = true;
+                break;
+            }
             case ':': {
                 builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
                 return STATUS_INVALID_ARGS;
@@ -86,15 +93,17 @@ maybe_t<int> builtin_command(parser_t &parser, io_streams_t &streams, const wcha
         return STATUS_CMD_OK;
     }
 
-    // Quiet implies find_path.
-    if (!opts.find_path && !opts.all_paths && !opts.quiet) {
+    // Quiet and resolve_tilde imply find_path.
+    if (!opts.find_path && !opts.all_paths && !opts.quiet && !opts.resolve_tilde) {
         builtin_print_help(parser, streams, cmd);
         return STATUS_INVALID_ARGS;
     }
 
     int found = 0;
     for (int idx = optind; argv[idx]; ++idx) {
-        const wchar_t *command_name = argv[idx];
+        wcstring command_name = argv[idx];
+        // Expand a leading ~ or ~user, e.g. in a quoted argument or one from a variable.
+        if (opts.resolve_tilde) expand_tilde(command_name, parser.vars());
         if (opts.all_paths) {
             wcstring_list_t paths = path_get_paths(command_name, parser.vars());
             for (const auto &path : paths) {
@@ -148,6 +148,7 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, const
     bool line_mode = false;
     bool search_mode = false;
     bool paging_mode = false;
+    bool is_valid = false;
     const wchar_t *begin = nullptr, *end = nullptr;
 
     const auto &ld = parser.libdata();
@@ -192,6 +193,7 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, const
                                                   {L"line", no_argument, nullptr, 'L'},
                                                   {L"search-mode", no_argument, nullptr, 'S'},
                                                   {L"paging-mode", no_argument, nullptr, 'P'},
+                                                  {L"is-valid", no_argument, nullptr, 1},
                                                   {nullptr, 0, nullptr, 0}};
 
     int opt;
@@ -263,6 +265,10 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, const
                 paging_mode = true;
                 break;
             }
+            case 1: {
+                is_valid = true;
+                break;
+            }
             case 'h': {
                 builtin_print_help(parser, streams, cmd);
                 return STATUS_CMD_OK;
@@ -286,7 +292,7 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, const
 
         // Check for invalid switch combinations.
         if (buffer_part || cut_at_cursor || append_mode || tokenize || cursor_mode || line_mode ||
-            search_mode || paging_mode) {
+            search_mode || paging_mode || is_valid) {
             streams.err.append_format(BUILTIN_ERR_COMBO, argv[0]);
             builtin_print_error_trailer(parser, streams.err, cmd);
             return STATUS_INVALID_ARGS;
@@ -344,7 +350,7 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, const
     }
 
     if ((buffer_part || tokenize || cut_at_cursor) &&
-        (cursor_mode || line_mode || search_mode || paging_mode)) {
+        (cursor_mode || line_mode || search_mode || paging_mode || is_valid)) {
         streams.err.append_format(BUILTIN_ERR_COMBO, argv[0]);
         builtin_print_error_trailer(parser, streams.err, cmd);
         return STATUS_INVALID_ARGS;
@@ -407,6 +413,20 @@ maybe_t<int> builtin_commandline(parser_t &parser, io_streams_t &streams, const
         return reader_has_pager_contents() ? 0 : 1;
     }
 
+    if (is_valid) {
+        if (argc - w.woptind) {
+            streams.err.append_format(BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd);
+            builtin_print_error_trailer(parser, streams.err, cmd);
+            return STATUS_INVALID_ARGS;
+        }
+        // Return 2 if the commandline is incomplete, like the reader does when it would insert a
+        // newline on execute instead.
+        parser_test_error_bits_t res =
+            parse_util_detect_errors(current_buffer, nullptr, true /* accept incomplete */);
+        if (res & PARSER_TEST_INCOMPLETE) return 2;
+        return res & PARSER_TEST_ERROR ? STATUS_CMD_ERROR : STATUS_CMD_OK;
+    }
+
     switch (buffer_part) {
         case STRING_MODE: {
             begin = current_buffer;
@@ -136,6 +136,7 @@ maybe_t<int> builtin_complete(parser_t &parser, io_streams_t &streams, const wch
     bool do_complete = false;
     bool have_do_complete_param = false;
     wcstring do_complete_param;
+    bool escape_output = false;
     wcstring_list_t cmd_to_complete;
     wcstring_list_t path;
     wcstring_list_t wrap_targets;
@@ -163,6 +164,10 @@ maybe_t<int> builtin_complete(parser_t &parser, io_streams_t &streams, const wch
         {L"do-complete", optional_argument, nullptr, 'C'},
         {L"help", no_argument, nullptr, 'h'},
         {L"keep-order", no_argument, nullptr, 'k'},
+        {L"escape", no_argument, nullptr, 1},
+        {L"only", no_argument, nullptr, 2},
+        {L"position", required_argument, nullptr, 3},
+        {L"short-option-requires-argument", no_argument, nullptr, 4},
         {nullptr, 0, nullptr, 0}};
 
     int opt;
@@ -273,6 +278,31 @@ maybe_t<int> builtin_complete(parser_t &parser, io_streams_t &streams, const wch
                 if (have_do_complete_param) do_complete_param = w.woptarg;
                 break;
             }
+            case 1: {
+                escape_output = true;
+                break;
+            }
+            case 2: {
+                result_mode.only = true;
+                break;
+            }
+            case 3: {
+                // A position like 2, or 2- for the second argument and those after it.
+                wcstring position = w.woptarg;
+                result_mode.position_or_later = string_suffixes_string(L"-", position);
+                if (result_mode.position_or_later) position.pop_back();
+                int n = fish_wcstoi(position.c_str());
+                if (errno || n <= 0) {
+                    streams.err.append_format(_(L"%ls: Invalid position '%ls'\n"), cmd, w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                result_mode.position = static_cast<size_t>(n);
+                break;
+            }
+            case 4: {
+                result_mode.attached_param = true;
+                break;
+            }
             case 'h': {
                 builtin_print_help(parser, streams, cmd);
                 return STATUS_CMD_OK;
@@ -392,7 +422,10 @@ maybe_t<int> builtin_complete(parser_t &parser, io_streams_t &streams, const wch
                 // The input data is meant to be something like you would have on the command
                 // line, e.g. includes backslashes. The output should be raw, i.e. unescaped. So
                 // we need to unescape the command line. See #1127.
-                unescape_string_in_place(&faux_cmdline_with_completion, UNESCAPE_DEFAULT);
+                // With --escape, the output is kept as it would be inserted into the commandline.
+                if (!escape_output) {
+                    unescape_string_in_place(&faux_cmdline_with_completion, UNESCAPE_DEFAULT);
+                }
                 streams.out.append(faux_cmdline_with_completion);
 
                 // Append any description.
@@ -407,7 +440,8 @@ maybe_t<int> builtin_complete(parser_t &parser, io_streams_t &streams, const wch
         }
     } else if (path.empty() && gnu_opt.empty() && short_opt.empty() && old_opt.empty() && !remove &&
                !*comp && !*desc && !*condition && wrap_targets.empty() && !result_mode.no_files &&
-               !result_mode.force_files && !result_mode.requires_param) {
+               !result_mode.force_files && !result_mode.requires_param &&
+               !result_mode.attached_param) {
         // No arguments that would add or remove anything specified, so we print the definitions of
         // all matching completions.
         if (cmd_to_complete.empty()) {
@@ -63,7 +63,7 @@ maybe_t<int> builtin_disown(parser_t &parser, io_streams_t &streams, const wchar
         // Even jobs that aren't under job control can be disowned!
         job_t *job = nullptr;
         for (const auto &j : parser.jobs()) {
-            if (j->is_constructed() && (!j->is_completed())) {
+            if (j->is_constructed() && (!j->is_completed()) && !j->is_hidden()) {
                 job = j.get();
                 break;
             }
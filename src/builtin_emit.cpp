@@ -8,16 +8,59 @@
 #include "event.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "io.h"
+#include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
+struct emit_cmd_opts_t {
+    bool print_help = false;
+    bool async = false;
+};
+static const wchar_t *const short_options = L"+:h";
+static const struct woption long_options[] = {{L"help", no_argument, nullptr, 'h'},
+                                              {L"async", no_argument, nullptr, 1},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(emit_cmd_opts_t &opts, int *optind,  //!OCLINT(high ncss method)
+                          int argc, const wchar_t **argv, parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case 1: {
+                opts.async = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
 /// Implementation of the builtin emit command, used to create events.
 maybe_t<int> builtin_emit(parser_t &parser, io_streams_t &streams, const wchar_t **argv) {
     const wchar_t *cmd = argv[0];
     int argc = builtin_count_args(argv);
-    help_only_cmd_opts_t opts;
+    emit_cmd_opts_t opts;
 
     int optind;
-    int retval = parse_help_only_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
     if (retval != STATUS_CMD_OK) return retval;
 
     if (opts.print_help) {
@@ -32,6 +75,10 @@ maybe_t<int> builtin_emit(parser_t &parser, io_streams_t &streams, const wchar_t
 
     const wchar_t *eventname = argv[optind];
     wcstring_list_t args(argv + optind + 1, argv + argc);
-    event_fire_generic(parser, eventname, &args);
+    if (opts.async) {
+        event_enqueue_generic(parser, eventname, &args);
+    } else {
+        event_fire_generic(parser, eventname, &args);
+    }
     return STATUS_CMD_OK;
 }
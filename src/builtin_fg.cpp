@@ -36,12 +36,13 @@ maybe_t<int> builtin_fg(parser_t &parser, io_streams_t &streams, const wchar_t *
     }
 
     job_t *job = nullptr;
+    retval = STATUS_CMD_OK;
     if (optind == argc) {
         // Select last constructed job (i.e. first job in the job queue) that can be brought
         // to the foreground.
 
         for (const auto &j : parser.jobs()) {
-            if (j->is_constructed() && (!j->is_completed()) &&
+            if (j->is_constructed() && (!j->is_completed()) && !j->is_hidden() &&
                 ((j->is_stopped() || (!j->is_foreground())) && j->wants_job_control())) {
                 job = j.get();
                 break;
@@ -49,6 +50,7 @@ maybe_t<int> builtin_fg(parser_t &parser, io_streams_t &streams, const wchar_t *
         }
         if (!job) {
             streams.err.append_format(_(L"%ls: There are no suitable jobs\n"), cmd);
+            retval = STATUS_NOT_FOUND;
         }
     } else if (optind + 1 < argc) {
         // Specifying more than one job to put to the foreground is a syntax error, we still
@@ -68,27 +70,39 @@ maybe_t<int> builtin_fg(parser_t &parser, io_streams_t &streams, const wchar_t *
 
         job = nullptr;
         builtin_print_error_trailer(parser, streams.err, cmd);
+        retval = STATUS_INVALID_ARGS;
     } else {
         int pid = abs(fish_wcstoi(argv[optind]));
         if (errno) {
             streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, argv[optind]);
             builtin_print_error_trailer(parser, streams.err, cmd);
+            retval = STATUS_INVALID_ARGS;
         } else {
             job = parser.job_get_from_pid(pid);
             if (!job || !job->is_constructed() || job->is_completed()) {
                 streams.err.append_format(_(L"%ls: No suitable job: %d\n"), cmd, pid);
                 job = nullptr;
+                retval = STATUS_NOT_FOUND;
             } else if (!job->wants_job_control()) {
                 streams.err.append_format(_(L"%ls: Can't put job %d, '%ls' to foreground because "
                                             L"it is not under job control\n"),
                                           cmd, pid, job->command_wcstr());
                 job = nullptr;
+                retval = STATUS_INVALID_STATE;
             }
         }
     }
 
     if (!job) {
-        return STATUS_INVALID_ARGS;
+        return retval;
+    }
+
+    // An orphaned group can't get our terminal, and nobody would continue it if it stopped again.
+    if (job->group->is_orphan()) {
+        streams.err.append_format(_(L"%ls: Can't put job %d, '%ls' to foreground because "
+                                    L"it is an orphaned process group\n"),
+                                  cmd, job->job_id(), job->command_wcstr());
+        return STATUS_INVALID_STATE;
     }
 
     if (streams.err_is_redirected) {
@@ -35,6 +35,7 @@
 struct functions_cmd_opts_t {
     bool print_help = false;
     bool erase = false;
+    bool erase_autoloaded = false;
     bool list = false;
     bool show_hidden = false;
     bool query = false;
@@ -56,6 +57,7 @@ static const struct woption long_options[] = {{L"erase", no_argument, nullptr, '
                                               {L"copy", no_argument, nullptr, 'c'},
                                               {L"details", no_argument, nullptr, 'D'},
                                               {L"no-details", no_argument, nullptr, 1},
+                                              {L"all-autoloaded", no_argument, nullptr, 2},
                                               {L"verbose", no_argument, nullptr, 'v'},
                                               {L"handlers", no_argument, nullptr, 'H'},
                                               {L"handlers-type", required_argument, nullptr, 't'},
@@ -84,6 +86,10 @@ static int parse_cmd_opts(functions_cmd_opts_t &opts, int *optind,  //!OCLINT(hi
                 opts.no_metadata = true;
                 break;
             }
+            case 2: {
+                opts.erase_autoloaded = true;
+                break;
+            }
             case 'd': {
                 opts.description = w.woptarg;
                 break;
@@ -227,7 +233,15 @@ maybe_t<int> builtin_functions(parser_t &parser, io_streams_t &streams, const wc
         return STATUS_INVALID_ARGS;
     }
 
+    if (opts.erase_autoloaded && !opts.erase) {
+        streams.err.append_format(BUILTIN_ERR_COMBO2, cmd,
+                                  _(L"--all-autoloaded can only be used with --erase"));
+        builtin_print_error_trailer(parser, streams.err, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
     if (opts.erase) {
+        if (opts.erase_autoloaded) function_remove_autoloaded();
         for (int i = optind; i < argc; i++) function_remove(argv[i]);
         return STATUS_CMD_OK;
     }
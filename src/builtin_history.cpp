@@ -3,9 +3,11 @@
 
 #include "builtin_history.h"
 
+#include <algorithm>
 #include <cerrno>
 #include <cstddef>
 #include <cstdint>
+#include <ctime>
 #include <cwchar>
 #include <string>
 #include <vector>
@@ -15,17 +17,29 @@
 #include "fallback.h"  // IWYU pragma: keep
 #include "history.h"
 #include "io.h"
+#include "job_journal.h"
 #include "parser.h"
 #include "reader.h"
 #include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
-enum hist_cmd_t { HIST_SEARCH = 1, HIST_DELETE, HIST_CLEAR, HIST_MERGE, HIST_SAVE, HIST_UNDEF };
+enum hist_cmd_t {
+    HIST_SEARCH = 1,
+    HIST_DELETE,
+    HIST_CLEAR,
+    HIST_MERGE,
+    HIST_SAVE,
+    HIST_DEDUPLICATE,
+    HIST_JOBS,
+    HIST_UNDEF
+};
 
 // Must be sorted by string, not enum or random.
 static const enum_map<hist_cmd_t> hist_enum_map[] = {
-    {HIST_CLEAR, L"clear"}, {HIST_DELETE, L"delete"}, {HIST_MERGE, L"merge"},
-    {HIST_SAVE, L"save"},   {HIST_SEARCH, L"search"}, {HIST_UNDEF, nullptr}};
+    {HIST_CLEAR, L"clear"}, {HIST_DEDUPLICATE, L"deduplicate"},
+    {HIST_DELETE, L"delete"}, {HIST_JOBS, L"jobs"},
+    {HIST_MERGE, L"merge"}, {HIST_SAVE, L"save"},
+    {HIST_SEARCH, L"search"}, {HIST_UNDEF, nullptr}};
 
 struct history_cmd_opts_t {
     hist_cmd_t hist_cmd = HIST_UNDEF;
@@ -199,6 +213,28 @@ static int parse_cmd_opts(history_cmd_opts_t &opts, int *optind,  //!OCLINT(high
     return STATUS_CMD_OK;
 }
 
+/// Print the completed jobs from the job journal, newest first unless \p reverse is set.
+static void print_job_journal(size_t max_items, bool reverse, io_streams_t &streams) {
+    std::vector<job_journal_entry_t> entries = job_journal_read();
+    if (max_items < entries.size()) {
+        entries.erase(entries.begin(), entries.end() - max_items);
+    }
+    if (!reverse) std::reverse(entries.begin(), entries.end());
+
+    for (const job_journal_entry_t &entry : entries) {
+        char timestamp[64] = "";
+        struct tm tm {};
+        if (localtime_r(&entry.timestamp, &tm)) {
+            std::strftime(timestamp, sizeof timestamp, "%Y-%m-%d %H:%M:%S", &tm);
+        }
+        streams.out.append(str2wcstring(timestamp));
+        streams.out.append_format(L"\t%d\t%lld.%03llds\t", entry.status, entry.duration_ms / 1000,
+                                  entry.duration_ms % 1000);
+        streams.out.append(entry.command);
+        streams.out.push_back(L'\n');
+    }
+}
+
 /// Manipulate history of interactive commands executed by the user.
 maybe_t<int> builtin_history(parser_t &parser, io_streams_t &streams, const wchar_t **argv) {
     const wchar_t *cmd = argv[0];
@@ -303,6 +339,23 @@ maybe_t<int> builtin_history(parser_t &parser, io_streams_t &streams, const wcha
             history->save();
             break;
         }
+        case HIST_DEDUPLICATE: {
+            if (check_for_unexpected_hist_args(opts, cmd, args, streams)) {
+                status = STATUS_INVALID_ARGS;
+                break;
+            }
+            size_t removed = history->deduplicate_in_place();
+            streams.out.append_format(L"%lu\n", static_cast<unsigned long>(removed));
+            break;
+        }
+        case HIST_JOBS: {
+            if (check_for_unexpected_hist_args(opts, cmd, args, streams)) {
+                status = STATUS_INVALID_ARGS;
+                break;
+            }
+            print_job_journal(opts.max_items, opts.reverse, streams);
+            break;
+        }
         case HIST_UNDEF: {
             DIE("Unexpected HIST_UNDEF seen");
         }
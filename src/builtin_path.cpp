@@ -0,0 +1,704 @@
+// Implementation of the path builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_path.h"
+
+#include <fcntl.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include <algorithm>
+#include <cerrno>
+#include <cstdarg>
+#include <cwchar>
+#include <functional>
+#include <iterator>
+#include <set>
+#include <string>
+#include <utility>
+#include <vector>
+
+#include "builtin.h"
+#include "common.h"
+#include "env.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "maybe.h"
+#include "parser.h"
+#include "path.h"
+#include "util.h"
+#include "wcstringutil.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+static void path_error(io_streams_t &streams, const wchar_t *fmt, ...) {
+    streams.err.append(L"path ");
+    va_list va;
+    va_start(va, fmt);
+    streams.err.append_formatv(fmt, va);
+    va_end(va);
+}
+
+static void path_unknown_option(parser_t &parser, io_streams_t &streams, const wchar_t *subcmd,
+                                const wchar_t *opt) {
+    path_error(streams, BUILTIN_ERR_UNKNOWN, subcmd, opt);
+    builtin_print_error_trailer(parser, streams.err, L"path");
+}
+
+/// The file types `path is` and `path filter` check for. A path matches if it has any of them.
+enum {
+    path_type_file = 1 << 0,
+    path_type_dir = 1 << 1,
+    path_type_link = 1 << 2,
+    path_type_block = 1 << 3,
+    path_type_char = 1 << 4,
+    path_type_fifo = 1 << 5,
+    path_type_socket = 1 << 6,
+};
+using path_type_flags_t = unsigned int;
+
+/// The permissions `path is` and `path filter` check for. A path matches if it has all of them.
+enum {
+    path_perm_read = 1 << 0,
+    path_perm_write = 1 << 1,
+    path_perm_exec = 1 << 2,
+    path_perm_suid = 1 << 3,
+    path_perm_sgid = 1 << 4,
+    path_perm_user = 1 << 5,
+    path_perm_group = 1 << 6,
+};
+using path_perm_flags_t = unsigned int;
+
+/// What `path sort` sorts by.
+enum class path_sort_key_t {
+    path,
+    basename,
+    mtime,
+};
+
+static const struct {
+    const wchar_t *name;
+    path_type_flags_t flag;
+} path_type_names[] = {
+    {L"file", path_type_file},   {L"dir", path_type_dir},   {L"link", path_type_link},
+    {L"block", path_type_block}, {L"char", path_type_char}, {L"fifo", path_type_fifo},
+    {L"socket", path_type_socket},
+};
+
+static const struct {
+    const wchar_t *name;
+    path_perm_flags_t flag;
+} path_perm_names[] = {
+    {L"read", path_perm_read}, {L"write", path_perm_write}, {L"exec", path_perm_exec},
+    {L"suid", path_perm_suid}, {L"sgid", path_perm_sgid},   {L"user", path_perm_user},
+    {L"group", path_perm_group},
+};
+
+// This is used by the path subcommands to communicate with the option parser which flags are
+// valid and get the result of parsing the command for flags.
+struct path_options_t {
+    bool quiet_valid = false;
+    bool null_valid = false;
+    bool type_valid = false;
+    bool perm_valid = false;
+    bool invert_valid = false;
+    bool sort_valid = false;
+
+    bool quiet = false;
+    bool null_in = false;
+    bool null_out = false;
+    bool invert = false;
+    bool natural = false;
+    bool reverse = false;
+    bool unique = false;
+    path_sort_key_t key = path_sort_key_t::path;
+
+    path_type_flags_t types = 0;
+    path_perm_flags_t perms = 0;
+    maybe_t<mode_t> mode{};
+
+    const wchar_t *arg1 = nullptr;
+};
+
+// Remember: adjust share/completions/path.fish when `path` options change
+// --reverse has no short option here because -r is taken by `path is`; see the 'r' case.
+enum { opt_reverse = 1 };
+static const struct woption long_options[] = {{L"quiet", no_argument, nullptr, 'q'},
+                                              {L"null-in", no_argument, nullptr, 'z'},
+                                              {L"null-out", no_argument, nullptr, 'Z'},
+                                              {L"type", required_argument, nullptr, 't'},
+                                              {L"perm", required_argument, nullptr, 'p'},
+                                              {L"mode", required_argument, nullptr, 'm'},
+                                              {L"invert", no_argument, nullptr, 'v'},
+                                              {L"natural", no_argument, nullptr, 'n'},
+                                              {L"key", required_argument, nullptr, 'k'},
+                                              {L"reverse", no_argument, nullptr, opt_reverse},
+                                              {L"unique", no_argument, nullptr, 'u'},
+                                              {nullptr, 0, nullptr, 0}};
+
+/// This constructs the wgetopt() short options string based on which arguments are valid for the
+/// subcommand.
+static wcstring construct_short_opts(const path_options_t &opts) {
+    wcstring short_opts(L":");
+    if (opts.quiet_valid) short_opts.append(L"q");
+    if (opts.null_valid) short_opts.append(L"zZ");
+    if (opts.type_valid) short_opts.append(L"t:fdl");
+    if (opts.perm_valid) short_opts.append(L"p:m:rwx");
+    if (opts.invert_valid) short_opts.append(L"v");
+    if (opts.sort_valid) short_opts.append(L"nk:ru");
+    return short_opts;
+}
+
+/// Parse a comma-separated list of names from \p table into flags.
+/// \return false if one of the names is unknown.
+template <typename Table>
+static bool parse_flag_names(const Table &table, const wchar_t *arg, unsigned int *out_flags,
+                             wcstring *out_bad_name) {
+    for (const wcstring &name : split_string(arg, L',')) {
+        auto found = std::find_if(std::begin(table), std::end(table),
+                                  [&](decltype(*std::begin(table)) entry) {
+                                      return name == entry.name;
+                                  });
+        if (found == std::end(table)) {
+            *out_bad_name = name;
+            return false;
+        }
+        *out_flags |= found->flag;
+    }
+    return true;
+}
+
+/// Parse the arguments for flags recognized by a specific path subcommand.
+static int parse_opts(path_options_t *opts, int *optind, int n_req_args, int argc,
+                      const wchar_t **argv, parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    wcstring short_opts = construct_short_opts(*opts);
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_opts.c_str(), long_options, nullptr)) != -1) {
+        // The long options are shared between subcommands, so check that this one applies.
+        bool valid = true;
+        switch (opt) {
+            case 'q': {
+                valid = opts->quiet_valid;
+                opts->quiet = true;
+                break;
+            }
+            case 'z': {
+                valid = opts->null_valid;
+                opts->null_in = true;
+                break;
+            }
+            case 'Z': {
+                valid = opts->null_valid;
+                opts->null_out = true;
+                break;
+            }
+            case 't': {
+                valid = opts->type_valid;
+                wcstring bad_name;
+                if (valid &&
+                    !parse_flag_names(path_type_names, w.woptarg, &opts->types, &bad_name)) {
+                    path_error(streams, _(L"%ls: Invalid type '%ls'\n"), cmd, bad_name.c_str());
+                    return STATUS_INVALID_ARGS;
+                }
+                break;
+            }
+            case 'f': {
+                opts->types |= path_type_file;
+                break;
+            }
+            case 'd': {
+                opts->types |= path_type_dir;
+                break;
+            }
+            case 'l': {
+                opts->types |= path_type_link;
+                break;
+            }
+            case 'p': {
+                valid = opts->perm_valid;
+                wcstring bad_name;
+                if (valid &&
+                    !parse_flag_names(path_perm_names, w.woptarg, &opts->perms, &bad_name)) {
+                    path_error(streams, _(L"%ls: Invalid permission '%ls'\n"), cmd,
+                               bad_name.c_str());
+                    return STATUS_INVALID_ARGS;
+                }
+                break;
+            }
+            case 'm': {
+                valid = opts->perm_valid;
+                if (!valid) break;
+                const wchar_t *end;
+                long mode = fish_wcstol(w.woptarg, &end, 8);
+                if (errno || mode < 0 || mode > 07777) {
+                    path_error(streams, _(L"%ls: Invalid mode '%ls'\n"), cmd, w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                opts->mode = static_cast<mode_t>(mode);
+                break;
+            }
+            case 'r': {
+                // -r is --reverse for `path sort`, and --perm=read for the others.
+                if (opts->sort_valid) {
+                    opts->reverse = true;
+                } else {
+                    opts->perms |= path_perm_read;
+                }
+                break;
+            }
+            case 'w': {
+                opts->perms |= path_perm_write;
+                break;
+            }
+            case 'x': {
+                opts->perms |= path_perm_exec;
+                break;
+            }
+            case 'v': {
+                valid = opts->invert_valid;
+                opts->invert = true;
+                break;
+            }
+            case 'n': {
+                valid = opts->sort_valid;
+                opts->natural = true;
+                break;
+            }
+            case 'k': {
+                valid = opts->sort_valid;
+                if (!valid) break;
+                if (std::wcscmp(w.woptarg, L"basename") == 0) {
+                    opts->key = path_sort_key_t::basename;
+                } else if (std::wcscmp(w.woptarg, L"mtime") == 0) {
+                    opts->key = path_sort_key_t::mtime;
+                } else {
+                    path_error(streams, _(L"%ls: Invalid sort key '%ls'\n"), cmd, w.woptarg);
+                    return STATUS_INVALID_ARGS;
+                }
+                break;
+            }
+            case opt_reverse: {
+                valid = opts->sort_valid;
+                opts->reverse = true;
+                break;
+            }
+            case 'u': {
+                valid = opts->sort_valid;
+                opts->unique = true;
+                break;
+            }
+            case ':': {
+                streams.err.append(L"path ");  // clone of path_error
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1],
+                                         false /* print_hints */);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                valid = false;
+                break;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+        if (!valid) {
+            path_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+            return STATUS_INVALID_ARGS;
+        }
+    }
+
+    *optind = w.woptind;
+
+    if (n_req_args) {
+        if (!argv[*optind]) {
+            path_error(streams, BUILTIN_ERR_ARG_COUNT0, cmd);
+            return STATUS_INVALID_ARGS;
+        }
+        opts->arg1 = argv[(*optind)++];
+    }
+
+    // At this point we should not have optional args and be reading args from stdin.
+    if (arg_iterator_t::args_from_stdin(streams) && argc > *optind) {
+        path_error(streams, BUILTIN_ERR_TOO_MANY_ARGUMENTS, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
+    return STATUS_CMD_OK;
+}
+
+/// Write one result of a path subcommand.
+static void path_out(io_streams_t &streams, const path_options_t &opts, const wcstring &str) {
+    if (opts.quiet) return;
+    if (opts.null_out) {
+        streams.out.append(str);
+        streams.out.push_back(L'\0');
+    } else {
+        streams.out.append_with_separation(str, separation_type_t::explicitly);
+    }
+}
+
+/// \return the position of the extension of \p path, i.e. of its last '.', or npos if it has none.
+/// Only the last component of the path counts, and a leading '.' marks a hidden file rather than
+/// an extension. \p path must not end in a slash.
+static size_t find_extension(const wcstring &path) {
+    size_t dot = path.rfind(L'.');
+    if (dot == wcstring::npos || dot == 0) return wcstring::npos;
+    size_t slash = path.rfind(L'/');
+    if (slash != wcstring::npos && dot <= slash + 1) return wcstring::npos;
+    return dot;
+}
+
+/// Strip trailing slashes from \p path, unless it consists only of slashes.
+static wcstring strip_trailing_slashes(wcstring path) {
+    size_t end = path.find_last_not_of(L'/');
+    if (end != wcstring::npos) path.resize(end + 1);
+    return path;
+}
+
+/// Resolve \p path like realpath(3) does, except that components that do not exist are allowed.
+/// These are appended to the resolved part that does exist, and normalized. Symbolic links whose
+/// target doesn't exist are followed as well.
+static wcstring resolve_path(const wcstring &path, const wcstring &pwd_slash) {
+    const wcstring abs = path_apply_working_directory(path, pwd_slash);
+    wcstring prefix = abs;
+    wcstring rest;
+    // Like the kernel, give up on following links after this many, as they may form a loop.
+    int links_left = 40;
+    for (;;) {
+        // wrealpath() accepts a last component that doesn't exist, so it would not follow a link
+        // to it. Do that first.
+        prefix = strip_trailing_slashes(prefix);
+        if (links_left > 0) {
+            if (auto target = wreadlink(prefix)) {
+                links_left--;
+                prefix = string_prefixes_string(L"/", *target) ? *target
+                                                                : wdirname(prefix) + L"/" + *target;
+                continue;
+            }
+        }
+        if (auto real = wrealpath(prefix)) {
+            return normalize_path(rest.empty() ? *real : *real + L"/" + rest);
+        }
+        size_t slash = prefix.rfind(L'/');
+        if (slash == wcstring::npos || prefix == L"/") return normalize_path(abs);
+        rest = prefix.substr(slash + 1) + (rest.empty() ? L"" : L"/" + rest);
+        prefix.resize(slash == 0 ? 1 : slash);
+    }
+}
+
+/// \return the fd of the parser's working directory, or AT_FDCWD if it could not be opened.
+static int path_cwd_fd(const parser_t &parser) {
+    const auto &cwd_fd = parser.libdata().cwd_fd;
+    return cwd_fd ? cwd_fd->fd() : AT_FDCWD;
+}
+
+/// \return whether \p path has any of the types, all of the permissions and the mode in \p opts.
+/// Without any of those, it only needs to exist. Relative paths are looked up from \p cwd_fd.
+static bool path_matches(const wcstring &path, const path_options_t &opts, int cwd_fd) {
+    if (path.empty()) return false;
+    const std::string narrow = wcs2string(path);
+    struct stat buf;
+    bool exists = fstatat(cwd_fd, narrow.c_str(), &buf, 0) == 0;
+
+    if (opts.types) {
+        bool type_matches = false;
+        if (opts.types & path_type_link) {
+            type_matches = wreadlink(path, cwd_fd).has_value();
+        }
+        if (!type_matches && exists) {
+            mode_t mode = buf.st_mode;
+            type_matches = ((opts.types & path_type_file) && S_ISREG(mode)) ||
+                           ((opts.types & path_type_dir) && S_ISDIR(mode)) ||
+                           ((opts.types & path_type_block) && S_ISBLK(mode)) ||
+                           ((opts.types & path_type_char) && S_ISCHR(mode)) ||
+                           ((opts.types & path_type_fifo) && S_ISFIFO(mode)) ||
+                           ((opts.types & path_type_socket) && S_ISSOCK(mode));
+        }
+        if (!type_matches) return false;
+    } else if (!exists) {
+        return false;
+    }
+
+    // Permissions are those of the target, so a broken symlink has none.
+    if ((opts.perms || opts.mode) && !exists) return false;
+    if (opts.mode && (buf.st_mode & 07777) != *opts.mode) return false;
+    if (opts.perms) {
+        int amode = 0;
+        if (opts.perms & path_perm_read) amode |= R_OK;
+        if (opts.perms & path_perm_write) amode |= W_OK;
+        if (opts.perms & path_perm_exec) amode |= X_OK;
+        if (amode && faccessat(cwd_fd, narrow.c_str(), amode, 0) != 0) return false;
+        if ((opts.perms & path_perm_suid) && !(buf.st_mode & S_ISUID)) return false;
+        if ((opts.perms & path_perm_sgid) && !(buf.st_mode & S_ISGID)) return false;
+        if ((opts.perms & path_perm_user) && buf.st_uid != geteuid()) return false;
+        if ((opts.perms & path_perm_group) && buf.st_gid != getegid()) return false;
+    }
+    return true;
+}
+
+/// Shared implementation of the subcommands that output one transformed path per input path.
+/// Empty arguments are not paths, so they produce no output.
+/// \return 0 if there was at least one path.
+static int path_transform(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv,
+                          int n_req_args,
+                          const std::function<wcstring(const wcstring &, const path_options_t &)>
+                              &transform) {
+    path_options_t opts;
+    opts.quiet_valid = true;
+    opts.null_valid = true;
+    int optind;
+    int retval = parse_opts(&opts, &optind, n_req_args, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    int n_paths = 0;
+    arg_iterator_t aiter(argv, optind, streams, true, opts.null_in ? '\0' : '\n');
+    while (const wcstring *arg = aiter.nextstr()) {
+        if (arg->empty()) continue;
+        n_paths++;
+        if (opts.quiet) return STATUS_CMD_OK;
+        path_out(streams, opts, transform(*arg, opts));
+    }
+    return n_paths > 0 ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+}
+
+/// Implementation of `path basename`.
+static int path_basename(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
+    return path_transform(parser, streams, argc, argv, 0,
+                          [](const wcstring &arg, const path_options_t &) {
+                              return wbasename(arg);
+                          });
+}
+
+/// Implementation of `path dirname`.
+static int path_dirname(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
+    return path_transform(parser, streams, argc, argv, 0,
+                          [](const wcstring &arg, const path_options_t &) {
+                              return wdirname(arg);
+                          });
+}
+
+/// Implementation of `path normalize`.
+static int path_normalize(parser_t &parser, io_streams_t &streams, int argc,
+                          const wchar_t **argv) {
+    return path_transform(parser, streams, argc, argv, 0,
+                          [](const wcstring &arg, const path_options_t &) {
+                              wcstring result = normalize_path(arg);
+                              // Don't let the result be mistaken for an option.
+                              if (string_prefixes_string(L"-", result)) result.insert(0, L"./");
+                              return result;
+                          });
+}
+
+/// Implementation of `path resolve`.
+static int path_resolve(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
+    const wcstring pwd_slash = parser.vars().get_pwd_slash();
+    return path_transform(parser, streams, argc, argv, 0,
+                          [&](const wcstring &arg, const path_options_t &) {
+                              return resolve_path(arg, pwd_slash);
+                          });
+}
+
+/// Implementation of `path change-extension`.
+static int path_change_extension(parser_t &parser, io_streams_t &streams, int argc,
+                                 const wchar_t **argv) {
+    return path_transform(parser, streams, argc, argv, 1,
+                          [](const wcstring &arg, const path_options_t &opts) {
+                              wcstring result = strip_trailing_slashes(arg);
+                              if (result == L"/") return result;
+                              size_t dot = find_extension(result);
+                              if (dot != wcstring::npos) result.resize(dot);
+                              if (opts.arg1[0] != L'\0' && opts.arg1[0] != L'.') {
+                                  result.push_back(L'.');
+                              }
+                              result.append(opts.arg1);
+                              return result;
+                          });
+}
+
+/// Implementation of `path extension`.
+/// This prints an empty line for paths without an extension, so the output lines up with the
+/// input, but only returns 0 if at least one path had an extension.
+static int path_extension(parser_t &parser, io_streams_t &streams, int argc,
+                          const wchar_t **argv) {
+    path_options_t opts;
+    opts.quiet_valid = true;
+    opts.null_valid = true;
+    int optind;
+    int retval = parse_opts(&opts, &optind, 0, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    int n_extensions = 0;
+    arg_iterator_t aiter(argv, optind, streams, true, opts.null_in ? '\0' : '\n');
+    while (const wcstring *arg = aiter.nextstr()) {
+        if (arg->empty()) continue;
+        wcstring path = strip_trailing_slashes(*arg);
+        size_t dot = find_extension(path);
+        if (dot != wcstring::npos) {
+            n_extensions++;
+            if (opts.quiet) return STATUS_CMD_OK;
+        }
+        path_out(streams, opts, dot == wcstring::npos ? wcstring() : path.substr(dot));
+    }
+    return n_extensions > 0 ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+}
+
+/// Implementation of `path is`.
+/// This prints nothing, and returns 0 if any of the paths matches the given types and permissions.
+static int path_is(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
+    path_options_t opts;
+    opts.null_valid = true;
+    opts.type_valid = true;
+    opts.perm_valid = true;
+    opts.invert_valid = true;
+    int optind;
+    int retval = parse_opts(&opts, &optind, 0, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    const int cwd_fd = path_cwd_fd(parser);
+    arg_iterator_t aiter(argv, optind, streams, true, opts.null_in ? '\0' : '\n');
+    while (const wcstring *arg = aiter.nextstr()) {
+        if (path_matches(*arg, opts, cwd_fd) != opts.invert) return STATUS_CMD_OK;
+    }
+    return STATUS_CMD_ERROR;
+}
+
+/// Implementation of `path filter`.
+/// This prints the paths that match the given types, permissions and mode, like `path is` checks
+/// them, and returns 0 if there was at least one.
+static int path_filter(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
+    path_options_t opts;
+    opts.quiet_valid = true;
+    opts.null_valid = true;
+    opts.type_valid = true;
+    opts.perm_valid = true;
+    opts.invert_valid = true;
+    int optind;
+    int retval = parse_opts(&opts, &optind, 0, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    int n_matches = 0;
+    const int cwd_fd = path_cwd_fd(parser);
+    arg_iterator_t aiter(argv, optind, streams, true, opts.null_in ? '\0' : '\n');
+    while (const wcstring *arg = aiter.nextstr()) {
+        // Empty arguments are not paths, even with --invert.
+        if (arg->empty() || path_matches(*arg, opts, cwd_fd) == opts.invert) continue;
+        n_matches++;
+        if (opts.quiet) return STATUS_CMD_OK;
+        path_out(streams, opts, *arg);
+    }
+    return n_matches > 0 ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+}
+
+/// Implementation of `path sort`.
+/// Paths are compared as strings, or naturally with numbers compared by value. The sort is stable,
+/// so paths with the same key keep their order.
+static int path_sort(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
+    path_options_t opts;
+    opts.quiet_valid = true;
+    opts.null_valid = true;
+    opts.sort_valid = true;
+    int optind;
+    int retval = parse_opts(&opts, &optind, 0, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    struct entry_t {
+        wcstring path;
+        // The string to compare, unless sorting by mtime.
+        wcstring key;
+        // The modification time. Paths that don't exist have -1, so they come first.
+        std::pair<time_t, long> mtime;
+    };
+    std::vector<entry_t> entries;
+    const int cwd_fd = path_cwd_fd(parser);
+    arg_iterator_t aiter(argv, optind, streams, true, opts.null_in ? '\0' : '\n');
+    while (const wcstring *arg = aiter.nextstr()) {
+        if (arg->empty()) continue;
+        if (opts.quiet) return STATUS_CMD_OK;
+        entry_t entry{*arg, *arg, {-1, -1}};
+        if (opts.key == path_sort_key_t::basename) {
+            entry.key = wbasename(*arg);
+        } else if (opts.key == path_sort_key_t::mtime) {
+            struct stat buf;
+            if (fstatat(cwd_fd, wcs2string(*arg).c_str(), &buf, 0) == 0) {
+                file_id_t id = file_id_t::from_stat(buf);
+                entry.mtime = {id.mod_seconds, id.mod_nanoseconds};
+            }
+        }
+        entries.push_back(std::move(entry));
+    }
+    if (entries.empty()) return STATUS_CMD_ERROR;
+
+    // \return the sign of the comparison of a and b, in ascending order.
+    auto compare = [&](const entry_t &a, const entry_t &b) {
+        if (opts.key == path_sort_key_t::mtime) {
+            return a.mtime < b.mtime ? -1 : (b.mtime < a.mtime ? 1 : 0);
+        }
+        return opts.natural ? wcsfilecmp(a.key.c_str(), b.key.c_str()) : a.key.compare(b.key);
+    };
+    std::stable_sort(entries.begin(), entries.end(), [&](const entry_t &a, const entry_t &b) {
+        int cmp = compare(a, b);
+        return opts.reverse ? cmp > 0 : cmp < 0;
+    });
+
+    // With --unique, keep the first path with each basename, or each path only once.
+    std::set<wcstring> seen;
+    for (const entry_t &entry : entries) {
+        if (opts.unique) {
+            const wcstring &id = opts.key == path_sort_key_t::basename ? entry.key : entry.path;
+            if (!seen.insert(id).second) continue;
+        }
+        path_out(streams, opts, entry.path);
+    }
+    return STATUS_CMD_OK;
+}
+
+// Keep sorted alphabetically
+static constexpr const struct path_subcommand {
+    const wchar_t *name;
+    int (*handler)(parser_t &, io_streams_t &, int argc,  //!OCLINT(unused param)
+                   const wchar_t **argv);                 //!OCLINT(unused param)
+} path_subcommands[] = {
+    {L"basename", &path_basename},   {L"change-extension", &path_change_extension},
+    {L"dirname", &path_dirname},     {L"extension", &path_extension},
+    {L"filter", &path_filter},       {L"is", &path_is},
+    {L"normalize", &path_normalize}, {L"resolve", &path_resolve},
+    {L"sort", &path_sort},
+};
+ASSERT_SORT_ORDER(path_subcommands, .name);
+
+/// The path builtin, for handling paths.
+maybe_t<int> builtin_path(parser_t &parser, io_streams_t &streams, const wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    int argc = builtin_count_args(argv);
+    if (argc <= 1) {
+        streams.err.append_format(BUILTIN_ERR_MISSING_SUBCMD, cmd);
+        builtin_print_error_trailer(parser, streams.err, L"path");
+        return STATUS_INVALID_ARGS;
+    }
+
+    if (std::wcscmp(argv[1], L"-h") == 0 || std::wcscmp(argv[1], L"--help") == 0) {
+        builtin_print_help(parser, streams, L"path");
+        return STATUS_CMD_OK;
+    }
+
+    const wchar_t *subcmd_name = argv[1];
+    auto subcmd = std::find_if(std::begin(path_subcommands), std::end(path_subcommands),
+                               [&](const path_subcommand &sub) {
+                                   return std::wcscmp(sub.name, subcmd_name) == 0;
+                               });
+    if (subcmd == std::end(path_subcommands)) {
+        streams.err.append_format(BUILTIN_ERR_INVALID_SUBCMD, cmd, subcmd_name);
+        builtin_print_error_trailer(parser, streams.err, L"path");
+        return STATUS_INVALID_ARGS;
+    }
+
+    if (argc >= 3 && (std::wcscmp(argv[2], L"-h") == 0 || std::wcscmp(argv[2], L"--help") == 0)) {
+        builtin_print_help(parser, streams, L"path");
+        return STATUS_CMD_OK;
+    }
+    argc--;
+    argv++;
+    return subcmd->handler(parser, streams, argc, argv);
+}
@@ -0,0 +1,170 @@
+// Implementation of the path-add builtin.
+#include "config.h"  // IWYU pragma: keep
+
+#include "builtin_path_add.h"
+
+#include <algorithm>
+#include <cwchar>
+#include <unordered_set>
+
+#include "builtin.h"
+#include "common.h"
+#include "env.h"
+#include "fallback.h"  // IWYU pragma: keep
+#include "io.h"
+#include "parser.h"
+#include "path.h"
+#include "wgetopt.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+struct path_add_cmd_opts_t {
+    bool print_help = false;
+    bool append = false;
+    bool move = false;
+    env_mode_flags_t scope = ENV_USER;
+};
+
+static const wchar_t *const short_options = L"+:aghlmpU";
+static const struct woption long_options[] = {{L"append", no_argument, nullptr, 'a'},
+                                              {L"prepend", no_argument, nullptr, 'p'},
+                                              {L"move", no_argument, nullptr, 'm'},
+                                              {L"global", no_argument, nullptr, 'g'},
+                                              {L"local", no_argument, nullptr, 'l'},
+                                              {L"universal", no_argument, nullptr, 'U'},
+                                              {L"help", no_argument, nullptr, 'h'},
+                                              {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(path_add_cmd_opts_t &opts, int *optind,  //!OCLINT(high ncss method)
+                          int argc, const wchar_t **argv, parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'a': {
+                opts.append = true;
+                break;
+            }
+            case 'p': {
+                opts.append = false;
+                break;
+            }
+            case 'm': {
+                opts.move = true;
+                break;
+            }
+            case 'g': {
+                opts.scope |= ENV_GLOBAL;
+                break;
+            }
+            case 'l': {
+                opts.scope |= ENV_LOCAL;
+                break;
+            }
+            case 'U': {
+                opts.scope |= ENV_UNIVERSAL;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Add directories to a path variable, unless they are already in it. With --move, a directory
+/// that is already in it is moved to the front (or the back with --append) instead.
+/// Relative directories are resolved against the working directory.
+///
+/// Returns 0 if the variable was changed, 1 if there was nothing to do.
+maybe_t<int> builtin_path_add(parser_t &parser, io_streams_t &streams, const wchar_t **argv) {
+    const wchar_t *cmd = argv[0];
+    path_add_cmd_opts_t opts;
+    int argc = builtin_count_args(argv);
+    int optind;
+
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    if (opts.print_help) {
+        builtin_print_help(parser, streams, cmd);
+        return STATUS_CMD_OK;
+    }
+
+    if ((opts.scope & ENV_LOCAL ? 1 : 0) + (opts.scope & ENV_GLOBAL ? 1 : 0) +
+            (opts.scope & ENV_UNIVERSAL ? 1 : 0) >
+        1) {
+        streams.err.append_format(BUILTIN_ERR_GLOCAL, cmd);
+        builtin_print_error_trailer(parser, streams.err, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
+    if (argc - optind < 2) {
+        streams.err.append_format(BUILTIN_ERR_MIN_ARG_COUNT1, cmd, 2, argc - optind);
+        builtin_print_error_trailer(parser, streams.err, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
+    const wchar_t *varname = argv[optind++];
+    if (!valid_var_name(varname)) {
+        streams.err.append_format(BUILTIN_ERR_VARNAME, cmd, varname);
+        builtin_print_error_trailer(parser, streams.err, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
+    // Make the given directories absolute and normalized, dropping duplicates.
+    const wcstring working_directory = parser.vars().get_pwd_slash();
+    wcstring_list_t dirs;
+    for (int i = optind; i < argc; i++) {
+        if (!*argv[i]) continue;
+        wcstring dir = normalize_path(path_apply_working_directory(argv[i], working_directory));
+        if (std::find(dirs.begin(), dirs.end(), dir) == dirs.end()) dirs.push_back(std::move(dir));
+    }
+
+    wcstring_list_t existing;
+    if (auto var = parser.vars().get(varname, opts.scope)) existing = var->as_list();
+
+    // Decide which directories to add. Without --move, present ones stay where they are.
+    std::unordered_set<wcstring> present(existing.begin(), existing.end());
+    wcstring_list_t added;
+    for (wcstring &dir : dirs) {
+        if (opts.move || !present.count(dir)) added.push_back(std::move(dir));
+    }
+    if (added.empty()) return STATUS_CMD_ERROR;
+
+    std::unordered_set<wcstring> moved(added.begin(), added.end());
+    wcstring_list_t result;
+    if (!opts.append) result = added;
+    for (const wcstring &dir : existing) {
+        if (!moved.count(dir)) result.push_back(dir);
+    }
+    if (opts.append) result.insert(result.end(), added.begin(), added.end());
+    // Moving directories that are already in place changes nothing.
+    if (result == existing) return STATUS_CMD_ERROR;
+
+    int res = parser.set_var_and_fire(varname, opts.scope, std::move(result));
+    if (res == ENV_PERM) {
+        streams.err.append_format(_(L"%ls: Tried to change the read-only variable '%ls'\n"), cmd,
+                                  varname);
+        return STATUS_CMD_ERROR;
+    } else if (res != ENV_OK) {
+        streams.err.append_format(_(L"%ls: Could not set '%ls'\n"), cmd, varname);
+        return STATUS_CMD_ERROR;
+    }
+    return STATUS_CMD_OK;
+}
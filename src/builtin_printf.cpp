@@ -52,6 +52,7 @@
 
 #include <sys/types.h>
 
+#include <algorithm>
 #include <cerrno>
 #include <climits>
 #include <clocale>
@@ -62,11 +63,13 @@
 #include <cstring>
 #include <cwchar>
 #include <cwctype>
+#include <vector>
 
 #include "builtin.h"
 #include "common.h"
 #include "io.h"
 #include "wcstringutil.h"
+#include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
 class parser_t;
@@ -737,13 +740,107 @@ int builtin_printf_state_t::print_formatted(const wchar_t *format, int argc, con
 }
 
 /// The printf builtin.
+struct printf_cmd_opts_t {
+    bool print_help = false;
+    bool table = false;
+    const wchar_t *separator = nullptr;
+};
+
+// Options are only recognized before the format, so arguments may start with a "-".
+enum { opt_table = 1, opt_field_separator };
+static const wchar_t *const short_options = L"+:h";
+static const struct woption long_options[] = {
+    {L"table", no_argument, nullptr, opt_table},
+    {L"field-separator", required_argument, nullptr, opt_field_separator},
+    {L"sep", required_argument, nullptr, opt_field_separator},
+    {L"help", no_argument, nullptr, 'h'},
+    {nullptr, 0, nullptr, 0}};
+
+static int parse_cmd_opts(printf_cmd_opts_t &opts, int *optind, int argc, const wchar_t **argv,
+                          parser_t &parser, io_streams_t &streams) {
+    const wchar_t *cmd = argv[0];
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case opt_table: {
+                opts.table = true;
+                break;
+            }
+            case opt_field_separator: {
+                opts.separator = w.woptarg;
+                break;
+            }
+            case 'h': {
+                opts.print_help = true;
+                break;
+            }
+            case ':': {
+                builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            case '?': {
+                builtin_unknown_option(parser, streams, cmd, argv[w.woptind - 1]);
+                return STATUS_INVALID_ARGS;
+            }
+            default: {
+                DIE("unexpected retval from wgetopt_long");
+            }
+        }
+    }
+
+    if (opts.separator && !opts.table) {
+        streams.err.append_format(BUILTIN_ERR_COMBO2, cmd,
+                                  _(L"--field-separator can only be used with --table"));
+        return STATUS_INVALID_ARGS;
+    }
+    if (opts.separator && !*opts.separator) {
+        streams.err.append_format(_(L"%ls: The field separator must not be empty\n"), cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
+    *optind = w.woptind;
+    return STATUS_CMD_OK;
+}
+
+/// Print each of the \p argc arguments in \p argv as a row of a table, with its fields split on
+/// \p sep. Each field except the last one in its row is padded to the width of the widest field in
+/// its column, and the columns are separated by two spaces.
+static void print_table(io_streams_t &streams, const wcstring &sep, int argc,
+                        const wchar_t **argv) {
+    std::vector<wcstring_list_t> rows;
+    std::vector<int> widths;
+    for (int i = 0; i < argc; i++) {
+        const wcstring row = argv[i];
+        wcstring_list_t fields;
+        split_about(row.begin(), row.end(), sep.begin(), sep.end(), &fields);
+        for (size_t col = 0; col < fields.size(); col++) {
+            if (widths.size() <= col) widths.push_back(0);
+            widths[col] = std::max(widths[col], fish_wcswidth(fields[col]));
+        }
+        rows.push_back(std::move(fields));
+    }
+
+    for (const wcstring_list_t &fields : rows) {
+        wcstring line;
+        for (size_t col = 0; col < fields.size(); col++) {
+            line.append(fields[col]);
+            if (col + 1 == fields.size()) break;
+            int padding = widths[col] - fish_wcswidth(fields[col]);
+            line.append(std::max(padding, 0) + 2, L' ');
+        }
+        line.push_back(L'\n');
+        streams.out.append(line);
+    }
+}
+
 maybe_t<int> builtin_printf(parser_t &parser, io_streams_t &streams, const wchar_t **argv) {
     const wchar_t *cmd = argv[0];
     int argc = builtin_count_args(argv);
-    help_only_cmd_opts_t opts;
+    printf_cmd_opts_t opts;
 
     int optind;
-    int retval = parse_help_only_cmd_opts(opts, &optind, argc, argv, parser, streams);
+    int retval = parse_cmd_opts(opts, &optind, argc, argv, parser, streams);
     if (retval != STATUS_CMD_OK) return retval;
 
     if (opts.print_help) {
@@ -753,6 +850,10 @@ maybe_t<int> builtin_printf(parser_t &parser, io_streams_t &streams, const wchar
 
     argc -= optind;
     argv += optind;
+    if (opts.table) {
+        print_table(streams, opts.separator ? opts.separator : L"\t", argc, argv);
+        return STATUS_CMD_OK;
+    }
     if (argc < 1) {
         return STATUS_INVALID_ARGS;
     }
@@ -41,7 +41,11 @@ struct read_cmd_opts_t {
     wcstring prompt_cmd;
     const wchar_t *prompt = nullptr;
     const wchar_t *prompt_str = nullptr;
+    wcstring right_prompt_cmd;
     const wchar_t *right_prompt = L"";
+    const wchar_t *right_prompt_str = nullptr;
+    const wchar_t *validate = nullptr;
+    int max_tries = 0;
     const wchar_t *commandline = L"";
     // If a delimiter was given. Used to distinguish between the default
     // empty string and a given empty delimiter.
@@ -52,6 +56,9 @@ struct read_cmd_opts_t {
     bool array = false;
     bool silent = false;
     bool split_null = false;
+    // If --until was given, the character that ends the input, or none to read until EOF.
+    bool have_until = false;
+    maybe_t<wchar_t> until;
     bool to_stdout = false;
     int nchars = 0;
     bool one_line = false;
@@ -72,6 +79,10 @@ static const struct woption long_options[] = {{L"array", no_argument, nullptr, '
                                               {L"prompt", required_argument, nullptr, 'p'},
                                               {L"prompt-str", required_argument, nullptr, 'P'},
                                               {L"right-prompt", required_argument, nullptr, 'R'},
+                                              {L"right-prompt-str", required_argument, nullptr, 1},
+                                              {L"validate", required_argument, nullptr, 2},
+                                              {L"max-tries", required_argument, nullptr, 3},
+                                              {L"until", required_argument, nullptr, 4},
                                               {L"shell", no_argument, nullptr, 'S'},
                                               {L"silent", no_argument, nullptr, 's'},
                                               {L"tokenize", no_argument, nullptr, 't'},
@@ -149,6 +160,36 @@ static int parse_cmd_opts(read_cmd_opts_t &opts, int *optind,  //!OCLINT(high nc
                 opts.right_prompt = w.woptarg;
                 break;
             }
+            case 1: {
+                opts.right_prompt_str = w.woptarg;
+                break;
+            }
+            case 2: {
+                opts.validate = w.woptarg;
+                break;
+            }
+            case 3: {
+                opts.max_tries = fish_wcstoi(w.woptarg);
+                if (errno || opts.max_tries < 0) {
+                    streams.err.append_format(BUILTIN_ERR_NOT_NUMBER, cmd, w.woptarg);
+                    builtin_print_error_trailer(parser, streams.err, cmd);
+                    return STATUS_INVALID_ARGS;
+                }
+                break;
+            }
+            case 4: {
+                if (std::wcslen(w.woptarg) > 1) {
+                    streams.err.append_format(
+                        _(L"%ls: Invalid terminator '%ls', expected a single character\n"), cmd,
+                        w.woptarg);
+                    builtin_print_error_trailer(parser, streams.err, cmd);
+                    return STATUS_INVALID_ARGS;
+                }
+                opts.have_until = true;
+                opts.until = none();
+                if (*w.woptarg) opts.until = w.woptarg[0];
+                break;
+            }
             case 's': {
                 opts.silent = true;
                 break;
@@ -246,17 +287,29 @@ static int read_interactive(parser_t &parser, wcstring &buff, int nchars, bool s
     return exit_res;
 }
 
+/// Run the --validate code \p code with \p value in $argv.
+/// \return whether it accepted the value, i.e. returned 0.
+static bool validate_interactive_input(parser_t &parser, const io_streams_t &streams,
+                                       const wcstring &code, const wcstring &value) {
+    auto &vars = parser.vars();
+    vars.push(false);
+    vars.set_one(L"argv", ENV_LOCAL, value);
+    eval_res_t res = parser.eval(code, *streams.io_chain, streams.job_group);
+    vars.pop();
+    return res.status.status_value() == STATUS_CMD_OK;
+}
+
 /// Bash uses 128 bytes for its chunk size. Very informal testing I did suggested that a smaller
 /// chunk size performed better. However, we're going to use the bash value under the assumption
 /// they've done more extensive testing.
 #define READ_CHUNK_SIZE 128
 
-/// Read from the fd in chunks until we see newline or null, as requested, is seen. This is only
-/// used when the fd is seekable (so not from a tty or pipe) and we're not reading a specific number
-/// of chars.
+/// Read from the fd in chunks until the terminator is seen, or until the end of input if there is
+/// none. This is only used when the fd is seekable (so not from a tty or pipe), we're not reading a
+/// specific number of chars and the terminator is a single byte.
 ///
 /// Returns an exit status.
-static int read_in_chunks(int fd, wcstring &buff, bool split_null) {
+static int read_in_chunks(int fd, wcstring &buff, maybe_t<wchar_t> terminator) {
     int exit_res = STATUS_CMD_OK;
     std::string str;
     bool eof = false;
@@ -271,7 +324,9 @@ static int read_in_chunks(int fd, wcstring &buff, bool split_null) {
             break;
         }
 
-        const char *end = std::find(inbuf, inbuf + bytes_read, split_null ? L'\0' : L'\n');
+        const char *end =
+            terminator ? std::find(inbuf, inbuf + bytes_read, static_cast<char>(*terminator))
+                       : inbuf + bytes_read;
         long bytes_consumed = end - inbuf;  // must be signed for use in lseek
         assert(bytes_consumed <= bytes_read);
         str.append(inbuf, bytes_consumed);
@@ -297,10 +352,12 @@ static int read_in_chunks(int fd, wcstring &buff, bool split_null) {
     return exit_res;
 }
 
-/// Read from the fd on char at a time until we've read the requested number of characters or a
-/// newline or null, as appropriate, is seen. This is inefficient so should only be used when the
-/// fd is not seekable.
-static int read_one_char_at_a_time(int fd, wcstring &buff, int nchars, bool split_null) {
+/// Read from the fd on char at a time until we've read the requested number of characters or the
+/// terminator, if any, is seen. This is inefficient so should only be used when the fd is not
+/// seekable. If \p out_short_read is given, it is set when the end of input came before we had
+/// \p nchars characters.
+static int read_one_char_at_a_time(int fd, wcstring &buff, int nchars, maybe_t<wchar_t> terminator,
+                                   bool *out_short_read = nullptr) {
     int exit_res = STATUS_CMD_OK;
     bool eof = false;
     size_t nbytes = 0;
@@ -336,8 +393,7 @@ static int read_one_char_at_a_time(int fd, wcstring &buff, int nchars, bool spli
             break;
         }
         if (eof) break;
-        if (!split_null && res == L'\n') break;
-        if (split_null && res == L'\0') break;
+        if (terminator && res == *terminator) break;
 
         buff.push_back(res);
         if (nchars > 0 && static_cast<size_t>(nchars) <= buff.size()) {
@@ -348,6 +404,9 @@ static int read_one_char_at_a_time(int fd, wcstring &buff, int nchars, bool spli
     if (buff.empty() && eof) {
         exit_res = STATUS_CMD_ERROR;
     }
+    if (out_short_read) {
+        *out_short_read = eof && nchars > 0 && static_cast<size_t>(nchars) > buff.size();
+    }
 
     return exit_res;
 }
@@ -361,6 +420,12 @@ static int validate_read_args(const wchar_t *cmd, read_cmd_opts_t &opts, int arg
         builtin_print_error_trailer(parser, streams.err, cmd);
         return STATUS_INVALID_ARGS;
     }
+    if (*opts.right_prompt && opts.right_prompt_str) {
+        streams.err.append_format(_(L"%ls: Options %ls and %ls cannot be used together\n"), cmd,
+                                  L"-R", L"--right-prompt-str");
+        builtin_print_error_trailer(parser, streams.err, cmd);
+        return STATUS_INVALID_ARGS;
+    }
 
     if (opts.have_delimiter && opts.one_line) {
         streams.err.append_format(_(L"%ls: Options %ls and %ls cannot be used together\n"), cmd,
@@ -372,6 +437,11 @@ static int validate_read_args(const wchar_t *cmd, read_cmd_opts_t &opts, int arg
                                   L"-z", L"--line");
         return STATUS_INVALID_ARGS;
     }
+    if (opts.have_until && (opts.one_line || opts.split_null)) {
+        streams.err.append_format(_(L"%ls: Options %ls and %ls cannot be used together\n"), cmd,
+                                  L"--until", opts.one_line ? L"--line" : L"-z");
+        return STATUS_INVALID_ARGS;
+    }
 
     if (opts.prompt_str) {
         opts.prompt_cmd = L"echo " + escape_string(opts.prompt_str, ESCAPE_ALL);
@@ -379,6 +449,10 @@ static int validate_read_args(const wchar_t *cmd, read_cmd_opts_t &opts, int arg
     } else if (!opts.prompt) {
         opts.prompt = DEFAULT_READ_PROMPT;
     }
+    if (opts.right_prompt_str) {
+        opts.right_prompt_cmd = L"echo " + escape_string(opts.right_prompt_str, ESCAPE_ALL);
+        opts.right_prompt = opts.right_prompt_cmd.c_str();
+    }
 
     if ((opts.place & ENV_UNEXPORT) && (opts.place & ENV_EXPORT)) {
         streams.err.append_format(BUILTIN_ERR_EXPUNEXP, cmd);
@@ -475,6 +549,20 @@ maybe_t<int> builtin_read(parser_t &parser, io_streams_t &streams, const wchar_t
         opts.shell = false;
     }
 
+    // The character that ends the input. With --until and --list, we read everything and split it
+    // on that character instead.
+    maybe_t<wchar_t> terminator = opts.split_null ? L'\0' : L'\n';
+    bool split_on_until = false;
+    if (opts.have_until) {
+        terminator = opts.until;
+        if (opts.array && opts.until) {
+            split_on_until = true;
+            terminator = none();
+        }
+    }
+    // Reading in chunks looks for a byte, so it only handles single-byte terminators.
+    bool can_read_in_chunks = !terminator || *terminator < 0x80;
+
     const wchar_t *const *var_ptr = argv;
     auto vars_left = [&]() { return argv + argc - var_ptr; };
     auto clear_remaining_vars = [&]() {
@@ -484,6 +572,10 @@ maybe_t<int> builtin_read(parser_t &parser, io_streams_t &streams, const wchar_t
         }
     };
 
+    // Whether input ended before we read the requested number of chars. Then we still set the vars,
+    // but return an error.
+    bool short_read = false;
+
     // Normally, we either consume a line of input or all available input. But if we are reading a
     // line at a time, we need a middle ground where we only consume as many lines as we need to
     // fill the given vars.
@@ -493,17 +585,28 @@ maybe_t<int> builtin_read(parser_t &parser, io_streams_t &streams, const wchar_t
         // TODO: Determine if the original set of conditions for interactive reads should be
         // reinstated: if (isatty(0) && streams.stdin_fd == STDIN_FILENO && !split_null) {
         int stream_stdin_is_a_tty = isatty(streams.stdin_fd);
-        if (stream_stdin_is_a_tty && !opts.split_null) {
-            // Read interactively using reader_readline(). This does not support splitting on null.
-            exit_res =
-                read_interactive(parser, buff, opts.nchars, opts.shell, opts.silent, opts.prompt,
-                                 opts.right_prompt, opts.commandline, streams.stdin_fd);
-        } else if (!opts.nchars && !stream_stdin_is_a_tty &&
+        if (stream_stdin_is_a_tty && terminator == L'\n') {
+            // Read interactively using reader_readline(). This only supports reading a line.
+            // With --validate, ask again until the input is accepted.
+            int tries = 0;
+            for (;;) {
+                exit_res = read_interactive(parser, buff, opts.nchars, opts.shell, opts.silent,
+                                            opts.prompt, opts.right_prompt, opts.commandline,
+                                            streams.stdin_fd);
+                if (exit_res != STATUS_CMD_OK || !opts.validate) break;
+                if (validate_interactive_input(parser, streams, opts.validate, buff)) break;
+                if (opts.max_tries > 0 && ++tries >= opts.max_tries) {
+                    exit_res = STATUS_CMD_ERROR;
+                    break;
+                }
+                buff.clear();
+            }
+        } else if (!opts.nchars && !stream_stdin_is_a_tty && can_read_in_chunks &&
                    lseek(streams.stdin_fd, 0, SEEK_CUR) != -1) {
-            exit_res = read_in_chunks(streams.stdin_fd, buff, opts.split_null);
+            exit_res = read_in_chunks(streams.stdin_fd, buff, terminator);
         } else {
-            exit_res =
-                read_one_char_at_a_time(streams.stdin_fd, buff, opts.nchars, opts.split_null);
+            exit_res = read_one_char_at_a_time(streams.stdin_fd, buff, opts.nchars, terminator,
+                                               &short_read);
         }
 
         if (exit_res != STATUS_CMD_OK) {
@@ -513,7 +616,18 @@ maybe_t<int> builtin_read(parser_t &parser, io_streams_t &streams, const wchar_t
 
         if (opts.to_stdout) {
             streams.out.append(buff);
-            return exit_res;
+            return short_read ? STATUS_CMD_ERROR : exit_res;
+        }
+
+        if (split_on_until) {
+            // Every terminated record becomes an element; a final terminator does not start
+            // another one.
+            wcstring_list_t records;
+            wcstring sep(1, *opts.until);
+            split_about(buff.begin(), buff.end(), sep.begin(), sep.end(), &records);
+            if (!buff.empty() && buff.back() == sep.front()) records.pop_back();
+            parser.set_var_and_fire(*var_ptr++, opts.place, std::move(records));
+            continue;
         }
 
         if (opts.tokenize) {
@@ -632,12 +746,12 @@ maybe_t<int> builtin_read(parser_t &parser, io_streams_t &streams, const wchar_t
                 }
             }
         }
-    } while (opts.one_line && vars_left());
+    } while (opts.one_line && vars_left() && !short_read);
 
     if (!opts.array) {
         // In case there were more args than splits
         clear_remaining_vars();
     }
 
-    return exit_res;
+    return short_read ? STATUS_CMD_ERROR : exit_res;
 }
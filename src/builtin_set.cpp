@@ -14,6 +14,7 @@
 #include <memory>
 #include <set>
 #include <string>
+#include <unordered_set>
 #include <vector>
 
 #include "builtin.h"
@@ -45,6 +46,7 @@ struct set_cmd_opts_t {
     bool shorten_ok = true;
     bool append = false;
     bool prepend = false;
+    bool dedupe = false;
     bool preserve_failure_exit_status = true;
 };
 
@@ -52,6 +54,7 @@ struct set_cmd_opts_t {
 enum {
     opt_path = 1,
     opt_unpath = 2,
+    opt_dedupe = 3,
 };
 
 // Variables used for parsing the argument list. This command is atypical in using the "+"
@@ -66,7 +69,8 @@ static const struct woption long_options[] = {
     {L"query", no_argument, nullptr, 'q'},     {L"show", no_argument, nullptr, 'S'},
     {L"append", no_argument, nullptr, 'a'},    {L"prepend", no_argument, nullptr, 'p'},
     {L"path", no_argument, nullptr, opt_path}, {L"unpath", no_argument, nullptr, opt_unpath},
-    {L"help", no_argument, nullptr, 'h'},      {nullptr, 0, nullptr, 0}};
+    {L"dedupe", no_argument, nullptr, opt_dedupe}, {L"help", no_argument, nullptr, 'h'},
+    {nullptr, 0, nullptr, 0}};
 
 // Hint for invalid path operation with a colon.
 #define BUILTIN_SET_PATH_ERROR _(L"%ls: Warning: $%ls entry \"%ls\" is not valid (%s)\n")
@@ -139,6 +143,10 @@ static int parse_cmd_opts(set_cmd_opts_t &opts, int *optind,  //!OCLINT(high ncs
                 opts.unpathvar = true;
                 break;
             }
+            case opt_dedupe: {
+                opts.dedupe = true;
+                break;
+            }
             case 'U': {
                 opts.universal = true;
                 break;
@@ -223,6 +231,13 @@ static int validate_cmd_opts(const wchar_t *cmd,
         return STATUS_INVALID_ARGS;
     }
 
+    // Removing duplicates only makes sense when setting a value.
+    if (opts.dedupe && (opts.erase || opts.list || opts.query || opts.show)) {
+        streams.err.append_format(BUILTIN_ERR_COMBO, cmd);
+        builtin_print_error_trailer(parser, streams.err, cmd);
+        return STATUS_INVALID_ARGS;
+    }
+
     if (argc == 0 && opts.erase) {
         streams.err.append_format(BUILTIN_SET_ERASE_NO_VAR, cmd);
         builtin_print_error_trailer(parser, streams.err, cmd);
@@ -306,7 +321,7 @@ static bool validate_path_warning_on_colons(const wchar_t *cmd,
 }
 
 static void handle_env_return(int retval, const wchar_t *cmd, const wcstring &key,
-                              io_streams_t &streams) {
+                              io_streams_t &streams, const wcstring_list_t *val = nullptr) {
     switch (retval) {
         case ENV_OK: {
             break;
@@ -323,9 +338,15 @@ static void handle_env_return(int retval, const wchar_t *cmd, const wcstring &ke
             break;
         }
         case ENV_INVALID: {
-            streams.err.append_format(
-                _(L"%ls: Tried to modify the special variable '%ls' to an invalid value\n"), cmd,
-                key.c_str());
+            if (val) {
+                streams.err.append_format(_(L"%ls: Tried to modify the special variable '%ls' to "
+                                            L"an invalid value '%ls'\n"),
+                                          cmd, key.c_str(), join_strings(*val, L' ').c_str());
+            } else {
+                streams.err.append_format(
+                    _(L"%ls: Tried to modify the special variable '%ls' to an invalid value\n"),
+                    cmd, key.c_str());
+            }
             break;
         }
         case ENV_NOT_FOUND: {
@@ -348,8 +369,9 @@ static int env_set_reporting_errors(const wchar_t *cmd, const wcstring &key, int
         return STATUS_CMD_ERROR;
     }
 
-    int retval = vars.set(key, scope | ENV_USER, std::move(list), evts);
-    handle_env_return(retval, cmd, key, streams);
+    // Keep the value so we can show it if it is rejected.
+    int retval = vars.set(key, scope | ENV_USER, list, evts);
+    handle_env_return(retval, cmd, key, streams, &list);
 
     return retval;
 }
@@ -384,54 +406,12 @@ maybe_t<split_var_t> split_var_and_indexes(const wchar_t *arg, env_mode_flags_t
         return res;
     }
 
-    long varsize = res.varsize();
-    const wchar_t *p = open_bracket + 1;
-    while (*p != L']') {
-        const wchar_t *end;
-        long l_ind;
-        if (res.indexes.empty() && p[0] == L'.' && p[1] == L'.') {
-            // If we are at the first index expression, a missing start-index means the range starts
-            // at the first item.
-            l_ind = 1;  // first index
-        } else {
-            const wchar_t *end = nullptr;
-            l_ind = fish_wcstol(p, &end);
-            if (errno > 0) {  // ignore errno == -1 meaning the int did not end with a '\0'
-                streams.err.append_format(_(L"%ls: Invalid index starting at '%ls'\n"), L"set",
-                                          res.varname.c_str());
-                return none();
-            }
-            p = end;
-        }
-
-        // Convert negative index to a positive index.
-        if (l_ind < 0) l_ind = varsize + l_ind + 1;
-
-        if (p[0] == L'.' && p[1] == L'.') {
-            p += 2;
-            long l_ind2;
-            // If we are at the last index range expression, a missing end-index means the range
-            // spans until the last item.
-            if (res.indexes.empty() && *p == L']') {
-                l_ind2 = -1;
-            } else {
-                l_ind2 = fish_wcstol(p, &end);
-                if (errno > 0) {  // ignore errno == -1 meaning there was text after the int
-                    return none();
-                }
-                p = end;
-            }
-
-            // Convert negative index to a positive index.
-            if (l_ind2 < 0) l_ind2 = varsize + l_ind2 + 1;
-
-            int direction = l_ind2 < l_ind ? -1 : 1;
-            for (long jjj = l_ind; jjj * direction <= l_ind2 * direction; jjj += direction) {
-                res.indexes.push_back(jjj);
-            }
-        } else {
-            res.indexes.push_back(l_ind);
-        }
+    size_t bad_pos = parse_slice(open_bracket, nullptr, res.indexes,
+                                 static_cast<size_t>(res.varsize()), slice_mode_t::write);
+    if (bad_pos != 0) {
+        streams.err.append_format(_(L"%ls: Invalid index starting at '%ls'\n"), L"set",
+                                  open_bracket + bad_pos);
+        return none();
     }
     return res;
 }
@@ -519,6 +499,7 @@ static int builtin_set_list(const wchar_t *cmd, set_cmd_opts_t &opts, int argc,
 }
 
 // Query mode. Return the number of variables that do NOT exist out of the specified variables.
+// With --long, also print the variables (or elements) that do exist, like `name=value`.
 static int builtin_set_query(const wchar_t *cmd, set_cmd_opts_t &opts, int argc,
                              const wchar_t **argv, parser_t &parser, io_streams_t &streams) {
     int retval = 0;
@@ -533,12 +514,27 @@ static int builtin_set_query(const wchar_t *cmd, set_cmd_opts_t &opts, int argc,
 
         if (split->indexes.empty()) {
             // No indexes, just increment if our variable is missing.
-            if (!split->var) retval++;
+            if (!split->var) {
+                retval++;
+            } else if (!opts.shorten_ok) {
+                streams.out.append_format(L"%ls=%ls\n", argv[i],
+                                          expand_escape_variable(*split->var).c_str());
+            }
         } else {
             // Increment for every index out of range.
             long varsize = split->varsize();
+            wcstring_list_t found;
             for (long idx : split->indexes) {
-                if (idx < 1 || idx > varsize) retval++;
+                if (idx < 1 || idx > varsize) {
+                    retval++;
+                } else {
+                    found.push_back(split->var->as_list().at(idx - 1));
+                }
+            }
+            if (!opts.shorten_ok && !found.empty()) {
+                env_var_t elements{std::move(found), 0};
+                streams.out.append_format(L"%ls=%ls\n", argv[i],
+                                          expand_escape_variable(elements).c_str());
             }
         }
     }
@@ -573,7 +569,14 @@ static void show_scope(const wchar_t *var_name, int scope, io_streams_t &streams
     }
 
     const wchar_t *exportv = var->exports() ? _(L"exported") : _(L"unexported");
-    const wchar_t *pathvarv = var->is_pathvar() ? _(L" a path variable") : L"";
+    // Say whether the path variable flag was chosen with --path or --unpath, or inferred.
+    const wchar_t *pathvarv = L"";
+    if (var->is_pathvar()) {
+        pathvarv = var->pathvar_is_explicit() ? _(L" a path variable")
+                                              : _(L" a path variable (inferred)");
+    } else if (var->pathvar_is_explicit()) {
+        pathvarv = _(L" not a path variable");
+    }
     wcstring_list_t vals = var->as_list();
     streams.out.append_format(_(L"$%ls: set in %ls scope, %ls,%ls with %d elements\n"), var_name,
                               scope_name, exportv, pathvarv, vals.size());
@@ -805,6 +808,14 @@ static int builtin_set_set(const wchar_t *cmd, set_cmd_opts_t &opts, int argc, c
         new_values = new_var_values_by_index(*split, argc, argv);
     }
 
+    if (opts.dedupe) {
+        // Keep the first occurrence of each element.
+        std::unordered_set<wcstring> seen;
+        auto is_dup = [&](const wcstring &val) { return !seen.insert(val).second; };
+        new_values.erase(std::remove_if(new_values.begin(), new_values.end(), is_dup),
+                         new_values.end());
+    }
+
     bool have_shadowing_global = check_global_scope_exists(cmd, opts, split->varname, streams, parser);
     // Set the value back in the variable stack and fire any events.
     std::vector<event_t> evts;
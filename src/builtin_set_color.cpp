@@ -33,8 +33,16 @@
 
 class parser_t;
 
+/// \return the extended terminfo string capability \p name, or nullptr if the terminal lacks it.
+static const char *extended_capability(const char *name) {
+    const char *val = tigetstr(const_cast<char *>(name));
+    if (val == reinterpret_cast<const char *>(-1)) return nullptr;
+    return val;
+}
+
 static void print_modifiers(outputter_t &outp, bool bold, bool underline, bool italics, bool dim,
-                            bool reverse, rgb_color_t bg) {
+                            bool reverse, bool blink, bool strikethrough, bool overline,
+                            rgb_color_t bg) {
     if (bold && enter_bold_mode) {
         // These casts are needed to work with different curses implementations.
         writembs_nofail(outp, tparm(const_cast<char *>(enter_bold_mode)));
@@ -52,6 +60,20 @@ static void print_modifiers(outputter_t &outp, bool bold, bool underline, bool i
         writembs_nofail(outp, enter_dim_mode);
     }
 
+    if (blink && enter_blink_mode) {
+        writembs_nofail(outp, enter_blink_mode);
+    }
+
+    // There are no standard capabilities for these, but many terminal descriptions have these
+    // extended ones.
+    if (strikethrough) {
+        if (const char *smxx = extended_capability("smxx")) writembs_nofail(outp, smxx);
+    }
+
+    if (overline) {
+        if (const char *smol = extended_capability("Smol")) writembs_nofail(outp, smol);
+    }
+
     if (reverse && enter_reverse_mode) {
         writembs_nofail(outp, enter_reverse_mode);
     } else if (reverse && enter_standout_mode) {
@@ -63,11 +85,13 @@ static void print_modifiers(outputter_t &outp, bool bold, bool underline, bool i
 }
 
 static void print_colors(io_streams_t &streams, bool bold, bool underline, bool italics, bool dim,
-                         bool reverse, rgb_color_t bg) {
+                         bool reverse, bool blink, bool strikethrough, bool overline,
+                         rgb_color_t bg) {
     outputter_t outp;
     for (const auto &color_name : rgb_color_t::named_color_names()) {
         if (!streams.out_is_redirected && isatty(STDOUT_FILENO)) {
-            print_modifiers(outp, bold, underline, italics, dim, reverse, bg);
+            print_modifiers(outp, bold, underline, italics, dim, reverse, blink, strikethrough,
+                            overline, bg);
             rgb_color_t color = rgb_color_t(color_name);
             outp.set_color(color, rgb_color_t::none());
             if (!bg.is_none()) {
@@ -94,6 +118,9 @@ static const struct woption long_options[] = {{L"background", required_argument,
                                               {L"italics", no_argument, nullptr, 'i'},
                                               {L"dim", no_argument, nullptr, 'd'},
                                               {L"reverse", no_argument, nullptr, 'r'},
+                                              {L"blink", no_argument, nullptr, 1},
+                                              {L"strikethrough", no_argument, nullptr, 2},
+                                              {L"overline", no_argument, nullptr, 3},
                                               {L"version", no_argument, nullptr, 'v'},
                                               {L"print-colors", no_argument, nullptr, 'c'},
                                               {nullptr, 0, nullptr, 0}};
@@ -135,7 +162,7 @@ maybe_t<int> builtin_set_color(parser_t &parser, io_streams_t &streams, const wc
 
     const wchar_t *bgcolor = nullptr;
     bool bold = false, underline = false, italics = false, dim = false, reverse = false,
-         print = false;
+         blink = false, strikethrough = false, overline = false, print = false;
 
     // Parse options to obtain the requested operation and the modifiers.
     int opt;
@@ -170,6 +197,18 @@ maybe_t<int> builtin_set_color(parser_t &parser, io_streams_t &streams, const wc
                 underline = true;
                 break;
             }
+            case 1: {
+                blink = true;
+                break;
+            }
+            case 2: {
+                strikethrough = true;
+                break;
+            }
+            case 3: {
+                overline = true;
+                break;
+            }
             case 'c': {
                 print = true;
                 break;
@@ -202,7 +241,8 @@ maybe_t<int> builtin_set_color(parser_t &parser, io_streams_t &streams, const wc
         if (bgcolor && bg.is_special()) {
             bg = rgb_color_t(L"");
         }
-        print_colors(streams, bold, underline, italics, dim, reverse, bg);
+        print_colors(streams, bold, underline, italics, dim, reverse, blink, strikethrough,
+                     overline, bg);
         return STATUS_CMD_OK;
     }
 
@@ -229,7 +269,8 @@ maybe_t<int> builtin_set_color(parser_t &parser, io_streams_t &streams, const wc
     }
     outputter_t outp;
 
-    print_modifiers(outp, bold, underline, italics, dim, reverse, bg);
+    print_modifiers(outp, bold, underline, italics, dim, reverse, blink, strikethrough, overline,
+                    bg);
 
     if (bgcolor != nullptr && bg.is_normal()) {
         writembs_nofail(outp, tparm(const_cast<char *>(exit_attribute_mode)));
@@ -3,12 +3,15 @@
 
 #include "builtin_status.h"
 
+#include <unistd.h>
+
 #include <cstddef>
 #include <cwchar>
 #include <string>
 
 #include "builtin.h"
 #include "common.h"
+#include "env.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "future_feature_flags.h"
 #include "io.h"
@@ -33,10 +36,12 @@ enum status_cmd_t {
     STATUS_IS_INTERACTIVE_JOB_CTRL,
     STATUS_IS_LOGIN,
     STATUS_IS_NO_JOB_CTRL,
+    STATUS_IS_TTY,
     STATUS_LINE_NUMBER,
     STATUS_SET_JOB_CONTROL,
     STATUS_STACK_TRACE,
     STATUS_TEST_FEATURE,
+    STATUS_UNIVERSAL_FILE,
     STATUS_UNDEF
 };
 
@@ -62,14 +67,19 @@ const enum_map<status_cmd_t> status_enum_map[] = {
     {STATUS_IS_INTERACTIVE_JOB_CTRL, L"is-interactive-job-control"},
     {STATUS_IS_LOGIN, L"is-login"},
     {STATUS_IS_NO_JOB_CTRL, L"is-no-job-control"},
+    {STATUS_IS_TTY, L"is-tty"},
     {STATUS_SET_JOB_CONTROL, L"job-control"},
     {STATUS_LINE_NUMBER, L"line-number"},
     {STATUS_STACK_TRACE, L"print-stack-trace"},
     {STATUS_STACK_TRACE, L"stack-trace"},
     {STATUS_TEST_FEATURE, L"test-feature"},
+    {STATUS_UNIVERSAL_FILE, L"universal-file"},
     {STATUS_UNDEF, nullptr}};
 #define status_enum_map_len (sizeof status_enum_map / sizeof *status_enum_map)
 
+// Long options that modify a subcommand. Their values must not collide with status_cmd_t.
+enum { STATUS_OPT_JSON = STATUS_UNDEF + 1 };
+
 #define CHECK_FOR_UNEXPECTED_STATUS_ARGS(status_cmd)                                        \
     if (!args.empty()) {                                                                    \
         const wchar_t *subcmd_str = enum_to_str(status_cmd, status_enum_map);               \
@@ -95,12 +105,40 @@ static maybe_t<job_control_t> job_control_str_to_mode(const wchar_t *mode, const
     return none();
 }
 
+/// Return whether \p fd, as seen by the builtin, refers to a terminal.
+/// This follows the builtin's redirections, so e.g. `status is-tty stdout > file` is false.
+static bool fd_is_tty(int fd, const io_streams_t &streams) {
+    if (fd == STDIN_FILENO) {
+        return streams.stdin_fd >= 0 && isatty(streams.stdin_fd);
+    }
+    if (!streams.io_chain) return isatty(fd);
+    // Resolve fd-to-fd redirections like 2>&1. Bound the walk by the chain size, in case of
+    // cycles.
+    for (size_t hops = 0; hops <= streams.io_chain->size(); hops++) {
+        auto io = streams.io_chain->io_for_fd(fd);
+        if (!io) return isatty(fd);
+        switch (io->io_mode) {
+            case io_mode_t::file:
+                return isatty(io->source_fd);
+            case io_mode_t::fd:
+                fd = io->source_fd;
+                break;
+            case io_mode_t::pipe:
+            case io_mode_t::bufferfill:
+            case io_mode_t::close:
+                return false;
+        }
+    }
+    return false;
+}
+
 struct status_cmd_opts_t {
     int level{1};
     maybe_t<job_control_t> new_job_control_mode{};
     const wchar_t *feature_name{};
     status_cmd_t status_cmd{STATUS_UNDEF};
     bool print_help{false};
+    bool json{false};
 };
 
 /// Note: Do not add new flags that represent subcommands. We're encouraging people to switch to
@@ -122,6 +160,7 @@ static const struct woption long_options[] = {
     {L"is-login", no_argument, nullptr, 'l'},
     {L"is-no-job-control", no_argument, nullptr, STATUS_IS_NO_JOB_CTRL},
     {L"job-control", required_argument, nullptr, 'j'},
+    {L"json", no_argument, nullptr, STATUS_OPT_JSON},
     {L"level", required_argument, nullptr, 'L'},
     {L"line", no_argument, nullptr, 'n'},
     {L"line-number", no_argument, nullptr, 'n'},
@@ -255,6 +294,10 @@ static int parse_cmd_opts(status_cmd_opts_t &opts, int *optind,  //!OCLINT(high
                 opts.print_help = true;
                 break;
             }
+            case STATUS_OPT_JSON: {
+                opts.json = true;
+                break;
+            }
             case ':': {
                 builtin_missing_argument(parser, streams, cmd, argv[w.woptind - 1]);
                 return STATUS_INVALID_ARGS;
@@ -306,6 +349,12 @@ maybe_t<int> builtin_status(parser_t &parser, io_streams_t &streams, const wchar
     // Every argument that we haven't consumed already is an argument for a subcommand.
     const wcstring_list_t args(argv + optind, argv + argc);
 
+    if (opts.json && opts.status_cmd != STATUS_STACK_TRACE) {
+        streams.err.append_format(BUILTIN_ERR_COMBO2, cmd,
+                                  _(L"--json can only be used with stack-trace"));
+        return STATUS_INVALID_ARGS;
+    }
+
     switch (opts.status_cmd) {
         case STATUS_UNDEF: {
             CHECK_FOR_UNEXPECTED_STATUS_ARGS(opts.status_cmd)
@@ -435,9 +484,36 @@ maybe_t<int> builtin_status(parser_t &parser, io_streams_t &streams, const wchar
             retval = get_job_control_mode() != job_control_t::none;
             break;
         }
+        case STATUS_IS_TTY: {
+            if (args.size() > 1) {
+                const wchar_t *subcmd_str = enum_to_str(opts.status_cmd, status_enum_map);
+                streams.err.append_format(BUILTIN_ERR_ARG_COUNT2, cmd, subcmd_str, 1, args.size());
+                return STATUS_INVALID_ARGS;
+            }
+            int fd = STDIN_FILENO;
+            if (!args.empty()) {
+                const wcstring &arg = args.front();
+                if (arg == L"stdin") {
+                    fd = STDIN_FILENO;
+                } else if (arg == L"stdout") {
+                    fd = STDOUT_FILENO;
+                } else if (arg == L"stderr") {
+                    fd = STDERR_FILENO;
+                } else {
+                    fd = fish_wcstoi(arg.c_str());
+                    if (errno || fd < 0) {
+                        streams.err.append_format(_(L"%ls: Invalid file descriptor '%ls'\n"), cmd,
+                                                  arg.c_str());
+                        return STATUS_INVALID_ARGS;
+                    }
+                }
+            }
+            retval = fd_is_tty(fd, streams) ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+            break;
+        }
         case STATUS_STACK_TRACE: {
             CHECK_FOR_UNEXPECTED_STATUS_ARGS(opts.status_cmd)
-            streams.out.append(parser.stack_trace());
+            streams.out.append(opts.json ? parser.stack_trace_json() : parser.stack_trace());
             break;
         }
         case STATUS_CURRENT_CMD: {
@@ -459,6 +535,18 @@ maybe_t<int> builtin_status(parser_t &parser, io_streams_t &streams, const wchar
             streams.out.push_back(L'\n');
             break;
         }
+        case STATUS_UNIVERSAL_FILE: {
+            CHECK_FOR_UNEXPECTED_STATUS_ARGS(opts.status_cmd)
+            wcstring path = env_universal_file_path();
+            if (path.empty()) {
+                // Universal variables are not in use.
+                retval = STATUS_CMD_ERROR;
+                break;
+            }
+            streams.out.append(path);
+            streams.out.push_back(L'\n');
+            break;
+        }
     }
 
     return retval;
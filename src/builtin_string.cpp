@@ -23,6 +23,7 @@
 #include <vector>
 
 #include "builtin.h"
+#include "builtin_string.h"
 #include "common.h"
 #include "env.h"
 #include "fallback.h"  // IWYU pragma: keep
@@ -64,78 +65,20 @@ static const wchar_t *string_get_arg_argv(int *argidx, const wchar_t *const *arg
     return argv && argv[*argidx] ? argv[(*argidx)++] : nullptr;
 }
 
-// A helper type for extracting arguments from either argv or stdin.
-namespace {
-class arg_iterator_t {
-    // The list of arguments passed to the string builtin.
-    const wchar_t *const *argv_;
-    // If using argv, index of the next argument to return.
-    int argidx_;
-    // If not using argv, a string to store bytes that have been read but not yet returned.
-    std::string buffer_;
-    // If set, when reading from a stream, split on newlines.
-    const bool split_;
-    // Backing storage for the next() string.
-    wcstring storage_;
-    const io_streams_t &streams_;
-
-    /// Reads the next argument from stdin, returning true if an argument was produced and false if
-    /// not. On true, the string is stored in storage_.
-    bool get_arg_stdin() {
-        assert(string_args_from_stdin(streams_) && "should not be reading from stdin");
-        assert(streams_.stdin_fd >= 0 && "should have a valid fd");
-        // Read in chunks from fd until buffer has a line (or the end if split_ is unset).
-        size_t pos;
-        while (!split_ || (pos = buffer_.find('\n')) == std::string::npos) {
-            char buf[STRING_CHUNK_SIZE];
-            long n = read_blocked(streams_.stdin_fd, buf, STRING_CHUNK_SIZE);
-            if (n == 0) {
-                // If we still have buffer contents, flush them,
-                // in case there was no trailing sep.
-                if (buffer_.empty()) return false;
-                storage_ = str2wcstring(buffer_);
-                buffer_.clear();
-                return true;
-            }
-            if (n == -1) {
-                // Some error happened. We can't do anything about it,
-                // so ignore it.
-                // (read_blocked already retries for EAGAIN and EINTR)
-                storage_ = str2wcstring(buffer_);
-                buffer_.clear();
-                return false;
-            }
-            buffer_.append(buf, n);
-        }
-
-        // Split the buffer on the sep and return the first part.
-        storage_ = str2wcstring(buffer_, pos);
-        buffer_.erase(0, pos + 1);
-        return true;
-    }
-
-   public:
-    arg_iterator_t(const wchar_t *const *argv, int argidx, const io_streams_t &streams,
-                   bool split = true)
-        : argv_(argv), argidx_(argidx), split_(split), streams_(streams) {}
-
-    const wcstring *nextstr() {
-        if (string_args_from_stdin(streams_)) {
-            return get_arg_stdin() ? &storage_ : nullptr;
-        }
-        if (auto arg = string_get_arg_argv(&argidx_, argv_)) {
-            storage_ = arg;
-            return &storage_;
-        } else {
-            return nullptr;
-        }
-    }
+// A range of fields given to --fields, inclusive. A single field has begin == end.
+// Fields start at 1, and negative fields count from the end.
+struct field_range_t {
+    int begin;
+    int end;
 };
-}  // namespace
+
+/// How `string distance` compares strings.
+enum class distance_algorithm_t { levenshtein, jaro_winkler };
 
 // This is used by the string subcommands to communicate with the option parser which flags are
 // valid and get the result of parsing the command for flags.
 struct options_t {  //!OCLINT(too many fields)
+    bool algorithm_valid = false;
     bool all_valid = false;
     bool char_to_pad_valid = false;
     bool chars_to_trim_valid = false;
@@ -161,6 +104,8 @@ struct options_t {  //!OCLINT(too many fields)
     bool fields_valid = false;
     bool allow_empty_valid = false;
     bool width_valid = false;
+    bool upper_valid = false;
+    bool validate_refs_valid = false;
 
     bool all = false;
     bool entire = false;
@@ -177,6 +122,8 @@ struct options_t {  //!OCLINT(too many fields)
     bool no_empty = false;
     bool no_trim_newlines = false;
     bool allow_empty = false;
+    bool upper = false;
+    bool validate_refs = false;
 
     long count = 0;
     long length = 0;
@@ -187,7 +134,9 @@ struct options_t {  //!OCLINT(too many fields)
 
     wchar_t char_to_pad = L' ';
 
-    std::vector<int> fields;
+    distance_algorithm_t algorithm = distance_algorithm_t::levenshtein;
+
+    std::vector<field_range_t> fields;
 
     const wchar_t *chars_to_trim = L" \f\n\r\t\v";
     const wchar_t *arg1 = nullptr;
@@ -221,6 +170,35 @@ static int handle_flag_1(const wchar_t **argv, parser_t &parser, io_streams_t &s
     return STATUS_INVALID_ARGS;
 }
 
+/// This handles the `--validate-refs` flag.
+static int handle_flag_2(const wchar_t **argv, parser_t &parser, io_streams_t &streams,
+                         const wgetopter_t &w, options_t *opts) {
+    if (opts->validate_refs_valid) {
+        opts->validate_refs = true;
+        return STATUS_CMD_OK;
+    }
+    string_unknown_option(parser, streams, argv[0], argv[w.woptind - 1]);
+    return STATUS_INVALID_ARGS;
+}
+
+/// This handles the `--algorithm=xxx` flag.
+static int handle_flag_3(const wchar_t **argv, parser_t &parser, io_streams_t &streams,
+                         const wgetopter_t &w, options_t *opts) {
+    if (opts->algorithm_valid) {
+        if (std::wcscmp(w.woptarg, L"levenshtein") == 0) {
+            opts->algorithm = distance_algorithm_t::levenshtein;
+        } else if (std::wcscmp(w.woptarg, L"jaro-winkler") == 0) {
+            opts->algorithm = distance_algorithm_t::jaro_winkler;
+        } else {
+            string_error(streams, _(L"%ls: Invalid algorithm '%ls'\n"), argv[0], w.woptarg);
+            return STATUS_INVALID_ARGS;
+        }
+        return STATUS_CMD_OK;
+    }
+    string_unknown_option(parser, streams, argv[0], argv[w.woptind - 1]);
+    return STATUS_INVALID_ARGS;
+}
+
 static int handle_flag_N(const wchar_t **argv, parser_t &parser, io_streams_t &streams,
                          const wgetopter_t &w, options_t *opts) {
     if (opts->no_newline_valid) {
@@ -292,47 +270,24 @@ static int handle_flag_f(const wchar_t **argv, parser_t &parser, io_streams_t &s
         return STATUS_CMD_OK;
     } else if (opts->fields_valid) {
         for (const wcstring &s : split_string(w.woptarg, L',')) {
-            wcstring_list_t range = split_string(s, L'-');
-            if (range.size() == 2) {
-                int begin = fish_wcstoi(range.at(0).c_str());
-                if (begin <= 0 || errno == ERANGE) {
-                    string_error(streams, _(L"%ls: Invalid range value for field '%ls'\n"), argv[0],
-                                 w.woptarg);
-                    return STATUS_INVALID_ARGS;
-                } else if (errno) {
-                    string_error(streams, BUILTIN_ERR_NOT_NUMBER, argv[0], w.woptarg);
-                    return STATUS_INVALID_ARGS;
-                }
-                int end = fish_wcstoi(range.at(1).c_str());
-                if (end <= 0 || errno == ERANGE) {
-                    string_error(streams, _(L"%ls: Invalid range value for field '%ls'\n"), argv[0],
-                                 w.woptarg);
-                    return STATUS_INVALID_ARGS;
-                } else if (errno) {
-                    string_error(streams, BUILTIN_ERR_NOT_NUMBER, argv[0], w.woptarg);
-                    return STATUS_INVALID_ARGS;
-                }
-                if (begin <= end) {
-                    for (int i = begin; i <= end; i++) {
-                        opts->fields.push_back(i);
-                    }
-                } else {
-                    for (int i = begin; i >= end; i--) {
-                        opts->fields.push_back(i);
-                    }
-                }
-            } else {
-                int field = fish_wcstoi(s.c_str());
-                if (field <= 0 || errno == ERANGE) {
-                    string_error(streams, _(L"%ls: Invalid fields value '%ls'\n"), argv[0],
-                                 w.woptarg);
-                    return STATUS_INVALID_ARGS;
-                } else if (errno) {
-                    string_error(streams, BUILTIN_ERR_NOT_NUMBER, argv[0], w.woptarg);
-                    return STATUS_INVALID_ARGS;
-                }
-                opts->fields.push_back(field);
+            // A field is a nonzero number, negative ones counting from the end, or a range of two
+            // fields like "1-3" or "-3--1".
+            const wchar_t *range_end = nullptr;
+            int begin = fish_wcstoi(s.c_str(), &range_end);
+            int end = begin;
+            if (errno == -1 && *range_end == L'-') {
+                end = fish_wcstoi(range_end + 1);
             }
+            // A malformed range like "1-" is not a number, like any other malformed field.
+            if (errno && errno != ERANGE) {
+                string_error(streams, BUILTIN_ERR_NOT_NUMBER, argv[0], w.woptarg);
+                return STATUS_INVALID_ARGS;
+            } else if (begin == 0 || end == 0 || errno == ERANGE) {
+                string_error(streams, _(L"%ls: Invalid fields value '%ls'\n"), argv[0],
+                             w.woptarg);
+                return STATUS_INVALID_ARGS;
+            }
+            opts->fields.push_back({begin, end});
         }
         return STATUS_CMD_OK;
     }
@@ -456,6 +411,16 @@ static int handle_flag_s(const wchar_t **argv, parser_t &parser, io_streams_t &s
     return STATUS_INVALID_ARGS;
 }
 
+static int handle_flag_u(const wchar_t **argv, parser_t &parser, io_streams_t &streams,
+                         const wgetopter_t &w, options_t *opts) {
+    if (opts->upper_valid) {
+        opts->upper = true;
+        return STATUS_CMD_OK;
+    }
+    string_unknown_option(parser, streams, argv[0], argv[w.woptind - 1]);
+    return STATUS_INVALID_ARGS;
+}
+
 static int handle_flag_v(const wchar_t **argv, parser_t &parser, io_streams_t &streams,
                          const wgetopter_t &w, options_t *opts) {
     if (opts->invert_valid) {
@@ -514,13 +479,15 @@ static wcstring construct_short_opts(options_t *opts) {  //!OCLINT(high npath co
     if (opts->fields_valid) short_opts.append(L"f:");
     if (opts->allow_empty_valid) short_opts.append(L"a");
     if (opts->width_valid) short_opts.append(L"w:");
+    if (opts->upper_valid) short_opts.append(L"u");
     return short_opts;
 }
 
 // Note that several long flags share the same short flag. That is okay. The caller is expected
 // to indicate that a max of one of the long flags sharing a short flag is valid.
 // Remember: adjust share/completions/string.fish when `string` options change
-static const struct woption long_options[] = {{L"all", no_argument, nullptr, 'a'},
+static const struct woption long_options[] = {{L"algorithm", required_argument, nullptr, 3},
+                                              {L"all", no_argument, nullptr, 'a'},
                                               {L"chars", required_argument, nullptr, 'c'},
                                               {L"count", required_argument, nullptr, 'n'},
                                               {L"entire", no_argument, nullptr, 'e'},
@@ -544,13 +511,16 @@ static const struct woption long_options[] = {{L"all", no_argument, nullptr, 'a'
                                               {L"fields", required_argument, nullptr, 'f'},
                                               {L"allow-empty", no_argument, nullptr, 'a'},
                                               {L"width", required_argument, nullptr, 'w'},
+                                              {L"upper", no_argument, nullptr, 'u'},
+                                              {L"validate-refs", no_argument, nullptr, 2},
                                               {nullptr, 0, nullptr, 0}};
 
 static const std::unordered_map<char, decltype(*handle_flag_N)> flag_to_function = {
     {'N', handle_flag_N}, {'a', handle_flag_a}, {'c', handle_flag_c}, {'e', handle_flag_e},
     {'f', handle_flag_f}, {'i', handle_flag_i}, {'l', handle_flag_l}, {'m', handle_flag_m},
     {'n', handle_flag_n}, {'q', handle_flag_q}, {'r', handle_flag_r}, {'s', handle_flag_s},
-    {'v', handle_flag_v}, {'w', handle_flag_w}, {1, handle_flag_1}};
+    {'u', handle_flag_u}, {'v', handle_flag_v}, {'w', handle_flag_w}, {1, handle_flag_1},
+    {2, handle_flag_2}, {3, handle_flag_3}};
 
 /// Parse the arguments for flags recognized by a specific string subcommand.
 static int parse_opts(options_t *opts, int *optind, int n_req_args, int argc, const wchar_t **argv,
@@ -606,6 +576,53 @@ static int parse_opts(options_t *opts, int *optind, int n_req_args, int argc, co
     return STATUS_CMD_OK;
 }
 
+/// Format a Jaro-Winkler distance, which is between 0 and 1, with up to six decimals. This does not
+/// use printf so the decimal separator does not depend on the locale.
+static wcstring format_fraction(double fraction) {
+    auto millionths = static_cast<long>(fraction * 1000000 + 0.5);
+    if (millionths <= 0) return L"0";
+    if (millionths >= 1000000) return L"1";
+    wcstring result = format_string(L"0.%06ld", millionths);
+    while (result.back() == L'0') result.pop_back();
+    return result;
+}
+
+static int string_distance(parser_t &parser, io_streams_t &streams, int argc,
+                           const wchar_t **argv) {
+    options_t opts;
+    opts.algorithm_valid = true;
+    int optind;
+    int retval = parse_opts(&opts, &optind, 1, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    arg_iterator_t aiter(argv, optind, streams);
+    // With "-", the first string is the first of the others, for example the first line of input.
+    wcstring first = opts.arg1;
+    if (first == L"-") {
+        const wcstring *arg = aiter.nextstr();
+        if (!arg) return STATUS_CMD_ERROR;
+        first = *arg;
+    }
+
+    int ncompared = 0;
+    while (const wcstring *arg = aiter.nextstr()) {
+        switch (opts.algorithm) {
+            case distance_algorithm_t::levenshtein: {
+                streams.out.append(to_string(levenshtein_distance(first, *arg)));
+                break;
+            }
+            case distance_algorithm_t::jaro_winkler: {
+                streams.out.append(format_fraction(1 - jaro_winkler_similarity(first, *arg)));
+                break;
+            }
+        }
+        streams.out.append(L'\n');
+        ncompared++;
+    }
+
+    return ncompared > 0 ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+}
+
 static int string_escape(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
     options_t opts;
     opts.no_quoted_valid = true;
@@ -660,6 +677,80 @@ static int string_unescape(parser_t &parser, io_streams_t &streams, int argc,
     DIE("should never reach this statement");
 }
 
+/// Convert a single hex digit to its value, or return -1 if it isn't one.
+static int hex_digit_value(wchar_t c) {
+    if (c >= L'0' && c <= L'9') return c - L'0';
+    if (c >= L'a' && c <= L'f') return c - L'a' + 10;
+    if (c >= L'A' && c <= L'F') return c - L'A' + 10;
+    return -1;
+}
+
+static int string_hex(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
+    options_t opts;
+    opts.upper_valid = true;
+    int optind;
+    int retval = parse_opts(&opts, &optind, 1, argc, argv, parser, streams);
+    if (retval != STATUS_CMD_OK) return retval;
+
+    bool decode;
+    if (std::wcscmp(opts.arg1, L"encode") == 0) {
+        decode = false;
+    } else if (std::wcscmp(opts.arg1, L"decode") == 0) {
+        decode = true;
+    } else {
+        string_error(streams, _(L"%ls: Invalid mode '%ls', expected 'encode' or 'decode'\n"),
+                     argv[0], opts.arg1);
+        return STATUS_INVALID_ARGS;
+    }
+    if (decode && opts.upper) {
+        string_error(streams, BUILTIN_ERR_COMBO2, argv[0],
+                     _(L"--upper is only valid when encoding"));
+        return STATUS_INVALID_ARGS;
+    }
+
+    const wchar_t *digits = opts.upper ? L"0123456789ABCDEF" : L"0123456789abcdef";
+    int nout = 0;
+    bool invalid = false;
+    arg_iterator_t aiter(argv, optind, streams);
+    while (const wcstring *arg = aiter.nextstr()) {
+        wcstring result;
+        if (!decode) {
+            // Encode the bytes fish would write for this argument, so encoding errors round-trip.
+            for (unsigned char c : wcs2string(*arg)) {
+                result.push_back(digits[c >> 4]);
+                result.push_back(digits[c & 0xF]);
+            }
+        } else {
+            if (arg->size() % 2 != 0) {
+                string_error(streams, _(L"%ls: Odd number of hex digits in '%ls'\n"), argv[0],
+                             arg->c_str());
+                invalid = true;
+                continue;
+            }
+            std::string bytes;
+            for (size_t i = 0; i < arg->size(); i += 2) {
+                int hi = hex_digit_value(arg->at(i));
+                int lo = hex_digit_value(arg->at(i + 1));
+                if (hi < 0 || lo < 0) break;
+                bytes.push_back(static_cast<char>((hi << 4) | lo));
+            }
+            if (bytes.size() * 2 != arg->size()) {
+                string_error(streams, _(L"%ls: Invalid hex digit in '%ls'\n"), argv[0],
+                             arg->c_str());
+                invalid = true;
+                continue;
+            }
+            result = str2wcstring(bytes);
+        }
+        streams.out.append(result);
+        streams.out.append(L'\n');
+        nout++;
+    }
+
+    if (invalid) return STATUS_INVALID_ARGS;
+    return nout > 0 ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+}
+
 static int string_join_maybe0(parser_t &parser, io_streams_t &streams, int argc,
                               const wchar_t **argv, bool is_join0) {
     options_t opts;
@@ -1286,6 +1377,68 @@ static maybe_t<wcstring> interpret_escapes(const wcstring &arg) {
     return result;
 }
 
+/// \return the first reference in \p replacement to a group that \p code does not have, or none.
+/// This follows the syntax of pcre2_substitute() in extended mode: $n, ${n}, $name and ${name} are
+/// references, also at the start of ${n:-default} and the like, while $$, $*MARK and escaped
+/// characters are not.
+static maybe_t<wcstring> find_unknown_group_ref(const pcre2_code *code,
+                                                const wcstring &replacement) {
+    uint32_t group_count = 0;
+    pcre2_pattern_info(code, PCRE2_INFO_CAPTURECOUNT, &group_count);
+    auto is_digit = [](wchar_t c) { return c >= L'0' && c <= L'9'; };
+    auto is_word = [&](wchar_t c) {
+        return is_digit(c) || (c >= L'a' && c <= L'z') || (c >= L'A' && c <= L'Z') || c == L'_';
+    };
+
+    const size_t len = replacement.size();
+    size_t i = 0;
+    while (i < len) {
+        if (replacement[i] == L'\\') {
+            // \Q quotes everything up to \E, other escapes a single character.
+            if (i + 1 < len && replacement[i + 1] == L'Q') {
+                size_t end = replacement.find(L"\\E", i + 2);
+                i = end == wcstring::npos ? len : end + 2;
+            } else {
+                i += 2;
+            }
+            continue;
+        }
+        if (replacement[i] != L'$') {
+            i++;
+            continue;
+        }
+        const size_t start = i++;
+        if (i < len && replacement[i] == L'$') {
+            i++;
+            continue;
+        }
+        bool braced = i < len && replacement[i] == L'{';
+        if (braced) i++;
+        if (i < len && replacement[i] == L'*') continue;
+
+        const size_t name_start = i;
+        bool known;
+        if (i < len && is_digit(replacement[i])) {
+            while (i < len && is_digit(replacement[i])) i++;
+            wcstring digits = replacement.substr(name_start, i - name_start);
+            unsigned long long group = fish_wcstoull(digits.c_str());
+            known = errno == 0 && group <= group_count;
+        } else {
+            while (i < len && is_word(replacement[i])) i++;
+            // A lone $ is an error that pcre2_substitute() reports itself.
+            if (i == name_start) continue;
+            wcstring name = replacement.substr(name_start, i - name_start);
+            known = pcre2_substring_number_from_name(code, PCRE2_SPTR(name.c_str())) !=
+                    PCRE2_ERROR_NOSUBSTRING;
+        }
+        if (!known) {
+            if (braced && i < len && replacement[i] == L'}') i++;
+            return replacement.substr(start, i - start);
+        }
+    }
+    return none();
+}
+
 class regex_replacer_t : public string_replacer_t {
     compiled_regex_t regex;
     maybe_t<wcstring> replacement;
@@ -1300,6 +1453,13 @@ class regex_replacer_t : public string_replacer_t {
         } else {
             replacement = interpret_escapes(replacement_);
         }
+        if (opts.validate_refs && regex.code && replacement) {
+            if (auto ref = find_unknown_group_ref(regex.code, *replacement)) {
+                string_error(streams, _(L"%ls: Reference to unknown group '%ls' in replacement\n"),
+                             argv0, ref->c_str());
+                replacement = none();
+            }
+        }
     }
 
     bool replace_matches(const wcstring &arg) override;
@@ -1406,10 +1566,17 @@ static int string_replace(parser_t &parser, io_streams_t &streams, int argc, con
     opts.ignore_case_valid = true;
     opts.quiet_valid = true;
     opts.regex_valid = true;
+    opts.validate_refs_valid = true;
     int optind;
     int retval = parse_opts(&opts, &optind, 2, argc, argv, parser, streams);
     if (retval != STATUS_CMD_OK) return retval;
 
+    if (opts.validate_refs && !opts.regex) {
+        string_error(streams, BUILTIN_ERR_COMBO2, argv[0],
+                     _(L"--validate-refs can only be used with --regex"));
+        return STATUS_INVALID_ARGS;
+    }
+
     const wchar_t *pattern = opts.arg1;
     const wchar_t *replacement = opts.arg2;
 
@@ -1429,6 +1596,73 @@ static int string_replace(parser_t &parser, io_streams_t &streams, int argc, con
     return replacer->replace_count() > 0 ? STATUS_CMD_OK : STATUS_CMD_ERROR;
 }
 
+/// Split stdin on NUL for `string split0`, writing each element as soon as it is complete.
+/// This produces the same output and status as splitting all of stdin as one argument, but without
+/// holding the entire input in memory several times over, which matters for huge inputs like
+/// `find -print0 | string split0` in a command substitution.
+static int string_split0_stdin(const options_t &opts, io_streams_t &streams) {
+    std::string buffer;
+    size_t start = 0;  // where the current element starts
+    bool have_input = false;
+    size_t split_count = 0;
+    for (;;) {
+        size_t nul = buffer.find('\0', start);
+        if (nul != std::string::npos) {
+            if (!opts.no_empty || nul > start) {
+                split_count++;
+                if (opts.quiet) return STATUS_CMD_OK;
+                streams.out.append_with_separation(str2wcstring(buffer.data() + start, nul - start),
+                                                   separation_type_t::explicitly);
+            }
+            start = nul + 1;
+            continue;
+        }
+
+        // Drop the elements we already wrote, and read some more.
+        buffer.erase(0, start);
+        start = 0;
+        char buf[STRING_CHUNK_SIZE];
+        long n = read_blocked(streams.stdin_fd, buf, STRING_CHUNK_SIZE);
+        if (n <= 0) break;
+        have_input = true;
+        buffer.append(buf, n);
+    }
+    if (!have_input) return STATUS_CMD_ERROR;
+
+    // The last element is only written if it is not empty, so a trailing NUL does not produce an
+    // extra empty element. It still counts as a split, though.
+    if (!opts.no_empty || !buffer.empty()) split_count++;
+    if (!buffer.empty() && !opts.quiet) {
+        streams.out.append_with_separation(str2wcstring(buffer), separation_type_t::explicitly);
+    }
+    // We split something if we have more than one element.
+    return split_count > 1 ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+}
+
+/// Resolve the ranges from --fields against a list of \p count fields, appending the zero-based
+/// indexes to \p out_indexes in the order given. \return false if some fields do not exist; those
+/// are left out.
+static bool resolve_fields(const std::vector<field_range_t> &fields, size_t count,
+                           std::vector<size_t> *out_indexes) {
+    bool all_exist = true;
+    const long lcount = static_cast<long>(count);
+    for (const field_range_t &range : fields) {
+        long begin = range.begin < 0 ? lcount + 1 + range.begin : range.begin;
+        long end = range.end < 0 ? lcount + 1 + range.end : range.end;
+        long lo = std::min(begin, end);
+        long hi = std::max(begin, end);
+        if (lo < 1 || hi > lcount) all_exist = false;
+        lo = std::max(lo, 1L);
+        hi = std::min(hi, lcount);
+        if (begin <= end) {
+            for (long i = lo; i <= hi; i++) out_indexes->push_back(i - 1);
+        } else {
+            for (long i = hi; i >= lo; i--) out_indexes->push_back(i - 1);
+        }
+    }
+    return all_exist;
+}
+
 static int string_split_maybe0(parser_t &parser, io_streams_t &streams, int argc,
                                const wchar_t **argv, bool is_split0) {
     const wchar_t *cmd = argv[0];
@@ -1450,6 +1684,12 @@ static int string_split_maybe0(parser_t &parser, io_streams_t &streams, int argc
         return STATUS_INVALID_ARGS;
     }
 
+    // Options that need to see all the pieces at once can't be streamed.
+    if (is_split0 && string_args_from_stdin(streams) && !opts.right && opts.max == LONG_MAX &&
+        opts.fields.empty()) {
+        return string_split0_stdin(opts, streams);
+    }
+
     const wcstring sep = is_split0 ? wcstring(1, L'\0') : wcstring(opts.arg1);
 
     std::vector<wcstring_list_t> all_splits;
@@ -1465,11 +1705,11 @@ static int string_split_maybe0(parser_t &parser, io_streams_t &streams, int argc
             split_about(arg->begin(), arg->end(), sep.begin(), sep.end(), &splits, opts.max,
                         opts.no_empty);
         }
-        all_splits.push_back(splits);
         // If we're quiet, we return early if we've found something to split.
         if (opts.quiet && splits.size() > 1) return STATUS_CMD_OK;
         split_count += splits.size();
         arg_count++;
+        all_splits.push_back(std::move(splits));
     }
 
     for (auto &splits : all_splits) {
@@ -1492,19 +1732,13 @@ static int string_split_maybe0(parser_t &parser, io_streams_t &streams, int argc
             if (opts.fields.size() > 0) {
                 // Print nothing and return error if any of the supplied
                 // fields do not exist, unless `--allow-empty` is used.
-                if (!opts.allow_empty) {
-                    for (const auto &field : opts.fields) {
-                        // field indexing starts from 1
-                        if (field - 1 >= (long)splits.size()) {
-                            return STATUS_CMD_ERROR;
-                        }
-                    }
+                std::vector<size_t> indexes;
+                if (!resolve_fields(opts.fields, splits.size(), &indexes) && !opts.allow_empty) {
+                    return STATUS_CMD_ERROR;
                 }
-                for (const auto &field : opts.fields) {
-                    if (field - 1 < (long)splits.size()) {
-                        streams.out.append_with_separation(splits.at(field - 1),
-                                                           separation_type_t::explicitly);
-                    }
+                for (size_t idx : indexes) {
+                    streams.out.append_with_separation(splits.at(idx),
+                                                       separation_type_t::explicitly);
                 }
             } else {
                 for (const wcstring &split : splits) {
@@ -1525,6 +1759,11 @@ static int string_split0(parser_t &parser, io_streams_t &streams, int argc, cons
     return string_split_maybe0(parser, streams, argc, argv, true /* is_split0 */);
 }
 
+bool string_args_only_split0(const wcstring_list_t &args) {
+    // Any option or argument changes what is split or how, so only accept the bare form.
+    return args.size() == 2 && args[0] == L"string" && args[1] == L"split0";
+}
+
 static int string_collect(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
     options_t opts;
     opts.no_trim_newlines_valid = true;
@@ -1563,13 +1802,26 @@ static wcstring wcsrepeat(const wcstring &to_repeat, size_t count) {
 }
 
 // Helper function to abstract the repeat until logic from string_repeat
-// returns the to_repeat string, repeated until max char has been reached.
-static wcstring wcsrepeat_until(const wcstring &to_repeat, size_t max) {
-    if (to_repeat.length() == 0) return wcstring();
-    size_t count = max / to_repeat.length();
-    size_t mod = max % to_repeat.length();
-
-    return wcsrepeat(to_repeat, count) + to_repeat.substr(0, mod);
+// writes the to_repeat string, repeated until total chars have been written, to out.
+// This is done in chunks, so the whole output is never held in memory, and stops as soon as the
+// output can't take any more, e.g. when writing to a pipe whose reader has exited.
+// returns 0, or the error from the output stream if it stopped early.
+static int write_repeated(const wcstring &to_repeat, size_t total, output_stream_t &out) {
+    if (to_repeat.empty() || total == 0) return STATUS_CMD_OK;
+    const size_t needed = (total + to_repeat.length() - 1) / to_repeat.length();
+    const size_t per_chunk = std::max(static_cast<size_t>(1), size_t(4096) / to_repeat.length());
+    const wcstring chunk = wcsrepeat(to_repeat, std::min(needed, per_chunk));
+
+    size_t remaining = total;
+    while (remaining > 0) {
+        size_t amt = std::min(remaining, chunk.length());
+        out.append(chunk.data(), amt);
+        remaining -= amt;
+        if (remaining > 0) {
+            if (int err = out.flush_and_check_error()) return err;
+        }
+    }
+    return STATUS_CMD_OK;
 }
 
 static int string_repeat(parser_t &parser, io_streams_t &streams, int argc, const wchar_t **argv) {
@@ -1594,9 +1846,9 @@ static int string_repeat(parser_t &parser, io_streams_t &streams, int argc, cons
         const bool limit_repeat =
             (opts.max > 0 && word->length() * opts.count > static_cast<size_t>(opts.max)) ||
             !opts.count;
-        const wcstring repeated =
-            limit_repeat ? wcsrepeat_until(*word, opts.max) : wcsrepeat(*word, opts.count);
-        if (!repeated.empty()) {
+        const size_t total =
+            limit_repeat ? static_cast<size_t>(opts.max) : word->length() * opts.count;
+        if (!word->empty() && total > 0) {
             all_empty = false;
             if (opts.quiet) {
                 // Early out if we can - see #7495.
@@ -1606,7 +1858,7 @@ static int string_repeat(parser_t &parser, io_streams_t &streams, int argc, cons
 
         // Append if not quiet.
         if (!opts.quiet) {
-            streams.out.append(repeated);
+            if (int err = write_repeated(*word, total, streams.out)) return err;
         }
     }
 
@@ -1767,12 +2019,12 @@ static constexpr const struct string_subcommand {
     int (*handler)(parser_t &, io_streams_t &, int argc,  //!OCLINT(unused param)
                    const wchar_t **argv);                 //!OCLINT(unused param)
 } string_subcommands[] = {
-    {L"collect", &string_collect}, {L"escape", &string_escape}, {L"join", &string_join},
-    {L"join0", &string_join0},     {L"length", &string_length}, {L"lower", &string_lower},
-    {L"match", &string_match},     {L"pad", &string_pad},       {L"repeat", &string_repeat},
-    {L"replace", &string_replace}, {L"split", &string_split},   {L"split0", &string_split0},
-    {L"sub", &string_sub},         {L"trim", &string_trim},     {L"unescape", &string_unescape},
-    {L"upper", &string_upper},
+    {L"collect", &string_collect},   {L"distance", &string_distance}, {L"escape", &string_escape},
+    {L"hex", &string_hex},           {L"join", &string_join},         {L"join0", &string_join0},
+    {L"length", &string_length},     {L"lower", &string_lower},       {L"match", &string_match},
+    {L"pad", &string_pad},           {L"repeat", &string_repeat},     {L"replace", &string_replace},
+    {L"split", &string_split},       {L"split0", &string_split0},     {L"sub", &string_sub},
+    {L"trim", &string_trim},         {L"unescape", &string_unescape}, {L"upper", &string_upper},
 };
 ASSERT_SORT_ORDER(string_subcommands, .name);
 
@@ -5,7 +5,14 @@
 #include <cstring>
 #include <cwchar>
 
+#include "common.h"
+
 class parser_t;
 
 maybe_t<int> builtin_string(parser_t &parser, io_streams_t &streams, const wchar_t **argv);
+
+/// \return whether the `string` builtin with the arguments \p args (including "string") does
+/// nothing but split its standard input on NUL, like a bare `string split0`. A command
+/// substitution that ends in such a command splits its output on NUL itself instead.
+bool string_args_only_split0(const wcstring_list_t &args);
 #endif
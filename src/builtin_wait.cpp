@@ -17,7 +17,7 @@
 
 /// \return true if we can wait on a job.
 static bool can_wait_on_job(const std::shared_ptr<job_t> &j) {
-    return j->is_constructed() && !j->is_foreground() && !j->is_stopped();
+    return j->is_constructed() && !j->is_foreground() && !j->is_stopped() && !j->is_hidden();
 }
 
 /// \return true if a wait handle matches a pid or a process name. Exactly one should be passed.
@@ -43,6 +43,7 @@
 #include "env.h"
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "flog.h"
 #include "future_feature_flags.h"
 #include "global_safety.h"
@@ -501,9 +502,7 @@ void append_format(wcstring &str, const wchar_t *format, ...) {
     va_end(va);
 }
 
-wchar_t *quote_end(const wchar_t *pos) {
-    wchar_t c = *pos;
-
+wchar_t *quote_end(const wchar_t *pos, wchar_t quote) {
     while (true) {
         pos++;
 
@@ -513,7 +512,7 @@ wchar_t *quote_end(const wchar_t *pos) {
             pos++;
             if (!*pos) return nullptr;
         } else {
-            if (*pos == c) {
+            if (*pos == quote || (quote == L'"' && pos[0] == L'$' && pos[1] == L'(')) {
                 return const_cast<wchar_t *>(pos);
             }
         }
@@ -776,7 +775,7 @@ wcstring reformat_for_screen(const wcstring &msg, const termsize_t &termsize) {
 
 /// Escape a string in a fashion suitable for using as a URL. Store the result in out_str.
 static void escape_string_url(const wcstring &in, wcstring &out) {
-    const std::string narrow = wcs2string(in);
+    const std::string narrow = wcs2utf8(in);
     for (auto &c1 : narrow) {
         // This silliness is so we get the correct result whether chars are signed or unsigned.
         unsigned int c2 = static_cast<unsigned int>(c1) & 0xFF;
@@ -821,14 +820,14 @@ static bool unescape_string_url(const wchar_t *in, wcstring *out) {
         }
     }
 
-    *out = str2wcstring(result);
+    *out = utf8towcs(result);
     return true;
 }
 
 /// Escape a string in a fashion suitable for using as a fish var name. Store the result in out_str.
 static void escape_string_var(const wcstring &in, wcstring &out) {
     bool prev_was_hex_encoded = false;
-    const std::string narrow = wcs2string(in);
+    const std::string narrow = wcs2utf8(in);
     for (auto c1 : narrow) {
         // This silliness is so we get the correct result whether chars are signed or unsigned.
         unsigned int c2 = static_cast<unsigned int>(c1) & 0xFF;
@@ -892,7 +891,7 @@ static bool unescape_string_var(const wchar_t *in, wcstring *out) {
         }
     }
 
-    *out = str2wcstring(result);
+    *out = utf8towcs(result);
     return true;
 }
 
@@ -1441,14 +1440,28 @@ static bool unescape_string_internal(const wchar_t *const input, const size_t in
                         to_append_or_none = BRACE_END;
                         if (!braces.empty()) {
                             // If we didn't have a var or separator since the last '{',
-                            // put the literal back.
+                            // put the literal back - unless it is a range like {1..5}.
                             if (vars_or_seps.empty() || vars_or_seps.back() < braces.back()) {
-                                result[braces.back()] = L'{';
-                                // We also need to turn all spaces back.
-                                for (size_t i = braces.back() + 1; i < result.size(); i++) {
-                                    if (result[i] == BRACE_SPACE) result[i] = L' ';
+                                wcstring contents(result, braces.back() + 1);
+                                if (parse_brace_range(contents, L"..")) {
+                                    // Mark the ".." of the range.
+                                    result.resize(braces.back() + 1);
+                                    for (size_t i = 0; i < contents.size(); i++) {
+                                        if (contents.compare(i, 2, L"..") == 0) {
+                                            result.push_back(BRACE_RANGE);
+                                            i++;
+                                        } else {
+                                            result.push_back(contents[i]);
+                                        }
+                                    }
+                                } else {
+                                    result[braces.back()] = L'{';
+                                    // We also need to turn all spaces back.
+                                    for (size_t i = braces.back() + 1; i < result.size(); i++) {
+                                        if (result[i] == BRACE_SPACE) result[i] = L' ';
+                                    }
+                                    to_append_or_none = L'}';
                                 }
-                                to_append_or_none = L'}';
                             }
 
                             // Remove all seps inside the current brace pair, so if we have a
@@ -1855,7 +1868,7 @@ bool fish_reserved_codepoint(wchar_t c) {
 /// become invalid.
 void redirect_tty_output() {
     struct termios t;
-    int fd = open("/dev/null", O_WRONLY);
+    int fd = open_cloexec("/dev/null", O_WRONLY);
     if (fd == -1) {
         __fish_assert("Could not open /dev/null!", __FILE__, __LINE__, errno);
     }
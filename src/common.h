@@ -476,11 +476,14 @@ std::unique_ptr<T> make_unique(Args &&...args) {
 }
 #endif
 
-/// This functions returns the end of the quoted substring beginning at \c in. The type of quoting
-/// character is detemrined by examining \c in. Returns 0 on error.
+/// This functions returns the end of the quoted substring beginning at \c pos. Returns 0 on error.
 ///
-/// \param in the position of the opening quote.
-wchar_t *quote_end(const wchar_t *pos);
+/// A command substitution like "$(cmd)" interrupts a double quoted string; in that case this
+/// returns the position of its '$' instead. The quoted string resumes after the matching ')'.
+///
+/// \param pos the position of the opening quote, or of the ')' that resumes a quoted string.
+/// \param quote the quoting character.
+wchar_t *quote_end(const wchar_t *pos, wchar_t quote);
 
 /// This function should be called after calling `setlocale()` to perform fish specific locale
 /// initialization.
@@ -661,6 +664,14 @@ enum {
     /// arguments that might result in a command failure. An invalid args condition is something
     /// like an unrecognized flag, missing or too many arguments, an invalid integer, etc. But
     STATUS_INVALID_ARGS = 2,
+    /// The status code used when what a command was asked to act on, like a job or a directory,
+    /// does not exist.
+    STATUS_NOT_FOUND = 3,
+    /// The status code used when a command was not allowed to access what it was asked to act on.
+    STATUS_PERMISSION_DENIED = 4,
+    /// The status code used when what a command was asked to act on exists, but cannot be used in
+    /// that way, like a job that is not under job control.
+    STATUS_INVALID_STATE = 5,
 
     /// The status code used when a command was not found.
     STATUS_CMD_UNKNOWN = 127,
@@ -36,8 +36,10 @@
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "function.h"
+#include "global_safety.h"
 #include "history.h"
 #include "iothread.h"
+#include "lru.h"
 #include "parse_constants.h"
 #include "parse_util.h"
 #include "parser.h"
@@ -273,8 +275,7 @@ completion_list_t completion_receiver_t::take() {
 }
 
 completion_receiver_t completion_receiver_t::subreceiver() const {
-    size_t remaining_capacity = limit_ < size() ? 0 : limit_ - size();
-    return completion_receiver_t(remaining_capacity);
+    return completion_receiver_t(remaining_capacity());
 }
 
 // If these functions aren't force inlined, it is actually faster to call
@@ -325,8 +326,13 @@ void completions_sort_and_prioritize(completion_list_t *comps, completion_reques
 
     // Sort, provided COMPLETE_DONT_SORT isn't set.
     // Here we do not pass suppress_exact, so that exact matches appear first.
+    // Transposed matches needing fewer swaps come first.
     stable_sort(comps->begin(), comps->end(), [&](const completion_t &a, const completion_t &b) {
-        return a.rank() < b.rank() || natural_compare_completions(a, b);
+        if (a.rank() != b.rank()) return a.rank() < b.rank();
+        if (a.match.transpositions != b.match.transpositions) {
+            return a.match.transpositions < b.match.transpositions;
+        }
+        return natural_compare_completions(a, b);
     });
 
     // Lastly, if this is for an autosuggestion, prefer to avoid completions that duplicate
@@ -359,6 +365,9 @@ class completer_t {
     using condition_cache_t = std::unordered_map<wcstring, bool>;
     condition_cache_t condition_cache;
 
+    /// The commandline we are currently completing.
+    wcstring current_cmdline;
+
     enum complete_type_t { COMPLETE_DEFAULT, COMPLETE_AUTOSUGGEST };
 
     complete_type_t type() const {
@@ -374,11 +383,14 @@ class completer_t {
     bool try_complete_user(const wcstring &str);
 
     bool complete_param_for_command(const wcstring &cmd_orig, const wcstring &popt,
-                                    const wcstring &str, bool use_switches, bool *out_do_file);
+                                    const wcstring &str, const wcstring_list_t &preceding_args,
+                                    bool use_switches, bool *out_do_file);
 
     void complete_param_expand(const wcstring &str, bool do_file,
                                bool handle_as_special_cd = false);
 
+    void unexpand_leading_variable(const wcstring &str, size_t first_idx);
+
     void complete_cmd(const wcstring &str);
 
     /// Attempt to complete an abbreviation for the given string.
@@ -415,6 +427,9 @@ class completer_t {
         // The unescaped argument which is being completed, or empty if none.
         wcstring current_argument{};
 
+        // The unescaped arguments between the command and the argument which is being completed.
+        wcstring_list_t preceding_arguments{};
+
         // Whether a -- has been encountered, which suppresses options.
         bool had_ddash{false};
 
@@ -785,6 +800,87 @@ void completer_t::complete_abbr(const wcstring &cmd) {
     this->complete_strings(cmd, desc_func, possible_comp, COMPLETE_NO_SPACE);
 }
 
+/// \return the number of seconds for which expanded completion arguments are still used after other
+/// commands ran, from $fish_completion_cache_ttl. 0 means they are not.
+static double completion_cache_ttl(const environment_t &vars) {
+    auto var = vars.get(L"fish_completion_cache_ttl");
+    if (var.missing_or_empty()) return 0;
+    const wcstring str = var->as_string();
+    wchar_t *end = nullptr;
+    double ttl = fish_wcstod(str.c_str(), &end);
+    return (*end != L'\0' || !(ttl > 0)) ? 0 : ttl;
+}
+
+/// Bumped to make every parser forget its expanded completion arguments.
+static relaxed_atomic_t<uint32_t> s_arguments_cache_resets{0};
+
+/// A completion argument list as expanded by completer_t::complete_from_args.
+struct cached_arguments_t {
+    completion_list_t completions;
+    /// The cache generation it was expanded in.
+    uint64_t generation;
+    /// When it stops being used in later generations, or 0 if it isn't.
+    double expiry;
+};
+
+/// The last 16 completion argument lists (as given to complete -a) that a parser expanded, with
+/// their expansions. Several completions often share a command substitution as their argument
+/// list, and pressing tab again on the same commandline needs the same arguments again.
+///
+/// Other commands may change what the arguments expand to, so a new generation starts whenever the
+/// parser ran any commands between two completion requests. Entries from earlier generations are
+/// only used for $fish_completion_cache_ttl seconds.
+class completion_arguments_cache_t
+    : public lru_cache_t<completion_arguments_cache_t, cached_arguments_t> {
+   public:
+    completion_arguments_cache_t()
+        : lru_cache_t<completion_arguments_cache_t, cached_arguments_t>(16) {}
+
+    /// Note that a completion request starts, with the parser's exec_count at \p exec_count.
+    void request_started(uint64_t exec_count) {
+        if (resets_ != s_arguments_cache_resets) {
+            resets_ = s_arguments_cache_resets;
+            this->evict_all_nodes();
+        }
+        if (exec_count != exec_count_) generation_++;
+    }
+
+    /// Note that a completion request ended, with the parser's exec_count at \p exec_count. The
+    /// commands it ran itself don't start a new generation.
+    void request_finished(uint64_t exec_count) { exec_count_ = exec_count; }
+
+    /// \return the expansions stored under \p key, if they may be used with a TTL of \p ttl.
+    const completion_list_t *lookup(const wcstring &key, double ttl) {
+        cached_arguments_t *entry = this->get(key);
+        if (!entry) return nullptr;
+        if (entry->generation == generation_ || (ttl > 0 && entry->expiry > timef())) {
+            return &entry->completions;
+        }
+        this->evict_node(key);
+        return nullptr;
+    }
+
+    /// Store the expansions \p comps under \p key, for later generations too if \p ttl is set.
+    void add(wcstring key, completion_list_t comps, double ttl) {
+        double expiry = ttl > 0 ? timef() + ttl : 0;
+        this->insert(std::move(key), cached_arguments_t{std::move(comps), generation_, expiry});
+    }
+
+   private:
+    uint64_t generation_{0};
+    uint64_t exec_count_{0};
+    uint32_t resets_{0};
+};
+
+/// \return the arguments cache of \p parser, creating it if necessary.
+static const std::shared_ptr<completion_arguments_cache_t> &arguments_cache_of(parser_t &parser) {
+    auto &cache = parser.libdata().completion_arguments_cache;
+    if (!cache) cache = std::make_shared<completion_arguments_cache_t>();
+    return cache;
+}
+
+void complete_invalidate_argument_cache() { s_arguments_cache_resets++; }
+
 /// Evaluate the argument list (as supplied by complete -a) and insert any
 /// return matching completions. Matching is done using @c
 /// copy_strings_with_prefix, meaning the completion may contain wildcards.
@@ -804,6 +900,29 @@ void completer_t::complete_from_args(const wcstring &str, const wcstring &args,
                                      const wcstring &desc, complete_flags_t flags) {
     bool is_autosuggest = (this->type() == COMPLETE_AUTOSUGGEST);
 
+    // Reuse the expansion from earlier in this or an earlier completion request. Autosuggestions
+    // don't run command substitutions, so they don't take part. As command substitutions may look
+    // at the commandline, the key also includes the commandline up to the cursor, and any
+    // transient commandline for wrapped commands.
+    completion_arguments_cache_t *cache = nullptr;
+    if (ctx.parser && !is_autosuggest) cache = arguments_cache_of(*ctx.parser).get();
+    wcstring cache_key = args;
+    double ttl = 0;
+    if (cache) {
+        cache_key.push_back(L'\0');
+        cache_key.append(current_cmdline);
+        if (!ctx.parser->libdata().transient_commandlines.empty()) {
+            cache_key.push_back(L'\0');
+            cache_key.append(ctx.parser->libdata().transient_commandlines.back());
+        }
+        ttl = completion_cache_ttl(ctx.vars);
+        if (const completion_list_t *cached = cache->lookup(cache_key, ttl)) {
+            this->complete_strings(escape_string(str, ESCAPE_ALL), const_desc(desc), *cached,
+                                   flags);
+            return;
+        }
+    }
+
     bool saved_interactive = false;
     statuses_t status;
     if (ctx.parser) {
@@ -825,6 +944,7 @@ void completer_t::complete_from_args(const wcstring &str, const wcstring &args,
     }
 
     this->complete_strings(escape_string(str, ESCAPE_ALL), const_desc(desc), possible_comp, flags);
+    if (cache) cache->add(std::move(cache_key), std::move(possible_comp), ttl);
 }
 
 static size_t leading_dash_count(const wchar_t *str) {
@@ -898,13 +1018,83 @@ static size_t short_option_pos(const wcstring &arg, const option_list_t &options
             if (pos == 1) return wcstring::npos;
             return pos - 1;
         }
-        if (match->result_mode.requires_param) {
+        if (match->result_mode.requires_param || match->result_mode.attached_param) {
             return pos;
         }
     }
     return arg.size() - 1;
 }
 
+/// \return the position of the argument after \p args among the arguments that are not options,
+/// counting from 1. Arguments starting with a dash are options, until "--". An option that requires
+/// a parameter and does not contain it, like "-o" in "-o file", makes the next argument its
+/// parameter, which is not counted either.
+static size_t argument_position(const wcstring_list_t &args, const option_list_t &options) {
+    size_t position = 1;
+    bool had_ddash = false;
+    bool next_is_parameter = false;
+    for (const wcstring &arg : args) {
+        if (next_is_parameter) {
+            next_is_parameter = false;
+        } else if (had_ddash || arg.size() < 2 || arg.at(0) != L'-') {
+            // A lone dash is an argument, usually meaning stdin.
+            position++;
+        } else if (arg == L"--") {
+            had_ddash = true;
+        } else {
+            size_t short_opt_pos = short_option_pos(arg, options);
+            for (const complete_entry_opt_t &o : options) {
+                if (!o.result_mode.requires_param) continue;
+                if (o.type == option_type_short) {
+                    next_is_parameter |= !o.result_mode.attached_param &&
+                                         short_opt_pos != wcstring::npos &&
+                                         short_opt_pos + 1 == arg.size() &&
+                                         o.option.at(0) == arg.at(short_opt_pos);
+                } else {
+                    next_is_parameter |= param_match(&o, arg.c_str());
+                }
+            }
+        }
+    }
+    return position;
+}
+
+/// \return whether the completion entry \p o applies to the argument at \p position.
+static bool position_matches(const complete_entry_opt_t &o, size_t position) {
+    const completion_mode_t &mode = o.result_mode;
+    if (mode.position == 0) return true;
+    return mode.position_or_later ? position >= mode.position : position == mode.position;
+}
+
+/// \return the unescaped arguments in \p cmdline after the command at \p cmdrange, without the
+/// argument being completed at the end. Redirections and their targets are left out.
+static wcstring_list_t preceding_arguments(const wcstring &cmdline, source_range_t cmdrange) {
+    wcstring_list_t result;
+    const size_t start = std::min(size_t(cmdrange.start) + cmdrange.length, cmdline.size());
+    const wchar_t *args_start = cmdline.c_str() + start;
+    const size_t args_length = cmdline.size() - start;
+    tokenizer_t tokenizer(args_start, TOK_ACCEPT_UNFINISHED);
+    bool after_redirection = false;
+    while (auto tok = tokenizer.next()) {
+        if (tok->type == token_type_t::redirect) {
+            after_redirection = true;
+            continue;
+        }
+        if (tok->type != token_type_t::string) continue;
+        // The cursor is at the end, so a token that reaches it is the one being completed.
+        if (tok->offset + tok->length >= args_length) break;
+        if (after_redirection) {
+            after_redirection = false;
+            continue;
+        }
+        wcstring arg(args_start + tok->offset, tok->length);
+        wcstring unescaped;
+        if (unescape_string(arg, &unescaped, UNESCAPE_DEFAULT)) arg = std::move(unescaped);
+        result.push_back(std::move(arg));
+    }
+    return result;
+}
+
 /// Load command-specific completions for the specified command.
 static void complete_load(const wcstring &name) {
     // We have to load this as a function, since it may define a --wraps or signature.
@@ -935,8 +1125,9 @@ static void complete_load(const wcstring &name) {
 ///   echo hello world<tab> -> ("echo", "hello", "world")
 ///
 bool completer_t::complete_param_for_command(const wcstring &cmd_orig, const wcstring &popt,
-                                             const wcstring &str, bool use_switches,
-                                             bool *out_do_file) {
+                                             const wcstring &str,
+                                             const wcstring_list_t &preceding_args,
+                                             bool use_switches, bool *out_do_file) {
     bool use_common = true, use_files = true, has_force = false;
 
     wcstring cmd, path;
@@ -979,7 +1170,29 @@ bool completer_t::complete_param_for_command(const wcstring &cmd_orig, const wcs
 
     // Now release the lock and test each option that we captured above. We have to do this outside
     // the lock because callouts (like the condition) may add or remove completions. See issue 2.
+
+    // The position of the argument being completed, which depends on the options of the command.
+    std::vector<size_t> positions;
     for (const option_list_t &options : all_options) {
+        positions.push_back(argument_position(preceding_args, options));
+    }
+
+    // If any arguments given with `complete --only` apply, they replace everything else.
+    bool have_only = false;
+    for (size_t i = 0; i < all_options.size(); i++) {
+        for (const complete_entry_opt_t &o : all_options.at(i)) {
+            if (o.option.empty() && o.result_mode.only && position_matches(o, positions.at(i)) &&
+                this->condition_test(o.condition)) {
+                have_only = true;
+                break;
+            }
+        }
+        if (have_only) break;
+    }
+    if (have_only) use_files = false;
+
+    for (size_t i = 0; i < all_options.size(); i++) {
+        const option_list_t &options = all_options.at(i);
         size_t short_opt_pos = short_option_pos(str, options);
         bool last_option_requires_param = false;
         use_common = true;
@@ -992,13 +1205,16 @@ bool completer_t::complete_param_for_command(const wcstring &cmd_orig, const wcs
                     if (o.type == option_type_short) {
                         if (short_opt_pos == wcstring::npos) continue;
                         if (o.option.at(0) != str.at(short_opt_pos)) continue;
-                        last_option_requires_param = o.result_mode.requires_param;
+                        last_option_requires_param =
+                            o.result_mode.requires_param || o.result_mode.attached_param;
                         arg = str.c_str() + short_opt_pos + 1;
                     } else {
                         arg = param_match2(&o, str.c_str());
                     }
                     if (arg != nullptr && this->condition_test(o.condition)) {
-                        if (o.result_mode.requires_param) use_common = false;
+                        if (o.result_mode.requires_param || o.result_mode.attached_param) {
+                            use_common = false;
+                        }
                         if (o.result_mode.no_files) use_files = false;
                         if (o.result_mode.force_files) has_force = true;
                         complete_from_args(arg, o.comp, o.localized_desc(), o.flags);
@@ -1035,7 +1251,8 @@ bool completer_t::complete_param_for_command(const wcstring &cmd_orig, const wcs
 
                         bool match = false;
                         if (o.type == option_type_short) {
-                            match = prev_short_opt_pos != wcstring::npos &&
+                            match = !o.result_mode.attached_param &&
+                                    prev_short_opt_pos != wcstring::npos &&
                                     // Only if the option was the last char in the token,
                                     // i.e. there is no parameter yet.
                                     prev_short_opt_pos + 1 == popt.size() &&
@@ -1060,7 +1277,9 @@ bool completer_t::complete_param_for_command(const wcstring &cmd_orig, const wcs
 
         // Now we try to complete an option itself
         for (const complete_entry_opt_t &o : options) {
+            if (have_only && !(o.option.empty() && o.result_mode.only)) continue;
             // If this entry is for the base command, check if any of the arguments match.
+            if (!position_matches(o, positions.at(i))) continue;
             if (!this->condition_test(o.condition)) continue;
             if (o.option.empty()) {
                 use_files = use_files && (!(o.result_mode.no_files));
@@ -1214,9 +1433,40 @@ void completer_t::complete_param_expand(const wcstring &str, bool do_file,
         // consider relaxing this if there was a preceding double-dash argument.
         if (string_prefixes_string(L"-", str)) flags.clear(expand_flag::fuzzy_match);
 
+        size_t first_idx = this->completions.size();
         if (expand_string(str, &this->completions, flags, ctx) == expand_result_t::error) {
             FLOGF(complete, L"Error while expanding string '%ls'", str.c_str());
         }
+        this->unexpand_leading_variable(str, first_idx);
+    }
+}
+
+/// A token like "$HOME/doc" is completed with the variable expanded, so completions that replace
+/// the token start with its value. Put the variable back, so we complete "$HOME/Documents/" instead
+/// of "/home/me/Documents/". Only completions from \p first_idx on are changed.
+void completer_t::unexpand_leading_variable(const wcstring &str, size_t first_idx) {
+    if (str.empty() || str.front() != L'$') return;
+    size_t name_end = 1;
+    while (name_end < str.size() && valid_var_name_char(str.at(name_end))) name_end++;
+    if (name_end == 1 || name_end == str.size() || str.at(name_end) != L'/') return;
+
+    // A list expands to several paths, so there is no one value to replace.
+    auto var = ctx.vars.get(str.substr(1, name_end - 1));
+    if (!var || var->as_list().size() != 1) return;
+    const wcstring value = var->as_string();
+    if (value.empty()) return;
+
+    const wcstring var_prefix = str.substr(0, name_end);
+    for (size_t i = first_idx; i < this->completions.size(); i++) {
+        completion_t &comp = this->completions.at(i);
+        if (!(comp.flags & COMPLETE_REPLACES_TOKEN) || (comp.flags & COMPLETE_DONT_ESCAPE) ||
+            !string_prefixes_string(value, comp.completion)) {
+            continue;
+        }
+        // The variable must not be escaped, so escape the rest ourselves.
+        comp.completion = var_prefix + escape_string(comp.completion.substr(value.size()),
+                                                     ESCAPE_ALL | ESCAPE_NO_QUOTED);
+        comp.flags |= COMPLETE_DONT_ESCAPE;
     }
 }
 
@@ -1231,7 +1481,8 @@ bool completer_t::complete_variable(const wcstring &str, size_t start_offset) {
     for (const wcstring &env_name : ctx.vars.get_names(0)) {
         bool anchor_start = !fuzzy();
         maybe_t<string_fuzzy_match_t> match =
-            string_fuzzy_match_string(var, env_name, anchor_start);
+            string_fuzzy_match_string(var, env_name, anchor_start,
+                                      !anchor_start && get_fuzzy_completion_matching());
         if (!match) continue;
 
         wcstring comp;
@@ -1374,13 +1625,13 @@ bool completer_t::try_complete_user(const wcstring &str) {
         const wchar_t *pw_name = pw_name_str.c_str();
         if (std::wcsncmp(user_name, pw_name, name_len) == 0) {
             wcstring desc = format_string(COMPLETE_USER_DESC, pw_name);
-            // Append a user name.
+            // Append a user name, and the slash so the user's files can be completed next.
             // TODO: propagate overflow?
-            ignore_result(
-                this->completions.add(&pw_name[name_len], std::move(desc), COMPLETE_NO_SPACE));
+            ignore_result(this->completions.add(wcstring(&pw_name[name_len]) + L'/',
+                                                std::move(desc), COMPLETE_NO_SPACE));
             result = true;
         } else if (wcsncasecmp(user_name, pw_name, name_len) == 0) {
-            wcstring name = format_string(L"~%ls", pw_name);
+            wcstring name = format_string(L"~%ls/", pw_name);
             wcstring desc = format_string(COMPLETE_USER_DESC, pw_name);
             // Append a user name
             ignore_result(this->completions.add(
@@ -1456,8 +1707,8 @@ void completer_t::complete_custom(const wcstring &cmd, const wcstring &cmdline,
     if (ctx.check_cancel()) return;
 
     if (!complete_param_for_command(
-            cmd, ad->previous_argument, ad->current_argument, !ad->had_ddash,
-            &ad->do_file)) {  // Invoke any custom completions for this command.
+            cmd, ad->previous_argument, ad->current_argument, ad->preceding_arguments,
+            !ad->had_ddash, &ad->do_file)) {  // Invoke any custom completions for this command.
     }
 }
 
@@ -1475,6 +1726,9 @@ void completer_t::walk_wrap_chain(const wcstring &cmd, const wcstring &cmdline,
     if (ctx.cancel_checker()) return;
 
     // Extract command from the command line and invoke the receiver with it.
+    // Wrap targets may add arguments, so positions are counted from this command.
+    const scoped_push<wcstring_list_t> saved_args(&ad->preceding_arguments,
+                                                  preceding_arguments(cmdline, cmdrange));
     complete_custom(cmd, cmdline, ad);
 
     wcstring_list_t targets = complete_get_wrap_targets(cmd);
@@ -1609,6 +1863,7 @@ void completer_t::perform_for_commandline(wcstring cmdline) {
 
     const size_t cursor_pos = cmdline.size();
     const bool is_autosuggest = (flags & completion_request_t::autosuggestion);
+    const scoped_push<wcstring> remember_cmdline(&current_cmdline, cmdline);
 
     // Find the process to operate on. The cursor may be past it (#1261), so backtrack
     // until we know we're no longer in a space. But the space may actually be part of the
@@ -1769,8 +2024,17 @@ completion_list_t complete(const wcstring &cmd_with_subcmds, completion_request_
                                &cmdsubst_end);
     assert(cmdsubst_begin != nullptr && cmdsubst_end != nullptr && cmdsubst_end >= cmdsubst_begin);
     wcstring cmd = wcstring(cmdsubst_begin, cmdsubst_end - cmdsubst_begin);
+
+    // Completion requests made while completing, like `complete -C` in a completion function, are
+    // part of the outer request for the arguments cache.
+    std::shared_ptr<completion_arguments_cache_t> cache;
+    if (ctx.parser && ctx.parser->libdata().complete_recursion_level == 0) {
+        cache = arguments_cache_of(*ctx.parser);
+        cache->request_started(ctx.parser->libdata().exec_count);
+    }
     completer_t completer(ctx, flags);
     completer.perform_for_commandline(std::move(cmd));
+    if (cache) cache->request_finished(ctx.parser->libdata().exec_count);
     return completer.acquire_completions();
 }
 
@@ -1806,6 +2070,14 @@ static wcstring completion2string(const complete_entry_opt_t &o, const wcstring
         append_switch(out, L"requires-param");
     }
 
+    if (o.result_mode.attached_param) append_switch(out, L"short-option-requires-argument");
+    if (o.result_mode.only) append_switch(out, L"only");
+    if (o.result_mode.position > 0) {
+        wcstring position = to_string(o.result_mode.position);
+        if (o.result_mode.position_or_later) position.push_back(L'-');
+        append_switch(out, L"position", position);
+    }
+
     if (is_path)
         append_switch(out, L'p', cmd);
     else {
@@ -21,6 +21,18 @@ struct completion_mode_t {
 
     /// If set, require a parameter after completion.
     bool requires_param{false};
+
+    /// If set, a short option requires a parameter in the same token, like -n5. The next argument
+    /// is never its parameter.
+    bool attached_param{false};
+
+    /// If set, and the condition is true, these arguments replace all other completions.
+    bool only{false};
+
+    /// If nonzero, only complete the argument at this position among the arguments that are not
+    /// options, counting from 1. If position_or_later is set, also those after it.
+    size_t position{0};
+    bool position_or_later{false};
 };
 
 /// Character that separates the completion and description on programmable completions.
@@ -163,6 +175,9 @@ class completion_receiver_t {
     /// \return how many completions we have stored.
     size_t size() const { return completions_.size(); }
 
+    /// \return how many more completions we can add before reaching our limit.
+    size_t remaining_capacity() const { return limit_ < size() ? 0 : limit_ - size(); }
+
     /// \return a completion at an index.
     completion_t &at(size_t idx) { return completions_.at(idx); }
     const completion_t &at(size_t idx) const { return completions_.at(idx); }
@@ -278,4 +293,7 @@ wcstring_list_t complete_get_wrap_targets(const wcstring &command);
 // Observes that fish_complete_path has changed.
 void complete_invalidate_path();
 
+// Forget the argument expansions kept across completion requests.
+void complete_invalidate_argument_cache();
+
 #endif
@@ -13,6 +13,7 @@
 
 #include <algorithm>
 #include <iterator>
+#include <map>
 #include <mutex>
 #include <set>
 #include <utility>
@@ -71,6 +72,11 @@ static relaxed_atomic_bool_t s_uvar_scope_is_global{false};
 
 bool env_universal_barrier() { return env_stack_t::principal().universal_barrier(); }
 
+wcstring env_universal_file_path() {
+    if (s_uvar_scope_is_global) return wcstring{};
+    return uvars()->get_path();
+}
+
 struct electric_var_t {
     enum {
         freadonly = 1 << 0,  // May not be modified by the user.
@@ -94,7 +100,7 @@ struct electric_var_t {
 static constexpr const electric_var_t electric_variables[] = {
     {L"FISH_VERSION", electric_var_t::freadonly},
     {L"PWD", electric_var_t::freadonly | electric_var_t::fcomputed | electric_var_t::fexports},
-    {L"SHLVL", electric_var_t::freadonly | electric_var_t::fexports},
+    {L"SHLVL", electric_var_t::fexports},
     {L"_", electric_var_t::freadonly},
     {L"fish_kill_signal", electric_var_t::freadonly | electric_var_t::fcomputed},
     {L"fish_killring", electric_var_t::freadonly | electric_var_t::fcomputed},
@@ -139,8 +145,32 @@ static bool is_read_only(const wchar_t *key) {
 static bool is_read_only(const wcstring &key) { return is_read_only(key.c_str()); }
 
 /// Return true if a variable should become a path variable by default. See #436.
-static bool variable_should_auto_pathvar(const wcstring &name) {
-    return string_suffixes_string(L"PATH", name);
+/// The name has to end in PATH, and a single value is only a path if it has a colon in it. That
+/// keeps a value like NIX_PATH=nixpkgs as it is, while `set FOOPATH a b` is still a path.
+static bool variable_should_auto_pathvar(const wcstring &name, const wcstring_list_t &val) {
+    if (!string_suffixes_string(L"PATH", name)) return false;
+    return val.size() != 1 || val.front().find(PATH_ARRAY_SEP) != wcstring::npos;
+}
+
+/// The --path and --unpath decisions made in this session, by variable name. They outlive the
+/// variable, so erasing it and setting it again keeps the decision.
+static acquired_lock<std::map<wcstring, bool>> explicit_pathvars() {
+    // Leaked to avoid shutdown dtor registration.
+    static auto *const s_explicit_pathvars = new owning_lock<std::map<wcstring, bool>>();
+    return s_explicit_pathvars->acquire();
+}
+
+/// \return whether \p key was made a path variable with --path or --unpath, in this session or as
+/// a universal variable, or none() if that was never decided.
+static maybe_t<bool> explicit_pathvar(const wcstring &key) {
+    {
+        auto decisions = explicit_pathvars();
+        auto iter = decisions->find(key);
+        if (iter != decisions->end()) return iter->second;
+    }
+    auto uvar = uvars()->get(key);
+    if (uvar && uvar->pathvar_is_explicit()) return uvar->is_pathvar();
+    return none();
 }
 // This is a big dorky lock we take around everything that might read from or modify an env_node_t.
 // Fine grained locking is annoying here because env_nodes may be shared between env_stacks, so each
@@ -256,8 +286,23 @@ static void setup_path() {
     }
 }
 
+/// \return our copy of the process umask.
+/// umask() can only be read by changing it, and another thread might spawn a process with the
+/// wrong umask in between. So we read it once, and afterwards only set_umask() changes it.
+static relaxed_atomic_t<mode_t> &cached_umask() {
+    static relaxed_atomic_t<mode_t> s_umask{[] {
+        mode_t res = umask(0);
+        umask(res);
+        return res;
+    }()};
+    return s_umask;
+}
+
 void env_init(const struct config_paths_t *paths, bool do_uvars, bool default_paths) {
     env_stack_t &vars = env_stack_t::principal();
+    // Read the umask while we are still the only thread.
+    (void)cached_umask();
+
     // Import environment variables. Walk backwards so that the first one out of any duplicates wins
     // (See issue #2784).
     wcstring key, val;
@@ -293,9 +338,9 @@ void env_init(const struct config_paths_t *paths, bool do_uvars, bool default_pa
         vars.set_one(FISH_HELPDIR_VAR, ENV_GLOBAL, paths->doc);
         vars.set_one(FISH_BIN_DIR, ENV_GLOBAL, paths->bin);
         if (default_paths) {
-            wcstring scstr = paths->data;
-            scstr.append(L"/functions");
-            vars.set_one(L"fish_function_path", ENV_GLOBAL, scstr);
+            // Only the bundled functions and completions.
+            vars.set_one(L"fish_function_path", ENV_GLOBAL, paths->data + L"/functions");
+            vars.set_one(L"fish_complete_path", ENV_GLOBAL, paths->data + L"/completions");
         }
     }
 
@@ -372,17 +417,19 @@ void env_init(const struct config_paths_t *paths, bool do_uvars, bool default_pa
     get_hostname_identifier(hostname);
     vars.set_one(L"hostname", ENV_GLOBAL, hostname);
 
-    // Set up SHLVL variable. Not we can't use vars.get() because SHLVL is read-only, and therefore
+    // Set up SHLVL variable. Note we can't use vars.get() because SHLVL is electric, and therefore
     // was not inherited from the environment.
-    if (is_interactive_session()) {
+    // Only shells that a user actually works in count as a level, so `fish -c` leaves it alone.
+    if (is_interactive_session() || get_login()) {
         wcstring nshlvl_str = L"1";
         if (const char *shlvl_var = getenv("SHLVL")) {
             const wchar_t *end;
-            // TODO: Figure out how to handle invalid numbers better. Shouldn't we issue a
-            // diagnostic?
             long shlvl_i = fish_wcstol(str2wcstring(shlvl_var).c_str(), &end);
             if (!errno && shlvl_i >= 0) {
                 nshlvl_str = to_string(shlvl_i + 1);
+            } else {
+                FLOGF(env_export, L"Inherited SHLVL '%s' is not a valid level, treating it as 0",
+                      shlvl_var);
             }
         }
         vars.set_one(L"SHLVL", ENV_GLOBAL | ENV_EXPORT, nshlvl_str);
@@ -442,6 +489,20 @@ void env_init(const struct config_paths_t *paths, bool do_uvars, bool default_pa
         for (const auto &kv : table) {
             const wcstring &name = kv.first;
             const env_var_t &uvar = kv.second;
+
+            // An inherited variable was split before we knew about a --path or --unpath decision
+            // for the universal variable of the same name. Join it and split it again.
+            if (uvar.pathvar_is_explicit()) {
+                maybe_t<env_var_t> inherited = vars.globals().get(name, ENV_GLOBAL);
+                if (inherited && !inherited->pathvar_is_explicit() &&
+                    inherited->is_pathvar() != uvar.is_pathvar()) {
+                    env_mode_flags_t mode = ENV_GLOBAL;
+                    mode |= inherited->exports() ? ENV_EXPORT : ENV_UNEXPORT;
+                    mode |= uvar.is_pathvar() ? ENV_PATHVAR : ENV_UNPATHVAR;
+                    vars.globals().set_one(name, mode, inherited->as_string());
+                }
+            }
+
             if (!uvar.exports()) continue;
             // Look for a global exported variable with the same name.
             maybe_t<env_var_t> global = vars.globals().get(name, ENV_GLOBAL | ENV_EXPORT);
@@ -460,7 +521,9 @@ static int set_umask(const wcstring_list_t &list_val) {
 
     if (errno || mask > 0777 || mask < 0) return ENV_INVALID;
     // Do not actually create a umask variable. On env_stack_t::get() it will be calculated.
+    // Our caller holds the env_lock, so the two can't get out of sync.
     umask(mask);
+    cached_umask() = static_cast<mode_t>(mask);
     return ENV_OK;
 }
 
@@ -518,9 +581,10 @@ struct query_t {
 
 // Struct representing one level in the function variable stack.
 class env_node_t {
+    /// Variable table. This may be shared with snapshots, so it is copied before being modified.
+    std::shared_ptr<var_table_t> env_{std::make_shared<var_table_t>()};
+
    public:
-    /// Variable table.
-    var_table_t env;
     /// Does this node imply a new variable scope? If yes, all non-global variables below this one
     /// in the stack are invisible. If new_scope is set for the global variable node, the universe
     /// will explode.
@@ -534,9 +598,27 @@ class env_node_t {
     env_node_t(bool is_new_scope, std::shared_ptr<env_node_t> next_scope)
         : new_scope(is_new_scope), next(std::move(next_scope)) {}
 
+    /// \return the variable table, for reading.
+    const var_table_t &env() const { return *env_; }
+
+    /// \return the variable table, for modification. If a snapshot shares it, it is copied first.
+    /// Only snapshots made under the env lock can share it, so this is safe under that lock.
+    var_table_t &mutable_env() {
+        if (env_.use_count() > 1) env_ = std::make_shared<var_table_t>(*env_);
+        return *env_;
+    }
+
+    /// \return a new node sharing our variables, for a snapshot.
+    std::shared_ptr<env_node_t> share_with_next(std::shared_ptr<env_node_t> next_scope) const {
+        auto result = std::make_shared<env_node_t>(new_scope, std::move(next_scope));
+        result->env_ = env_;
+        result->export_gen = export_gen;
+        return result;
+    }
+
     maybe_t<env_var_t> find_entry(const wcstring &key) {
-        auto it = env.find(key);
-        if (it != env.end()) return it->second;
+        auto it = env().find(key);
+        if (it != env().end()) return it->second;
         return none();
     }
 
@@ -631,7 +713,7 @@ static void get_exported(const env_node_ref_t &n, var_table_t &table) {
     // Allow parent scopes to populate first, since we may want to overwrite those results.
     get_exported(n->next, table);
 
-    for (const auto &kv : n->env) {
+    for (const auto &kv : n->env()) {
         const wcstring &key = kv.first;
         const env_var_t &var = kv.second;
         if (var.exports()) {
@@ -756,13 +838,7 @@ maybe_t<env_var_t> env_scoped_impl_t::try_get_computed(const wcstring &key) cons
         const auto &js = perproc_data().statuses;
         return env_var_t(L"fish_kill_signal", to_string(js.kill_signal));
     } else if (key == L"umask") {
-        // note umask() is an absurd API: you call it to set the value and it returns the old
-        // value. Thus we have to call it twice, to reset the value. The env_lock protects
-        // against races. Guess what the umask is; if we guess right we don't need to reset it.
-        mode_t guess = 022;
-        mode_t res = umask(guess);
-        if (res != guess) umask(res);
-        return env_var_t(L"umask", format_string(L"0%0.3o", res));
+        return env_var_t(L"umask", format_string(L"0%0.3o", static_cast<mode_t>(cached_umask())));
     }
     // We should never get here unless the electric var list is out of sync with the above code.
     DIE("unrecognized computed var name");
@@ -771,8 +847,8 @@ maybe_t<env_var_t> env_scoped_impl_t::try_get_computed(const wcstring &key) cons
 maybe_t<env_var_t> env_scoped_impl_t::try_get_local(const wcstring &key) const {
     auto cursor = locals_;
     while (cursor) {
-        auto where = cursor->env.find(key);
-        if (where != cursor->env.end()) {
+        auto where = cursor->env().find(key);
+        if (where != cursor->env().end()) {
             return where->second;
         }
         cursor = cursor->next;
@@ -781,8 +857,8 @@ maybe_t<env_var_t> env_scoped_impl_t::try_get_local(const wcstring &key) const {
 }
 
 maybe_t<env_var_t> env_scoped_impl_t::try_get_global(const wcstring &key) const {
-    auto where = globals_->env.find(key);
-    if (where != globals_->env.end()) {
+    auto where = globals_->env().find(key);
+    if (where != globals_->env().end()) {
         return where->second;
     }
     return none();
@@ -833,12 +909,12 @@ wcstring_list_t env_scoped_impl_t::get_names(int flags) const {
 
     if (query.local) {
         for (auto cursor = locals_; cursor != nullptr; cursor = cursor->next) {
-            add_keys(cursor->env);
+            add_keys(cursor->env());
         }
     }
 
     if (query.global) {
-        add_keys(globals_->env);
+        add_keys(globals_->env());
         // Add electrics.
         for (const auto &ev : electric_variables) {
             if (ev.exports() ? query.exports : query.unexports) {
@@ -856,22 +932,17 @@ wcstring_list_t env_scoped_impl_t::get_names(int flags) const {
 }
 
 /// Recursive helper to snapshot a series of nodes.
+/// The variable tables are shared, and only copied when the live environment modifies them.
 static env_node_ref_t copy_node_chain(const env_node_ref_t &node) {
     if (node == nullptr) {
         return nullptr;
     }
-
-    auto next = copy_node_chain(node->next);
-    auto result = std::make_shared<env_node_t>(node->new_scope, next);
-    // Copy over variables.
-    // Note assigning env is a potentially big copy.
-    result->export_gen = node->export_gen;
-    result->env = node->env;
-    return result;
+    return node->share_with_next(copy_node_chain(node->next));
 }
 
 std::shared_ptr<environment_t> env_scoped_impl_t::snapshot() const {
-    auto ret = std::make_shared<env_scoped_impl_t>(copy_node_chain(locals_), globals_);
+    auto ret = std::make_shared<env_scoped_impl_t>(copy_node_chain(locals_),
+                                                   copy_node_chain(globals_));
     ret->perproc_data_ = this->perproc_data_;
     return ret;
 }
@@ -887,6 +958,10 @@ struct mod_result_t {
     // Whether we modified universal variables.
     bool uvar_modified{false};
 
+    // Whether the variable's value or flags changed, or it did not exist before.
+    // Setting a variable to what it already is does not fire a variable event.
+    bool value_changed{true};
+
     explicit mod_result_t(int status) : status(status) {}
 };
 
@@ -926,9 +1001,13 @@ class env_stack_impl_t final : public env_scoped_impl_t {
 
     /// A restricted set of variable flags.
     struct var_flags_t {
-        // if set, whether we should become a path variable; otherwise guess based on the name.
+        // if set, whether we should become a path variable; otherwise use an earlier explicit
+        // decision, or guess based on the name and value.
         maybe_t<bool> pathvar{};
 
+        // whether pathvar comes from --path or --unpath.
+        bool pathvar_explicit{};
+
         // if set, the new export value; otherwise inherit any existing export value.
         maybe_t<bool> exports{};
 
@@ -939,7 +1018,7 @@ class env_stack_impl_t final : public env_scoped_impl_t {
     /// Find the first node in the chain starting at \p node which contains the given key \p key.
     static env_node_ref_t find_in_chain(const env_node_ref_t &node, const wcstring &key) {
         for (auto cursor = node; cursor; cursor = cursor->next) {
-            if (cursor->env.count(key)) {
+            if (cursor->env().count(key)) {
                 return cursor;
             }
         }
@@ -950,12 +1029,12 @@ class env_stack_impl_t final : public env_scoped_impl_t {
     /// \return true if the variable was found and removed.
     bool remove_from_chain(const env_node_ref_t &node, const wcstring &key) const {
         for (auto cursor = node; cursor; cursor = cursor->next) {
-            auto iter = cursor->env.find(key);
-            if (iter != cursor->env.end()) {
+            auto iter = cursor->env().find(key);
+            if (iter != cursor->env().end()) {
                 if (iter->second.exports()) {
                     node->changed_exported();
                 }
-                cursor->env.erase(iter);
+                cursor->mutable_env().erase(key);
                 return true;
             }
         }
@@ -968,10 +1047,12 @@ class env_stack_impl_t final : public env_scoped_impl_t {
     maybe_t<int> try_set_electric(const wcstring &key, const query_t &query, wcstring_list_t &val);
 
     /// Set a universal value.
-    void set_universal(const wcstring &key, wcstring_list_t val, const query_t &query);
+    /// \return whether the variable changed.
+    bool set_universal(const wcstring &key, wcstring_list_t val, const query_t &query);
 
     /// Set a variable in a given node \p node.
-    void set_in_node(const env_node_ref_t &node, const wcstring &key, wcstring_list_t &&val,
+    /// \return whether the variable changed.
+    bool set_in_node(const env_node_ref_t &node, const wcstring &key, wcstring_list_t &&val,
                      const var_flags_t &flags);
 
     // Implement the default behavior of 'set' by finding the node for an unspecified scope.
@@ -988,8 +1069,8 @@ class env_stack_impl_t final : public env_scoped_impl_t {
         env_node_ref_t node = find_in_chain(locals_, key);
         if (!node) node = find_in_chain(globals_, key);
         if (node) {
-            auto iter = node->env.find(key);
-            assert(iter != node->env.end() && "Node should contain key");
+            auto iter = node->env().find(key);
+            assert(iter != node->env().end() && "Node should contain key");
             return &iter->second;
         }
         return nullptr;
@@ -1004,9 +1085,9 @@ void env_stack_impl_t::push_shadowing() {
     // Propagate local exported variables.
     auto node = std::make_shared<env_node_t>(true, nullptr);
     for (auto cursor = locals_; cursor; cursor = cursor->next) {
-        for (const auto &var : cursor->env) {
+        for (const auto &var : cursor->env()) {
             if (var.second.exports()) {
-                node->env.insert(var);
+                node->mutable_env().insert(var);
                 node->changed_exported();
             }
         }
@@ -1040,16 +1121,27 @@ static wcstring_list_t colon_split(const wcstring_list_t &val) {
     return split_val;
 }
 
-void env_stack_impl_t::set_in_node(const env_node_ref_t &node, const wcstring &key,
+bool env_stack_impl_t::set_in_node(const env_node_ref_t &node, const wcstring &key,
                                    wcstring_list_t &&val, const var_flags_t &flags) {
-    env_var_t &var = node->env[key];
+    bool existed = node->env().count(key) > 0;
+    env_var_t &var = node->mutable_env()[key];
+    const env_var_t old_var = var;
 
     // Use an explicit exports, or inherit from the existing variable.
     bool res_exports = flags.exports.has_value() ? *flags.exports : var.exports();
 
-    // Pathvar is inferred from the name. If set, split our entry about colons.
-    bool res_pathvar =
-        flags.pathvar.has_value() ? *flags.pathvar : variable_should_auto_pathvar(key);
+    // Pathvar is inferred from the name and value, unless it was decided explicitly. If set, split
+    // our entry about colons.
+    bool res_pathvar_explicit = flags.pathvar_explicit;
+    bool res_pathvar;
+    if (flags.pathvar.has_value()) {
+        res_pathvar = *flags.pathvar;
+    } else if (auto decided = explicit_pathvar(key)) {
+        res_pathvar = *decided;
+        res_pathvar_explicit = true;
+    } else {
+        res_pathvar = variable_should_auto_pathvar(key, val);
+    }
     if (res_pathvar) {
         val = colon_split(val);
     }
@@ -1057,6 +1149,7 @@ void env_stack_impl_t::set_in_node(const env_node_ref_t &node, const wcstring &k
     var = var.setting_vals(std::move(val))
               .setting_exports(res_exports)
               .setting_pathvar(res_pathvar)
+              .setting_pathvar_explicit(res_pathvar_explicit)
               .setting_read_only(is_read_only(key));
 
     // Perhaps mark that this node contains an exported variable, or shadows an exported variable.
@@ -1064,6 +1157,7 @@ void env_stack_impl_t::set_in_node(const env_node_ref_t &node, const wcstring &k
     if (res_exports || flags.parent_exports) {
         node->changed_exported();
     }
+    return !existed || var != old_var;
 }
 
 maybe_t<int> env_stack_impl_t::try_set_electric(const wcstring &key, const query_t &query,
@@ -1113,7 +1207,7 @@ maybe_t<int> env_stack_impl_t::try_set_electric(const wcstring &key, const query
 }
 
 /// Set a universal variable, inheriting as applicable from the given old variable.
-void env_stack_impl_t::set_universal(const wcstring &key, wcstring_list_t val,
+bool env_stack_impl_t::set_universal(const wcstring &key, wcstring_list_t val,
                                      const query_t &query) {
     ASSERT_IS_MAIN_THREAD();
     auto oldvar = uvars()->get(key);
@@ -1128,12 +1222,17 @@ void env_stack_impl_t::set_universal(const wcstring &key, wcstring_list_t val,
     // Resolve whether to be a path variable.
     // Here we fall back to the auto-pathvar behavior.
     bool pathvar = false;
+    bool pathvar_explicit = true;
     if (query.has_pathvar_unpathvar) {
         pathvar = query.pathvar;
-    } else if (oldvar) {
-        pathvar = oldvar->is_pathvar();
+    } else if (auto decided = explicit_pathvar(key)) {
+        pathvar = *decided;
+    } else if (oldvar && oldvar->is_pathvar() && !string_suffixes_string(L"PATH", key)) {
+        // This can only have come from --path, in a version of fish that didn't record that.
+        pathvar = true;
     } else {
-        pathvar = variable_should_auto_pathvar(key);
+        pathvar = variable_should_auto_pathvar(key, val);
+        pathvar_explicit = false;
     }
 
     // Split about ':' if it's a path variable.
@@ -1149,9 +1248,12 @@ void env_stack_impl_t::set_universal(const wcstring &key, wcstring_list_t val,
     env_var_t::env_var_flags_t varflags = 0;
     if (exports) varflags |= env_var_t::flag_export;
     if (pathvar) varflags |= env_var_t::flag_pathvar;
+    if (pathvar_explicit) varflags |= env_var_t::flag_pathvar_explicit;
     env_var_t new_var{val, varflags};
 
+    bool changed = !oldvar || *oldvar != new_var;
     uvars()->set(key, new_var);
+    return changed;
 }
 
 mod_result_t env_stack_impl_t::set(const wcstring &key, env_mode_flags_t mode,
@@ -1163,13 +1265,17 @@ mod_result_t env_stack_impl_t::set(const wcstring &key, env_mode_flags_t mode,
     }
 
     // Resolve as much of our flags as we can. Note these contain maybes, and we may defer the final
-    // decision until the set_in_node call. Also note that we only inherit pathvar, not export. For
-    // example, if you have a global exported variable, a local variable with the same name will not
-    // automatically be exported. But if you have a global pathvar, a local variable with the same
-    // name will be a pathvar. This is historical.
+    // decision until the set_in_node call. Also note that we only inherit an explicit pathvar, not
+    // export. For example, if you have a global exported variable, a local variable with the same
+    // name will not automatically be exported. But if you have a global `set --path` variable, a
+    // local variable with the same name will be a pathvar. This is historical. A pathvar that was
+    // only inferred is inferred again from the new value.
     var_flags_t flags{};
     if (const env_var_t *existing = find_variable(key)) {
-        flags.pathvar = existing->is_pathvar();
+        if (existing->pathvar_is_explicit()) {
+            flags.pathvar = existing->is_pathvar();
+            flags.pathvar_explicit = true;
+        }
         flags.parent_exports = existing->exports();
     }
     if (query.has_export_unexport) {
@@ -1177,6 +1283,8 @@ mod_result_t env_stack_impl_t::set(const wcstring &key, env_mode_flags_t mode,
     }
     if (query.has_pathvar_unpathvar) {
         flags.pathvar = query.pathvar;
+        flags.pathvar_explicit = true;
+        (*explicit_pathvars())[key] = query.pathvar;
     }
 
     mod_result_t result{ENV_OK};
@@ -1185,33 +1293,33 @@ mod_result_t env_stack_impl_t::set(const wcstring &key, env_mode_flags_t mode,
         //
         // If we don't have uvars, fall back to using globals
         if (query.universal && !s_uvar_scope_is_global) {
-            set_universal(key, std::move(val), query);
+            result.value_changed = set_universal(key, std::move(val), query);
             result.uvar_modified = true;
         } else if (query.global || (query.universal && s_uvar_scope_is_global)) {
-            set_in_node(globals_, key, std::move(val), flags);
+            result.value_changed = set_in_node(globals_, key, std::move(val), flags);
             result.global_modified = true;
         } else if (query.local) {
             assert(locals_ != globals_ && "Locals should not be globals");
-            set_in_node(locals_, key, std::move(val), flags);
+            result.value_changed = set_in_node(locals_, key, std::move(val), flags);
         } else {
             DIE("Unknown scope");
         }
     } else if (env_node_ref_t node = find_in_chain(locals_, key)) {
         // Existing local variable.
-        set_in_node(node, key, std::move(val), flags);
+        result.value_changed = set_in_node(node, key, std::move(val), flags);
     } else if (env_node_ref_t node = find_in_chain(globals_, key)) {
         // Existing global variable.
-        set_in_node(node, key, std::move(val), flags);
+        result.value_changed = set_in_node(node, key, std::move(val), flags);
         result.global_modified = true;
     } else if (uvars()->get(key)) {
         // Existing universal variable.
-        set_universal(key, std::move(val), query);
+        result.value_changed = set_universal(key, std::move(val), query);
         result.uvar_modified = true;
     } else {
         // Unspecified scope with no existing variables.
         auto node = resolve_unspecified_scope();
         assert(node && "Should always resolve some scope");
-        set_in_node(node, key, std::move(val), flags);
+        result.value_changed = set_in_node(node, key, std::move(val), flags);
         result.global_modified = (node == globals_);
     }
     return result;
@@ -1330,7 +1438,7 @@ int env_stack_t::set(const wcstring &key, env_mode_flags_t mode, wcstring_list_t
         if (ret.global_modified || is_principal()) {
             env_dispatch_var_change(key, *this);
         }
-        if (out_events) {
+        if (out_events && ret.value_changed) {
             out_events->push_back(event_t::variable(key, {L"VARIABLE", L"SET", key}));
         }
     }
@@ -1401,7 +1509,7 @@ void env_stack_t::pop() {
     if (this == principal_ref().get()) {
         // TODO: we would like to coalesce locale / curses changes, so that we only re-initialize
         // once.
-        for (const auto &kv : popped->env) {
+        for (const auto &kv : popped->env()) {
             env_dispatch_var_change(kv.first, *this);
         }
     }
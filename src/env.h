@@ -105,9 +105,10 @@ class env_var_t {
 
    public:
     enum {
-        flag_export = 1 << 0,     // whether the variable is exported
-        flag_read_only = 1 << 1,  // whether the variable is read only
-        flag_pathvar = 1 << 2,    // whether the variable is a path variable
+        flag_export = 1 << 0,            // whether the variable is exported
+        flag_read_only = 1 << 1,         // whether the variable is read only
+        flag_pathvar = 1 << 2,           // whether the variable is a path variable
+        flag_pathvar_explicit = 1 << 3,  // whether flag_pathvar came from --path or --unpath
     };
 
     // Constructors.
@@ -131,6 +132,7 @@ class env_var_t {
     bool read_only() const { return flags_ & flag_read_only; }
     bool exports() const { return flags_ & flag_export; }
     bool is_pathvar() const { return flags_ & flag_pathvar; }
+    bool pathvar_is_explicit() const { return flags_ & flag_pathvar_explicit; }
     env_var_flags_t get_flags() const { return flags_; }
 
     wcstring as_string() const;
@@ -165,6 +167,16 @@ class env_var_t {
         return env_var_t{vals_, flags};
     }
 
+    env_var_t setting_pathvar_explicit(bool explicit_) const {
+        env_var_flags_t flags = flags_;
+        if (explicit_) {
+            flags |= flag_pathvar_explicit;
+        } else {
+            flags &= ~flag_pathvar_explicit;
+        }
+        return env_var_t{vals_, flags};
+    }
+
     env_var_t setting_read_only(bool read_only) const {
         env_var_flags_t flags = flags_;
         if (read_only) {
@@ -311,12 +323,20 @@ class env_stack_t final : public environment_t {
 
 bool get_use_posix_spawn();
 
+/// Whether $fish_completion_matching asks for fuzzy completions, which also match candidates with
+/// some characters out of order.
+bool get_fuzzy_completion_matching();
+
 extern bool term_has_xn;  // does the terminal have the "eat_newline_glitch"
 
 /// Synchronizes all universal variable changes: writes everything out, reads stuff in.
 /// \return true if any value changed.
 bool env_universal_barrier();
 
+/// \return the path of the universal variable file, or an empty string if universal variables are
+/// not in use.
+wcstring env_universal_file_path();
+
 /// Returns true if we think the terminal supports setting its title.
 bool term_supports_setting_title();
 
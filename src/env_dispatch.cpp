@@ -49,15 +49,14 @@
 #include "maybe.h"
 #include "output.h"
 #include "parser.h"
+#include "path.h"
 #include "proc.h"
 #include "reader.h"
 #include "screen.h"
 #include "termsize.h"
+#include "wildcard.h"
 #include "wutil.h"  // IWYU pragma: keep
 
-#define DEFAULT_TERM1 "ansi"
-#define DEFAULT_TERM2 "dumb"
-
 /// List of all locale environment variable names that might trigger (re)initializing the locale
 /// subsystem.
 static const wcstring locale_variables[] = {
@@ -110,7 +109,7 @@ class var_dispatch_table_t {
 
 // Forward declarations.
 static void init_curses(const environment_t &vars);
-static void init_locale(const environment_t &vars);
+static void init_locale(const environment_t &vars, bool warn);
 static void update_fish_color_support(const environment_t &vars);
 
 /// True if we think we can set the terminal title.
@@ -245,14 +244,23 @@ static void handle_complete_path_change(const env_stack_t &vars) {
     complete_invalidate_path();
 }
 
+static void handle_path_dirs_change(const wcstring &var_name, const env_stack_t &vars) {
+    auto var = vars.get(var_name);
+    path_check_dirs(var_name, var ? var->as_list() : wcstring_list_t{});
+}
+
 static void handle_tz_change(const wcstring &var_name, const env_stack_t &vars) {
     handle_timezone(var_name.c_str(), vars);
 }
 
 static void handle_locale_change(const environment_t &vars) {
-    init_locale(vars);
+    // Don't warn about the locale fish picks itself when none is configured, see
+    // __fish_set_locale.
+    init_locale(vars, !is_within_fish_initialization());
     // We need to re-guess emoji width because the locale might have changed to a multibyte one.
     guess_emoji_width(vars);
+    // Cached completions may have been sorted or described in the old locale.
+    complete_invalidate_argument_cache();
 }
 
 static void handle_curses_change(const environment_t &vars) {
@@ -292,6 +300,15 @@ static void handle_fish_use_posix_spawn_change(const environment_t &vars) {
     }
 }
 
+static relaxed_atomic_bool_t g_fuzzy_completion_matching{false};
+
+bool get_fuzzy_completion_matching() { return g_fuzzy_completion_matching; }
+
+static void handle_fish_completion_matching_change(const environment_t &vars) {
+    auto var = vars.get(L"fish_completion_matching");
+    g_fuzzy_completion_matching = var && var->as_string() == L"fuzzy";
+}
+
 /// Allow the user to override the limit on how much data the `read` command will process.
 /// This is primarily for testing but could be used by users in special situations.
 static void handle_read_limit_change(const environment_t &vars) {
@@ -306,6 +323,65 @@ static void handle_read_limit_change(const environment_t &vars) {
     }
 }
 
+/// Allow the user to change how deep recursive wildcards descend.
+static void handle_glob_max_depth_change(const environment_t &vars) {
+    size_t depth = kDefaultGlobMaxDepth;
+    auto depth_var = vars.get(L"fish_glob_max_depth");
+    if (!depth_var.missing_or_empty()) {
+        depth = fish_wcstoull(depth_var->as_string().c_str());
+        if (errno) {
+            FLOGF(warning, "Ignoring fish_glob_max_depth since it is not valid");
+            depth = kDefaultGlobMaxDepth;
+        }
+    }
+    glob_max_depth = depth;
+}
+
+static void handle_history_max_size_change(const environment_t &vars) {
+    size_t max_size = 0;
+    auto max_size_var = vars.get(L"fish_history_max_size");
+    if (!max_size_var.missing_or_empty()) {
+        max_size = fish_wcstoull(max_size_var->as_string().c_str());
+        if (errno) {
+            FLOGF(warning, "Ignoring fish_history_max_size since it is not valid");
+            max_size = 0;
+        }
+    }
+    history_set_max_size(max_size);
+}
+
+static void handle_history_dedup_change(const environment_t &vars) {
+    history_dedup_t dedup = history_dedup_t::global;
+    auto dedup_var = vars.get(L"fish_history_dedup");
+    if (!dedup_var.missing_or_empty()) {
+        const wcstring &val = dedup_var->as_string();
+        if (val == L"none") {
+            dedup = history_dedup_t::none;
+        } else if (val == L"consecutive") {
+            dedup = history_dedup_t::consecutive;
+        } else if (val != L"global") {
+            FLOGF(warning, "Ignoring fish_history_dedup since it is not valid");
+        }
+    }
+    history_set_dedup(dedup);
+}
+
+static void handle_history_save_policy_change(const environment_t &vars) {
+    history_save_policy_t policy = history_save_policy_t::periodic;
+    auto policy_var = vars.get(L"fish_history_save_policy");
+    if (!policy_var.missing_or_empty()) {
+        const wcstring &val = policy_var->as_string();
+        if (val == L"immediate") {
+            policy = history_save_policy_t::immediate;
+        } else if (val == L"on-exit") {
+            policy = history_save_policy_t::on_exit;
+        } else if (val != L"periodic") {
+            FLOGF(warning, "Ignoring fish_history_save_policy since it is not valid");
+        }
+    }
+    history_set_save_policy(policy);
+}
+
 /// Populate the dispatch table used by `env_dispatch_var_change()` to efficiently call the
 /// appropriate function to handle a change to a variable.
 /// Note this returns a new-allocated value that we expect to leak.
@@ -319,6 +395,7 @@ static std::unique_ptr<const var_dispatch_table_t> create_dispatch_table() {
         var_dispatch_table->add(var_name, handle_curses_change);
     }
 
+    var_dispatch_table->add(L"fish_term_capabilities", handle_curses_change);
     var_dispatch_table->add(L"fish_term256", handle_fish_term_change);
     var_dispatch_table->add(L"fish_term24bit", handle_fish_term_change);
     var_dispatch_table->add(L"fish_escape_delay_ms", update_wait_on_escape_ms);
@@ -329,9 +406,16 @@ static std::unique_ptr<const var_dispatch_table_t> create_dispatch_table() {
     var_dispatch_table->add(L"fish_complete_path", handle_complete_path_change);
     var_dispatch_table->add(L"fish_function_path", handle_function_path_change);
     var_dispatch_table->add(L"fish_read_limit", handle_read_limit_change);
+    var_dispatch_table->add(L"fish_glob_max_depth", handle_glob_max_depth_change);
     var_dispatch_table->add(L"fish_history", handle_fish_history_change);
+    var_dispatch_table->add(L"fish_history_max_size", handle_history_max_size_change);
+    var_dispatch_table->add(L"fish_history_dedup", handle_history_dedup_change);
+    var_dispatch_table->add(L"fish_history_save_policy", handle_history_save_policy_change);
+    var_dispatch_table->add(L"PATH", handle_path_dirs_change);
+    var_dispatch_table->add(L"fish_user_paths", handle_path_dirs_change);
     var_dispatch_table->add(L"TZ", handle_tz_change);
     var_dispatch_table->add(L"fish_use_posix_spawn", handle_fish_use_posix_spawn_change);
+    var_dispatch_table->add(L"fish_completion_matching", handle_fish_completion_matching_change);
 
     // This std::move is required to avoid a build error on old versions of libc++ (#5801),
     // but it causes a different warning under newer versions of GCC (observed under GCC 9.3.0,
@@ -348,12 +432,17 @@ static std::unique_ptr<const var_dispatch_table_t> create_dispatch_table() {
 
 static void run_inits(const environment_t &vars) {
     // This is the subset of those dispatch functions which want to be run at startup.
-    init_locale(vars);
+    init_locale(vars, false);
     init_curses(vars);
     guess_emoji_width(vars);
     update_wait_on_escape_ms(vars);
     handle_read_limit_change(vars);
+    handle_glob_max_depth_change(vars);
+    handle_history_max_size_change(vars);
+    handle_history_dedup_change(vars);
+    handle_history_save_policy_change(vars);
     handle_fish_use_posix_spawn_change(vars);
+    handle_fish_completion_matching_change(vars);
 }
 
 /// Updates our idea of whether we support term256 and term24bit (see issue #10222).
@@ -452,27 +541,14 @@ static void update_fish_color_support(const environment_t &vars) {
     output_set_color_support(support);
 }
 
-// Try to initialize the terminfo/curses subsystem using our fallback terminal name. Do not set
-// `TERM` to our fallback. We're only doing this in the hope of getting a minimally functional
-// shell. If we launch an external command that uses TERM it should get the same value we were
-// given, if any.
-static bool initialize_curses_using_fallback(const char *term) {
-    // If $TERM is already set to the fallback name we're about to use there isn't any point in
-    // seeing if the fallback name can be used.
-    auto &vars = env_stack_t::globals();
-    auto term_var = vars.get(L"TERM");
-    if (term_var.missing_or_empty()) return false;
-
-    auto term_env = wcs2string(term_var->as_string());
-    if (term_env == DEFAULT_TERM1 || term_env == DEFAULT_TERM2) return false;
-
-    if (is_interactive_session()) FLOGF(warning, _(L"Using fallback terminal type '%s'."), term);
-
-    int err_ret;
-    if (setupterm(const_cast<char *>(term), STDOUT_FILENO, &err_ret) == OK) return true;
-    if (is_interactive_session()) {
-        FLOGF(warning, _(L"Could not set up terminal using the fallback terminal type '%s'."),
-              term);
+/// Whether the built-in capabilities used for a terminal unknown to terminfo should have 256
+/// colors. Like for the color support above, this goes by $TERM and $COLORTERM.
+static bool fallback_wants_256_colors(const environment_t &vars) {
+    if (auto term = vars.get(L"TERM")) {
+        if (term->as_string().find(L"256color") != wcstring::npos) return true;
+    }
+    if (auto ct = vars.get(L"COLORTERM")) {
+        return ct->as_string() == L"truecolor" || ct->as_string() == L"24bit";
     }
     return false;
 }
@@ -528,23 +604,29 @@ static void init_curses(const environment_t &vars) {
         }
     }
 
+    wcstring_list_t overrides;
+    if (auto caps = vars.get(L"fish_term_capabilities")) caps->to_list(overrides);
+
     int err_ret;
-    if (setupterm(nullptr, STDOUT_FILENO, &err_ret) == ERR) {
+    bool have_entry = setupterm(nullptr, STDOUT_FILENO, &err_ret) == OK;
+    if (!have_entry) {
+        // Without a terminfo entry we make do with built-in capabilities that work with any
+        // xterm-compatible terminal - except when we were told the terminal is dumb.
+        // Do not set `TERM` to anything else, so external commands get the value we were given.
         auto term = vars.get(L"TERM");
-        if (is_interactive_session()) {
-            FLOGF(warning, _(L"Could not set up terminal."));
-            if (term.missing_or_empty()) {
-                FLOGF(warning, _(L"TERM environment variable not set."));
-            } else {
-                FLOGF(warning, _(L"TERM environment variable set to '%ls'."),
-                      term->as_string().c_str());
-                FLOGF(warning, _(L"Check that this terminal type is supported on this system."));
-            }
-        }
-
-        if (!initialize_curses_using_fallback(DEFAULT_TERM1)) {
-            initialize_curses_using_fallback(DEFAULT_TERM2);
+        wcstring term_str = term ? term->as_string() : L"";
+        if ((term_str.empty() || term_str == L"dumb") && overrides.empty()) {
+            FLOGF(term_support, L"No terminfo entry for TERM='%ls', treating it as dumb",
+                  term_str.c_str());
+        } else {
+            bool use_256_colors = fallback_wants_256_colors(vars);
+            FLOGF(term_support, L"No terminfo entry for TERM='%ls', using built-in %s capabilities",
+                  term_str.c_str(), use_256_colors ? "xterm-256color" : "xterm");
+            setupterm_synthesized(false, use_256_colors, overrides);
         }
+    } else if (!overrides.empty()) {
+        FLOGF(term_support, L"Applying $fish_term_capabilities");
+        setupterm_synthesized(true, false, overrides);
     }
 
     can_set_term_title = does_term_support_setting_title(vars);
@@ -565,8 +647,19 @@ static const char *utf8_locales[] = {
     "UTF-8",
 };
 
-/// Initialize the locale subsystem.
-static void init_locale(const environment_t &vars) {
+/// The locale categories fish uses, for when they have to be set separately.
+static const struct {
+    int category;
+    const wchar_t *name;
+} locale_categories[] = {{LC_COLLATE, L"LC_COLLATE"}, {LC_CTYPE, L"LC_CTYPE"},
+                         {LC_MESSAGES, L"LC_MESSAGES"}, {LC_MONETARY, L"LC_MONETARY"},
+                         {LC_NUMERIC, L"LC_NUMERIC"}, {LC_TIME, L"LC_TIME"}};
+
+/// The categories that could not be set from the environment the last time.
+static wcstring_list_t s_failed_categories;
+
+/// Initialize the locale subsystem. If \p warn is set, complain if the locale is not valid.
+static void init_locale(const environment_t &vars, bool warn) {
     // We have to make a copy because the subsequent setlocale() call to change the locale will
     // invalidate the pointer from the this setlocale() call.
     char *old_msg_locale = strdup(setlocale(LC_MESSAGES, nullptr));
@@ -585,6 +678,28 @@ static void init_locale(const environment_t &vars) {
     }
 
     char *locale = setlocale(LC_ALL, "");
+    if (!locale) {
+        // setlocale() changes nothing if any of the categories can't be set, which would keep the
+        // old locale while the variables say otherwise. Set them one by one instead, using the C
+        // locale for the ones that fail.
+        wcstring_list_t failed, newly_failed;
+        for (const auto &cat : locale_categories) {
+            if (!setlocale(cat.category, "")) {
+                setlocale(cat.category, "C");
+                failed.push_back(cat.name);
+                if (!contains(s_failed_categories, cat.name)) newly_failed.push_back(cat.name);
+            }
+        }
+        // Only complain once about each category, in case it was broken to begin with.
+        if (warn && !newly_failed.empty()) {
+            FLOGF(warning, _(L"Could not set %ls from the environment, using 'C' instead."),
+                  join_strings(newly_failed, L',').c_str());
+        }
+        s_failed_categories = std::move(failed);
+        locale = setlocale(LC_ALL, nullptr);
+    } else {
+        s_failed_categories.clear();
+    }
 
     // Try to get a multibyte-capable encoding
     // A "C" locale is broken for our purposes - any wchar functions will break on it.
@@ -613,6 +728,8 @@ static void init_locale(const environment_t &vars) {
     const char *new_msg_locale = setlocale(LC_MESSAGES, nullptr);
     FLOGF(env_locale, L"old LC_MESSAGES locale: '%s'", old_msg_locale);
     FLOGF(env_locale, L"new LC_MESSAGES locale: '%s'", new_msg_locale);
+    // Our own translation cache has to follow LANGUAGE as well as the locale.
+    wgettext_locale_changed();
 #ifdef HAVE__NL_MSG_CAT_CNTR
     if (std::strcmp(old_msg_locale, new_msg_locale) != 0) {
         // Make change known to GNU gettext.
@@ -42,6 +42,7 @@
 #include "env.h"
 #include "env_universal_common.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "flog.h"
 #include "path.h"
 #include "signal.h"
@@ -55,6 +56,14 @@
 #include <notify.h>
 #endif
 
+#ifdef HAVE_INOTIFY_INIT1
+#include <sys/inotify.h>
+#endif
+
+#ifdef HAVE_KQUEUE
+#include <sys/event.h>
+#endif
+
 #ifdef __HAIKU__
 #define _BSD_SOURCE
 #include <bsd/ifaddrs.h>
@@ -72,6 +81,13 @@
 // Maximum file size we'll read.
 static constexpr size_t k_max_read_size = 16 * 1024 * 1024;
 
+/// If the file has more unparseable lines than this, we rewrite it without them.
+static constexpr size_t k_max_bad_lines = 3;
+
+/// How long we remember that a variable was erased. A fish that changed it earlier but had not yet
+/// written the file by then would bring it back.
+static constexpr uint64_t k_erased_lifetime_usec = 24ULL * 60 * 60 * 1000 * 1000;
+
 // Fields used in fish 2.x uvars.
 namespace fish2x_uvars {
 namespace {
@@ -86,6 +102,14 @@ namespace {
 constexpr const char *SETUVAR = "SETUVAR";
 constexpr const char *EXPORT = "--export";
 constexpr const char *PATH = "--path";
+// Whether --path (or its absence) was chosen with `set --path` or `set --unpath`.
+constexpr const char *EXPLICIT_PATH = "--explicit-path";
+// When the variable was last changed. Older fish skip this flag and drop it when rewriting the
+// file, after which any later change wins, as it used to.
+constexpr const char *GENERATION = "--generation=";
+// Erased variables are kept in comments like "# ERASED --generation=N name", which older fish
+// ignore.
+constexpr const char *ERASED = "# ERASED";
 }  // namespace
 }  // namespace fish3_uvars
 
@@ -115,8 +139,25 @@ static maybe_t<wcstring> default_vars_path_directory() {
     return path;
 }
 
+/// \return the path given by $FISH_UNIVERSAL_FILE when fish started, made absolute, or none if it
+/// was not set or empty.
+static const maybe_t<wcstring> &vars_path_from_environment() {
+    // Latch it, so changing the variable later has no effect.
+    static const maybe_t<wcstring> s_path = []() -> maybe_t<wcstring> {
+        const char *env = getenv(UNIVERSAL_FILE_ENV_NAME);
+        if (!env || !*env) return none();
+        wcstring path = str2wcstring(env);
+        if (path.front() != L'/') {
+            path = wgetcwd() + L'/' + path;
+        }
+        return normalize_path(path);
+    }();
+    return s_path;
+}
+
 /// \return the default variable path, or an empty string on failure.
 static wcstring default_vars_path() {
+    if (const auto &path = vars_path_from_environment()) return *path;
     if (auto path = default_vars_path_directory()) {
         path->append(L"/fish_variables");
         return path.acquire();
@@ -137,6 +178,44 @@ static bool match(const wchar_t **inout_cursor, const char *cmd) {
     return true;
 }
 
+static const wchar_t *skip_spaces(const wchar_t *str) {
+    while (*str == L' ' || *str == L'\t') str++;
+    return str;
+}
+
+/// Test if the message msg has a generation flag at the cursor.
+/// On success, sets \p out_generation and updates the cursor to just past the flag.
+static bool match_generation(const wchar_t **inout_cursor, uint64_t *out_generation) {
+    const char *flag = fish3_uvars::GENERATION;
+    const wchar_t *cursor = *inout_cursor;
+    size_t len = std::strlen(flag);
+    if (!std::equal(flag, flag + len, cursor)) {
+        return false;
+    }
+    const wchar_t *end = nullptr;
+    unsigned long long generation = fish_wcstoull(cursor + len, &end);
+    // fish_wcstoull sets errno to -1 if there is something after the number, like the name.
+    if ((errno && errno != -1) || (*end && end[-1] != L' ' && end[-1] != L'\t')) {
+        return false;
+    }
+    *out_generation = generation;
+    *inout_cursor = end;
+    return true;
+}
+
+/// \return a new generation for a variable changed now. These are times in microseconds, but
+/// increase strictly, even if the clock does not.
+static uint64_t next_generation() {
+    static std::atomic<uint64_t> s_last_generation{0};
+    const uint64_t now = static_cast<uint64_t>(get_time());
+    uint64_t last = s_last_generation.load();
+    uint64_t next;
+    do {
+        next = std::max(now, last + 1);
+    } while (!s_last_generation.compare_exchange_weak(last, next));
+    return next;
+}
+
 /// The universal variable format has some funny escaping requirements; here we try to be safe.
 static bool is_universal_safe_to_encode_directly(wchar_t c) {
     if (c < 32 || c > 128) return false;
@@ -174,8 +253,12 @@ static bool append_utf8(const wcstring &input, std::string *receiver, std::strin
 
 /// Creates a file entry like "SET fish_color_cwd:FF0". Appends the result to *result (as UTF8).
 /// Returns true on success. storage may be used for temporary storage, to avoid allocations.
+/// \p generation is when the variable was last changed, or 0 if we don't know. \p unknown_flags are
+/// flags from a newer fish that we read and write back unchanged.
 static bool append_file_entry(env_var_t::env_var_flags_t flags, const wcstring &key_in,
-                              const wcstring &val_in, std::string *result, std::string *storage) {
+                              const wcstring &val_in, uint64_t generation,
+                              const wcstring_list_t *unknown_flags, std::string *result,
+                              std::string *storage) {
     namespace f3 = fish3_uvars;
     assert(storage != nullptr);
     assert(result != nullptr);
@@ -197,6 +280,24 @@ static bool append_file_entry(env_var_t::env_var_flags_t flags, const wcstring &
         result->append(f3::PATH);
         result->push_back(' ');
     }
+    if (flags & env_var_t::flag_pathvar_explicit) {
+        result->append(f3::EXPLICIT_PATH);
+        result->push_back(' ');
+    }
+    if (generation > 0) {
+        result->append(f3::GENERATION);
+        result->append(std::to_string(generation));
+        result->push_back(' ');
+    }
+    if (unknown_flags) {
+        for (const wcstring &flag : *unknown_flags) {
+            if (!append_utf8(flag, result, storage)) {
+                FLOGF(error, L"Could not convert %ls to narrow character string", flag.c_str());
+                success = false;
+            }
+            result->push_back(' ');
+        }
+    }
 
     // Append variable name like "fish_color_cwd".
     if (!valid_var_name(key_in)) {
@@ -273,6 +374,7 @@ void env_universal_t::set(const wcstring &key, const env_var_t &var) {
         entry = var;
         this->modified.insert(key);
         if (entry.exports()) export_generation += 1;
+        this->generations[key] = next_generation();
     }
 }
 
@@ -281,6 +383,8 @@ bool env_universal_t::remove(const wcstring &key) {
     if (iter != this->vars.end()) {
         if (iter->second.exports()) export_generation += 1;
         this->vars.erase(iter);
+        this->unknown_flags.erase(key);
+        this->generations[key] = next_generation();
         this->modified.insert(key);
         return true;
     }
@@ -344,9 +448,31 @@ void env_universal_t::generate_callbacks_and_update_exports(const var_table_t &n
     }
 }
 
-void env_universal_t::acquire_variables(var_table_t &&vars_to_acquire) {
+void env_universal_t::drop_overridden_changes(const uvar_generations_t &new_generations) {
+    for (auto iter = this->modified.begin(); iter != this->modified.end();) {
+        const wcstring &key = *iter;
+        auto ours = this->generations.find(key);
+        auto theirs = new_generations.find(key);
+        if (theirs != new_generations.end() &&
+            (ours == this->generations.end() || ours->second < theirs->second)) {
+            // Another fish changed or erased it after we did.
+            FLOGF(uvar_file, L"universal log dropping our change to %ls for a later one",
+                  key.c_str());
+            iter = this->modified.erase(iter);
+        } else {
+            ++iter;
+        }
+    }
+}
+
+void env_universal_t::acquire_variables(var_table_t &&vars_to_acquire,
+                                        uvar_generations_t &&generations_to_acquire) {
     // Copy modified values from existing vars to vars_to_acquire.
     for (const auto &key : this->modified) {
+        auto generation = this->generations.find(key);
+        if (generation != this->generations.end()) {
+            generations_to_acquire[key] = generation->second;
+        }
         auto src_iter = this->vars.find(key);
         if (src_iter == this->vars.end()) {
             /* The value has been deleted. */
@@ -362,6 +488,7 @@ void env_universal_t::acquire_variables(var_table_t &&vars_to_acquire) {
 
     // We have constructed all the callbacks and updated vars_to_acquire. Acquire it!
     this->vars = std::move(vars_to_acquire);
+    this->generations = std::move(generations_to_acquire);
 }
 
 void env_universal_t::load_from_fd(int fd, callback_data_list_t &callbacks) {
@@ -373,7 +500,11 @@ void env_universal_t::load_from_fd(int fd, callback_data_list_t &callbacks) {
     } else {
         // Read a variables table from the file.
         var_table_t new_vars;
-        uvar_format_t format = this->read_message_internal(fd, &new_vars);
+        size_t bad_lines = 0;
+        uvar_unknown_flags_t new_unknown_flags;
+        uvar_generations_t new_generations;
+        uvar_format_t format = this->read_message_internal(fd, &new_vars, &bad_lines,
+                                                           &new_unknown_flags, &new_generations);
 
         // Hacky: if the read format is in the future, avoid overwriting the file: never try to
         // save.
@@ -381,11 +512,30 @@ void env_universal_t::load_from_fd(int fd, callback_data_list_t &callbacks) {
             ok_to_save = false;
         }
 
+        // The file may be damaged, e.g. truncated by a crash. Keep what we could read.
+        last_read_bad_lines = bad_lines;
+        if (bad_lines > 0) {
+            FLOGF(uvar_file, L"universal log skipped %lu bad lines, recovered %lu variables",
+                  static_cast<unsigned long>(bad_lines),
+                  static_cast<unsigned long>(new_vars.size()));
+        }
+        if (bad_lines > k_max_bad_lines && ok_to_save) {
+            FLOGF(warning, _(L"Universal variable file has %lu lines that cannot be parsed. "
+                             L"Rewriting it without them."),
+                  static_cast<unsigned long>(bad_lines));
+            needs_compaction = true;
+        }
+
+        // If another fish changed a variable after we did, its change wins, even if we write the
+        // file later.
+        this->drop_overridden_changes(new_generations);
+
         // Announce changes and update our exports generation.
         this->generate_callbacks_and_update_exports(new_vars, callbacks);
 
         // Acquire the new variables.
-        this->acquire_variables(std::move(new_vars));
+        this->acquire_variables(std::move(new_vars), std::move(new_generations));
+        this->unknown_flags = std::move(new_unknown_flags);
         last_read_file = current_file;
     }
 }
@@ -410,10 +560,13 @@ bool env_universal_t::load_from_path(const wcstring &path, callback_data_list_t
 }
 
 /// Serialize the contents to a string.
-std::string env_universal_t::serialize_with_vars(const var_table_t &vars) {
+std::string env_universal_t::serialize_with_vars(const var_table_t &vars,
+                                                 const uvar_unknown_flags_t &unknown_flags,
+                                                 const uvar_generations_t &generations) {
     std::string storage;
     std::string contents;
     contents.append(SAVE_MSG);
+    // Flags added since 3.0 don't change the version, as older fish skips flags it doesn't know.
     contents.append("# VERSION: " UVARS_VERSION_3_0 "\n");
 
     // Preserve legacy behavior by sorting the values first
@@ -429,9 +582,28 @@ std::string env_universal_t::serialize_with_vars(const var_table_t &vars) {
         // variable; soldier on.
         const wcstring &key = kv.first;
         const env_var_t &var = kv.second;
-        append_file_entry(var.get_flags(), key, encode_serialized(var.as_list()), &contents,
+        auto unknown = unknown_flags.find(key);
+        auto generation = generations.find(key);
+        append_file_entry(var.get_flags(), key, encode_serialized(var.as_list()),
+                          generation == generations.end() ? 0 : generation->second,
+                          unknown == unknown_flags.end() ? nullptr : &unknown->second, &contents,
                           &storage);
     }
+
+    // Append erased variables, again sorted.
+    std::vector<std::pair<wcstring, uint64_t>> erased;
+    for (const auto &kv : generations) {
+        if (!vars.count(kv.first)) erased.push_back(kv);
+    }
+    std::sort(erased.begin(), erased.end());
+    for (const auto &kv : erased) {
+        std::string line = std::string(fish3_uvars::ERASED) + ' ' + fish3_uvars::GENERATION +
+                           std::to_string(kv.second) + ' ';
+        if (valid_var_name(kv.first) && append_utf8(kv.first, &line, &storage)) {
+            contents.append(line);
+            contents.push_back('\n');
+        }
+    }
     return contents;
 }
 
@@ -439,7 +611,18 @@ std::string env_universal_t::serialize_with_vars(const var_table_t &vars) {
 bool env_universal_t::write_to_fd(int fd, const wcstring &path) {
     assert(fd >= 0);
     bool success = true;
-    std::string contents = serialize_with_vars(vars);
+
+    // Forget variables erased long ago. By now, every fish has seen that.
+    const uint64_t now = static_cast<uint64_t>(get_time());
+    for (auto iter = generations.begin(); iter != generations.end();) {
+        if (!vars.count(iter->first) && iter->second + k_erased_lifetime_usec < now) {
+            iter = generations.erase(iter);
+        } else {
+            ++iter;
+        }
+    }
+
+    std::string contents = serialize_with_vars(vars, unknown_flags, generations);
     if (write_loop(fd, contents.data(), contents.size()) < 0) {
         const char *error = std::strerror(errno);
         FLOGF(error, _(L"Unable to write to universal variables file '%ls': %s"), path.c_str(),
@@ -495,8 +678,14 @@ void env_universal_t::initialize_at_path(callback_data_list_t &callbacks, wcstri
 }
 
 void env_universal_t::initialize(callback_data_list_t &callbacks) {
-    // Set do_flock to false immediately if the default variable path is on a remote filesystem.
+    // Set do_flock to false immediately if the variable path is on a remote filesystem.
     // See #7968.
+    // Legacy files are only looked for next to the usual location.
+    if (const auto &path = vars_path_from_environment()) {
+        if (path_is_remote(wdirname(*path) + L'/') == 1) do_flock = false;
+        this->initialize_at_path(callbacks, *path, false /* migrate legacy */);
+        return;
+    }
     if (path_get_config_is_remote() == 1) do_flock = false;
     this->initialize_at_path(callbacks, default_vars_path(), true /* migrate legacy */);
 }
@@ -548,6 +737,7 @@ bool env_universal_t::open_and_acquire_lock(const wcstring &path, autoclose_fd_t
     //
     // We pass O_RDONLY with O_CREAT; this creates a potentially empty file. We do this so that we
     // have something to lock on.
+    // Like any new variables file, it is only accessible by us.
     bool locked_by_open = false;
     int flags = O_RDWR | O_CREAT;
 
@@ -560,7 +750,7 @@ bool env_universal_t::open_and_acquire_lock(const wcstring &path, autoclose_fd_t
 
     autoclose_fd_t fd{};
     while (!fd.valid()) {
-        fd = autoclose_fd_t{wopen_cloexec(path, flags, 0644)};
+        fd = autoclose_fd_t{wopen_cloexec(path, flags, 0600)};
 
         if (!fd.valid()) {
             int err = errno;
@@ -614,7 +804,9 @@ bool env_universal_t::sync(callback_data_list_t &callbacks) {
     // 2. Lock the file (may be combined with step 1 on systems with O_EXLOCK)
     // 3. After taking the lock, check if the file at the given path is different from what we
     // opened. If so, start over.
-    // 4. Read from the file. This can be elided if its dev/inode is unchanged since the last read
+    // 4. Read from the file. This can be elided if its dev/inode is unchanged since the last read.
+    // Variables we changed keep our change, unless the file says another fish changed or erased
+    // them later (see drop_overridden_changes).
     // 5. Open an adjacent temporary file
     // 6. Write our changes to an adjacent file
     // 7. Move the adjacent file into place via rename. This is assumed to be atomic.
@@ -637,11 +829,13 @@ bool env_universal_t::sync(callback_data_list_t &callbacks) {
     // instances of fish will not be able to obtain it. This seems to be a greater risk than that of
     // data loss on lockless NFS. Users who put their home directory on lockless NFS are playing
     // with fire anyways.
-    // If we have no changes, just load.
+    // If we have no changes, just load. If that finds a damaged file, we still rewrite it.
     if (modified.empty()) {
         this->load_from_path(vars_path_, callbacks);
-        FLOGF(uvar_file, L"universal log no modifications");
-        return false;
+        if (!needs_compaction || !ok_to_save) {
+            FLOGF(uvar_file, L"universal log no modifications");
+            return false;
+        }
     }
 
     const wcstring directory = wdirname(vars_path_);
@@ -695,12 +889,15 @@ bool env_universal_t::save(const wcstring &directory, const wcstring &vars_path)
         }
 
         // Ensure we maintain ownership and permissions (#2176).
+        // A new file is only readable by us, whatever mkstemp and the umask did.
         struct stat sbuf;
         if (wstat(real_path, &sbuf) >= 0) {
             if (fchown(private_fd.fd(), sbuf.st_uid, sbuf.st_gid) == -1)
                 FLOGF(uvar_file, L"universal log fchown() failed");
             if (fchmod(private_fd.fd(), sbuf.st_mode) == -1)
                 FLOGF(uvar_file, L"universal log fchmod() failed");
+        } else if (fchmod(private_fd.fd(), S_IRUSR | S_IWUSR) == -1) {
+            FLOGF(uvar_file, L"universal log fchmod() failed");
         }
 
         // Linux by default stores the mtime with low precision, low enough that updates that occur
@@ -719,6 +916,22 @@ bool env_universal_t::save(const wcstring &directory, const wcstring &vars_path)
         }
 #endif
 
+        // Make sure the contents are on disk before the new file replaces the old one, so a crash
+        // cannot leave a truncated file behind.
+        if (fsync(private_fd.fd()) == -1) {
+            FLOGF(uvar_file, L"universal log fsync() failed");
+        }
+
+        // Keep the old file around as a backup, unless we found it to be damaged. A hard link
+        // keeps the old file in place until the rename replaces it.
+        if (last_read_bad_lines == 0 && file_id_for_path(real_path) != kInvalidFileID) {
+            const wcstring backup_path = real_path + L".bak";
+            wunlink(backup_path);
+            if (link(wcs2string(real_path).c_str(), wcs2string(backup_path).c_str()) == -1) {
+                FLOGF(uvar_file, L"universal log link() to backup failed");
+            }
+        }
+
         // Apply new file.
         success = this->move_new_vars_file_into_place(private_file_path, real_path);
         if (!success) FLOGF(uvar_file, L"universal log move_new_vars_file_into_place() failed");
@@ -734,13 +947,18 @@ bool env_universal_t::save(const wcstring &directory, const wcstring &vars_path)
         wunlink(private_file_path);
     }
     if (success) {
-        // All of our modified variables have now been written out.
+        // All of our modified variables have now been written out, to a file without bad lines.
         modified.clear();
+        last_read_bad_lines = 0;
+        needs_compaction = false;
     }
     return success;
 }
 
-uvar_format_t env_universal_t::read_message_internal(int fd, var_table_t *vars) {
+uvar_format_t env_universal_t::read_message_internal(int fd, var_table_t *vars,
+                                                     size_t *out_bad_lines,
+                                                     uvar_unknown_flags_t *out_unknown_flags,
+                                                     uvar_generations_t *out_generations) {
     // Read everything from the fd. Put a sane limit on it.
     std::string contents;
     while (contents.size() < k_max_read_size) {
@@ -760,7 +978,20 @@ uvar_format_t env_universal_t::read_message_internal(int fd, var_table_t *vars)
         contents.resize(newline == wcstring::npos ? 0 : newline);
     }
 
-    return populate_variables(contents, vars);
+    return populate_variables(contents, vars, out_bad_lines, out_unknown_flags, out_generations);
+}
+
+/// \return the format for the version \p version from a file's header.
+static uvar_format_t format_for_version(const char *version) {
+    // Versions are MAJOR.MINOR. A later minor version only adds flags, which we skip when reading
+    // and keep when writing, so we can read and write it like 3.1. Another major version, or
+    // anything we can't make sense of, may mean something else entirely.
+    int major = -1, minor = -1;
+    char extra;
+    if (sscanf(version, "%d.%d%c", &major, &minor, &extra) != 2 || major != 3 || minor < 0) {
+        return uvar_format_t::future;
+    }
+    return minor == 0 ? uvar_format_t::fish_3_0 : uvar_format_t::fish_3_1;
 }
 
 /// \return the format corresponding to file contents \p s.
@@ -771,60 +1002,87 @@ uvar_format_t env_universal_t::format_for_contents(const std::string &s) {
         const std::string &line = iter.line();
         if (line.empty()) continue;
         if (line.front() != L'#') {
-            // Exhausted leading comments.
-            break;
+            // Exhausted leading comments without finding a version, so this is from fish 2.x.
+            // Unless the header was damaged, and the variables are written like in 3.0.
+            std::string setuvar = std::string(fish3_uvars::SETUVAR) + ' ';
+            return line.compare(0, setuvar.size(), setuvar) == 0 ? uvar_format_t::fish_3_0
+                                                                 : uvar_format_t::fish_2_x;
         }
         // Note scanf %s is max characters to write; add 1 for null terminator.
         char versionbuf[64 + 1];
         if (sscanf(line.c_str(), "# VERSION: %64s", versionbuf) != 1) continue;
-
-        // Try reading the version.
-        if (!std::strcmp(versionbuf, UVARS_VERSION_3_0)) {
-            return uvar_format_t::fish_3_0;
-        } else {
-            // Unknown future version.
-            return uvar_format_t::future;
-        }
+        return format_for_version(versionbuf);
     }
     // No version found, assume 2.x
     return uvar_format_t::fish_2_x;
 }
 
-uvar_format_t env_universal_t::populate_variables(const std::string &s, var_table_t *out_vars) {
+/// Parse an erased variable from a comment like "# ERASED --generation=N name", and put it in
+/// \p generations unless the variable is set.
+static void parse_erased_variable(const wcstring &msgstr, const var_table_t &vars,
+                                  uvar_generations_t *generations) {
+    const wchar_t *cursor = msgstr.c_str();
+    uint64_t generation = 0;
+    if (!match(&cursor, fish3_uvars::ERASED)) return;
+    cursor = skip_spaces(cursor);
+    if (!match_generation(&cursor, &generation)) return;
+    wcstring key = skip_spaces(cursor);
+    if (valid_var_name(key) && !vars.count(key)) {
+        (*generations)[key] = generation;
+    }
+}
+
+uvar_format_t env_universal_t::populate_variables(const std::string &s, var_table_t *out_vars,
+                                                  size_t *out_bad_lines,
+                                                  uvar_unknown_flags_t *out_unknown_flags,
+                                                  uvar_generations_t *out_generations) {
     // Decide on the format.
     const uvar_format_t format = format_for_contents(s);
 
     line_iterator_t<std::string> iter{s};
     wcstring wide_line;
     wcstring storage;
+    size_t bad_lines = 0;
     while (iter.next()) {
         const std::string &line = iter.line();
         // Skip empties and constants.
-        if (line.empty() || line.front() == L'#') continue;
+        if (line.empty()) continue;
+        if (line.front() == L'#') {
+            // Comments may hold erased variables, which are written after the set ones.
+            wide_line.clear();
+            if (out_generations && format != uvar_format_t::fish_2_x &&
+                utf8_to_wchar(line.data(), line.size(), &wide_line, 0)) {
+                parse_erased_variable(wide_line, *out_vars, out_generations);
+            }
+            continue;
+        }
 
         // Convert to UTF8.
         wide_line.clear();
-        if (!utf8_to_wchar(line.data(), line.size(), &wide_line, 0)) continue;
+        if (!utf8_to_wchar(line.data(), line.size(), &wide_line, 0)) {
+            bad_lines++;
+            continue;
+        }
 
+        bool parsed = false;
         switch (format) {
             case uvar_format_t::fish_2_x:
-                env_universal_t::parse_message_2x_internal(wide_line, out_vars, &storage);
+                parsed = env_universal_t::parse_message_2x_internal(wide_line, out_vars, &storage);
                 break;
             case uvar_format_t::fish_3_0:
+            case uvar_format_t::fish_3_1:
             // For future formats, just try with the most recent one.
             case uvar_format_t::future:
-                env_universal_t::parse_message_30_internal(wide_line, out_vars, &storage);
+                parsed = env_universal_t::parse_message_30_internal(
+                    wide_line, out_vars, &storage, out_unknown_flags, out_generations);
                 break;
         }
+        if (!parsed) bad_lines++;
     }
+    if (out_bad_lines) *out_bad_lines = bad_lines;
     return format;
 }
 
-static const wchar_t *skip_spaces(const wchar_t *str) {
-    while (*str == L' ' || *str == L'\t') str++;
-    return str;
-}
-
 bool env_universal_t::populate_1_variable(const wchar_t *input, env_var_t::env_var_flags_t flags,
                                           var_table_t *vars, wcstring *storage) {
     const wchar_t *str = skip_spaces(input);
@@ -845,47 +1103,78 @@ bool env_universal_t::populate_1_variable(const wchar_t *input, env_var_t::env_v
     return true;
 }
 
-/// Parse message msg per fish 3.0 format.
-void env_universal_t::parse_message_30_internal(const wcstring &msgstr, var_table_t *vars,
-                                                wcstring *storage) {
+/// Parse message msg per fish 3.0 format. Flags that we don't know are put in \p unknown_flags, and
+/// the generation in \p generations, if given.
+/// \return false if it could not be parsed.
+bool env_universal_t::parse_message_30_internal(const wcstring &msgstr, var_table_t *vars,
+                                                wcstring *storage,
+                                                uvar_unknown_flags_t *unknown_flags,
+                                                uvar_generations_t *generations) {
     namespace f3 = fish3_uvars;
     const wchar_t *const msg = msgstr.c_str();
-    if (msg[0] == L'#') return;
+    if (msg[0] == L'#') return true;
 
     const wchar_t *cursor = msg;
     if (!match(&cursor, f3::SETUVAR)) {
-        FLOGF(warning, PARSE_ERR, msg);
-        return;
+        FLOGF(uvar_file, PARSE_ERR, msg);
+        return false;
     }
     // Parse out flags.
     env_var_t::env_var_flags_t flags = 0;
+    uint64_t generation = 0;
+    wcstring_list_t unknown;
     for (;;) {
         cursor = skip_spaces(cursor);
         if (*cursor != L'-') break;
         if (match(&cursor, f3::EXPORT)) {
             flags |= env_var_t::flag_export;
+        } else if (match(&cursor, f3::EXPLICIT_PATH)) {
+            flags |= env_var_t::flag_pathvar_explicit;
         } else if (match(&cursor, f3::PATH)) {
             flags |= env_var_t::flag_pathvar;
+        } else if (match_generation(&cursor, &generation)) {
+            // The generation is not a flag of the variable itself.
         } else {
-            // Skip this unknown flag, for future proofing.
+            // Skip this unknown flag, for future proofing, but remember it to write it back.
+            const wchar_t *flag_start = cursor;
             while (*cursor && *cursor != L' ' && *cursor != L'\t') cursor++;
+            unknown.emplace_back(flag_start, cursor);
         }
     }
 
     // Populate the variable with these flags.
     if (!populate_1_variable(cursor, flags, vars, storage)) {
-        FLOGF(warning, PARSE_ERR, msg);
+        FLOGF(uvar_file, PARSE_ERR, msg);
+        return false;
     }
+    // populate_1_variable leaves the key in storage. A later line for the same variable replaces
+    // any flags from an earlier one.
+    if (unknown_flags) {
+        if (unknown.empty()) {
+            unknown_flags->erase(*storage);
+        } else {
+            (*unknown_flags)[*storage] = std::move(unknown);
+        }
+    }
+    if (generations) {
+        if (generation == 0) {
+            generations->erase(*storage);
+        } else {
+            (*generations)[*storage] = generation;
+        }
+    }
+    return true;
 }
 
 /// Parse message msg per fish 2.x format.
-void env_universal_t::parse_message_2x_internal(const wcstring &msgstr, var_table_t *vars,
+/// \return false if it could not be parsed.
+bool env_universal_t::parse_message_2x_internal(const wcstring &msgstr, var_table_t *vars,
                                                 wcstring *storage) {
     namespace f2x = fish2x_uvars;
     const wchar_t *const msg = msgstr.c_str();
     const wchar_t *cursor = msg;
 
-    if (cursor[0] == L'#') return;
+    if (cursor[0] == L'#') return true;
 
     env_var_t::env_var_flags_t flags = 0;
     if (match(&cursor, f2x::SET_EXPORT)) {
@@ -893,13 +1182,15 @@ void env_universal_t::parse_message_2x_internal(const wcstring &msgstr, var_tabl
     } else if (match(&cursor, f2x::SET)) {
         flags |= 0;
     } else {
-        FLOGF(warning, PARSE_ERR, msg);
-        return;
+        FLOGF(uvar_file, PARSE_ERR, msg);
+        return false;
     }
 
     if (!populate_1_variable(cursor, flags, vars, storage)) {
-        FLOGF(warning, PARSE_ERR, msg);
+        FLOGF(uvar_file, PARSE_ERR, msg);
+        return false;
     }
+    return true;
 }
 
 /// Maximum length of hostname. Longer hostnames are truncated.
@@ -917,7 +1208,12 @@ void env_universal_t::parse_message_2x_internal(const wcstring &msgstr, var_tabl
 static bool get_mac_address(unsigned char macaddr[MAC_ADDRESS_MAX_LEN],
                             const char *interface = "eth0") {
     bool result = false;
+#ifdef SOCK_CLOEXEC
+    const int dummy = socket(AF_INET, SOCK_STREAM | SOCK_CLOEXEC, 0);
+#else
     const int dummy = socket(AF_INET, SOCK_STREAM, 0);
+    if (dummy >= 0) set_cloexec(dummy);
+#endif
     if (dummy >= 0) {
         struct ifreq r;
         strncpy(const_cast<char *>(r.ifr_name), interface, sizeof r.ifr_name - 1);
@@ -1256,7 +1552,7 @@ static autoclose_fd_t make_fifo(const wchar_t *test_path, const wchar_t *suffix)
         const wchar_t *errmsg = _(L"Unable to open a pipe for universal variables using '%ls': %s");
         FLOGF(error, errmsg, vars_path.c_str(), error);
     }
-    return res;
+    return heightenize_fd(std::move(res), true);
 }
 
 // Named-pipe based notifier. All clients open the same named pipe for reading and writing. The
@@ -1490,9 +1786,225 @@ class universal_notifier_named_pipe_t final : public universal_notifier_t {
 #endif
 };
 
+/// Base class for the notifiers that watch the variables file itself. Saving the file is the
+/// notification for other sessions watching it. post_notification() also writes to the named pipe,
+/// for sessions that use that instead, like older versions of fish or ones that fell back to it.
+/// Changes are only received by watching the file.
+///
+/// A save causes several events, and sessions may save one after another. So a change is not
+/// reported right away: the first event starts a short settling period, and poll() reports the
+/// change when it is over. Events that come in meanwhile are part of the same change.
+class universal_notifier_file_watch_t : public universal_notifier_t {
+    // When the settling period started, in microseconds since epoch, or -1 if there is none.
+    long long settle_start_usec{-1};
+
+    // The named pipe, which we only post to. It still needs polling to drain what we wrote.
+    universal_notifier_named_pipe_t pipe_notifier;
+
+   protected:
+    explicit universal_notifier_file_watch_t(const wchar_t *test_path)
+        : pipe_notifier(test_path) {}
+
+    /// Read all pending events. \return true if the variables file changed.
+    virtual bool drain_events() = 0;
+
+   public:
+    // How long we wait for more events before reporting a change.
+    static constexpr long long k_settle_usec = 2e4;
+
+    bool notification_fd_became_readable(int fd) override {
+        UNUSED(fd);
+        if (drain_events() && settle_start_usec < 0) {
+            FLOG(uvar_notifier, "vars file changed, settling");
+            settle_start_usec = get_time();
+        }
+        return false;
+    }
+
+    void post_notification() override { pipe_notifier.post_notification(); }
+
+    unsigned long usec_delay_between_polls() const override {
+        unsigned long pipe_delay = pipe_notifier.usec_delay_between_polls();
+        if (settle_start_usec < 0) return pipe_delay;
+        return pipe_delay > 0 ? std::min<unsigned long>(pipe_delay, k_settle_usec) : k_settle_usec;
+    }
+
+    bool poll() override {
+        // Other sessions' posts to the pipe are also saves we see in the file, so ignore them.
+        pipe_notifier.poll();
+        if (settle_start_usec < 0 || get_time() < settle_start_usec + k_settle_usec) {
+            return false;
+        }
+        settle_start_usec = -1;
+        FLOG(uvar_notifier, "polled true: vars file changed");
+        return true;
+    }
+};
+
+/// An inotify-based notifier, for Linux. This watches the directory containing the variables file
+/// instead of the file, because saving replaces the file via rename, which would end a watch on
+/// the file. Events for other files in the directory are ignored.
+class universal_notifier_inotify_t final : public universal_notifier_file_watch_t {
+#ifdef HAVE_INOTIFY_INIT1
+    autoclose_fd_t inotify_fd{};
+
+    // The name of the variables file in the watched directory.
+    std::string file_name{};
+
+    bool drain_events() override {
+        bool changed = false;
+        alignas(struct inotify_event) char buff[4096];
+        ssize_t amt;
+        while ((amt = read(inotify_fd.fd(), buff, sizeof buff)) > 0) {
+            for (ssize_t offset = 0; offset < amt;) {
+                const auto *event = reinterpret_cast<const struct inotify_event *>(buff + offset);
+                // If the queue overflowed, we don't know what we missed.
+                if ((event->mask & IN_Q_OVERFLOW) ||
+                    (event->len > 0 && file_name == event->name)) {
+                    changed = true;
+                }
+                offset += sizeof(struct inotify_event) + event->len;
+            }
+        }
+        return changed;
+    }
+
+   public:
+    explicit universal_notifier_inotify_t(const wchar_t *test_path)
+        : universal_notifier_file_watch_t(test_path) {
+        wcstring vars_path = test_path ? wcstring(test_path) : default_vars_path();
+        if (vars_path.empty()) return;
+        autoclose_fd_t fd{inotify_init1(IN_CLOEXEC | IN_NONBLOCK)};
+        if (!fd.valid()) {
+            FLOGF(uvar_notifier, "inotify_init1() failed: %s", std::strerror(errno));
+            return;
+        }
+        // IN_CLOSE_WRITE is a write in place, IN_MOVED_TO a new file renamed into place.
+        std::string dir = wcs2string(wdirname(vars_path));
+        if (inotify_add_watch(fd.fd(), dir.c_str(), IN_CLOSE_WRITE | IN_MOVED_TO) < 0) {
+            FLOGF(uvar_notifier, "inotify_add_watch() failed for '%s': %s", dir.c_str(),
+                  std::strerror(errno));
+            return;
+        }
+        file_name = wcs2string(wbasename(vars_path));
+        inotify_fd = heightenize_fd(std::move(fd), true);
+    }
+
+    int notification_fd() const override { return inotify_fd.fd(); }
+
+#else  // this class isn't valid on this system
+   public:
+    [[noreturn]] explicit universal_notifier_inotify_t(const wchar_t *test_path)
+        : universal_notifier_file_watch_t(test_path) {
+        DIE("universal_notifier_inotify_t cannot be used on this system");
+    }
+
+   private:
+    bool drain_events() override { return false; }
+#endif
+};
+
+/// A kqueue-based notifier, for the BSDs. This watches both the variables file, for writes in
+/// place, and its directory, for a new file renamed into place. The directory also changes when
+/// other files in it do, so we compare the file's ID to tell whether the variables file changed.
+class universal_notifier_kqueue_t final : public universal_notifier_file_watch_t {
+#ifdef HAVE_KQUEUE
+    autoclose_fd_t kq{};
+    autoclose_fd_t dir_fd{};
+    autoclose_fd_t file_fd{};
+
+    // The path to the variables file.
+    std::string vars_path{};
+
+    // The ID of the variables file when we last looked at it.
+    file_id_t file_id{};
+
+    /// Open \p path for watching, or \return an invalid fd.
+    static autoclose_fd_t open_for_events(const std::string &path) {
+#ifdef O_EVTONLY
+        return autoclose_fd_t{open_cloexec(path, O_EVTONLY)};
+#else
+        return autoclose_fd_t{open_cloexec(path, O_RDONLY)};
+#endif
+    }
+
+    /// Add a watch for \p fflags on \p fd. \return true on success.
+    bool add_watch(int fd, unsigned int fflags) const {
+        struct kevent change;
+        EV_SET(&change, fd, EVFILT_VNODE, EV_ADD | EV_CLEAR, fflags, 0, nullptr);
+        if (kevent(kq.fd(), &change, 1, nullptr, 0, nullptr) < 0) {
+            FLOGF(uvar_notifier, "kevent() failed for '%s': %s", vars_path.c_str(),
+                  std::strerror(errno));
+            return false;
+        }
+        return true;
+    }
+
+    /// (Re)open the variables file and watch it. This follows the file when it is replaced.
+    /// Closing the old fd removes its watch.
+    void watch_file() {
+        file_fd = open_for_events(vars_path);
+        file_id = file_fd.valid() ? file_id_for_fd(file_fd) : file_id_t{};
+        if (file_fd.valid()) {
+            add_watch(file_fd.fd(), NOTE_WRITE | NOTE_EXTEND | NOTE_DELETE | NOTE_RENAME);
+        }
+    }
+
+    bool drain_events() override {
+        struct kevent events[16];
+        const struct timespec no_wait = {0, 0};
+        bool any = false;
+        while (kevent(kq.fd(), nullptr, 0, events, 16, &no_wait) > 0) {
+            any = true;
+        }
+        if (!any) return false;
+        file_id_t old_id = file_id;
+        watch_file();
+        return file_id != old_id;
+    }
+
+   public:
+    explicit universal_notifier_kqueue_t(const wchar_t *test_path)
+        : universal_notifier_file_watch_t(test_path) {
+        wcstring path = test_path ? wcstring(test_path) : default_vars_path();
+        if (path.empty()) return;
+        autoclose_fd_t fd{kqueue()};
+        if (!fd.valid()) {
+            FLOGF(uvar_notifier, "kqueue() failed: %s", std::strerror(errno));
+            return;
+        }
+        kq = heightenize_fd(std::move(fd), false);
+        vars_path = wcs2string(path);
+        // NOTE_WRITE on a directory means that an entry was added, removed or renamed.
+        dir_fd = open_for_events(wcs2string(wdirname(path)));
+        if (!dir_fd.valid() || !add_watch(dir_fd.fd(), NOTE_WRITE)) {
+            kq.close();
+            return;
+        }
+        watch_file();
+    }
+
+    int notification_fd() const override { return kq.fd(); }
+
+#else  // this class isn't valid on this system
+   public:
+    [[noreturn]] explicit universal_notifier_kqueue_t(const wchar_t *test_path)
+        : universal_notifier_file_watch_t(test_path) {
+        DIE("universal_notifier_kqueue_t cannot be used on this system");
+    }
+
+   private:
+    bool drain_events() override { return false; }
+#endif
+};
+
 universal_notifier_t::notifier_strategy_t universal_notifier_t::resolve_default_strategy() {
 #ifdef FISH_NOTIFYD_AVAILABLE
     return strategy_notifyd;
+#elif defined(HAVE_INOTIFY_INIT1)
+    return strategy_inotify;
+#elif defined(HAVE_KQUEUE)
+    return strategy_kqueue;
 #elif defined(__CYGWIN__)
     return strategy_shmem_polling;
 #else
@@ -1501,8 +2013,18 @@ universal_notifier_t::notifier_strategy_t universal_notifier_t::resolve_default_
 }
 
 universal_notifier_t &universal_notifier_t::default_notifier() {
-    static std::unique_ptr<universal_notifier_t> result =
-        new_notifier_for_strategy(universal_notifier_t::resolve_default_strategy());
+    static std::unique_ptr<universal_notifier_t> result = [] {
+        auto strategy = universal_notifier_t::resolve_default_strategy();
+        auto notifier = new_notifier_for_strategy(strategy);
+        // Watching the file can fail, e.g. if inotify is out of watches or the config directory is
+        // missing. Fall back to the named pipe.
+        if ((strategy == strategy_inotify || strategy == strategy_kqueue) &&
+            notifier->notification_fd() < 0) {
+            FLOG(uvar_notifier, "cannot watch the vars file, using a named pipe instead");
+            notifier = new_notifier_for_strategy(strategy_named_pipe);
+        }
+        return notifier;
+    }();
     return *result;
 }
 
@@ -1518,6 +2040,12 @@ std::unique_ptr<universal_notifier_t> universal_notifier_t::new_notifier_for_str
         case strategy_named_pipe: {
             return make_unique<universal_notifier_named_pipe_t>(test_path);
         }
+        case strategy_inotify: {
+            return make_unique<universal_notifier_inotify_t>(test_path);
+        }
+        case strategy_kqueue: {
+            return make_unique<universal_notifier_kqueue_t>(test_path);
+        }
     }
     DIE("should never reach this statement");
     return nullptr;
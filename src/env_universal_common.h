@@ -6,6 +6,7 @@
 #include <stdio.h>
 
 #include <memory>
+#include <unordered_map>
 #include <unordered_set>
 #include <vector>
 
@@ -32,8 +33,19 @@ struct callback_data_t {
 using callback_data_list_t = std::vector<callback_data_t>;
 
 // List of fish universal variable formats.
+// fish_3_1 is also used for later 3.x versions, which only add flags.
+// future is another major version, which we must not overwrite.
 // This is exposed for testing.
-enum class uvar_format_t { fish_2_x, fish_3_0, future };
+enum class uvar_format_t { fish_2_x, fish_3_0, fish_3_1, future };
+
+/// Flags of universal variables that this fish does not know, by variable name. These are written
+/// back unchanged, so a newer fish does not lose them.
+using uvar_unknown_flags_t = std::unordered_map<wcstring, wcstring_list_t>;
+
+/// Generations of universal variables, by variable name. A generation is the time in microseconds
+/// at which some fish last changed the variable; when two fish change it, the later change wins. A
+/// name with a generation but no variable was erased at that time.
+using uvar_generations_t = std::unordered_map<wcstring, uint64_t>;
 
 bool get_hostname_identifier(wcstring &result);
 
@@ -74,20 +86,33 @@ class env_universal_t {
     /// written.
     bool sync(callback_data_list_t &callbacks);
 
-    /// Populate a variable table \p out_vars from a \p s string.
+    /// Populate a variable table \p out_vars from a \p s string. Lines that cannot be parsed are
+    /// skipped; if \p out_bad_lines is given, it is set to how many there were. Flags we don't
+    /// know are put in \p out_unknown_flags, and the generations of variables, including erased
+    /// ones, in \p out_generations, if given.
     /// This is exposed for testing only.
     /// \return the format of the file that we read.
-    static uvar_format_t populate_variables(const std::string &s, var_table_t *out_vars);
+    static uvar_format_t populate_variables(const std::string &s, var_table_t *out_vars,
+                                            size_t *out_bad_lines = nullptr,
+                                            uvar_unknown_flags_t *out_unknown_flags = nullptr,
+                                            uvar_generations_t *out_generations = nullptr);
 
     /// Guess a file format. Exposed for testing only.
     static uvar_format_t format_for_contents(const std::string &s);
 
-    /// Serialize a variable list. Exposed for testing only.
-    static std::string serialize_with_vars(const var_table_t &vars);
+    /// Serialize a variable list, with the \p unknown_flags and \p generations of its variables.
+    /// Generations of names not in \p vars are written as erased variables. Exposed for testing
+    /// only.
+    static std::string serialize_with_vars(const var_table_t &vars,
+                                           const uvar_unknown_flags_t &unknown_flags = {},
+                                           const uvar_generations_t &generations = {});
 
     /// Exposed for testing only.
     bool is_ok_to_save() const { return ok_to_save; }
 
+    /// \return the path of the file we read and write, or an empty string if not initialized.
+    const wcstring &get_path() const { return vars_path_; }
+
     /// Access the export generation.
     uint64_t get_export_generation() const { return export_generation; }
 
@@ -98,6 +123,12 @@ class env_universal_t {
     // The table of variables.
     var_table_t vars;
 
+    // Flags of our variables that a newer fish wrote, which we write back.
+    uvar_unknown_flags_t unknown_flags;
+
+    // When our variables were last changed, and when recently erased ones were erased.
+    uvar_generations_t generations;
+
     // Keys that have been modified, and need to be written. A value here that is not present in
     // vars indicates a deleted value.
     std::unordered_set<wcstring> modified;
@@ -116,6 +147,12 @@ class env_universal_t {
     // File id from which we last read.
     file_id_t last_read_file = kInvalidFileID;
 
+    // How many lines of the file we last read could not be parsed.
+    size_t last_read_bad_lines{0};
+
+    // Whether the file has so many bad lines that we should rewrite it, even without changes.
+    bool needs_compaction{false};
+
     /// \return whether we are initialized.
     bool initialized() const { return !vars_path_.empty(); }
 
@@ -133,17 +170,26 @@ class env_universal_t {
     void generate_callbacks_and_update_exports(const var_table_t &new_vars,
                                                callback_data_list_t &callbacks);
 
-    // Given a variable table, copy unmodified values into self.
-    void acquire_variables(var_table_t &&vars_to_acquire);
+    // Given the generations of variables in the file, forget our changes to variables that were
+    // changed again later, so we take those instead.
+    void drop_overridden_changes(const uvar_generations_t &new_generations);
+
+    // Given a variable table and its generations, copy unmodified values into self.
+    void acquire_variables(var_table_t &&vars_to_acquire,
+                           uvar_generations_t &&generations_to_acquire);
 
     static bool populate_1_variable(const wchar_t *input, env_var_t::env_var_flags_t flags,
                                     var_table_t *vars, wcstring *storage);
 
-    static void parse_message_2x_internal(const wcstring &msg, var_table_t *vars,
-                                          wcstring *storage);
-    static void parse_message_30_internal(const wcstring &msg, var_table_t *vars,
+    static bool parse_message_2x_internal(const wcstring &msg, var_table_t *vars,
                                           wcstring *storage);
-    static uvar_format_t read_message_internal(int fd, var_table_t *vars);
+    static bool parse_message_30_internal(const wcstring &msg, var_table_t *vars,
+                                          wcstring *storage,
+                                          uvar_unknown_flags_t *unknown_flags = nullptr,
+                                          uvar_generations_t *generations = nullptr);
+    static uvar_format_t read_message_internal(int fd, var_table_t *vars, size_t *out_bad_lines,
+                                               uvar_unknown_flags_t *out_unknown_flags,
+                                               uvar_generations_t *out_generations);
 
     bool save(const wcstring &directory, const wcstring &vars_path);
 };
@@ -177,6 +223,12 @@ class universal_notifier_t {
         // Strategy that uses a named pipe. Somewhat complex, but portable and doesn't require
         // polling most of the time.
         strategy_named_pipe,
+
+        // Linux-specific: watch the variables file's directory with inotify.
+        strategy_inotify,
+
+        // BSD-specific: watch the variables file and its directory with kqueue.
+        strategy_kqueue,
     };
 
    protected:
@@ -221,4 +273,8 @@ wcstring get_runtime_path();
 // fetch_default_strategy_from_environment for names.
 #define UNIVERSAL_NOTIFIER_ENV_NAME "fish_universal_notifier"
 
+// Environment variable for moving the universal variable file elsewhere. This is only read when
+// fish starts.
+#define UNIVERSAL_FILE_ENV_NAME "FISH_UNIVERSAL_FILE"
+
 #endif
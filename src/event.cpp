@@ -489,6 +489,33 @@ void event_fire_generic(parser_t &parser, const wchar_t *name, const wcstring_li
     event_fire(parser, ev);
 }
 
+void event_enqueue_generic(parser_t &parser, const wchar_t *name, const wcstring_list_t *args) {
+    assert(name && "Null name");
+
+    auto ev = std::make_shared<event_t>(event_type_t::generic);
+    ev->desc.str_param1 = name;
+    if (args) ev->arguments = *args;
+    parser.libdata().queued_events.push_back(std::move(ev));
+}
+
+void event_fire_queued(parser_t &parser) {
+    auto &ld = parser.libdata();
+    if (ld.queued_events.empty()) return;
+    // Wait until we are back at the top level, so the command that queued the events is done.
+    if (ld.is_event || ld.is_subshell) return;
+    for (const auto &b : parser.blocks()) {
+        if (b.is_function_call()) return;
+    }
+    // Do not invoke new event handlers if we are unwinding (#6649).
+    if (signal_check_cancel()) return;
+
+    std::vector<shared_ptr<const event_t>> to_send;
+    to_send.swap(ld.queued_events);
+    for (const auto &evt : to_send) {
+        event_fire(parser, *evt);
+    }
+}
+
 event_description_t event_description_t::signal(int sig) {
     event_description_t event(event_type_t::signal);
     event.param1.signal = sig;
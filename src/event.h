@@ -153,4 +153,14 @@ wcstring event_get_desc(const parser_t &parser, const event_t &e);
 void event_fire_generic(parser_t &parser, const wchar_t *name,
                         const wcstring_list_t *args = nullptr);
 
+/// Queue a generic event with the specified name, to be fired by event_fire_queued() once the
+/// current command is done. This is `emit --async`.
+void event_enqueue_generic(parser_t &parser, const wchar_t *name,
+                           const wcstring_list_t *args = nullptr);
+
+/// Fire all queued events in the order they were queued, unless a function, event handler or
+/// command substitution is running; then they stay queued. Events queued by the handlers are
+/// left for the next call.
+void event_fire_queued(parser_t &parser);
+
 #endif
@@ -16,6 +16,7 @@
 #include <paths.h>
 #include <stdio.h>
 #include <sys/wait.h>
+#include <termios.h>
 #include <unistd.h>
 
 #include <algorithm>
@@ -28,11 +29,14 @@
 #include <type_traits>
 #include <vector>
 
+#include "ast.h"
 #include "builtin.h"
+#include "builtin_string.h"
 #include "common.h"
 #include "env.h"
 #include "exec.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "flog.h"
 #include "function.h"
 #include "io.h"
@@ -50,6 +54,7 @@
 #include "timer.h"
 #include "trace.h"
 #include "wcstringutil.h"
+#include "wgetopt.h"
 #include "wutil.h"  // IWYU pragma: keep
 
 /// Number of calls to fork() or posix_spawn().
@@ -139,20 +144,16 @@ bool is_thompson_shell_script(const char *path) {
     return res;
 }
 
-/// This function is executed by the child process created by a call to fork(). It should be called
-/// after \c child_setup_process. It calls execve to replace the fish process image with the command
-/// specified in \c p. It never returns. Called in a forked child! Do not allocate memory, etc.
-[[noreturn]] static void safe_launch_process(process_t *p, const char *actual_cmd,
-                                             const char *const *cargv, const char *const *cenvv) {
-    UNUSED(p);
-    int err;
-
-    // This function never returns, so we take certain liberties with constness.
+/// Call execve to replace the fish process image with \p actual_cmd, falling back to running it
+/// with /bin/sh if it looks like a script without a shebang. This only returns on failure, and
+/// returns the errno of the failed execve. Safe to call in a forked child.
+static int safe_exec(const char *actual_cmd, const char *const *cargv, const char *const *cenvv) {
+    // We take certain liberties with constness, since execve doesn't modify its arguments.
     auto envv = const_cast<char **>(cenvv);
     auto argv = const_cast<char **>(cargv);
 
     execve(actual_cmd, argv, envv);
-    err = errno;
+    int err = errno;
 
     // The shebang wasn't introduced until UNIX Seventh Edition, so if
     // the kernel won't run the binary we hand it off to the interpreter
@@ -172,15 +173,25 @@ bool is_thompson_shell_script(const char *path) {
             execve(_PATH_BSHELL, argv2, envv);
         }
     }
+    return err;
+}
 
+/// This function is executed by the child process created by a call to fork(). It should be called
+/// after \c child_setup_process. It calls execve to replace the fish process image with the command
+/// specified in \c p. It never returns. Called in a forked child! Do not allocate memory, etc.
+[[noreturn]] static void safe_launch_process(process_t *p, const char *actual_cmd,
+                                             const char *const *argv, const char *const *envv) {
+    UNUSED(p);
+    int err = safe_exec(actual_cmd, argv, envv);
     errno = err;
     safe_report_exec_error(errno, actual_cmd, argv, envv);
     exit_without_destructors(exit_code_from_exec_error(err));
 }
 
 /// This function is similar to launch_process, except it is not called after a fork (i.e. it only
-/// calls exec) and therefore it can allocate memory.
-[[noreturn]] static void launch_process_nofork(env_stack_t &vars, process_t *p) {
+/// calls exec) and therefore it can allocate memory. It only returns if the exec failed, in which
+/// case the error will have been printed, and the errno is returned.
+static int launch_process_nofork(env_stack_t &vars, process_t *p) {
     ASSERT_IS_MAIN_THREAD();
     ASSERT_IS_NOT_FORKED_CHILD();
 
@@ -196,8 +207,9 @@ bool is_thompson_shell_script(const char *path) {
 
     // Ensure the terminal modes are what they were before we changed them.
     restore_term_mode();
-    // Bounce to launch_process. This never returns.
-    safe_launch_process(p, actual_cmd.c_str(), argv, envp);
+    int err = safe_exec(actual_cmd.c_str(), argv, envp);
+    safe_report_exec_error(err, actual_cmd.c_str(), argv, envp);
+    return err;
 }
 
 // Returns whether we can use posix spawn for a given process in a given job.
@@ -231,23 +243,123 @@ static bool can_use_posix_spawn_for_job(const std::shared_ptr<job_t> &job,
     return true;
 }
 
-static void internal_exec(env_stack_t &vars, job_t *j, const io_chain_t &block_io) {
+/// Options for exec, given like "exec --keep-fd 3 cmd".
+struct exec_options_t {
+    /// fds which should stay open in the new process.
+    std::vector<int> keep_fds;
+    /// Whether to exit if the exec fails, instead of returning to the shell.
+    bool force{false};
+};
+
+/// An fd which exec changes, and a copy of what it was before, so it can be restored if the exec
+/// fails.
+struct saved_fd_t {
+    int fd;
+    /// Whether the fd was close-on-exec, or none if it was not open.
+    maybe_t<bool> cloexec;
+    autoclose_fd_t copy;
+};
+
+/// \return a close-on-exec copy of \p fd at or above \p first_fd, or -1 on error.
+static int dup_cloexec_above(int fd, int first_fd) {
+#ifdef F_DUPFD_CLOEXEC
+    return fcntl(fd, F_DUPFD_CLOEXEC, first_fd);
+#else
+    int copy = fcntl(fd, F_DUPFD, first_fd);
+    if (copy >= 0) set_cloexec(copy);
+    return copy;
+#endif
+}
+
+/// Save every fd that \p redirs or \p keep_fds change, placing the copies at or above \p first_fd.
+static std::vector<saved_fd_t> save_fds(const dup2_list_t &redirs, const std::vector<int> &keep_fds,
+                                        int first_fd) {
+    std::vector<int> fds = keep_fds;
+    for (const auto &act : redirs.get_actions()) {
+        fds.push_back(act.target < 0 ? act.src : act.target);
+    }
+    std::vector<saved_fd_t> result;
+    for (int fd : fds) {
+        bool seen = std::any_of(result.begin(), result.end(),
+                                [=](const saved_fd_t &saved) { return saved.fd == fd; });
+        if (seen) continue;
+        maybe_t<bool> cloexec = get_cloexec(fd);
+        // An fd that isn't open is simply closed again afterwards.
+        if (!cloexec && errno != EBADF) wperror(L"fcntl");
+        autoclose_fd_t copy{cloexec ? dup_cloexec_above(fd, first_fd) : -1};
+        result.push_back(saved_fd_t{fd, cloexec, std::move(copy)});
+    }
+    return result;
+}
+
+/// Undo the changes to the fds in \p saved.
+static void restore_fds(const std::vector<saved_fd_t> &saved) {
+    for (const auto &s : saved) {
+        if (!s.cloexec) {
+            close(s.fd);
+        } else if (s.copy.valid()) {
+            dup2(s.copy.fd(), s.fd);
+            set_cloexec(s.fd, *s.cloexec);
+        }
+    }
+}
+
+/// Replace fish with the process of \p j. If that fails, everything is put back the way it was, and
+/// the process status is set to the failure - unless \p opts says to exit instead.
+static void internal_exec(env_stack_t &vars, job_t *j, const io_chain_t &block_io,
+                          const exec_options_t &opts) {
     // Do a regular launch -  but without forking first...
     process_t *p = j->processes.front().get();
+
+    // Check the command before changing anything, so the common failures leave fish untouched.
+    std::string actual_cmd = wcs2string(p->actual_cmd);
+    if (access(actual_cmd.c_str(), X_OK) != 0) {
+        int err = errno;
+        FLOGF(warning, _(L"exec: Cannot execute '%ls': %s"), p->actual_cmd.c_str(),
+              std::strerror(err));
+        if (opts.force) exit_without_destructors(exit_code_from_exec_error(err));
+        p->status = proc_status_t::from_exit_code(exit_code_from_exec_error(err));
+        return;
+    }
+    for (int fd : opts.keep_fds) {
+        if (!get_cloexec(fd)) {
+            FLOGF(warning, _(L"exec: fd %d is not open"), fd);
+            p->status = proc_status_t::from_exit_code(STATUS_INVALID_ARGS);
+            return;
+        }
+    }
+
     io_chain_t all_ios = block_io;
     if (!all_ios.append_from_specs(p->redirection_specs(), vars.get_pwd_slash())) {
+        if (opts.force) exit_without_destructors(STATUS_CMD_ERROR);
+        p->status = proc_status_t::from_exit_code(STATUS_CMD_ERROR);
         return;
     }
+    dup2_list_t redirs = dup2_list_t::resolve_chain(all_ios);
+
+    // Remember everything we are about to change.
+    int first_save_fd = all_ios.first_fd_above(k_first_high_fd);
+    for (int fd : opts.keep_fds) first_save_fd = std::max(first_save_fd, fd + 1);
+    std::vector<saved_fd_t> saved_fds = save_fds(redirs, opts.keep_fds, first_save_fd);
+    sigset_t saved_sigmask;
+    sigprocmask(SIG_SETMASK, nullptr, &saved_sigmask);
+    std::vector<struct sigaction> saved_actions(NSIG);
+    for (int sig = 1; sig < NSIG; sig++) sigaction(sig, nullptr, &saved_actions[sig]);
+    struct termios saved_modes;
+    bool have_modes = tcgetattr(STDIN_FILENO, &saved_modes) == 0;
+    auto saved_shlvl = vars.get(L"SHLVL", ENV_GLOBAL | ENV_EXPORT);
 
     // child_setup_process makes sure signals are properly set up.
-    dup2_list_t redirs = dup2_list_t::resolve_chain(all_ios);
-    if (child_setup_process(INVALID_PID, INVALID_PID, *j, false, redirs) == 0) {
+    int err = child_setup_process(INVALID_PID, INVALID_PID, *j, false, redirs);
+    if (err == 0) {
+        for (int fd : opts.keep_fds) set_cloexec(fd, false);
+
         // Decrement SHLVL as we're removing ourselves from the shell "stack".
-        if (is_interactive_session()) {
-            auto shlvl_var = vars.get(L"SHLVL", ENV_GLOBAL | ENV_EXPORT);
+        // This mirrors the increment in env_init.
+        if (is_interactive_session() || get_login()) {
             wcstring shlvl_str = L"0";
-            if (shlvl_var) {
-                long shlvl = fish_wcstol(shlvl_var->as_string().c_str());
+            if (saved_shlvl) {
+                long shlvl = fish_wcstol(saved_shlvl->as_string().c_str());
                 if (!errno && shlvl > 0) {
                     shlvl_str = to_string(shlvl - 1);
                 }
@@ -255,9 +367,143 @@ static void internal_exec(env_stack_t &vars, job_t *j, const io_chain_t &block_i
             vars.set_one(L"SHLVL", ENV_GLOBAL | ENV_EXPORT, std::move(shlvl_str));
         }
 
-        // launch_process _never_ returns.
-        launch_process_nofork(vars, p);
+        // launch_process_nofork only returns if the exec failed.
+        err = launch_process_nofork(vars, p);
+    } else {
+        err = errno;
+        wperror(L"dup2");
+    }
+    if (opts.force) exit_without_destructors(exit_code_from_exec_error(err));
+
+    // The exec failed, so put everything back.
+    restore_fds(saved_fds);
+    for (int sig = 1; sig < NSIG; sig++) sigaction(sig, &saved_actions[sig], nullptr);
+    sigprocmask(SIG_SETMASK, &saved_sigmask, nullptr);
+    if (have_modes) tcsetattr(STDIN_FILENO, TCSANOW, &saved_modes);
+    if (is_interactive_session() || get_login()) {
+        if (saved_shlvl) {
+            vars.set(L"SHLVL", ENV_GLOBAL | ENV_EXPORT, saved_shlvl->as_list());
+        } else {
+            vars.remove(L"SHLVL", ENV_GLOBAL | ENV_EXPORT);
+        }
+    }
+    p->status = proc_status_t::from_exit_code(exit_code_from_exec_error(err));
+}
+
+/// \return whether \p j is an "exec" with options, like "exec --keep-fd 3 cmd". The parser sees
+/// these as the exec builtin, because of the leading dash.
+static bool is_exec_with_options(const job_t *j) {
+    if (j->processes.size() != 1) return false;
+    process_t *p = j->processes.front().get();
+    if (p->type != process_type_t::builtin || p->argv0() != wcstring(L"exec")) return false;
+    const wcstring_list_t &argv = p->argv();
+    // Help is handled by the builtin.
+    return argv.size() > 1 && string_prefixes_string(L"-", argv.at(1)) && argv.at(1) != L"-h" &&
+           argv.at(1) != L"--help";
+}
+
+/// Parse the options of an "exec" like "exec --keep-fd 3 cmd" into \p opts, and turn \p p into an
+/// exec process for the remaining command.
+/// \return true on success, false on error, in which case an error will have been printed and the
+/// process status set.
+static bool parse_exec_options(const parser_t &parser, process_t *p, exec_options_t *opts) {
+    const wcstring_list_t args = p->argv();
+    null_terminated_array_t<wchar_t> argv_array(args);
+    const wchar_t **argv = argv_array.get();
+    int argc = static_cast<int>(args.size());
+
+    static const wchar_t *const short_options = L"+k:f";
+    static const struct woption long_options[] = {{L"keep-fd", required_argument, nullptr, 'k'},
+                                                  {L"force", no_argument, nullptr, 'f'},
+                                                  {nullptr, 0, nullptr, 0}};
+    int opt;
+    wgetopter_t w;
+    while ((opt = w.wgetopt_long(argc, argv, short_options, long_options, nullptr)) != -1) {
+        switch (opt) {
+            case 'k': {
+                int fd = fish_wcstoi(w.woptarg);
+                if (errno || fd < 0) {
+                    FLOGF(warning, _(L"exec: Invalid file descriptor '%ls'"), w.woptarg);
+                    p->status = proc_status_t::from_exit_code(STATUS_INVALID_ARGS);
+                    return false;
+                }
+                opts->keep_fds.push_back(fd);
+                break;
+            }
+            case 'f': {
+                opts->force = true;
+                break;
+            }
+            default: {
+                FLOGF(warning, _(L"exec: Unknown option '%ls'"), argv[w.woptind - 1]);
+                p->status = proc_status_t::from_exit_code(STATUS_INVALID_ARGS);
+                return false;
+            }
+        }
+    }
+    if (w.woptind == argc) {
+        FLOGF(warning, _(L"exec: Expected a command to execute"));
+        p->status = proc_status_t::from_exit_code(STATUS_INVALID_ARGS);
+        return false;
+    }
+
+    wcstring_list_t cmd_args(args.begin() + w.woptind, args.end());
+    wcstring path;
+    if (!path_get_path(cmd_args.front(), &path, parser.vars())) {
+        int err = errno;
+        FLOGF(warning, _(L"exec: Unknown command '%ls'"), cmd_args.front().c_str());
+        p->status = proc_status_t::from_exit_code(exit_code_from_exec_error(err ? err : ENOENT));
+        return false;
+    }
+    p->type = process_type_t::exec;
+    p->set_argv(std::move(cmd_args));
+    p->actual_cmd = std::move(path);
+    return true;
+}
+
+/// \return whether \p j is a bare "exec" with redirections but without a command, like
+/// "exec 5< file".
+static bool is_bare_exec_with_redirections(const job_t *j) {
+    if (j->processes.size() != 1) return false;
+    process_t *p = j->processes.front().get();
+    return p->type == process_type_t::builtin && p->argv().size() == 1 &&
+           p->argv0() == wcstring(L"exec") && !p->redirection_specs().empty();
+}
+
+/// Apply the redirections of a bare "exec" to fish itself, so that the fds stay open (and are
+/// inherited by external commands) until they are closed again with e.g. "exec 5<&-".
+/// Only fds above stderr which fish is not using internally may be changed.
+/// \return true on success, false on error, in which case an error will have been printed.
+static bool exec_redirections_in_shell(parser_t &parser, process_t *p) {
+    io_chain_t ios;
+    if (!ios.append_from_specs(p->redirection_specs(), parser.vars().get_pwd_slash())) {
+        return false;
+    }
+
+    // Check everything first so that we don't apply only some of the redirections.
+    for (const auto &io : ios) {
+        if (io->fd <= STDERR_FILENO) {
+            FLOGF(warning, _(L"exec: Cannot redirect fd %d of the shell"), io->fd);
+            return false;
+        }
+        // All of fish's own fds are CLOEXEC, user fds from earlier execs are not.
+        if (get_cloexec(io->fd) == maybe_t<bool>{true}) {
+            FLOGF(warning, _(L"exec: fd %d is in use by the shell"), io->fd);
+            return false;
+        }
+    }
+
+    for (const auto &io : ios) {
+        if (io->source_fd < 0) {
+            close(io->fd);
+        } else if (io->source_fd == io->fd) {
+            set_cloexec(io->fd, false);
+        } else if (dup2(io->source_fd, io->fd) < 0) {
+            wperror(L"dup2");
+            return false;
+        }
     }
+    return true;
 }
 
 /// If our pgroup assignment mode wants us to use the first external proc, then apply it here.
@@ -546,6 +792,17 @@ static launch_result_t exec_external_command(parser_t &parser, const std::shared
     const char *actual_cmd = actual_cmd_str.c_str();
     const wchar_t *file = parser.libdata().current_filename;
 
+#ifdef FISH_CHECK_FD_LEAKS
+    if (should_flog(fd_leaks)) {
+        // The child gets the targets of our redirections, and doesn't see the fds we close.
+        std::vector<int> expected;
+        for (const auto &act : dup2s.get_actions()) {
+            expected.push_back(act.target >= 0 ? act.target : act.src);
+        }
+        flog_leaked_fds(expected, actual_cmd);
+    }
+#endif
+
 #if FISH_USE_POSIX_SPAWN
     // Prefer to use posix_spawn, since it's faster on some systems like OS X.
     if (can_use_posix_spawn_for_job(j, dup2s)) {
@@ -721,6 +978,16 @@ static launch_result_t exec_block_or_func_process(parser_t &parser, const std::s
     return launch_result_t::ok;
 }
 
+/// \return whether \p fd may be used by a builtin with the io chain \p io_chain: it is either
+/// provided by the chain, or an fd the user opened with e.g. "exec 5< file". fish's own fds are all
+/// CLOEXEC and are off limits.
+static bool fd_is_user_visible(int fd, const io_chain_t &io_chain) {
+    for (const auto &io : io_chain) {
+        if (io->io_mode != io_mode_t::fd && io->source_fd == fd) return true;
+    }
+    return get_cloexec(fd) == maybe_t<bool>{false};
+}
+
 static proc_performer_t get_performer_for_builtin(
     process_t *p, job_t *job, const io_chain_t &io_chain,
     const std::shared_ptr<output_stream_t> output_stream,
@@ -754,18 +1021,13 @@ static proc_performer_t get_performer_for_builtin(
         auto out_io = io_chain.io_for_fd(STDOUT_FILENO);
         auto err_io = io_chain.io_for_fd(STDERR_FILENO);
 
-        // Figure out what fd to use for the builtin's stdin.
-        int local_builtin_stdin = STDIN_FILENO;
-        if (const auto in = io_chain.io_for_fd(STDIN_FILENO)) {
-            // Ignore fd redirections from an fd other than the
-            // standard ones. e.g. in source <&3 don't actually read from fd 3,
-            // which is internal to fish. We still respect this redirection in
-            // that we pass it on as a block IO to the code that source runs,
-            // and therefore this is not an error.
-            bool ignore_redirect = in->io_mode == io_mode_t::fd && in->source_fd >= 3;
-            if (!ignore_redirect) {
-                local_builtin_stdin = in->source_fd;
-            }
+        // Figure out what fd to use for the builtin's stdin, following the chain of fd
+        // redirections, e.g. in source <&3 3<&0 we read from fish's stdin.
+        int local_builtin_stdin =
+            dup2_list_t::resolve_chain(io_chain).fd_for_target_fd(STDIN_FILENO);
+        if (local_builtin_stdin > STDERR_FILENO &&
+            !fd_is_user_visible(local_builtin_stdin, io_chain)) {
+            local_builtin_stdin = -1;
         }
 
         // Populate our io_streams_t. This is a bag of information for the builtin.
@@ -1015,6 +1277,12 @@ bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &bl
     }
 
     // Handle an exec call.
+    exec_options_t exec_opts;
+    if (is_exec_with_options(j.get()) &&
+        !parse_exec_options(parser, j->processes.front().get(), &exec_opts)) {
+        j->processes.front()->mark_aborted_before_launch();
+        return false;
+    }
     if (j->processes.front()->type == process_type_t::exec) {
         // If we are interactive, perhaps disallow exec if there are background jobs.
         if (!allow_exec_with_background_jobs(parser)) {
@@ -1024,11 +1292,9 @@ bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &bl
             return false;
         }
 
-        internal_exec(parser.vars(), j.get(), block_io);
-        // internal_exec only returns if it failed to set up redirections.
-        // In case of an successful exec, this code is not reached.
-        int status = j->flags().negate ? 0 : 1;
-        parser.set_last_statuses(statuses_t::just(status));
+        internal_exec(parser.vars(), j.get(), block_io, exec_opts);
+        // internal_exec only returns if it failed, in which case it has put everything back and
+        // set the process status. In case of an successful exec, this code is not reached.
 
         // A false return tells the caller to remove the job from the list.
         for (const auto &p : j->processes) {
@@ -1036,6 +1302,26 @@ bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &bl
         }
         return false;
     }
+    // Handle e.g. "exec 5< file", which changes fish's own fds.
+    if (is_bare_exec_with_redirections(j.get())) {
+        process_t *p = j->processes.front().get();
+        bool ok = exec_redirections_in_shell(parser, p);
+        p->status = proc_status_t::from_exit_code(ok ? EXIT_SUCCESS : EXIT_FAILURE);
+        p->completed = true;
+        // There is no process to wait for, so remove the job right away.
+        return false;
+    }
+
+    // Refuse to clobber any file before launching anything, so that a failed noclobber redirection
+    // cancels the whole job instead of leaving earlier processes in the pipeline running.
+    for (const auto &p : j->processes) {
+        if (!io_chain_t::check_noclobber_specs(p->redirection_specs(),
+                                               parser.vars().get_pwd_slash())) {
+            abort_pipeline_from(j, j->processes.front().get());
+            return false;
+        }
+    }
+
     cleanup_t timer = push_timer(j->wants_timing() && !no_exec());
 
     // Get the deferred process, if any. We will have to remember its pipes.
@@ -1058,6 +1344,13 @@ bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &bl
     autoclose_fd_t pipe_next_read;
     bool aborted_pipeline = false;
     size_t procs_launched = 0;
+
+    // Place our pipes above every fd the user redirects, so that e.g. "12< file" cannot clobber a
+    // pipe before it is dup'd into place.
+    int first_pipe_fd = block_io.first_fd_above(k_first_high_fd);
+    for (const auto &p : j->processes) {
+        first_pipe_fd = first_fd_above_specs(p->redirection_specs(), first_pipe_fd);
+    }
     for (const auto &procptr : j->processes) {
         process_t *p = procptr.get();
 
@@ -1067,7 +1360,7 @@ bool exec_job(parser_t &parser, const shared_ptr<job_t> &j, const io_chain_t &bl
         autoclose_pipes_t proc_pipes;
         proc_pipes.read = std::move(pipe_next_read);
         if (!p->is_last_in_job) {
-            auto pipes = make_autoclose_pipes();
+            auto pipes = make_autoclose_pipes(first_pipe_fd);
             if (!pipes) {
                 FLOGF(warning, PIPE_ERROR);
                 wperror(L"pipe");
@@ -1178,6 +1471,75 @@ static void populate_subshell_output(wcstring_list_t *lst, const separated_buffe
     }
 }
 
+/// Populate \p lst with the output of \p buffer split on NUL, exactly like piping it into
+/// `string split0` would; explicitly separated elements are followed by a newline in a pipe.
+/// \return the status that `string split0` would have.
+static int populate_subshell_output_split0(wcstring_list_t *lst, const separated_buffer_t &buffer) {
+    bool found_nul = false;
+    // The start of an element that spans buffer elements.
+    std::string pending;
+    for (const auto &elem : buffer.elements()) {
+        const char *cursor = elem.contents.data();
+        const char *end = cursor + elem.contents.size();
+        while (auto nul = static_cast<const char *>(std::memchr(cursor, '\0', end - cursor))) {
+            found_nul = true;
+            if (pending.empty()) {
+                lst->push_back(str2wcstring(cursor, nul - cursor));
+            } else {
+                pending.append(cursor, nul - cursor);
+                lst->push_back(str2wcstring(pending));
+                pending.clear();
+            }
+            cursor = nul + 1;
+        }
+        pending.append(cursor, end - cursor);
+        if (elem.is_explicitly_separated()) pending.push_back('\n');
+    }
+    // Like `string split0`, a trailing NUL does not produce an empty element.
+    if (!pending.empty()) lst->push_back(str2wcstring(pending));
+    // It splits something if there was at least one NUL.
+    return found_nul ? STATUS_CMD_OK : STATUS_CMD_ERROR;
+}
+
+/// If \p cmd is a single job whose last process is a plain `string split0`, \return the source of
+/// the job without that process. The command substitution can then split the output on NUL itself,
+/// instead of piping all of it through `string` first.
+static maybe_t<wcstring> strip_trailing_split0(const wcstring &cmd, parser_t &parser) {
+    auto ast = ast::ast_t::parse(cmd);
+    if (ast.errored()) return none();
+    const auto &jobs = *ast.top()->as<ast::job_list_t>();
+    if (jobs.count() != 1) return none();
+    const ast::job_conjunction_t &jc = *jobs.at(0);
+    if (jc.decorator || !jc.continuations.empty()) return none();
+
+    // `time`, `not` and `&` apply to the whole job, including `string`.
+    const ast::job_t &job = jc.job;
+    if (job.time || job.bg || job.continuation.empty()) return none();
+    if (job.statement.contents->try_as<ast::not_statement_t>()) return none();
+
+    // `&|` would pipe stderr as well.
+    const ast::job_continuation_t &last = *job.continuation.at(0, true /* reverse */);
+    if (last.pipe.source(cmd) != L"|" || !last.variables.empty()) return none();
+    const auto *stmt = last.statement.contents->try_as<ast::decorated_statement_t>();
+    if (!stmt) return none();
+    statement_decoration_t decoration = stmt->decoration();
+    if (decoration != statement_decoration_t::none &&
+        decoration != statement_decoration_t::builtin) {
+        return none();
+    }
+    wcstring_list_t args{stmt->command.source(cmd)};
+    for (const ast::argument_or_redirection_t &arg : stmt->args_or_redirs) {
+        if (!arg.is_argument()) return none();
+        args.push_back(arg.source(cmd));
+    }
+    if (!string_args_only_split0(args)) return none();
+    // A function called "string" runs instead of the builtin.
+    if (decoration == statement_decoration_t::none && function_exists(L"string", parser)) {
+        return none();
+    }
+    return cmd.substr(0, last.pipe.source_range().start);
+}
+
 /// Execute \p cmd in a subshell in \p parser. If \p lst is not null, populate it with the output.
 /// Return $status in \p out_status.
 /// If \p job_group is set, any spawned commands should join that job group.
@@ -1204,6 +1566,11 @@ static int exec_subshell_internal(const wcstring &cmd, parser_t &parser,
 
     const bool split_output = !parser.vars().get(L"IFS").missing_or_empty();
 
+    // Output that is split on NUL in the end is never split on newlines or IFS, so we can do that
+    // split here. This keeps `$(find -print0 | string split0)` from copying all of the output once
+    // more.
+    maybe_t<wcstring> split0_cmd = lst ? strip_trailing_split0(cmd, parser) : none();
+
     // IO buffer creation may fail (e.g. if we have too many open files to make a pipe), so this may
     // be null.
     auto bufferfill = io_bufferfill_t::create(ld.read_limit);
@@ -1211,7 +1578,8 @@ static int exec_subshell_internal(const wcstring &cmd, parser_t &parser,
         *break_expand = true;
         return STATUS_CMD_ERROR;
     }
-    eval_res_t eval_res = parser.eval(cmd, io_chain_t{bufferfill}, job_group, block_type_t::subst);
+    eval_res_t eval_res = parser.eval(split0_cmd ? *split0_cmd : cmd, io_chain_t{bufferfill},
+                                      job_group, block_type_t::subst);
     separated_buffer_t buffer = io_bufferfill_t::finish(std::move(bufferfill));
     if (buffer.discarded()) {
         *break_expand = true;
@@ -1223,10 +1591,19 @@ static int exec_subshell_internal(const wcstring &cmd, parser_t &parser,
         return eval_res.status.status_value();
     }
 
+    *break_expand = false;
+    if (split0_cmd) {
+        // Report the status as if `string split0` had run as the last process of the job.
+        int status = populate_subshell_output_split0(lst, buffer);
+        statuses_t statuses = parser.get_last_statuses();
+        statuses.status = status;
+        statuses.pipestatus.push_back(status);
+        parser.set_last_statuses(std::move(statuses));
+        return status;
+    }
     if (lst) {
         populate_subshell_output(lst, buffer, split_output);
     }
-    *break_expand = false;
     return eval_res.status.status_value();
 }
 
@@ -1251,3 +1628,89 @@ int exec_subshell(const wcstring &cmd, parser_t &parser, wcstring_list_t &output
     return exec_subshell_internal(cmd, parser, nullptr, &outputs, &break_expand, apply_exit_status,
                                   false);
 }
+
+std::unique_ptr<cmdsub_stream_t> cmdsub_stream_t::launch(const wcstring &job_src,
+                                                         parser_t &parser) {
+    ASSERT_IS_MAIN_THREAD();
+    auto pipes = make_autoclose_pipes();
+    if (!pipes) return nullptr;
+
+    // Run the job in the background so we don't wait for it, but in fish's own process group
+    // (without job control) and without telling the user about it, like any command substitution.
+    // Launching it must not change $status or $last_pid.
+    auto &ld = parser.libdata();
+    scoped_push<bool> is_subshell(&ld.is_subshell, true);
+    scoped_push<bool> noninteractive(&ld.is_interactive, false);
+    // Don't let a quick job be reaped before we get to see it.
+    scoped_push<bool> no_reaping(&ld.is_cleaning_procs, true);
+    // A `wait` or `fg` in the loop body must not find the job: waiting for it would deadlock once
+    // the pipe is full.
+    scoped_push<bool> hidden(&ld.is_hidden_job, true);
+    auto prev_statuses = parser.get_last_statuses();
+    auto prev_last_pid = parser.vars().get(L"last_pid", ENV_GLOBAL);
+    const job_t *prev_front = parser.jobs().empty() ? nullptr : parser.jobs().front().get();
+
+    io_chain_t io{std::make_shared<io_pipe_t>(STDOUT_FILENO, false, std::move(pipes->write))};
+    eval_res_t eval_res = parser.eval(job_src + L" &", io, nullptr, block_type_t::subst);
+    // Close our copy of the write end, so we see the end of the output.
+    io.clear();
+
+    std::shared_ptr<job_t> job;
+    if (!parser.jobs().empty() && parser.jobs().front().get() != prev_front) {
+        job = parser.jobs().front();
+    }
+    if (!job || eval_res.break_expand) return nullptr;
+
+    parser.set_last_statuses(prev_statuses);
+    if (prev_last_pid) {
+        parser.vars().set(L"last_pid", ENV_GLOBAL, prev_last_pid->as_list());
+    } else {
+        parser.vars().remove(L"last_pid", ENV_GLOBAL);
+    }
+    return make_unique<cmdsub_stream_t>(std::move(job), std::move(pipes->read));
+}
+
+bool cmdsub_stream_t::next_line(wcstring *out) {
+    for (;;) {
+        size_t pos = buffer_.find('\n', searched_);
+        if (pos != std::string::npos) {
+            *out = str2wcstring(buffer_.data(), pos);
+            buffer_.erase(0, pos + 1);
+            searched_ = 0;
+            return true;
+        }
+        searched_ = buffer_.size();
+        if (eof_) {
+            // A last line without a trailing newline.
+            if (buffer_.empty()) return false;
+            *out = str2wcstring(buffer_);
+            buffer_.clear();
+            searched_ = 0;
+            return true;
+        }
+
+        char buf[4096];
+        ssize_t amt = read(read_end_.fd(), buf, sizeof buf);
+        if (amt > 0) {
+            buffer_.append(buf, amt);
+        } else if (amt == 0) {
+            eof_ = true;
+        } else if (errno != EINTR || signal_check_cancel()) {
+            return false;
+        }
+    }
+}
+
+maybe_t<statuses_t> cmdsub_stream_t::finish(parser_t &parser, bool kill) {
+    ASSERT_IS_MAIN_THREAD();
+    read_end_.close();
+    bool killed = false;
+    while (!job_->is_completed()) {
+        if (!killed && (kill || signal_check_cancel())) {
+            job_->signal(SIGTERM);
+            killed = true;
+        }
+        proc_wait_any(parser);
+    }
+    return job_->get_statuses();
+}
@@ -4,9 +4,12 @@
 
 #include <stddef.h>
 
+#include <memory>
+#include <string>
 #include <vector>
 
 #include "common.h"
+#include "maybe.h"
 #include "proc.h"
 
 /// Execute the processes specified by \p j in the parser \p.
@@ -35,6 +38,39 @@ int exec_subshell(const wcstring &cmd, parser_t &parser, wcstring_list_t &output
 int exec_subshell_for_expand(const wcstring &cmd, parser_t &parser,
                              const job_group_ref_t &job_group, wcstring_list_t &outputs);
 
+/// A command substitution whose output is read line by line while it is still running, instead of
+/// after it has finished. This is used by `for` loops with the stream-for-cmdsub feature.
+/// The command must be a single job of external commands, since builtins and functions run in fish
+/// itself and so can't run at the same time as the loop.
+class cmdsub_stream_t {
+   public:
+    /// Launch \p job_src without waiting for it, with its output going into a pipe.
+    /// \return null if it could not be launched, in which case an error may have been printed and
+    /// $status set.
+    static std::unique_ptr<cmdsub_stream_t> launch(const wcstring &job_src, parser_t &parser);
+
+    /// Read the next line of output into \p out, like a command substitution splits it.
+    /// \return false at the end of the output, or if reading was cancelled by a signal.
+    bool next_line(wcstring *out);
+
+    /// Stop reading and wait for the job to finish. If \p kill is set, or reading was cancelled by
+    /// a signal, terminate the job first.
+    /// \return the statuses of the job, if it produced any.
+    maybe_t<statuses_t> finish(parser_t &parser, bool kill);
+
+    cmdsub_stream_t(std::shared_ptr<job_t> job, autoclose_fd_t read_end)
+        : job_(std::move(job)), read_end_(std::move(read_end)) {}
+
+   private:
+    std::shared_ptr<job_t> job_;
+    autoclose_fd_t read_end_;
+    // Bytes that have been read but not yet returned.
+    std::string buffer_;
+    // How much of buffer_ is known not to contain a newline.
+    size_t searched_{0};
+    bool eof_{false};
+};
+
 /// Loops over close until the syscall was run without being interrupted.
 void exec_close(int fd);
 
@@ -39,6 +39,7 @@
 #include "exec.h"
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "future_feature_flags.h"
 #include "history.h"
 #include "iothread.h"
 #include "parse_constants.h"
@@ -174,16 +175,13 @@ wcstring expand_escape_string(const wcstring &el) {
     return buff;
 }
 
-/// Parse an array slicing specification Returns 0 on success. If a parse error occurs, returns the
-/// index of the bad token. Note that 0 can never be a bad index because the string always starts
-/// with [.
-static size_t parse_slice(const wchar_t *in, wchar_t **end_ptr, std::vector<long> &idx,
-                          size_t array_size) {
+size_t parse_slice(const wchar_t *in, const wchar_t **end_ptr, std::vector<long> &idx,
+                   size_t array_size, slice_mode_t mode) {
     const long size = static_cast<long>(array_size);
     size_t pos = 1;  // skip past the opening square brace
 
     int zero_index = -1;
-    bool literal_zero_index = true;
+    bool literal_zero_index = mode == slice_mode_t::read;
 
     while (true) {
         while (iswspace(in[pos]) || (in[pos] == INTERNAL_SEPARATOR)) pos++;
@@ -205,11 +203,13 @@ static size_t parse_slice(const wchar_t *in, wchar_t **end_ptr, std::vector<long
 
         const wchar_t *end;
         long tmp;
+        bool implied_start = false;
         if (idx.empty() && in[pos] == L'.' && in[pos + 1] == L'.') {
             // If we are at the first index expression, a missing start-index means the range starts
             // at the first item.
             tmp = 1;  // first index
             end = &in[pos];
+            implied_start = true;
         } else {
             tmp = fish_wcstol(&in[pos], &end);
             if (errno > 0) {
@@ -242,11 +242,42 @@ static size_t parse_slice(const wchar_t *in, wchar_t **end_ptr, std::vector<long
                 }
             }
             pos = end - in;
-
             long i2 = tmp1 > -1 ? tmp1 : size + tmp1 + 1;
+
+            // An optional step, as in "1..-1..2".
+            while (in[pos] == INTERNAL_SEPARATOR) pos++;
+            if (in[pos] == L'.' && in[pos + 1] == L'.') {
+                const size_t step_pos = pos;
+                pos += 2;
+                while (in[pos] == INTERNAL_SEPARATOR) pos++;
+                long step = fish_wcstol(&in[pos], &end);
+                // A step needs both limits, so "..1..2" is not "1..1..2".
+                if (errno > 0 || step == 0 || implied_start) return step_pos;
+                pos = end - in;
+
+                if (mode == slice_mode_t::read) {
+                    // Clamp to the list. The start only moves by whole steps so "-4..-1..2" on
+                    // three elements still picks the second one.
+                    if (step > 0) {
+                        if (i1 < 1) i1 += (1 - i1 + step - 1) / step * step;
+                        i2 = std::min(i2, size);
+                    } else {
+                        if (i1 > size) i1 -= (i1 - size - step - 1) / -step * -step;
+                        i2 = std::max(i2, 1L);
+                    }
+                }
+                // The step gives the direction, so "1..3..-1" is empty.
+                if (step > 0 ? i1 > i2 : i1 < i2) continue;
+                for (long i = i1;; i += step) {
+                    idx.push_back(i);
+                    if (step > 0 ? i2 - i < step : i - i2 < -step) break;
+                }
+                continue;
+            }
+
             // Skip sequences that are entirely outside.
             // This means "17..18" expands to nothing if there are less than 17 elements.
-            if (i1 > size && i2 > size) {
+            if (mode == slice_mode_t::read && i1 > size && i2 > size) {
                 continue;
             }
             short direction = i2 < i1 ? -1 : 1;
@@ -255,7 +286,7 @@ static size_t parse_slice(const wchar_t *in, wchar_t **end_ptr, std::vector<long
             // Prevents `[x..-1]` from going reverse if less than x elements are there.
             if ((tmp1 > -1) != (tmp > -1)) {
                 direction = tmp1 > -1 ? -1 : 1;
-            } else {
+            } else if (mode == slice_mode_t::read) {
                 // Clamp to array size when not forcing direction
                 // - otherwise "2..-1" clamps both to 1 and then becomes "1..1".
                 i1 = i1 < size ? i1 : size;
@@ -277,7 +308,7 @@ static size_t parse_slice(const wchar_t *in, wchar_t **end_ptr, std::vector<long
     }
 
     if (end_ptr) {
-        *end_ptr = const_cast<wchar_t *>(in + pos);
+        *end_ptr = in + pos;
     }
 
     return 0;
@@ -376,7 +407,7 @@ static expand_result_t expand_variables(wcstring instr, completion_receiver_t *o
     if (slice_start < insize && instr.at(slice_start) == L'[') {
         all_values = false;
         const wchar_t *in = instr.c_str();
-        wchar_t *slice_end;
+        const wchar_t *slice_end;
         // If a variable is missing, behave as though we have one value, so that $var[1] always
         // works.
         size_t effective_val_count = 1;
@@ -498,6 +529,106 @@ static expand_result_t expand_variables(wcstring instr, completion_receiver_t *o
     return expand_result_t::ok;
 }
 
+/// Parse an integer part of a brace range, which is an optional minus sign followed by digits.
+static maybe_t<long long> parse_brace_range_number(const wcstring &str) {
+    size_t digits = str.size() > 0 && str.at(0) == L'-' ? 1 : 0;
+    if (digits == str.size()) return none();
+    for (size_t i = digits; i < str.size(); i++) {
+        if (str.at(i) < L'0' || str.at(i) > L'9') return none();
+    }
+    const wchar_t *end = nullptr;
+    long long result = fish_wcstoll(str.c_str(), &end);
+    if (errno || *end) return none();
+    return result;
+}
+
+maybe_t<brace_range_t> parse_brace_range(const wcstring &contents, const wcstring &sep) {
+    wcstring_list_t parts;
+    size_t pos = 0;
+    for (;;) {
+        size_t next = contents.find(sep, pos);
+        parts.push_back(contents.substr(pos, next == wcstring::npos ? wcstring::npos : next - pos));
+        if (next == wcstring::npos) break;
+        pos = next + sep.size();
+    }
+    if (parts.size() != 2 && parts.size() != 3) return none();
+
+    brace_range_t result{};
+    result.step = 1;
+    if (parts.size() == 3) {
+        maybe_t<long long> step = parse_brace_range_number(parts.at(2));
+        if (!step || *step == 0) return none();
+        // The direction comes from the endpoints, so only the magnitude of the step matters.
+        result.step = *step < 0 ? 0ULL - static_cast<unsigned long long>(*step) : *step;
+    }
+
+    const wcstring &first = parts.at(0), &last = parts.at(1);
+    maybe_t<long long> start = parse_brace_range_number(first);
+    maybe_t<long long> end = parse_brace_range_number(last);
+    if (start && end) {
+        result.start = *start;
+        result.end = *end;
+        // Like bash, a leading zero on either end pads all numbers to the width of the wider end.
+        auto is_padded = [](const wcstring &num) {
+            size_t digits = num.at(0) == L'-' ? 1 : 0;
+            return num.size() > digits + 1 && num.at(digits) == L'0';
+        };
+        if (is_padded(first) || is_padded(last)) {
+            result.width = std::max(first.size(), last.size());
+        }
+        return result;
+    }
+
+    // Otherwise both ends must be single characters, which are not digits.
+    if (first.size() != 1 || last.size() != 1 || start || end) return none();
+    for (wchar_t c : {first.at(0), last.at(0)}) {
+        if (fish_reserved_codepoint(c) || iswdigit(c)) return none();
+    }
+    result.start = first.at(0);
+    result.end = last.at(0);
+    result.is_char = true;
+    return result;
+}
+
+static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
+                                     completion_receiver_t *out, parse_error_list_t *errors);
+
+/// Expand a \p range written in braces, putting each element between \p prefix and \p suffix,
+/// which may contain further braces.
+static expand_result_t expand_brace_range(const brace_range_t &range, const wcstring &prefix,
+                                          const wchar_t *suffix, expand_flags_t flags,
+                                          completion_receiver_t *out, parse_error_list_t *errors) {
+    // Compute in unsigned arithmetic, where the distance between any two long longs fits.
+    using ull = unsigned long long;
+    bool ascending = range.start <= range.end;
+    ull distance = ascending ? static_cast<ull>(range.end) - static_cast<ull>(range.start)
+                             : static_cast<ull>(range.start) - static_cast<ull>(range.end);
+    ull last_idx = distance / range.step;
+
+    // Check the size before producing anything, so {1..999999999} fails fast.
+    if (last_idx >= out->remaining_capacity()) {
+        return append_overflow_error(errors);
+    }
+
+    for (ull i = 0; i <= last_idx; i++) {
+        ull offset = i * range.step;
+        auto value = static_cast<long long>(ascending ? static_cast<ull>(range.start) + offset
+                                                      : static_cast<ull>(range.start) - offset);
+        wcstring item = prefix;
+        if (range.is_char) {
+            item.push_back(static_cast<wchar_t>(value));
+        } else if (range.width > 0) {
+            append_format(item, L"%0*lld", static_cast<int>(range.width), value);
+        } else {
+            append_format(item, L"%lld", value);
+        }
+        item.append(suffix);
+        expand_result_t result = expand_braces(std::move(item), flags, out, errors);
+        if (result == expand_result_t::error) return result;
+    }
+    return expand_result_t::ok;
+}
+
 /// Perform brace expansion, placing the expanded strings into \p out.
 static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
                                      completion_receiver_t *out, parse_error_list_t *errors) {
@@ -506,6 +637,7 @@ static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
 
     const wchar_t *brace_begin = nullptr, *brace_end = nullptr;
     const wchar_t *last_sep = nullptr;
+    bool is_range = false;
 
     const wchar_t *item_begin;
     size_t length_preceding_braces, length_following_braces, tot_len;
@@ -516,7 +648,11 @@ static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
     for (const wchar_t *pos = in; (*pos) && !syntax_error; pos++) {
         switch (*pos) {
             case BRACE_BEGIN: {
-                if (brace_count == 0) brace_begin = pos;
+                if (brace_count == 0) {
+                    // A later pair replaces an earlier one, so forget whether that was a range.
+                    brace_begin = pos;
+                    is_range = false;
+                }
                 brace_count++;
                 break;
             }
@@ -533,6 +669,10 @@ static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
                 if (brace_count == 1) last_sep = pos;
                 break;
             }
+            case BRACE_RANGE: {
+                if (brace_count == 1) is_range = true;
+                break;
+            }
             default: {
                 break;  // we ignore all other characters here
             }
@@ -572,6 +712,13 @@ static expand_result_t expand_braces(wcstring &&instr, expand_flags_t flags,
         return expand_result_t::ok;
     }
 
+    if (is_range) {
+        auto range = parse_brace_range(wcstring(brace_begin + 1, brace_end), {BRACE_RANGE});
+        assert(range.has_value() && "Brace range should have been checked when unescaping");
+        return expand_brace_range(*range, wcstring(in, brace_begin), brace_end + 1, flags, out,
+                                  errors);
+    }
+
     length_preceding_braces = (brace_begin - in);
     length_following_braces = instr.size() - (brace_end - in) - 1;
     tot_len = length_preceding_braces + length_following_braces;
@@ -619,9 +766,11 @@ static expand_result_t expand_cmdsubst(wcstring input, const operation_context_t
     size_t paren_begin = 0;
     size_t paren_end = 0;
     wcstring subcmd;
+    bool is_quoted = false;
+    bool has_dollar = false;
 
     switch (parse_util_locate_cmdsubst_range(input, &cursor, &subcmd, &paren_begin, &paren_end,
-                                             false)) {
+                                             false, &is_quoted, &has_dollar)) {
         case -1: {
             append_syntax_error(errors, SOURCE_LOCATION_UNKNOWN, L"Mismatched parenthesis");
             return expand_result_t::make_error(STATUS_EXPAND_ERROR);
@@ -669,13 +818,19 @@ static expand_result_t expand_cmdsubst(wcstring input, const operation_context_t
         return expand_result_t::make_error(subshell_status);
     }
 
+    // A command substitution inside double quotes expands to a single argument, with newlines
+    // intact (but without the trailing one).
+    if (is_quoted) {
+        sub_res = wcstring_list_t{join_strings(sub_res, L'\n')};
+    }
+
     // Expand slices like (cat /var/words)[1]
     size_t tail_begin = paren_end + 1;
-    if (tail_begin < input.size() && input.at(tail_begin) == L'[') {
+    if (!is_quoted && tail_begin < input.size() && input.at(tail_begin) == L'[') {
         const wchar_t *in = input.c_str();
         std::vector<long> slice_idx;
         const wchar_t *const slice_begin = in + tail_begin;
-        wchar_t *slice_end = nullptr;
+        const wchar_t *slice_end = nullptr;
         size_t bad_pos = parse_slice(slice_begin, &slice_end, slice_idx, sub_res.size());
         if (bad_pos != 0) {
             if (slice_begin[bad_pos] == L'0') {
@@ -700,9 +855,17 @@ static expand_result_t expand_cmdsubst(wcstring input, const operation_context_t
     }
 
     // Recursively call ourselves to expand any remaining command substitutions. The result of this
-    // recursive call using the tail of the string is inserted into the tail_expand array list
+    // recursive call using the tail of the string is inserted into the tail_expand array list.
+    // A command substitution inside double quotes interrupts the quoted string, so close the quotes
+    // before it and reopen them after it.
+    wcstring prefix = input.substr(0, paren_begin - (has_dollar ? 1 : 0));
+    wcstring tail = input.substr(tail_begin);
+    if (is_quoted) {
+        prefix.push_back(L'"');
+        tail.insert(0, 1, L'"');
+    }
     completion_receiver_t tail_expand_recv = out->subreceiver();
-    expand_cmdsubst(input.substr(tail_begin), ctx, &tail_expand_recv,
+    expand_cmdsubst(std::move(tail), ctx, &tail_expand_recv,
                     errors);  // TODO: offset error locations
     completion_list_t tail_expand = tail_expand_recv.take();
 
@@ -712,9 +875,9 @@ static expand_result_t expand_cmdsubst(wcstring input, const operation_context_t
         wcstring sub_item2 = escape_string(sub_item, ESCAPE_ALL);
         for (const completion_t &tail_item : tail_expand) {
             wcstring whole_item;
-            whole_item.reserve(paren_begin + 1 + sub_item2.size() + 1 +
+            whole_item.reserve(prefix.size() + 1 + sub_item2.size() + 1 +
                                tail_item.completion.size());
-            whole_item.append(input, 0, paren_begin);
+            whole_item.append(prefix);
             whole_item.push_back(INTERNAL_SEPARATOR);
             whole_item.append(sub_item2);
             whole_item.push_back(INTERNAL_SEPARATOR);
@@ -746,6 +909,50 @@ static wcstring get_home_directory_name(const wcstring &input, size_t *out_tail_
     return input.substr(1, pos);
 }
 
+/// Look up the home directory of \p user in the passwd database.
+static maybe_t<wcstring> passwd_home_directory(const wcstring &user) {
+    std::string name_cstr = wcs2string(user);
+    struct passwd userinfo;
+    struct passwd *result;
+    char buf[8192];
+    int retval = getpwnam_r(name_cstr.c_str(), &userinfo, buf, sizeof(buf), &result);
+    if (!retval && result) {
+        return str2wcstring(userinfo.pw_dir);
+    }
+    return none();
+}
+
+maybe_t<wcstring> home_directory_cache_t::lookup(const wcstring &user) {
+    double now = clock_();
+    {
+        auto entries = entries_.acquire();
+        auto iter = entries->find(user);
+        if (iter != entries->end() && now < iter->second.expiry) {
+            return iter->second.home;
+        }
+    }
+
+    // Don't hold the lock while looking up, it may take a while.
+    maybe_t<wcstring> home = lookup_(user);
+    double ttl = home ? kFoundTimeToLive : kMissingTimeToLive;
+
+    auto entries = entries_.acquire();
+    if (entries->size() >= kMaxEntries) {
+        // Make room by dropping the expired entries, or everything if there are none.
+        for (auto iter = entries->begin(); iter != entries->end();) {
+            iter = now < iter->second.expiry ? std::next(iter) : entries->erase(iter);
+        }
+        if (entries->size() >= kMaxEntries) entries->clear();
+    }
+    (*entries)[user] = entry_t{home, now + ttl};
+    return home;
+}
+
+home_directory_cache_t &home_directory_cache_t::shared() {
+    static auto *s_cache = new home_directory_cache_t(passwd_home_directory);
+    return *s_cache;
+}
+
 /// Attempts tilde expansion of the string specified, modifying it in place.
 static void expand_home_directory(wcstring &input, const environment_t &vars) {
     if (!input.empty() && input.at(0) == HOME_DIRECTORY) {
@@ -764,14 +971,7 @@ static void expand_home_directory(wcstring &input, const environment_t &vars) {
             tail_idx = 1;
         } else {
             // Some other user's home directory.
-            std::string name_cstr = wcs2string(username);
-            struct passwd userinfo;
-            struct passwd *result;
-            char buf[8192];
-            int retval = getpwnam_r(name_cstr.c_str(), &userinfo, buf, sizeof(buf), &result);
-            if (!retval && result) {
-                home = str2wcstring(userinfo.pw_dir);
-            }
+            home = home_directory_cache_t::shared().lookup(username);
         }
 
         maybe_t<wcstring> realhome;
@@ -972,6 +1172,11 @@ expand_result_t expander_t::stage_home_and_self(wcstring input, completion_recei
     if (!(flags & expand_flag::skip_home_directories)) {
         expand_home_directory(input, ctx.vars);
     }
+    if (!input.empty() && input.front() == PROCESS_EXPAND_SELF &&
+        feature_test(features_t::remove_percent_self)) {
+        append_syntax_error(errors, 0, ERROR_PERCENT_SELF);
+        return expand_result_t::make_error(STATUS_EXPAND_ERROR);
+    }
     expand_percent_self(input);
     if (!out->add(std::move(input))) {
         return append_overflow_error(errors);
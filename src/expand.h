@@ -11,6 +11,7 @@
 
 #include <map>
 #include <string>
+#include <unordered_map>
 #include <vector>
 
 #include "common.h"
@@ -88,6 +89,8 @@ enum : wchar_t {
     BRACE_SEP,
     /// Character that takes the place of any whitespace within non-quoted text in braces
     BRACE_SPACE,
+    /// Character representing the ".." between the parts of a range in braces, like {1..10}.
+    BRACE_RANGE,
     /// Separate subtokens in a token with this character.
     INTERNAL_SEPARATOR,
     /// Character representing an empty variable expansion. Only used transitively while expanding
@@ -193,6 +196,42 @@ expand_result_t expand_to_command_and_args(const wcstring &instr, const operatio
 /// Suitable for pretty-printing.
 wcstring expand_escape_variable(const env_var_t &var);
 
+/// How parse_slice() treats indexes beyond the end of the list.
+enum class slice_mode_t {
+    /// Ranges are clamped to the list, and "[0]" is refused.
+    read,
+    /// Indexes are left as they are, because the list will be extended to fit.
+    write,
+};
+
+/// Parse a slice specification like "[1 3..5 -1..1..-2]" for a list of \p array_size elements.
+/// \p in must point at the opening bracket. The indexes are appended to \p idx, with ranges
+/// expanded and negative indexes turned into the positive ones they refer to. A range may have a
+/// step as a third part, whose sign gives its direction.
+/// \return 0 on success, in which case \p end_ptr (if not null) is set to just past the closing
+/// bracket, or the offset of the bad token in \p in. This is never 0 since \p in starts with '['.
+size_t parse_slice(const wchar_t *in, const wchar_t **end_ptr, std::vector<long> &idx,
+                   size_t array_size, slice_mode_t mode = slice_mode_t::read);
+
+/// A sequence written in braces, like {1..10}, {a..f} or {10..0..2}.
+struct brace_range_t {
+    /// The first and last element, either numbers or character codes. The last element is only
+    /// reached if the step leads there.
+    long long start;
+    long long end;
+    /// The distance between two elements, which is always positive.
+    unsigned long long step;
+    /// Whether the elements are characters rather than numbers.
+    bool is_char;
+    /// The width to zero-pad numbers to, or 0 for no padding.
+    size_t width;
+};
+
+/// Parse the \p contents of braces as a range, whose parts are separated by \p sep. The parts are
+/// either two integers or two single characters, optionally followed by an integer step.
+/// \return the range, or none() if this is not a range.
+maybe_t<brace_range_t> parse_brace_range(const wcstring &contents, const wcstring &sep);
+
 /// Convert a string value to a human readable form, i.e. escape things, handle arrays, etc.
 /// Suitable for pretty-printing.
 wcstring expand_escape_string(const wcstring &el);
@@ -205,6 +244,48 @@ void expand_tilde(wcstring &input, const environment_t &vars);
 /// Perform the opposite of tilde expansion on the string, which is modified in place.
 wcstring replace_home_directory_with_tilde(const wcstring &str, const environment_t &vars);
 
+/// A cache of other users' home directories, used for ~user expansion. Looking them up may be slow,
+/// e.g. with LDAP, and completion expands the same user on every keystroke. Entries, including
+/// those for users that do not exist, expire after a while so changes are noticed.
+class home_directory_cache_t {
+   public:
+    /// A function to look up the home directory of a user, returning none if there is no such
+    /// user. This and the clock are only replaced for testing.
+    using lookup_func_t = maybe_t<wcstring> (*)(const wcstring &user);
+
+    /// A function returning the current time in seconds.
+    using clock_func_t = double (*)();
+
+    /// How long the home directory of a user is cached, in seconds.
+    static constexpr double kFoundTimeToLive = 60;
+
+    /// How long we remember that a user does not exist, in seconds. This is shorter, since the user
+    /// may be typing the name of one.
+    static constexpr double kMissingTimeToLive = 5;
+
+    /// How many users we cache at most.
+    static constexpr size_t kMaxEntries = 64;
+
+    explicit home_directory_cache_t(lookup_func_t lookup, clock_func_t clock = timef)
+        : lookup_(lookup), clock_(clock) {}
+
+    /// \return the home directory of \p user, or none if there is no such user.
+    maybe_t<wcstring> lookup(const wcstring &user);
+
+    /// \return the shared cache, which looks users up in the passwd database.
+    static home_directory_cache_t &shared();
+
+   private:
+    struct entry_t {
+        maybe_t<wcstring> home;
+        double expiry;
+    };
+
+    owning_lock<std::unordered_map<wcstring, entry_t>> entries_{};
+    const lookup_func_t lookup_;
+    const clock_func_t clock_;
+};
+
 /// Abbreviation support. Expand src as an abbreviation, returning the expanded form if found,
 /// none() if not.
 maybe_t<wcstring> expand_abbreviation(const wcstring &src, const environment_t &vars);
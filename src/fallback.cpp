@@ -42,6 +42,7 @@
 
 #include "common.h"    // IWYU pragma: keep
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 
 #if defined(TPARM_SOLARIS_KLUDGE)
 #undef tparm
@@ -65,7 +66,7 @@ int fish_mkstemp_cloexec(char *name_template) {
 #endif
     int result_fd = mkstemp(name_template);
     if (result_fd != -1) {
-        fcntl(result_fd, F_SETFD, FD_CLOEXEC);
+        set_cloexec(result_fd);
     }
     return result_fd;
 }
@@ -3,7 +3,19 @@
 
 #include "fd_monitor.h"
 
+#include <poll.h>
+#ifdef HAVE_EPOLL_CREATE1
+#include <sys/epoll.h>
+#elif defined(HAVE_KQUEUE)
+#include <sys/types.h>
+#include <sys/event.h>
+#include <sys/time.h>
+#endif
+
+#include <algorithm>
+#include <climits>
 #include <cstring>
+#include <memory>
 #include <thread>  //this_thread::sleep_for
 
 #include "flog.h"
@@ -13,6 +25,184 @@
 
 static constexpr uint64_t kUsecPerMsec = 1000;
 
+/// A set of fds which the fd monitor waits on to become readable. Each fd is waited on for a single
+/// wakeup: after wait() has reported it, it needs to be added again. Unlike select(), this is not
+/// limited to fds below FD_SETSIZE.
+/// Only used on the fd monitor's background thread.
+class fd_readable_set_t {
+   public:
+    using id_t = fd_monitor_item_id_t;
+
+    virtual ~fd_readable_set_t() = default;
+
+    /// Wait for \p fd to become readable, reporting it as \p id. If it is already being waited on,
+    /// this waits again.
+    virtual void add(int fd, id_t id) = 0;
+
+    /// Stop waiting on the fd reported as \p id. This is called after the fd was closed.
+    virtual void remove(id_t id) = 0;
+
+    /// Wait up to \p timeout_usec for our fds to become readable, and put the IDs of those that did
+    /// into \p out, sorted.
+    virtual void wait(uint64_t timeout_usec, std::vector<id_t> *out) = 0;
+
+    /// \return the best set for this system.
+    static std::unique_ptr<fd_readable_set_t> create();
+};
+
+/// Convert a timeout in microseconds to milliseconds for poll() or epoll_wait(), rounding up so we
+/// don't wake before the timeout is hit.
+static int timeout_to_msec(uint64_t timeout_usec) {
+    if (timeout_usec == fd_monitor_item_t::kNoTimeout) return -1;
+    uint64_t msec = (timeout_usec + kUsecPerMsec - 1) / kUsecPerMsec;
+    return static_cast<int>(std::min(msec, static_cast<uint64_t>(INT_MAX)));
+}
+
+namespace {
+/// The portable fallback, using poll().
+class fd_poll_set_t final : public fd_readable_set_t {
+   public:
+    void add(int fd, id_t id) override {
+        auto where = std::find(ids_.begin(), ids_.end(), id);
+        if (where != ids_.end()) {
+            pollfds_.at(where - ids_.begin()).fd = fd;
+            return;
+        }
+        pollfds_.push_back(pollfd{fd, POLLIN, 0});
+        ids_.push_back(id);
+    }
+
+    void remove(id_t id) override {
+        auto where = std::find(ids_.begin(), ids_.end(), id);
+        if (where == ids_.end()) return;
+        pollfds_.erase(pollfds_.begin() + (where - ids_.begin()));
+        ids_.erase(where);
+    }
+
+    void wait(uint64_t timeout_usec, std::vector<id_t> *out) override {
+        int ret = poll(pollfds_.data(), pollfds_.size(), timeout_to_msec(timeout_usec));
+        if (ret < 0 && errno != EINTR) wperror(L"poll");
+        if (ret <= 0) return;
+        // Report and forget every fd with an event, including errors and hangups, which will make a
+        // read() return.
+        size_t kept = 0;
+        for (size_t i = 0; i < pollfds_.size(); i++) {
+            if (pollfds_[i].revents) {
+                out->push_back(ids_[i]);
+            } else {
+                pollfds_[kept] = pollfds_[i];
+                ids_[kept] = ids_[i];
+                kept++;
+            }
+        }
+        pollfds_.resize(kept);
+        ids_.resize(kept);
+        std::sort(out->begin(), out->end());
+    }
+
+   private:
+    // Parallel lists of the fds to poll and their IDs.
+    std::vector<pollfd> pollfds_;
+    std::vector<id_t> ids_;
+};
+
+#ifdef HAVE_EPOLL_CREATE1
+/// The Linux set, using epoll. Fds are registered as one-shot, so each is disabled after it is
+/// reported, and closing the fd removes it.
+class fd_epoll_set_t final : public fd_readable_set_t {
+   public:
+    explicit fd_epoll_set_t(autoclose_fd_t epfd) : epfd_(std::move(epfd)) {}
+
+    void add(int fd, id_t id) override {
+        struct epoll_event event {};
+        event.events = EPOLLIN | EPOLLONESHOT;
+        event.data.u64 = id;
+        if (epoll_ctl(epfd_.fd(), EPOLL_CTL_ADD, fd, &event) < 0) {
+            // It may already be registered, in which case re-arm it.
+            if (errno != EEXIST || epoll_ctl(epfd_.fd(), EPOLL_CTL_MOD, fd, &event) < 0) {
+                wperror(L"epoll_ctl");
+            }
+        }
+    }
+
+    void remove(id_t id) override {
+        // The fd is already closed, and so is removed from the epoll set.
+        UNUSED(id);
+    }
+
+    void wait(uint64_t timeout_usec, std::vector<id_t> *out) override {
+        struct epoll_event events[64];
+        int ret = epoll_wait(epfd_.fd(), events, 64, timeout_to_msec(timeout_usec));
+        if (ret < 0 && errno != EINTR) wperror(L"epoll_wait");
+        for (int i = 0; i < ret; i++) {
+            out->push_back(events[i].data.u64);
+        }
+        std::sort(out->begin(), out->end());
+    }
+
+   private:
+    autoclose_fd_t epfd_;
+};
+#elif defined(HAVE_KQUEUE)
+/// The BSD and macOS set, using kqueue. Events are added as one-shot, so each is deleted after it
+/// is reported, and closing the fd removes it.
+class fd_kqueue_set_t final : public fd_readable_set_t {
+   public:
+    explicit fd_kqueue_set_t(autoclose_fd_t kq) : kq_(std::move(kq)) {}
+
+    void add(int fd, id_t id) override {
+        struct kevent change;
+        EV_SET(&change, fd, EVFILT_READ, EV_ADD | EV_ONESHOT, 0, 0,
+               reinterpret_cast<void *>(static_cast<uintptr_t>(id)));
+        if (kevent(kq_.fd(), &change, 1, nullptr, 0, nullptr) < 0) {
+            wperror(L"kevent");
+        }
+    }
+
+    void remove(id_t id) override {
+        // The fd is already closed, which deletes its events.
+        UNUSED(id);
+    }
+
+    void wait(uint64_t timeout_usec, std::vector<id_t> *out) override {
+        struct timespec timeout;
+        struct timespec *timeout_ptr = nullptr;
+        if (timeout_usec != fd_monitor_item_t::kNoTimeout) {
+            timeout.tv_sec = timeout_usec / (1000 * kUsecPerMsec);
+            timeout.tv_nsec = (timeout_usec % (1000 * kUsecPerMsec)) * 1000;
+            timeout_ptr = &timeout;
+        }
+        struct kevent events[64];
+        int ret = kevent(kq_.fd(), nullptr, 0, events, 64, timeout_ptr);
+        if (ret < 0 && errno != EINTR) wperror(L"kevent");
+        for (int i = 0; i < ret; i++) {
+            out->push_back(static_cast<id_t>(reinterpret_cast<uintptr_t>(events[i].udata)));
+        }
+        std::sort(out->begin(), out->end());
+    }
+
+   private:
+    autoclose_fd_t kq_;
+};
+#endif
+}  // namespace
+
+std::unique_ptr<fd_readable_set_t> fd_readable_set_t::create() {
+#ifdef HAVE_EPOLL_CREATE1
+    autoclose_fd_t epfd{epoll_create1(EPOLL_CLOEXEC)};
+    if (epfd.valid()) return make_unique<fd_epoll_set_t>(std::move(epfd));
+    wperror(L"epoll_create1");
+#elif defined(HAVE_KQUEUE)
+    autoclose_fd_t kq{kqueue()};
+    if (kq.valid()) {
+        (void)set_cloexec(kq.fd());
+        return make_unique<fd_kqueue_set_t>(std::move(kq));
+    }
+    wperror(L"kqueue");
+#endif
+    return make_unique<fd_poll_set_t>();
+}
+
 fd_monitor_t::fd_monitor_t() = default;
 
 fd_monitor_t::~fd_monitor_t() {
@@ -87,9 +277,8 @@ uint64_t fd_monitor_item_t::usec_remaining(const time_point_t &now) const {
     return since >= timeout_usec ? 0 : timeout_usec - since;
 }
 
-bool fd_monitor_item_t::service_item(const select_wrapper_t &fds, const time_point_t &now) {
+bool fd_monitor_item_t::service_item(bool readable, const time_point_t &now) {
     bool should_retain = true;
-    bool readable = fds.test(fd.fd());
     bool timed_out = !readable && usec_remaining(now) == 0;
     if (readable || timed_out) {
         last_time = now;
@@ -113,53 +302,59 @@ bool fd_monitor_item_t::poke_item(const poke_list_t &pokelist) {
 void fd_monitor_t::run_in_background() {
     ASSERT_IS_BACKGROUND_THREAD();
     poke_list_t pokelist;
-    select_wrapper_t fds;
+    std::unique_ptr<fd_readable_set_t> fds = fd_readable_set_t::create();
+    std::vector<fd_monitor_item_id_t> ready;
+
+    // Our change_signaller is special cased. Item IDs start at 1, so it gets ID 0.
+    const fd_monitor_item_id_t change_signal_id = 0;
+    int change_signal_fd = change_signaller_.read_fd();
+    fds->add(change_signal_fd, change_signal_id);
+
     for (;;) {
         // Poke any items that need it.
         if (!pokelist.empty()) {
-            this->poke_in_background(std::move(pokelist));
+            this->poke_in_background(std::move(pokelist), *fds);
             pokelist.clear();
         }
 
-        fds.clear();
-
-        // Our change_signaller is special cased.
-        int change_signal_fd = change_signaller_.read_fd();
-        fds.add(change_signal_fd);
-
         auto now = std::chrono::steady_clock::now();
         uint64_t timeout_usec = fd_monitor_item_t::kNoTimeout;
 
         for (auto &item : items_) {
-            fds.add(item.fd.fd());
             if (!item.last_time.has_value()) item.last_time = now;
             timeout_usec = std::min(timeout_usec, item.usec_remaining(now));
         }
 
-        // If we have only one item, it means that we are not actively monitoring any fds other than
-        // our self-pipe. In this case we wish to allow the thread to exit, but after a time, so we
+        // If we have no items, it means that we are not actively monitoring any fds other than our
+        // self-pipe. In this case we wish to allow the thread to exit, but after a time, so we
         // aren't spinning up and tearing down the thread repeatedly.
         // Set a timeout of 16 msec; if nothing becomes readable by then we will exit.
         // We refer to this as the wait-lap.
-        bool is_wait_lap = (items_.size() == 1);
+        bool is_wait_lap = items_.empty();
         if (is_wait_lap) {
             assert(timeout_usec == fd_monitor_item_t::kNoTimeout &&
                    "Should not have a timeout on wait-lap");
             timeout_usec = 16 * kUsecPerMsec;
         }
 
-        // Call select().
-        int ret = fds.select(timeout_usec);
-        if (ret < 0 && errno != EINTR) {
-            // Surprising error.
-            wperror(L"select");
-        }
+        ready.clear();
+        fds->wait(timeout_usec, &ready);
+        auto is_ready = [&ready](fd_monitor_item_id_t id) {
+            return std::binary_search(ready.begin(), ready.end(), id);
+        };
 
         // A predicate which services each item in turn, returning true if it should be removed.
-        auto servicer = [&fds, &now](fd_monitor_item_t &item) {
+        // Readable items which we keep are waited on again.
+        auto servicer = [&](fd_monitor_item_t &item) {
             int fd = item.fd.fd();
-            bool remove = !item.service_item(fds, now);
-            if (remove) FLOG(fd_monitor, "Removing fd", fd);
+            bool readable = is_ready(item.item_id);
+            bool remove = !item.service_item(readable, now);
+            if (remove) {
+                FLOG(fd_monitor, "Removing fd", fd);
+                fds->remove(item.item_id);
+            } else if (readable) {
+                fds->add(item.fd.fd(), item.item_id);
+            }
             return remove;
         };
 
@@ -170,14 +365,18 @@ void fd_monitor_t::run_in_background() {
 
         // Handle any changes if the change signaller was set. Alternatively this may be the wait
         // lap, in which case we might want to commit to exiting.
-        if (fds.test(change_signal_fd) || is_wait_lap) {
+        bool change_signalled = is_ready(change_signal_id);
+        if (change_signalled || is_wait_lap) {
             // Clear the change signaller before processing incoming changes.
             change_signaller_.try_consume();
+            if (change_signalled) fds->add(change_signal_fd, change_signal_id);
             auto data = data_.acquire();
 
-            // Move from 'pending' to 'items'.
-            items_.insert(items_.end(), std::make_move_iterator(data->pending.begin()),
-                          std::make_move_iterator(data->pending.end()));
+            // Move from 'pending' to 'items', and start waiting on them.
+            for (fd_monitor_item_t &item : data->pending) {
+                fds->add(item.fd.fd(), item.item_id);
+                items_.push_back(std::move(item));
+            }
             data->pending.clear();
 
             // Grab any pokelist.
@@ -199,12 +398,15 @@ void fd_monitor_t::run_in_background() {
     }
 }
 
-void fd_monitor_t::poke_in_background(const poke_list_t &pokelist) {
+void fd_monitor_t::poke_in_background(const poke_list_t &pokelist, fd_readable_set_t &fds) {
     ASSERT_IS_BACKGROUND_THREAD();
-    auto poker = [&pokelist](fd_monitor_item_t &item) {
+    auto poker = [&pokelist, &fds](fd_monitor_item_t &item) {
         int fd = item.fd.fd();
         bool remove = !item.poke_item(pokelist);
-        if (remove) FLOG(fd_monitor, "Removing fd", fd);
+        if (remove) {
+            FLOG(fd_monitor, "Removing fd", fd);
+            fds.remove(item.item_id);
+        }
         return remove;
     };
     items_.erase(std::remove_if(items_.begin(), items_.end(), poker), items_.end());
@@ -4,14 +4,15 @@
 #include <chrono>
 #include <cstdint>
 #include <functional>
-// Needed for musl
-#include <sys/select.h>  // IWYU pragma: keep
+#include <limits>
+#include <vector>
 
 #include "common.h"
 #include "fds.h"
 #include "maybe.h"
 
 class fd_monitor_t;
+class fd_readable_set_t;
 
 /// Each item added to fd_monitor_t is assigned a unique ID, which is not recycled.
 /// Items may have their callback triggered immediately by passing the ID.
@@ -33,7 +34,7 @@ struct fd_monitor_item_t {
     using callback_t = std::function<void(autoclose_fd_t &fd, item_wake_reason_t reason)>;
 
     /// A sentinel value meaning no timeout.
-    static constexpr uint64_t kNoTimeout = select_wrapper_t::kNoTimeout;
+    static constexpr uint64_t kNoTimeout = std::numeric_limits<uint64_t>::max();
 
     /// The fd to monitor.
     autoclose_fd_t fd{};
@@ -69,9 +70,9 @@ struct fd_monitor_item_t {
     // A 0 return means we are at or past the timeout.
     uint64_t usec_remaining(const time_point_t &now) const;
 
-    // Invoke this item's callback if its value is set in fd or has timed out.
+    // Invoke this item's callback if it is \p readable or has timed out.
     // \return true to retain the item, false to remove it.
-    bool service_item(const select_wrapper_t &fds, const time_point_t &now);
+    bool service_item(bool readable, const time_point_t &now);
 
     // Invoke this item's callback with a poke, if its ID is present in the (sorted) pokelist.
     // \return true to retain the item, false to remove it.
@@ -107,10 +108,10 @@ class fd_monitor_t {
     // Called in the background thread.
     void handle_self_signal_in_background();
 
-    // Poke items in the pokelist, removing any items that close their FD.
-    // The pokelist is consumed after this.
+    // Poke items in the pokelist, removing any items that close their FD, and no longer waiting on
+    // them in \p fds. The pokelist is consumed after this.
     // This is only called in the background thread.
-    void poke_in_background(const poke_list_t &pokelist);
+    void poke_in_background(const poke_list_t &pokelist, fd_readable_set_t &fds);
 
     // The list of items to monitor. This is only accessed on the background thread.
     item_list_t items_{};
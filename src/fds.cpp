@@ -4,10 +4,12 @@
 
 #include "fds.h"
 
+#include <dirent.h>
 #include <errno.h>
 #include <fcntl.h>
 #include <unistd.h>
 
+#include <algorithm>
 #include <cstring>
 
 #include "common.h"
@@ -79,7 +81,9 @@ fd_event_signaller_t::fd_event_signaller_t() {
         wperror(L"eventfd");
         exit_without_destructors(1);
     }
-    fd_.reset(fd);
+    // Keep it out of the range of user redirections.
+    fd_ = heightenize_fd(autoclose_fd_t{fd}, true);
+    if (!fd_.valid()) exit_without_destructors(1);
 };
 
 int fd_event_signaller_t::write_fd() const { return fd_.fd(); }
@@ -148,31 +152,27 @@ bool fd_event_signaller_t::poll(bool wait) const {
 
 fd_event_signaller_t::~fd_event_signaller_t() = default;
 
-/// If the given fd is in the "user range", move it to a new fd in the "high range".
-/// zsh calls this movefd().
-/// \p input_has_cloexec describes whether the input has CLOEXEC already set, so we can avoid
-/// setting it again.
-/// \return the fd, which always has CLOEXEC set; or an invalid fd on failure, in
-/// which case an error will have been printed, and the input fd closed.
-static autoclose_fd_t heightenize_fd(autoclose_fd_t fd, bool input_has_cloexec) {
+// zsh calls this movefd().
+autoclose_fd_t heightenize_fd(autoclose_fd_t fd, bool input_has_cloexec, int first_fd) {
+    assert(first_fd >= k_first_high_fd && "Cannot heightenize into the user range");
     // Check if the fd is invalid or already in our high range.
     if (!fd.valid()) {
         return fd;
     }
-    if (fd.fd() >= k_first_high_fd) {
+    if (fd.fd() >= first_fd) {
         if (!input_has_cloexec) set_cloexec(fd.fd());
         return fd;
     }
 #if defined(F_DUPFD_CLOEXEC)
     // Here we are asking the kernel to give us a
-    int newfd = fcntl(fd.fd(), F_DUPFD_CLOEXEC, k_first_high_fd);
+    int newfd = fcntl(fd.fd(), F_DUPFD_CLOEXEC, first_fd);
     if (newfd < 0) {
         wperror(L"fcntl");
         return autoclose_fd_t{};
     }
     return autoclose_fd_t(newfd);
 #elif defined(F_DUPFD)
-    int newfd = fcntl(fd.fd(), F_DUPFD, k_first_high_fd);
+    int newfd = fcntl(fd.fd(), F_DUPFD, first_fd);
     if (newfd < 0) {
         wperror(L"fcntl");
         return autoclose_fd_t{};
@@ -187,11 +187,11 @@ static autoclose_fd_t heightenize_fd(autoclose_fd_t fd, bool input_has_cloexec)
         tmp_fd = dup(fd.fd());
     } while (tmp_fd < 0 && errno == EINTR);
     // Ok, we have a new candidate fd. Recurse.
-    return heightenize_fd(autoclose_fd_t{tmp_fd}, false);
+    return heightenize_fd(autoclose_fd_t{tmp_fd}, false, first_fd);
 #endif
 }
 
-maybe_t<autoclose_pipes_t> make_autoclose_pipes() {
+maybe_t<autoclose_pipes_t> make_autoclose_pipes(int first_fd) {
     int pipes[2] = {-1, -1};
 
     bool already_cloexec = false;
@@ -214,10 +214,10 @@ maybe_t<autoclose_pipes_t> make_autoclose_pipes() {
     autoclose_fd_t write_end{pipes[1]};
 
     // Ensure our fds are out of the user range.
-    read_end = heightenize_fd(std::move(read_end), already_cloexec);
+    read_end = heightenize_fd(std::move(read_end), already_cloexec, first_fd);
     if (!read_end.valid()) return none();
 
-    write_end = heightenize_fd(std::move(write_end), already_cloexec);
+    write_end = heightenize_fd(std::move(write_end), already_cloexec, first_fd);
     if (!write_end.valid()) return none();
 
     return autoclose_pipes_t(std::move(read_end), std::move(write_end));
@@ -239,10 +239,18 @@ int set_cloexec(int fd, bool should_set) {
     if (flags == new_flags) {
         return 0;
     } else {
-        return fcntl(fd, F_SETFD, new_flags);
+        return fcntl(fd, F_SETFD, new_flags) < 0 ? -1 : 0;
     }
 }
 
+maybe_t<bool> get_cloexec(int fd) {
+    int flags = fcntl(fd, F_GETFD, 0);
+    if (flags < 0) {
+        return none();
+    }
+    return bool(flags & FD_CLOEXEC);
+}
+
 int open_cloexec(const std::string &path, int flags, mode_t mode) {
     return open_cloexec(path.c_str(), flags, mode);
 }
@@ -276,3 +284,39 @@ void exec_close(int fd) {
         }
     }
 }
+
+#ifdef FISH_CHECK_FD_LEAKS
+/// \return the fds this process has open, in ascending order.
+static std::vector<int> get_open_fds() {
+    std::vector<int> result;
+    // Prefer asking the kernel, as the highest possible fd may be very large.
+    if (DIR *dir = opendir("/proc/self/fd")) {
+        int dir_fd = dirfd(dir);
+        while (const struct dirent *ent = readdir(dir)) {
+            if (ent->d_name[0] == '.') continue;
+            int fd = atoi(ent->d_name);
+            if (fd != dir_fd) result.push_back(fd);
+        }
+        closedir(dir);
+        std::sort(result.begin(), result.end());
+        return result;
+    }
+    long max_fd = sysconf(_SC_OPEN_MAX);
+    if (max_fd < 0 || max_fd > 65536) max_fd = 65536;
+    for (int fd = 0; fd < max_fd; fd++) {
+        if (fcntl(fd, F_GETFD) >= 0) result.push_back(fd);
+    }
+    return result;
+}
+
+void flog_leaked_fds(const std::vector<int> &expected, const char *cmd) {
+    if (!should_flog(fd_leaks)) return;
+    for (int fd : get_open_fds()) {
+        if (fd <= STDERR_FILENO) continue;
+        if (std::find(expected.begin(), expected.end(), fd) != expected.end()) continue;
+        if (get_cloexec(fd) == maybe_t<bool>{false}) {
+            FLOGF(fd_leaks, "fd %d is not close-on-exec and will leak into '%s'", fd, cmd);
+        }
+    }
+}
+#endif
@@ -20,8 +20,8 @@ using wcstring = std::wstring;
 /// Pipe redirection error message.
 #define PIPE_ERROR _(L"An error occurred while setting up pipe")
 
-/// The first "high fd", which is considered outside the range of valid user-specified redirections
-/// (like >&5).
+/// The first "high fd", which is the lowest fd used for fish's internal pipes and files. These are
+/// moved further up if a job names a higher fd in its redirections (like 12>&1).
 extern const int k_first_high_fd;
 
 /// A helper class for managing and automatically closing a file descriptor.
@@ -116,9 +116,18 @@ struct autoclose_pipes_t {
 };
 
 /// Call pipe(), populating autoclose fds.
-/// The pipes are marked CLO_EXEC and are placed in the high fd range.
+/// The pipes are marked CLO_EXEC and are placed at or above \p first_fd, which must be at least
+/// k_first_high_fd.
 /// \return pipes on success, none() on error.
-maybe_t<autoclose_pipes_t> make_autoclose_pipes();
+maybe_t<autoclose_pipes_t> make_autoclose_pipes(int first_fd = k_first_high_fd);
+
+/// If the given fd is below \p first_fd, move it to a new fd at or above it, closing the original.
+/// \p input_has_cloexec describes whether the input has CLOEXEC already set, so we can avoid
+/// setting it again.
+/// \return the fd, which always has CLOEXEC set; or an invalid fd on failure, in which case an
+/// error will have been printed, and the input fd closed.
+autoclose_fd_t heightenize_fd(autoclose_fd_t fd, bool input_has_cloexec,
+                              int first_fd = k_first_high_fd);
 
 /// An event signaller implemented using a file descriptor, so it can plug into select().
 /// This is like a binary semaphore. A call to post() will signal an event, making the fd readable.
@@ -164,8 +173,14 @@ class fd_event_signaller_t {
 };
 
 /// Sets CLO_EXEC on a given fd according to the value of \p should_set.
+/// \return 0 on success, or -1 with errno set; EBADF means \p fd is not open.
+/// This is safe to call after fork.
 int set_cloexec(int fd, bool should_set = true);
 
+/// \return whether CLO_EXEC is set on \p fd, or none() with errno set if that can't be determined.
+/// EBADF means \p fd is not open, which callers generally expect; anything else is a real error.
+maybe_t<bool> get_cloexec(int fd);
+
 /// Wide character version of open() that also sets the close-on-exec flag (atomically when
 /// possible).
 int wopen_cloexec(const wcstring &pathname, int flags, mode_t mode = 0);
@@ -183,4 +198,12 @@ int make_fd_blocking(int fd);
 /// Close a file descriptor \p fd, retrying on EINTR.
 void exec_close(int fd);
 
+#ifdef FISH_CHECK_FD_LEAKS
+/// Log every fd above stderr which is not marked CLO_EXEC, and so would be inherited by the child
+/// process running \p cmd, unless it is one of the fds in \p expected.
+/// This is a debugging aid which does nothing unless the fd-leaks flog category is enabled. It is
+/// only built with FISH_CHECK_FD_LEAKS, which debug builds set.
+void flog_leaked_fds(const std::vector<int> &expected, const char *cmd);
+#endif
+
 #endif
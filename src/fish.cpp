@@ -501,6 +501,12 @@ int main(int argc, char **argv) {
 
     if (!opts.no_exec && !opts.no_config) {
         read_init(parser, paths);
+    } else if (!opts.no_exec && opts.is_interactive_session) {
+        // Without any config, still give interactive sessions the preset key bindings. The
+        // variable is set first so the function doesn't wait for a handler to call it again.
+        parser.eval(L"set -g fish_key_bindings fish_default_key_bindings\n"
+                    L"fish_default_key_bindings",
+                    io_chain_t{});
     }
     // Re-read the terminal modes after config, it might have changed them.
     term_copy_modes();
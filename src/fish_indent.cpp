@@ -25,6 +25,7 @@ Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301, USA
 #include <stdlib.h>
 #include <wctype.h>
 
+#include <algorithm>
 #include <cstring>
 #include <cwchar>
 #include <memory>
@@ -107,6 +108,7 @@ struct pretty_printer_t {
           ast(ast_t::parse(src, parse_flags())),
           do_indent(do_indent),
           gaps(compute_gaps()),
+          continuation_locations(compute_continuation_locations()),
           preferred_semi_locations(compute_preferred_semi_locations()) {
         assert(indents.size() == source.size() && "indents and source should be same length");
     }
@@ -138,6 +140,10 @@ struct pretty_printer_t {
     // present in the ast.
     const std::vector<source_range_t> gaps;
 
+    // The sorted set of source offsets of tokens which follow an escaped newline, like 'b' in
+    // "echo a \\\n b".
+    const std::vector<uint32_t> continuation_locations;
+
     // The sorted set of source offsets of nl_semi_t which should be set as semis, not newlines.
     // This is computed ahead of time for convenience.
     const std::vector<uint32_t> preferred_semi_locations;
@@ -291,6 +297,17 @@ struct pretty_printer_t {
         return gaps;
     }
 
+    // Return the sorted offsets of tokens that the tokenizer marks as continuations.
+    std::vector<uint32_t> compute_continuation_locations() const {
+        std::vector<uint32_t> result;
+        tokenizer_t tokenizer(source.c_str(),
+                              TOK_SHOW_COMMENTS | TOK_ACCEPT_UNFINISHED | TOK_CONTINUE_AFTER_ERROR);
+        while (maybe_t<tok_t> tok = tokenizer.next()) {
+            if (tok->is_continuation) result.push_back(static_cast<uint32_t>(tok->offset));
+        }
+        return result;
+    }
+
     // Return sorted list of semi-preferring semi_nl nodes.
     std::vector<uint32_t> compute_preferred_semi_locations() const {
         std::vector<uint32_t> result;
@@ -390,15 +407,20 @@ struct pretty_printer_t {
         // Common case: if we are only spaces, do nothing.
         if (gap_text.find_first_not_of(L' ') == wcstring::npos) return false;
 
-        // Look to see if there is an escaped newline.
+        // Look to see if there is an escaped newline, which makes the token after it a
+        // continuation. That token is either in the gap, or the one which ends it.
         // Emit it if either we allow it, or it comes before the first comment.
         // Note we do not have to be concerned with escaped backslashes or escaped #s. This is gap
         // text - we already know it has no semantic significance.
-        size_t escaped_nl = gap_text.find(L"\\\n");
-        if (escaped_nl != wcstring::npos) {
+        auto continuation = std::lower_bound(continuation_locations.begin(),
+                                             continuation_locations.end(), range.start);
+        if (continuation != continuation_locations.end() &&
+            *continuation <= range.start + range.length) {
+            size_t escaped_nl = gap_text.find(L"\\\n");
             size_t comment_idx = gap_text.find(L'#');
-            if ((flags & allow_escaped_newlines) ||
-                (comment_idx != wcstring::npos && escaped_nl < comment_idx)) {
+            if (escaped_nl != wcstring::npos &&
+                ((flags & allow_escaped_newlines) ||
+                 (comment_idx != wcstring::npos && *continuation <= range.start + comment_idx))) {
                 // Emit a space before the escaped newline.
                 if (!at_line_start() && !has_preceding_space()) {
                     output.append(L" ");
@@ -840,6 +862,80 @@ static std::string html_colorize(const wcstring &text,
 
 static std::string no_colorize(const wcstring &text) { return wcs2string(text); }
 
+/// Return a line-based diff turning \p before into \p after, in the style of "diff -u" but
+/// without context lines. \p name is used for the header.
+static wcstring make_diff(const wcstring &name, const wcstring &before, const wcstring &after) {
+    wcstring_list_t old_lines = split_string(before, L'\n');
+    wcstring_list_t new_lines = split_string(after, L'\n');
+    // A trailing newline produces an empty last element, which is not a line.
+    if (!old_lines.empty() && old_lines.back().empty()) old_lines.pop_back();
+    if (!new_lines.empty() && new_lines.back().empty()) new_lines.pop_back();
+
+    // Lines shared at the start and end are never part of a hunk.
+    size_t prefix = 0;
+    while (prefix < old_lines.size() && prefix < new_lines.size() &&
+           old_lines.at(prefix) == new_lines.at(prefix)) {
+        prefix++;
+    }
+    size_t suffix = 0;
+    while (suffix < old_lines.size() - prefix && suffix < new_lines.size() - prefix &&
+           old_lines.at(old_lines.size() - 1 - suffix) ==
+               new_lines.at(new_lines.size() - 1 - suffix)) {
+        suffix++;
+    }
+    const size_t n = old_lines.size() - prefix - suffix;
+    const size_t m = new_lines.size() - prefix - suffix;
+
+    // lcs[i][j] is the length of the longest common subsequence of the remaining lines starting at
+    // old line i and new line j (relative to the prefix). Skip this for huge changes, and just
+    // report everything as replaced.
+    std::vector<std::vector<size_t>> lcs;
+    const bool do_lcs = n * m <= 1024 * 1024;
+    if (do_lcs) {
+        lcs.assign(n + 1, std::vector<size_t>(m + 1, 0));
+        for (size_t i = n; i-- > 0;) {
+            for (size_t j = m; j-- > 0;) {
+                if (old_lines.at(prefix + i) == new_lines.at(prefix + j)) {
+                    lcs[i][j] = lcs[i + 1][j + 1] + 1;
+                } else {
+                    lcs[i][j] = std::max(lcs[i + 1][j], lcs[i][j + 1]);
+                }
+            }
+        }
+    }
+
+    wcstring result = format_string(L"--- %ls\n+++ %ls (formatted)\n", name.c_str(), name.c_str());
+    size_t i = 0, j = 0;
+    while (i < n || j < m) {
+        if (i < n && j < m && old_lines.at(prefix + i) == new_lines.at(prefix + j)) {
+            i++;
+            j++;
+            continue;
+        }
+        // Collect one hunk of consecutive changes.
+        size_t old_start = i, new_start = j;
+        wcstring removed, added;
+        while (i < n || j < m) {
+            if (i < n && j < m && old_lines.at(prefix + i) == new_lines.at(prefix + j)) break;
+            if (j >= m || (i < n && (!do_lcs || lcs[i + 1][j] >= lcs[i][j + 1]))) {
+                removed.append(L"-" + old_lines.at(prefix + i) + L"\n");
+                i++;
+            } else {
+                added.append(L"+" + new_lines.at(prefix + j) + L"\n");
+                j++;
+            }
+        }
+        append_format(result, L"@@ -%lu,%lu +%lu,%lu @@\n",
+                      static_cast<unsigned long>(prefix + old_start + 1),
+                      static_cast<unsigned long>(i - old_start),
+                      static_cast<unsigned long>(prefix + new_start + 1),
+                      static_cast<unsigned long>(j - new_start));
+        result.append(removed);
+        result.append(added);
+    }
+    return result;
+}
+
 int main(int argc, char *argv[]) {
     program_name = L"fish_indent";
     set_main_thread();
@@ -1046,6 +1142,8 @@ int main(int argc, char *argv[]) {
                     if (argc) {
                         std::fwprintf(stderr, _(L"%s\n"), argv[i]);
                     }
+                    wcstring name = argc ? str2wcstring(argv[i]) : wcstring(L"-");
+                    colored_output = wcs2string(make_diff(name, src, output_wtext));
                     retval++;
                 }
                 break;
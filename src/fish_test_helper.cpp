@@ -40,6 +40,16 @@ static void nohup_wait() {
     }
 }
 
+static void return_terminal_then_sleep() {
+    // Give the terminal to our parent (presumably fish) without stopping, then keep running.
+    signal(SIGTTOU, SIG_IGN);
+    if (tcsetpgrp(STDIN_FILENO, getpgid(getppid())) < 0) {
+        perror("tcsetpgrp");
+        exit(EXIT_FAILURE);
+    }
+    usleep(1000000 * 3);  // 3 secs
+}
+
 static void report_foreground_loop() {
     int was_fg = -1;
     const auto grp = getpgrp();
@@ -175,6 +185,8 @@ static fth_command_t s_commands[] = {
      "Claim the terminal (tcsetpgrp) and then print to stderr"},
     {"nohup_wait", nohup_wait, "Ignore SIGHUP and just wait"},
     {"report_foreground", report_foreground, "Report to stderr whether we own the terminal"},
+    {"return_terminal_then_sleep", return_terminal_then_sleep,
+     "Give the terminal to the parent process without stopping, then sleep for 3 seconds"},
     {"report_foreground_loop", report_foreground_loop,
      "Continually report to stderr whether we own the terminal"},
     {"sigint_parent", sigint_parent, "Wait .25 seconds, then SIGINT the parent process"},
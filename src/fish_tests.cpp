@@ -60,6 +60,8 @@
 #include "input_common.h"
 #include "io.h"
 #include "iothread.h"
+#include "job_group.h"
+#include "job_journal.h"
 #include "kill.h"
 #include "lru.h"
 #include "maybe.h"
@@ -587,6 +589,72 @@ static void test_convert_private_use() {
     }
 }
 
+/// wcs2utf8 and utf8towcs convert regardless of the locale, and must round trip any bytes.
+static void test_convert_utf8() {
+    say(L"Testing locale-independent UTF-8 conversion");
+    const struct {
+        const char *utf8;
+        const wchar_t *wide;
+    } tests[] = {
+        {"", L""},
+        {"abc", L"abc"},
+        {"\xc3\xbc", L"\u00fc"},
+        {"\xe6\x97\xa5\xe6\x9c\xac", L"\u65e5\u672c"},
+        {"\xe2\x82\xac", L"\u20ac"},
+    };
+    for (const auto &test : tests) {
+        if (utf8towcs(test.utf8) != test.wide) {
+            err(L"utf8towcs of '%s' gave the wrong result", test.utf8);
+        }
+        if (wcs2utf8(test.wide) != test.utf8) {
+            err(L"wcs2utf8 of '%ls' gave the wrong result", test.wide);
+        }
+    }
+    if (sizeof(wchar_t) == 4) {
+        do_test(utf8towcs(std::string("\xf0\x9f\x90\x9f")) == wcstring(1, 0x1F41F));
+        do_test(wcs2utf8(wcstring(1, 0x1F41F)) == "\xf0\x9f\x90\x9f");
+    }
+
+    // Invalid bytes are stored in the private use area, one char per byte.
+    const char *invalid[] = {
+        "\xff",              // never valid
+        "\x80\x61",          // stray continuation byte
+        "\xc3",              // truncated sequence
+        "\xe6\x97",          // truncated sequence
+        "\xc0\xaf",          // overlong encoding of '/'
+        "\xed\xa0\x80",      // surrogate
+        "\xf4\x90\x80\x80",  // beyond U+10FFFF
+        "\xef\x98\x80",      // ENCODE_DIRECT_BASE itself
+    };
+    for (const char *s : invalid) {
+        std::string narrow = s;
+        wcstring wide = utf8towcs(narrow);
+        unsigned char first = narrow.front();
+        if (wide.empty() || wide.front() != ENCODE_DIRECT_BASE + first) {
+            err(L"utf8towcs did not encode the first byte of '%s' directly", s);
+        }
+        if (wcs2utf8(wide) != narrow) {
+            err(L"'%s' did not round trip through utf8towcs", s);
+        }
+    }
+
+    // Every byte value round trips, whatever comes before or after it.
+    for (int i = 0; i < 256; i++) {
+        std::string s = "a\xc3\xbc";
+        s.push_back(static_cast<char>(i));
+        s.append("\xe2\x82\xacz");
+        do_test(wcs2utf8(utf8towcs(s)) == s);
+    }
+
+    // Unpaired surrogates cannot be encoded, and are dropped.
+    if (sizeof(wchar_t) == 4) {
+        wcstring with_surrogate = L"a";
+        with_surrogate.push_back(static_cast<wchar_t>(0xD800));
+        with_surrogate.push_back(L'b');
+        do_test(wcs2utf8(with_surrogate) == "ab");
+    }
+}
+
 static void perf_convert_ascii() {
     std::string s(128 * 1024, '\0');
     for (size_t i = 0; i < s.size(); i++) {
@@ -697,6 +765,27 @@ static void test_tokenizer() {
         }
     }
 
+    // Test that tokens after escaped newlines are marked as continuations.
+    {
+        tokenizer_t t(L"echo a \\\n  b c \\\n# comment\n d\ne", TOK_SHOW_COMMENTS);
+        const wchar_t *texts[] = {L"echo", L"a", L"b", L"c", L"# comment", L"d", L"\n", L"e"};
+        const bool continuations[] = {false, false, true, false, true, true, false, false};
+        size_t i = 0;
+        while (auto token = t.next()) {
+            if (i >= sizeof texts / sizeof *texts) {
+                err(L"Too many tokens returned from tokenizer");
+                break;
+            }
+            do_test(t.text_of(*token) == texts[i]);
+            if (token->is_continuation != continuations[i]) {
+                err(L"Token '%ls' should%ls be a continuation", texts[i],
+                    continuations[i] ? L"" : L" not");
+            }
+            i++;
+        }
+        do_test(i == sizeof texts / sizeof *texts);
+    }
+
     // Test some errors.
     {
         tokenizer_t t(L"abc\\", 0);
@@ -730,6 +819,44 @@ static void test_tokenizer() {
         do_test(token->error_offset_within_token == 4);
     }
 
+    {
+        tokenizer_t t(L"abc d$(efg", 0);
+        auto token = t.next();
+        do_test(token.has_value());
+        token = t.next();
+        do_test(token.has_value());
+        do_test(token->type == token_type_t::error);
+        do_test(token->error == tokenizer_error_t::unterminated_dollar_subshell);
+        do_test(token->error_offset_within_token == 2);
+    }
+
+    {
+        // A command substitution inside double quotes, which itself contains quotes.
+        tokenizer_t t(L"abc \"d $(echo \"e f\") g\" hij", 0);
+        const wchar_t *texts[] = {L"abc", L"\"d $(echo \"e f\") g\"", L"hij"};
+        size_t i = 0;
+        while (auto token = t.next()) {
+            if (i >= sizeof texts / sizeof *texts) {
+                err(L"Too many tokens returned from tokenizer");
+                break;
+            }
+            do_test(token->type == token_type_t::string);
+            do_test(t.text_of(*token) == texts[i]);
+            i++;
+        }
+        do_test(i == sizeof texts / sizeof *texts);
+    }
+
+    {
+        tokenizer_t t(L"abc \"d$(efg)", 0);
+        auto token = t.next();
+        do_test(token.has_value());
+        token = t.next();
+        do_test(token.has_value());
+        do_test(token->type == token_type_t::error);
+        do_test(token->error == tokenizer_error_t::unterminated_quote);
+    }
+
     {
         tokenizer_t t(L"abc defg[hij (klm)", 0);
         auto token = t.next();
@@ -741,6 +868,27 @@ static void test_tokenizer() {
         do_test(token->error_offset_within_token == 4);
     }
 
+    {
+        tokenizer_t t(L"echo abc >>? file", 0);
+        auto token = t.next();
+        token = t.next();
+        token = t.next();
+        do_test(token.has_value());
+        do_test(token->type == token_type_t::error);
+        do_test(token->error == tokenizer_error_t::append_noclobber);
+        do_test(token->offset == 9);
+    }
+
+    {
+        tokenizer_t t(L"echo abc 2>>?file", 0);
+        auto token = t.next();
+        token = t.next();
+        token = t.next();
+        do_test(token.has_value());
+        do_test(token->type == token_type_t::error);
+        do_test(token->error == tokenizer_error_t::append_noclobber);
+    }
+
     // Test some redirection parsing.
     auto pipe_or_redir = [](const wchar_t *s) { return pipe_or_redir_t::from_string(s); };
     do_test(pipe_or_redir(L"|")->is_pipe);
@@ -764,6 +912,10 @@ static void test_tokenizer() {
     do_test(pipe_or_redir(L"&>")->stderr_merge);
     do_test(pipe_or_redir(L"&>>")->stderr_merge);
     do_test(pipe_or_redir(L"&>?")->stderr_merge);
+    do_test(!pipe_or_redir(L">?")->append_noclobber);
+    do_test(pipe_or_redir(L">>?")->append_noclobber);
+    do_test(pipe_or_redir(L"2>>?")->append_noclobber);
+    do_test(pipe_or_redir(L"&>>?")->append_noclobber);
 
     auto get_redir_mode = [](const wchar_t *s) -> maybe_t<redirection_mode_t> {
         if (auto redir = pipe_or_redir_t::from_string(s)) {
@@ -817,6 +969,61 @@ static int test_iothread_thread_call(std::atomic<int> *addr) {
     return after;
 }
 
+static void test_proc_status() {
+    say(L"Testing process statuses");
+    do_test(proc_status_t::from_exit_code(3).rusage() == nullptr);
+#ifdef HAVE_WAIT4
+    pid_t pid = fork();
+    if (pid == 0) _exit(3);
+    int statusv = -1;
+    struct rusage usage;
+    do_test(wait4(pid, &statusv, 0, &usage) == pid);
+    auto status = proc_status_t::from_rusage_exit(statusv, usage);
+    do_test(status.normal_exited() && status.exit_code() == 3);
+    do_test(status.rusage() != nullptr);
+    do_test(status.rusage()->ru_utime.tv_sec == usage.ru_utime.tv_sec);
+    do_test(proc_status_t::from_waitpid(statusv).rusage() == nullptr);
+#endif
+}
+
+static void test_terminal_reclaim() {
+    say(L"Testing reclaiming the terminal with a timeout");
+    const uint64_t timeout_usec = 10 * 1000;
+    auto succeed = [] { return 0; };
+    do_test(terminal_try_reclaim_with_timeout(getpgrp(), timeout_usec, succeed));
+    auto fail = [] {
+        errno = ENOTTY;
+        return -1;
+    };
+    do_test(!terminal_try_reclaim_with_timeout(getpgrp(), timeout_usec, fail));
+    do_test(errno == ENOTTY);
+
+    // An interrupted call is retried on a thread.
+    auto calls = std::make_shared<std::atomic<int>>(0);
+    auto interrupted_once = [calls] {
+        if ((*calls)++ > 0) return 0;
+        errno = EINTR;
+        return -1;
+    };
+    do_test(terminal_try_reclaim_with_timeout(getpgrp(), timeout_usec, interrupted_once));
+    do_test(*calls == 2);
+
+    // If the retry keeps blocking, we give up after twice the timeout.
+    *calls = 0;
+    auto blocks = [calls, timeout_usec] {
+        if ((*calls)++ == 0) {
+            errno = EINTR;
+            return -1;
+        }
+        usleep(timeout_usec * 10);
+        return 0;
+    };
+    double start = timef();
+    do_test(!terminal_try_reclaim_with_timeout(getpgrp(), timeout_usec, blocks));
+    do_test(errno == ETIMEDOUT);
+    do_test(timef() - start < timeout_usec * 10 / 1E6);
+}
+
 static void test_fd_monitor() {
     say(L"Testing fd_monitor");
 
@@ -951,6 +1158,84 @@ static void test_fd_monitor() {
     do_test(item_pokee.pokes == 1);
 }
 
+static void test_fd_monitor_many() {
+    say(L"Testing fd_monitor with many fds");
+
+    // Many pipes, each written from a pool of threads and read by the monitor. Every byte written
+    // to a pipe is its index, so we can tell that each byte was seen exactly once and by the right
+    // item.
+    constexpr size_t pipe_count = 300;
+    constexpr size_t writes_per_pipe = 16;
+    constexpr size_t thread_count = 8;
+
+    struct reader_t {
+        std::atomic<size_t> length_read{0};
+        std::atomic<size_t> wrong_bytes{0};
+        std::atomic<size_t> closes{0};
+        autoclose_fd_t writer;
+    };
+    std::vector<std::unique_ptr<reader_t>> readers;
+    std::atomic<size_t> closed_count{0};
+    {
+        fd_monitor_t monitor;
+        for (size_t i = 0; i < pipe_count; i++) {
+            readers.push_back(make_unique<reader_t>());
+            reader_t *reader = readers.back().get();
+            auto pipes = make_autoclose_pipes().acquire();
+            if (!pipes.read.valid()) {
+                err(L"Failed to create pipe %lu", static_cast<unsigned long>(i));
+                return;
+            }
+            reader->writer = std::move(pipes.write);
+            auto callback = [=, &closed_count](autoclose_fd_t &fd, item_wake_reason_t reason) {
+                if (reason != item_wake_reason_t::readable) return;
+                unsigned char buff[512];
+                ssize_t amt = read(fd.fd(), buff, sizeof buff);
+                if (amt <= 0) {
+                    fd.close();
+                    reader->closes += 1;
+                    closed_count += 1;
+                    return;
+                }
+                for (ssize_t j = 0; j < amt; j++) {
+                    if (buff[j] != static_cast<unsigned char>(i)) reader->wrong_bytes += 1;
+                }
+                reader->length_read += amt;
+            };
+            monitor.add(fd_monitor_item_t(std::move(pipes.read), std::move(callback)));
+        }
+
+        // Write chunks of increasing size, then close the write end.
+        std::vector<std::thread> writers;
+        for (size_t t = 0; t < thread_count; t++) {
+            writers.emplace_back([&, t] {
+                for (size_t i = t; i < pipe_count; i += thread_count) {
+                    std::vector<unsigned char> chunk;
+                    for (size_t w = 1; w <= writes_per_pipe; w++) {
+                        chunk.assign(w * 7, static_cast<unsigned char>(i));
+                        const char *data = reinterpret_cast<const char *>(chunk.data());
+                        (void)write_loop(readers[i]->writer.fd(), data, chunk.size());
+                    }
+                    readers[i]->writer.close();
+                }
+            });
+        }
+        for (std::thread &writer : writers) writer.join();
+
+        for (int i = 0; i < 500 && closed_count < pipe_count; i++) {
+            std::this_thread::sleep_for(std::chrono::milliseconds(10));
+        }
+    }
+
+    const size_t expected_length = 7 * writes_per_pipe * (writes_per_pipe + 1) / 2;
+    do_test(closed_count == pipe_count);
+    for (const auto &reader : readers) {
+        do_test(reader->length_read == expected_length);
+        do_test(reader->wrong_bytes == 0);
+        do_test(reader->closes == 1);
+    }
+}
+
 static void test_iothread() {
     say(L"Testing iothreads");
     std::unique_ptr<std::atomic<int>> int_ptr = make_unique<std::atomic<int>>(0);
@@ -1189,6 +1474,14 @@ static void test_parser() {
         err(L"unterminated multiline subshell not reported properly");
     }
 
+    if (detect_errors(L"echo $(foo) a$(bar)b \"c$(echo \"$(baz)\")d\"")) {
+        err(L"$(...) command substitution wrongly reported as error");
+    }
+
+    if (detect_errors(L"echo \"foo$(bar") != PARSER_TEST_INCOMPLETE) {
+        err(L"unterminated quoted $(...) not reported as incomplete");
+    }
+
     if (detect_errors(L"begin ; true ; end | ") != PARSER_TEST_INCOMPLETE) {
         err(L"unterminated pipe not reported properly");
     }
@@ -2146,6 +2439,34 @@ static void test_expand() {
 
     expand_test(L"foo", noflags, L"foo", 0, L"Strings do not expand to themselves");
     expand_test(L"a{b,c,d}e", noflags, L"abe", L"ace", L"ade", 0, L"Bracket expansion is broken");
+    expand_test(L"a{1..3}", noflags, L"a1", L"a2", L"a3", 0, L"Brace ranges are broken");
+    expand_test(L"{3..1}", noflags, L"3", L"2", L"1", 0, L"Reversed brace ranges are broken");
+    expand_test(L"{-1..1}", noflags, L"-1", L"0", L"1", 0, L"Negative brace ranges are broken");
+    expand_test(L"{1..10..4}", noflags, L"1", L"5", L"9", 0, L"Brace range steps are broken");
+    expand_test(L"{10..1..-4}", noflags, L"10", L"6", L"2", 0,
+                L"Reversed brace range steps are broken");
+    expand_test(L"{08..11}", noflags, L"08", L"09", L"10", L"11", 0,
+                L"Zero-padded brace ranges are broken");
+    expand_test(L"{010..8}", noflags, L"010", L"009", L"008", 0,
+                L"Reversed zero-padded brace ranges are broken");
+    expand_test(L"{a..c}", noflags, L"a", L"b", L"c", 0, L"Character brace ranges are broken");
+    expand_test(L"{e..a..2}", noflags, L"e", L"c", L"a", 0,
+                L"Reversed character brace ranges are broken");
+    expand_test(L"{x,{1..2}}{a..b}", noflags, L"xa", L"xb", L"1a", L"1b", L"2a", L"2b", 0,
+                L"Nested brace ranges are broken");
+    expand_test(L"{1..a} {1...3} {1..3..0} {ab..c}", noflags, L"{1..a} {1...3} {1..3..0} {ab..c}",
+                0, L"Invalid brace ranges should be literal");
+    {
+        completion_list_t output;
+        parse_error_list_t errors;
+        pwd_environment_t pwd{};
+        operation_context_t ctx{parser_t::principal_parser().shared(), pwd, no_cancel};
+        if (expand_string(L"{1..999999999}", &output, noflags, ctx, &errors) !=
+                expand_result_t::error ||
+            errors.empty() || !output.empty()) {
+            err(L"Huge brace range was not rejected");
+        }
+    }
     expand_test(L"a*", expand_flag::skip_wildcards, L"a*", 0, L"Cannot skip wildcard expansion");
     expand_test(L"/bin/l\\0", expand_flag::for_completions, 0,
                 L"Failed to handle null escape in expansion");
@@ -2305,6 +2626,47 @@ static void test_expand_overflow() {
     parser->vars().pop();
 }
 
+static void test_home_directory_cache() {
+    say(L"Testing home directory cache");
+    // A fake passwd database with one user, which counts lookups, and a fake clock.
+    static size_t s_lookups;
+    static double s_now;
+    s_lookups = 0;
+    s_now = 1000;
+    auto lookup = [](const wcstring &user) -> maybe_t<wcstring> {
+        s_lookups++;
+        if (user == L"alice") return wcstring{L"/home/alice"};
+        return none();
+    };
+    home_directory_cache_t cache(lookup, [] { return s_now; });
+
+    do_test(cache.lookup(L"alice") == wcstring{L"/home/alice"});
+    do_test(cache.lookup(L"alice") == wcstring{L"/home/alice"});
+    do_test(s_lookups == 1);
+
+    // Missing users are cached too, but not as long.
+    do_test(!cache.lookup(L"bob"));
+    do_test(!cache.lookup(L"bob"));
+    do_test(s_lookups == 2);
+    s_now += home_directory_cache_t::kMissingTimeToLive;
+    do_test(!cache.lookup(L"bob"));
+    do_test(cache.lookup(L"alice") == wcstring{L"/home/alice"});
+    do_test(s_lookups == 3);
+
+    // Found users expire eventually.
+    s_now += home_directory_cache_t::kFoundTimeToLive;
+    do_test(cache.lookup(L"alice") == wcstring{L"/home/alice"});
+    do_test(s_lookups == 4);
+
+    // The cache stays small.
+    for (size_t i = 0; i < 2 * home_directory_cache_t::kMaxEntries; i++) {
+        do_test(!cache.lookup(L"user" + to_string(i)));
+    }
+    do_test(s_lookups == 4 + 2 * home_directory_cache_t::kMaxEntries);
+    do_test(cache.lookup(L"alice") == wcstring{L"/home/alice"});
+    do_test(s_lookups == 5 + 2 * home_directory_cache_t::kMaxEntries);
+}
+
 static void test_fuzzy_match() {
     say(L"Testing fuzzy string matching");
     // Check that a string fuzzy match has the expected type and case folding.
@@ -2330,6 +2692,24 @@ static void test_fuzzy_match() {
     do_test(test_fuzzy(L"AA", L"ALPHA!", type_t::subseq, case_fold_t::samecase));
     do_test(!string_fuzzy_match_string(L"lh", L"ALPHA!").has_value());  // no subseq icase
     do_test(!string_fuzzy_match_string(L"BB", L"ALPHA!").has_value());
+
+    // Transposed matches are only tried if requested, and only after everything else.
+    auto transposed = [](const wchar_t *inp, const wchar_t *exp) -> maybe_t<uint32_t> {
+        auto m = string_fuzzy_match_string(inp, exp, false, true);
+        if (!m || m->type != type_t::transposed) return none();
+        return m->transpositions;
+    };
+    do_test(!string_fuzzy_match_string(L"gti", L"git").has_value());
+    do_test(transposed(L"gti", L"git") == 1u);
+    do_test(transposed(L"gti", L"gitk") == 1u);
+    do_test(transposed(L"bacd", L"abdc") == 2u);
+    do_test(transposed(L"bacd", L"zabcd") == 1u);
+    do_test(!transposed(L"gti", L"tig"));
+    do_test(!transposed(L"git", L"gitk"));  // prefix
+    do_test(!transposed(L"gt", L"git"));    // subseq
+    do_test(!transposed(L"gg", L"git"));
+    auto m = string_fuzzy_match_string(L"gti", L"git", false, true);
+    do_test(m && m->rank() > string_fuzzy_match_string(L"gt", L"git")->rank());
 }
 
 static void test_ifind() {
@@ -3233,6 +3613,34 @@ static void test_complete() {
     do_test(completions.size() == 1);
     do_test(completions.at(0).completion == L"qux");
 
+    // Fuzzy matching with characters out of order, ranked by how many had to be swapped.
+    complete_add(L"fuzzytest", false, wcstring(), option_type_args_only, no_files, NULL,
+                 L"git gitk tig abdc zabcd", NULL, COMPLETE_AUTO_SPACE);
+    completions = do_complete(L"fuzzytest gti", completion_request_t::fuzzy_match);
+    do_test(completions.empty());
+    parser->vars().set_one(L"fish_completion_matching", ENV_GLOBAL, L"fuzzy");
+    completions = do_complete(L"fuzzytest gti", completion_request_t::fuzzy_match);
+    completions_sort_and_prioritize(&completions);
+    do_test(completions.size() == 2);
+    do_test(completions.at(0).completion == L"git");
+    do_test(completions.at(1).completion == L"gitk");
+    completions = do_complete(L"fuzzytest bacd", completion_request_t::fuzzy_match);
+    completions_sort_and_prioritize(&completions);
+    do_test(completions.size() == 2);
+    do_test(completions.at(0).completion == L"zabcd");
+    do_test(completions.at(1).completion == L"abdc");
+    // Transposed matches are dropped if there are better ones.
+    completions = do_complete(L"fuzzytest gt", completion_request_t::fuzzy_match);
+    completions_sort_and_prioritize(&completions);
+    do_test(completions.size() == 2);
+    do_test(completions.at(0).completion == L"git");
+    do_test(completions.at(1).completion == L"gitk");
+    // Not without fuzzy matching.
+    completions = do_complete(L"fuzzytest gti", {});
+    do_test(completions.empty());
+    parser->vars().remove(L"fish_completion_matching", ENV_GLOBAL);
+    complete_remove_all(L"fuzzytest", false);
+
     // Don't complete variable names in single quotes (#1023).
     completions = do_complete(L"echo '$Foo", {});
     do_test(completions.empty());
@@ -3720,6 +4128,117 @@ static void test_input() {
     }
 }
 
+static void test_input_mode_inheritance() {
+    say(L"Testing bind mode inheritance");
+    auto &parser = parser_t::principal_parser();
+    inputter_t input{parser};
+    {
+        auto input_mapping = input_mappings();
+        input_mapping->add(L"zqa", L"up-line", L"inh_parent", L"");
+        input_mapping->add(L"zqb", L"down-line", L"inh_parent", L"");
+        input_mapping->add(L"zqb", L"end-of-line", L"inh_child", L"");
+        // The child's generic binding is only used if no mode has a matching sequence.
+        input_mapping->add(L"", L"self-insert", L"inh_child", L"");
+        do_test(input_mapping->set_mode_parent(L"inh_child", L"inh_parent"));
+        // Cycles are rejected.
+        do_test(!input_mapping->set_mode_parent(L"inh_parent", L"inh_child"));
+        do_test((input_mapping->get_mode_chain(L"inh_child") ==
+                 wcstring_list_t{L"inh_child", L"inh_parent"}));
+    }
+    parser.vars().set_one(FISH_BIND_MODE_VAR, ENV_GLOBAL, wcstring{L"inh_child"});
+
+    // A sequence the child doesn't bind falls through to the parent.
+    for (wchar_t c : wcstring{L"zqa"}) input.queue_char(c);
+    auto evt = input.read_char();
+    do_test(evt.is_readline() && evt.get_readline() == readline_cmd_t::up_line);
+
+    // The child's own binding wins over the parent's.
+    for (wchar_t c : wcstring{L"zqb"}) input.queue_char(c);
+    evt = input.read_char();
+    do_test(evt.is_readline() && evt.get_readline() == readline_cmd_t::end_of_line);
+
+    // Without the inheritance, the parent's binding is not used.
+    input_mappings()->set_mode_parent(L"inh_child", L"");
+    for (wchar_t c : wcstring{L"zqa"}) input.queue_char(c);
+    evt = input.read_char();
+    do_test(evt.is_char() && evt.get_char() == L'z');
+    for (int i = 0; i < 2; i++) input.read_char();
+
+    parser.vars().set_one(FISH_BIND_MODE_VAR, ENV_GLOBAL, wcstring{DEFAULT_BIND_MODE});
+}
+
+static void test_input_ignored() {
+    say(L"Testing ignored sequences");
+    auto &parser = parser_t::principal_parser();
+    inputter_t input{parser};
+    {
+        auto input_mapping = input_mappings();
+        // Ignored sequences have no commands.
+        const wchar_t *no_cmds[] = {nullptr};
+        input_mapping->add(L"", L"self-insert", L"ignore_mode", L"");
+        input_mapping->add(L"\x1b[1;5A", L"up-line", L"ignore_mode", L"");
+        input_mapping->add(L"\x1b[", no_cmds, 0, L"ignore_mode", L"", true);
+        input_mapping->add(L"zq", no_cmds, 0, L"ignore_mode", L"", true);
+    }
+    parser.vars().set_one(FISH_BIND_MODE_VAR, ENV_GLOBAL, wcstring{L"ignore_mode"});
+
+    // An unbound escape sequence is swallowed in its entirety.
+    for (wchar_t c : wcstring{L"\x1b[1;5Zx"}) input.queue_char(c);
+    auto evt = input.read_char();
+    do_test(evt.is_char() && evt.get_char() == L'x');
+
+    // Other bindings take precedence over the ignored one.
+    for (wchar_t c : wcstring{L"\x1b[1;5A"}) input.queue_char(c);
+    evt = input.read_char();
+    do_test(evt.is_readline() && evt.get_readline() == readline_cmd_t::up_line);
+
+    // Other ignored sequences are swallowed exactly.
+    for (wchar_t c : wcstring{L"zqzy"}) input.queue_char(c);
+    evt = input.read_char();
+    do_test(evt.is_char() && evt.get_char() == L'z');
+    evt = input.read_char();
+    do_test(evt.is_char() && evt.get_char() == L'y');
+
+    parser.vars().set_one(FISH_BIND_MODE_VAR, ENV_GLOBAL, wcstring{DEFAULT_BIND_MODE});
+}
+
+static void test_input_timeout() {
+    say(L"Testing binding timeouts");
+    auto &parser = parser_t::principal_parser();
+    auto pipes = make_autoclose_pipes().acquire();
+    inputter_t input{parser, pipes.read.fd()};
+    parser.vars().set_one(FISH_BIND_MODE_VAR, ENV_GLOBAL, wcstring{L"tmo_mode"});
+    const wchar_t *up_line[] = {L"up-line"};
+    input_mappings()->add(L"", L"self-insert", L"tmo_mode", L"");
+
+    // Send an escape, and the next character well after the escape delay.
+    auto send_slowly = [&] {
+        return std::thread([&] {
+            (void)write_loop(pipes.write.fd(), "\x1b", 1);
+            std::this_thread::sleep_for(std::chrono::milliseconds(200));
+            (void)write_loop(pipes.write.fd(), "q", 1);
+        });
+    };
+
+    // Without a timeout, that's an escape followed by a q.
+    input_mappings()->add(L"\x1bq", up_line, 1, L"tmo_mode", L"", true);
+    std::thread writer = send_slowly();
+    auto evt = input.read_char();
+    do_test(evt.is_char() && evt.get_char() == L'\x1b');
+    evt = input.read_char();
+    do_test(evt.is_char() && evt.get_char() == L'q');
+    writer.join();
+
+    // With a long enough timeout, it's the binding.
+    input_mappings()->add(L"\x1bq", up_line, 1, L"tmo_mode", L"", true, 2000);
+    writer = send_slowly();
+    evt = input.read_char();
+    do_test(evt.is_readline() && evt.get_readline() == readline_cmd_t::up_line);
+    writer.join();
+
+    parser.vars().set_one(FISH_BIND_MODE_VAR, ENV_GLOBAL, wcstring{DEFAULT_BIND_MODE});
+}
+
 static void test_line_iterator() {
     say(L"Testing line iterator");
 
@@ -3822,7 +4341,9 @@ static int test_universal_helper(int x) {
 
 static void test_universal() {
     say(L"Testing universal variables");
-    if (system("mkdir -p test/fish_uvars_test/")) err(L"mkdir failed");
+    if (system("rm -Rf test/fish_uvars_test/ && mkdir -p test/fish_uvars_test/")) {
+        err(L"mkdir failed");
+    }
 
     const int threads = 1;
     for (int i = 0; i < threads; i++) {
@@ -3852,6 +4373,17 @@ static void test_universal() {
             }
         }
     }
+
+    // A new variables file is only accessible by us.
+    struct stat buf;
+    do_test(wstat(UVARS_TEST_PATH, &buf) == 0);
+    do_test((buf.st_mode & 0777) == 0600);
+    // But if the user changed that, it is kept.
+    do_test(wchmod(UVARS_TEST_PATH, 0640) == 0);
+    uvars.set(L"perms_test", env_var_t{wcstring{L"1"}, 0});
+    uvars.sync(callbacks);
+    do_test(wstat(UVARS_TEST_PATH, &buf) == 0);
+    do_test((buf.st_mode & 0777) == 0640);
     system_assert("rm -Rf test/fish_uvars_test/");
 }
 
@@ -3860,6 +4392,7 @@ static void test_universal_output() {
 
     const env_var_t::env_var_flags_t flag_export = env_var_t::flag_export;
     const env_var_t::env_var_flags_t flag_pathvar = env_var_t::flag_pathvar;
+    const env_var_t::env_var_flags_t flag_pathvar_explicit = env_var_t::flag_pathvar_explicit;
 
     var_table_t vars;
     vars[L"varA"] = env_var_t(wcstring_list_t{L"ValA1", L"ValA2"}, 0);
@@ -3867,6 +4400,8 @@ static void test_universal_output() {
     vars[L"varC"] = env_var_t(wcstring_list_t{L"ValC1"}, 0);
     vars[L"varD"] = env_var_t(wcstring_list_t{L"ValD1"}, flag_export | flag_pathvar);
     vars[L"varE"] = env_var_t(wcstring_list_t{L"ValE1", L"ValE2"}, flag_pathvar);
+    vars[L"varF"] = env_var_t(wcstring_list_t{L"ValF1"}, flag_pathvar | flag_pathvar_explicit);
+    vars[L"varGPATH"] = env_var_t(wcstring_list_t{L"ValG1"}, flag_pathvar_explicit);
 
     std::string text = env_universal_t::serialize_with_vars(vars);
     const char *expected =
@@ -3876,7 +4411,9 @@ static void test_universal_output() {
         "SETUVAR --export varB:ValB1\n"
         "SETUVAR varC:ValC1\n"
         "SETUVAR --export --path varD:ValD1\n"
-        "SETUVAR --path varE:ValE1\\x1eValE2\n";
+        "SETUVAR --path varE:ValE1\\x1eValE2\n"
+        "SETUVAR --path --explicit-path varF:ValF1\n"
+        "SETUVAR --explicit-path varGPATH:ValG1\n";
     do_test(text == expected);
 }
 
@@ -3889,10 +4426,13 @@ static void test_universal_parsing() {
         "SETUVAR --export varB:ValB1\n"
         "SETUVAR --nonsenseflag varC:ValC1\n"
         "SETUVAR --export --path varD:ValD1\n"
-        "SETUVAR --path --path varE:ValE1\\x1eValE2\n";
+        "SETUVAR --path --path varE:ValE1\\x1eValE2\n"
+        "SETUVAR --explicit-path --path varF:ValF1\n"
+        "SETUVAR --explicit-path varGPATH:ValG1\n";
 
     const env_var_t::env_var_flags_t flag_export = env_var_t::flag_export;
     const env_var_t::env_var_flags_t flag_pathvar = env_var_t::flag_pathvar;
+    const env_var_t::env_var_flags_t flag_pathvar_explicit = env_var_t::flag_pathvar_explicit;
 
     var_table_t vars;
     vars[L"varA"] = env_var_t(wcstring_list_t{L"ValA1", L"ValA2"}, 0);
@@ -3900,6 +4440,8 @@ static void test_universal_parsing() {
     vars[L"varC"] = env_var_t(wcstring_list_t{L"ValC1"}, 0);
     vars[L"varD"] = env_var_t(wcstring_list_t{L"ValD1"}, flag_export | flag_pathvar);
     vars[L"varE"] = env_var_t(wcstring_list_t{L"ValE1", L"ValE2"}, flag_pathvar);
+    vars[L"varF"] = env_var_t(wcstring_list_t{L"ValF1"}, flag_pathvar | flag_pathvar_explicit);
+    vars[L"varGPATH"] = env_var_t(wcstring_list_t{L"ValG1"}, flag_pathvar_explicit);
 
     var_table_t parsed_vars;
     env_universal_t::populate_variables(input, &parsed_vars);
@@ -3978,6 +4520,97 @@ static void test_universal_callbacks() {
     system_assert("rm -Rf test/fish_uvars_test/");
 }
 
+static void test_universal_concurrent() {
+    say(L"Testing concurrent universal variable writers");
+    if (system("rm -Rf test/fish_uvars_test/ && mkdir -p test/fish_uvars_test/")) {
+        err(L"mkdir failed");
+    }
+    callback_data_list_t callbacks;
+    env_universal_t uvars1;
+    env_universal_t uvars2;
+    uvars1.initialize_at_path(callbacks, UVARS_TEST_PATH);
+    uvars2.initialize_at_path(callbacks, UVARS_TEST_PATH);
+
+    env_var_t::env_var_flags_t noflags = 0;
+    auto value_of = [](const env_universal_t &uvars, const wchar_t *name) -> wcstring {
+        auto var = uvars.get(name);
+        return var ? var->as_string() : L"<missing>";
+    };
+
+    // Both set a different variable before either writes. Neither is lost.
+    uvars1.set(L"alpha", env_var_t{L"1", noflags});
+    uvars2.set(L"beta", env_var_t{L"1", noflags});
+    uvars1.sync(callbacks);
+    uvars2.sync(callbacks);
+    uvars1.sync(callbacks);
+    do_test(value_of(uvars1, L"beta") == L"1");
+    do_test(value_of(uvars2, L"alpha") == L"1");
+
+    // Both set the same variable. The later set wins, even though it is written first.
+    uvars1.set(L"gamma", env_var_t{L"1", noflags});
+    uvars2.set(L"gamma", env_var_t{L"2", noflags});
+    uvars2.sync(callbacks);
+    callbacks.clear();
+    uvars1.sync(callbacks);
+    do_test(value_of(uvars1, L"gamma") == L"2");
+    do_test(callbacks.size() == 1);
+    do_test(callbacks.at(0).key == L"gamma");
+    do_test(callbacks.at(0).val == wcstring{L"2"});
+    uvars2.sync(callbacks);
+    do_test(value_of(uvars2, L"gamma") == L"2");
+
+    // An erase after a set wins, and is not brought back by the fish that set it.
+    uvars2.set(L"alpha", env_var_t{L"2", noflags});
+    uvars1.remove(L"alpha");
+    uvars1.sync(callbacks);
+    callbacks.clear();
+    uvars2.sync(callbacks);
+    do_test(value_of(uvars2, L"alpha") == L"<missing>");
+    do_test(callbacks.size() == 1);
+    do_test(callbacks.at(0).key == L"alpha");
+    do_test(callbacks.at(0).val == none());
+    uvars1.sync(callbacks);
+    do_test(value_of(uvars1, L"alpha") == L"<missing>");
+
+    // A set after an erase wins too.
+    uvars2.remove(L"beta");
+    uvars1.set(L"beta", env_var_t{L"2", noflags});
+    uvars1.sync(callbacks);
+    uvars2.sync(callbacks);
+    do_test(value_of(uvars2, L"beta") == L"2");
+
+    // The file remembers the erased variable, in a comment that older fish skip.
+    std::string contents;
+    {
+        autoclose_fd_t fd{wopen_cloexec(UVARS_TEST_PATH, O_RDONLY)};
+        char buff[4096];
+        ssize_t amt;
+        while ((amt = read(fd.fd(), buff, sizeof buff)) > 0) contents.append(buff, amt);
+    }
+    var_table_t saved_vars;
+    uvar_generations_t saved_generations;
+    size_t bad_lines = 0;
+    env_universal_t::populate_variables(contents, &saved_vars, &bad_lines, nullptr,
+                                        &saved_generations);
+    do_test(bad_lines == 0);
+    do_test(saved_vars.count(L"alpha") == 0);
+    do_test(saved_generations.count(L"alpha") == 1);
+    do_test(saved_generations.count(L"beta") == 1);
+    do_test(saved_generations.at(L"beta") > saved_generations.at(L"alpha"));
+    saved_vars.clear();
+    env_universal_t::populate_variables(contents, &saved_vars);
+    do_test(saved_vars.size() == 2);
+
+    // A fish that starts now sees the same variables.
+    env_universal_t uvars3;
+    uvars3.initialize_at_path(callbacks, UVARS_TEST_PATH);
+    do_test(env_universal_t::serialize_with_vars(uvars3.get_table()) ==
+            env_universal_t::serialize_with_vars(uvars1.get_table()));
+    do_test(env_universal_t::serialize_with_vars(uvars2.get_table()) ==
+            env_universal_t::serialize_with_vars(uvars1.get_table()));
+    system_assert("rm -Rf test/fish_uvars_test/");
+}
+
 static void test_universal_formats() {
     say(L"Testing universal format detection");
     const struct {
@@ -3991,7 +4624,26 @@ static void test_universal_formats() {
         {"# blah\n# VERSION: 3.0", uvar_format_t::fish_3_0},
         {"# blah\n#VERSION: 3.0", uvar_format_t::fish_3_0},
         {"# blah\n#VERSION:3.0", uvar_format_t::fish_3_0},
-        {"# blah\n#VERSION:3.1", uvar_format_t::future},
+        {"# blah\n#VERSION:3.1", uvar_format_t::fish_3_1},
+        // Later minor versions only add flags, so we can read them.
+        {"# VERSION: 3.17\nSETUVAR a:b", uvar_format_t::fish_3_1},
+        // Other major versions, and versions we can't make sense of, might be anything.
+        {"# VERSION: 4.0", uvar_format_t::future},
+        {"# VERSION: 99999.99", uvar_format_t::future},
+        {"# VERSION: 2.0", uvar_format_t::future},
+        {"# VERSION: 3", uvar_format_t::future},
+        {"# VERSION: 3.", uvar_format_t::future},
+        {"# VERSION: 3.-1", uvar_format_t::future},
+        {"# VERSION: 3.0beta", uvar_format_t::future},
+        {"# VERSION: three", uvar_format_t::future},
+        {"# VERSION: 3.0\r", uvar_format_t::fish_3_0},
+        // A damaged header or none at all, but variables in the 3.0 syntax.
+        {"# VERSION:\nSETUVAR a:b", uvar_format_t::fish_3_0},
+        {"# VERSON: 3.0\nSETUVAR a:b", uvar_format_t::fish_3_0},
+        {"SETUVAR a:b\n# VERSION: 3.0", uvar_format_t::fish_3_0},
+        {"# VERSION 3.0\nSET a:b", uvar_format_t::fish_2_x},
+        {"SETUVARa:b", uvar_format_t::fish_2_x},
+        {"", uvar_format_t::fish_2_x},
     };
     for (const auto &test : tests) {
         uvar_format_t format = env_universal_t::format_for_contents(test.str);
@@ -3999,6 +4651,126 @@ static void test_universal_formats() {
     }
 }
 
+static void test_universal_migration() {
+    say(L"Testing universal variable file versions");
+    const env_var_t::env_var_flags_t flag_export = env_var_t::flag_export;
+    const env_var_t::env_var_flags_t flag_pathvar = env_var_t::flag_pathvar;
+    const env_var_t::env_var_flags_t flag_pathvar_explicit = env_var_t::flag_pathvar_explicit;
+
+    // Files in each version load without losing anything, and are written back as 3.0. Older fish
+    // skips the flags it doesn't know, so they don't need a newer version.
+    const struct {
+        const char *contents;
+        uvar_format_t format;
+    } tests[] = {
+        {"# This file contains fish universal variable definitions.\n"
+         "SET varA:ValA1\\x1eValA2\n"
+         "SET_EXPORT varB:ValB1\n",
+         uvar_format_t::fish_2_x},
+        {"# VERSION: 3.0\n"
+         "SETUVAR varA:ValA1\\x1eValA2\n"
+         "SETUVAR --export varB:ValB1\n",
+         uvar_format_t::fish_3_0},
+        {"# VERSION: 3.1\n"
+         "SETUVAR varA:ValA1\\x1eValA2\n"
+         "SETUVAR --export varB:ValB1\n",
+         uvar_format_t::fish_3_1},
+        {"# VERSION: 3.1\n"
+         "SETUVAR varA:ValA1\\x1eValA2\n"
+         "SETUVAR --export varB:ValB1\n"
+         "SETUVAR --path --explicit-path varCPATH:ValC1\n",
+         uvar_format_t::fish_3_1},
+        {"# VERSION: 3.9\n"
+         "SETUVAR varA:ValA1\\x1eValA2\n"
+         "SETUVAR --export --frobnicate varB:ValB1\n",
+         uvar_format_t::fish_3_1},
+    };
+    for (const auto &test : tests) {
+        var_table_t vars;
+        uvar_unknown_flags_t unknown_flags;
+        size_t bad_lines = 0;
+        uvar_format_t format =
+            env_universal_t::populate_variables(test.contents, &vars, &bad_lines, &unknown_flags);
+        do_test(format == test.format);
+        do_test(bad_lines == 0);
+        do_test(vars.at(L"varA") == env_var_t(wcstring_list_t{L"ValA1", L"ValA2"}, 0));
+        do_test(vars.at(L"varB") == env_var_t(wcstring{L"ValB1"}, flag_export));
+
+        std::string text = env_universal_t::serialize_with_vars(vars, unknown_flags);
+        do_test(text.find("# VERSION: 3.0\n") != std::string::npos);
+
+        var_table_t reparsed;
+        uvar_unknown_flags_t reparsed_flags;
+        env_universal_t::populate_variables(text, &reparsed, &bad_lines, &reparsed_flags);
+        do_test(bad_lines == 0);
+        do_test(reparsed == vars);
+        do_test(reparsed_flags == unknown_flags);
+    }
+
+    // Unknown flags are kept in their place, and only for their variable.
+    var_table_t vars;
+    uvar_unknown_flags_t unknown_flags;
+    env_universal_t::populate_variables(
+        "# VERSION: 3.9\n"
+        "SETUVAR --frobnicate --path --later=1 varAPATH:ValA1\n"
+        "SETUVAR varB:ValB1\n"
+        "SETUVAR --frobnicate varC:ValC1\n"
+        "SETUVAR varC:ValC2\n",
+        &vars, nullptr, &unknown_flags);
+    do_test(vars.at(L"varAPATH") == env_var_t(wcstring{L"ValA1"}, flag_pathvar));
+    do_test((unknown_flags ==
+             uvar_unknown_flags_t{{L"varAPATH", {L"--frobnicate", L"--later=1"}}}));
+    do_test(env_universal_t::serialize_with_vars(vars, unknown_flags) ==
+            "# This file contains fish universal variable definitions.\n"
+            "# VERSION: 3.0\n"
+            "SETUVAR --path --frobnicate --later=1 varAPATH:ValA1\n"
+            "SETUVAR varB:ValB1\n"
+            "SETUVAR varC:ValC2\n");
+
+    // A file from a later 3.x can be saved, and keeps its unknown flags, also for variables that
+    // we change. A variable that we erase loses them.
+    if (system("rm -Rf test/fish_uvars_test/ && mkdir -p test/fish_uvars_test/")) {
+        err(L"mkdir failed");
+    }
+    constexpr const char contents[] =
+        "# VERSION: 3.9\n"
+        "SETUVAR --frobnicate varA:ValA1\n"
+        "SETUVAR --frobnicate varB:ValB1\n"
+        "SETUVAR --frobnicate varC:ValC1\n";
+    FILE *fp = fopen(wcs2string(UVARS_TEST_PATH).c_str(), "w");
+    assert(fp && "Failed to open UVARS_TEST_PATH for writing");
+    fwrite(contents, const_strlen(contents), 1, fp);
+    fclose(fp);
+
+    callback_data_list_t cbs;
+    env_universal_t uvars;
+    uvars.initialize_at_path(cbs, UVARS_TEST_PATH);
+    do_test(uvars.is_ok_to_save());
+    uvars.set(L"varB", env_var_t{wcstring{L"ValB2"}, flag_pathvar | flag_pathvar_explicit});
+    uvars.remove(L"varC");
+    uvars.set(L"varC", env_var_t{wcstring{L"ValC2"}, 0});
+    do_test(uvars.sync(cbs));
+
+    env_universal_t uvars2;
+    uvars2.initialize_at_path(cbs, UVARS_TEST_PATH);
+    do_test(env_universal_t::serialize_with_vars(uvars2.get_table(), {}) ==
+            env_universal_t::serialize_with_vars(uvars.get_table(), {}));
+    var_table_t saved_vars;
+    uvar_unknown_flags_t saved_flags;
+    autoclose_fd_t fd{wopen_cloexec(UVARS_TEST_PATH, O_RDONLY)};
+    std::string saved;
+    char buff[4096];
+    ssize_t amt;
+    while ((amt = read(fd.fd(), buff, sizeof buff)) > 0) saved.append(buff, amt);
+    do_test(env_universal_t::populate_variables(saved, &saved_vars, nullptr, &saved_flags) ==
+            uvar_format_t::fish_3_0);
+    do_test((saved_flags == uvar_unknown_flags_t{{L"varA", {L"--frobnicate"}},
+                                                  {L"varB", {L"--frobnicate"}}}));
+    do_test(saved_vars.at(L"varB") ==
+            env_var_t(wcstring{L"ValB2"}, flag_pathvar | flag_pathvar_explicit));
+    system_assert("rm -Rf test/fish_uvars_test/");
+}
+
 static void test_universal_ok_to_save() {
     // Ensure we don't try to save after reading from a newer fish.
     say(L"Testing universal Ok to save");
@@ -4028,6 +4800,73 @@ static void test_universal_ok_to_save() {
     system_assert("rm -Rf test/fish_uvars_test/");
 }
 
+static void test_universal_recovery() {
+    // Ensure we keep what we can from a damaged file, and rewrite it without the damage.
+    say(L"Testing universal variable recovery");
+    if (system("rm -Rf test/fish_uvars_test/ && mkdir -p test/fish_uvars_test/")) {
+        err(L"mkdir failed");
+    }
+    const std::string path = wcs2string(UVARS_TEST_PATH);
+    const std::string backup_path = path + ".bak";
+    auto read_file = [](const std::string &path) {
+        std::string result;
+        if (FILE *fp = fopen(path.c_str(), "r")) {
+            char buff[4096];
+            size_t amt;
+            while ((amt = fread(buff, 1, sizeof buff, fp)) > 0) result.append(buff, amt);
+            fclose(fp);
+        }
+        return result;
+    };
+
+    // Lines from two interleaved writers, and a final line truncated by a crash.
+    constexpr const char contents[] =
+        "# VERSION: 3.0\n"
+        "SETUVAR varA:ValA\n"
+        "SETUVSETUVAR varB:ValB\n"
+        "SETUVAR varC:ValC\n"
+        "SETUVAR --export\n"
+        "garbage\n"
+        "SETUVAR varD:ValD\n"
+        "SETUVAR varE";
+    var_table_t parsed_vars;
+    size_t bad_lines = 0;
+    env_universal_t::populate_variables(contents, &parsed_vars, &bad_lines);
+    do_test(bad_lines == 4);
+    do_test(parsed_vars.size() == 3);
+
+    FILE *fp = fopen(path.c_str(), "w");
+    assert(fp && "Failed to open UVARS_TEST_PATH for writing");
+    fwrite(contents, const_strlen(contents), 1, fp);
+    fclose(fp);
+
+    callback_data_list_t cbs;
+    env_universal_t uvars;
+    uvars.initialize_at_path(cbs, UVARS_TEST_PATH);
+    do_test(uvars.get(L"varA") == env_var_t(wcstring{L"ValA"}, 0));
+    do_test(uvars.get(L"varC") == env_var_t(wcstring{L"ValC"}, 0));
+    do_test(uvars.get(L"varD") == env_var_t(wcstring{L"ValD"}, 0));
+
+    // Too many bad lines, so even an unmodified sync rewrites the file. The damaged file does not
+    // become the backup.
+    do_test(uvars.sync(cbs));
+    parsed_vars.clear();
+    env_universal_t::populate_variables(read_file(path), &parsed_vars, &bad_lines);
+    do_test(bad_lines == 0);
+    do_test(parsed_vars == uvars.get_table());
+    do_test(access(backup_path.c_str(), F_OK) != 0);
+
+    // Now the file is good, so the next save keeps it as the backup.
+    uvars.set(L"varF", env_var_t{wcstring{L"ValF"}, 0});
+    do_test(uvars.sync(cbs));
+    parsed_vars.clear();
+    env_universal_t::populate_variables(read_file(backup_path), &parsed_vars, &bad_lines);
+    do_test(bad_lines == 0);
+    do_test(parsed_vars.size() == 3 && parsed_vars.count(L"varF") == 0);
+    do_test(!uvars.sync(cbs));
+    system_assert("rm -Rf test/fish_uvars_test/");
+}
+
 bool poll_notifier(const std::unique_ptr<universal_notifier_t> &note) {
     if (note->poll()) return true;
 
@@ -4110,13 +4949,92 @@ static void test_notifiers_with_strategy(universal_notifier_t::notifier_strategy
     }
 }
 
+/// Poll a notifier that watches the vars file: the first poll reads the events, and the change is
+/// reported after the settling period.
+static bool poll_file_watch_notifier(const std::unique_ptr<universal_notifier_t> &note) {
+    bool result = poll_notifier(note);
+    usleep(40000);  // twice the settling period
+    return poll_notifier(note) || result;
+}
+
+static void test_file_watch_notifiers_with_strategy(
+    universal_notifier_t::notifier_strategy_t strategy) {
+    say(L"Testing universal notifiers with strategy %d", (int)strategy);
+    constexpr size_t notifier_count = 4;
+    std::unique_ptr<universal_notifier_t> notifiers[notifier_count];
+    for (size_t i = 0; i < notifier_count; i++) {
+        notifiers[i] = universal_notifier_t::new_notifier_for_strategy(strategy, UVARS_TEST_PATH);
+        if (notifiers[i]->notification_fd() < 0) {
+            err(L"Universal variable notifier with strategy %d has no fd", (int)strategy);
+            return;
+        }
+    }
+
+    // Other files in the directory don't matter.
+    system_assert("echo other > test/fish_uvars_test/otherfile.txt");
+    for (size_t i = 0; i < notifier_count; i++) {
+        if (poll_file_watch_notifier(notifiers[i])) {
+            err(L"Universal variable notifier polled true for another file, with strategy %d",
+                (int)strategy);
+        }
+    }
+
+    // Writes in place and renames into place, in quick succession, are reported once.
+    system_assert("echo a > test/fish_uvars_test/varsfile.txt");
+    system_assert(
+        "echo b > test/fish_uvars_test/varsfile.tmp && "
+        "mv test/fish_uvars_test/varsfile.tmp test/fish_uvars_test/varsfile.txt");
+    system_assert("echo c >> test/fish_uvars_test/varsfile.txt");
+    for (size_t i = 0; i < notifier_count; i++) {
+        if (!poll_file_watch_notifier(notifiers[i])) {
+            err(L"Universal variable notifier (%lu) failed to notice changes, with strategy %d", i,
+                (int)strategy);
+        } else if (poll_file_watch_notifier(notifiers[i])) {
+            err(L"Universal variable notifier (%lu) polled twice in a row with strategy %d", i,
+                (int)strategy);
+        }
+    }
+
+    // The notifiers follow the replaced file.
+    system_assert("echo d >> test/fish_uvars_test/varsfile.txt");
+    for (size_t i = 0; i < notifier_count; i++) {
+        if (!poll_file_watch_notifier(notifiers[i])) {
+            err(L"Universal variable notifier (%lu) lost the replaced file, with strategy %d", i,
+                (int)strategy);
+        }
+    }
+
+    // Posting also notifies sessions that use the named pipe.
+    auto pipe_notifier = universal_notifier_t::new_notifier_for_strategy(
+        universal_notifier_t::strategy_named_pipe, UVARS_TEST_PATH);
+    notifiers[0]->post_notification();
+    if (!poll_notifier(pipe_notifier)) {
+        err(L"Named pipe notifier failed to notice a post with strategy %d", (int)strategy);
+    }
+    usleep(1000000 / 10);  // corresponds to NAMED_PIPE_FLASH_DURATION_USEC
+    poll_notifier(notifiers[0]);
+    poll_notifier(pipe_notifier);
+}
+
 static void test_universal_notifiers() {
     if (system("mkdir -p test/fish_uvars_test/ && touch test/fish_uvars_test/varsfile.txt")) {
         err(L"mkdir failed");
     }
 
     auto strategy = universal_notifier_t::resolve_default_strategy();
-    test_notifiers_with_strategy(strategy);
+    if (strategy == universal_notifier_t::strategy_inotify ||
+        strategy == universal_notifier_t::strategy_kqueue) {
+        // The named pipe is the fallback, so test it too.
+        test_notifiers_with_strategy(universal_notifier_t::strategy_named_pipe);
+    } else {
+        test_notifiers_with_strategy(strategy);
+    }
+#ifdef HAVE_INOTIFY_INIT1
+    test_file_watch_notifiers_with_strategy(universal_notifier_t::strategy_inotify);
+#endif
+#ifdef HAVE_KQUEUE
+    test_file_watch_notifiers_with_strategy(universal_notifier_t::strategy_kqueue);
+#endif
 }
 
 class history_tests_t {
@@ -4125,6 +5043,9 @@ class history_tests_t {
     static void test_history_merge();
     static void test_history_path_detection();
     static void test_history_formats();
+    static void test_history_deduplicate();
+    static void test_history_dedup_policy();
+    static void test_history_save_policy();
     // static void test_history_speed(void);
     static void test_history_races();
     static void test_history_races_pound_on_history(size_t item_count, size_t idx);
@@ -4610,6 +5531,118 @@ static bool history_equals(const shared_ptr<history_t> &hist, const wchar_t *con
     return true;
 }
 
+void history_tests_t::test_history_deduplicate() {
+    const wchar_t *name = L"history_sample_dups";
+    say(L"Testing history deduplication");
+    if (!install_sample_history(name)) {
+        err(L"Couldn't open file tests/%ls", name);
+        return;
+    }
+
+    // Repeated commands are collapsed, but duplicates which are not next to each other are kept.
+    auto hist = std::make_shared<history_t>(name);
+    do_test(hist->size() == 6);
+    do_test(hist->deduplicate_in_place() == 2);
+    const wchar_t *const expected[] = {L"echo c", L"echo a", L"echo b", L"echo a", NULL};
+    history_equals(hist, expected);
+    do_test(hist->deduplicate_in_place() == 0);
+
+    // The file has been rewritten.
+    time_barrier();
+    hist = std::make_shared<history_t>(name);
+    history_equals(hist, expected);
+
+    // Growing past $fish_history_max_size trims the history, even if the old items were not loaded.
+    history_set_max_size(2);
+    hist->add(L"echo d");
+    time_barrier();
+    hist = std::make_shared<history_t>(name);
+    const wchar_t *const expected_trimmed[] = {L"echo d", L"echo c", NULL};
+    history_equals(hist, expected_trimmed);
+
+    // Trimming leaves some room below the limit.
+    history_set_max_size(20);
+    for (int i = 0; i < 19; i++) hist->add(format_string(L"echo %d", i));
+    time_barrier();
+    hist = std::make_shared<history_t>(name);
+    do_test(hist->size() == 18);
+    history_set_max_size(0);
+    hist->clear();
+}
+
+void history_tests_t::test_history_dedup_policy() {
+    say(L"Testing history dedup policies");
+    const wchar_t *name = L"history_dedup_policy";
+    auto add_commands = [&](history_dedup_t dedup) {
+        history_set_dedup(dedup);
+        auto hist = std::make_shared<history_t>(name);
+        hist->clear();
+        for (const wchar_t *cmd : {L"echo a", L"echo a", L"echo b", L"echo a"}) hist->add(cmd);
+        return hist;
+    };
+    auto check_saved = [&](const shared_ptr<history_t> &hist, const wchar_t *const *expected) {
+        history_equals(hist, expected);
+        hist->save();
+        time_barrier();
+        history_equals(std::make_shared<history_t>(name), expected);
+    };
+
+    const wchar_t *const expected_none[] = {L"echo a", L"echo b", L"echo a", L"echo a", NULL};
+    check_saved(add_commands(history_dedup_t::none), expected_none);
+
+    const wchar_t *const expected_consecutive[] = {L"echo a", L"echo b", L"echo a", NULL};
+    check_saved(add_commands(history_dedup_t::consecutive), expected_consecutive);
+
+    const wchar_t *const expected_global[] = {L"echo a", L"echo b", NULL};
+    check_saved(add_commands(history_dedup_t::global), expected_global);
+
+    // A new policy applies to new commands only; what is stored already is kept.
+    auto hist = add_commands(history_dedup_t::none);
+    hist->save();
+    history_set_dedup(history_dedup_t::consecutive);
+    hist->add(L"echo b");
+    hist->add(L"echo c");
+    const wchar_t *const expected_switched[] = {L"echo c", L"echo b", L"echo a", L"echo b",
+                                                L"echo a", L"echo a", NULL};
+    check_saved(hist, expected_switched);
+
+    history_set_dedup(history_dedup_t::global);
+    hist->clear();
+}
+
+void history_tests_t::test_history_save_policy() {
+    say(L"Testing history save policies");
+    const wchar_t *name = L"history_save_policy";
+    auto on_disk = [&](const wchar_t *cmd) {
+        // A new history ignores items from the same second, so it does not see its own session.
+        time_barrier();
+        auto fresh = std::make_shared<history_t>(name);
+        return fresh->item_at_index(1).str() == cmd;
+    };
+    auto vars = std::make_shared<test_environment_t>();
+    auto hist = std::make_shared<history_t>(name);
+    hist->clear();
+
+    // With on-exit, nothing is written until we save explicitly.
+    history_set_save_policy(history_save_policy_t::on_exit);
+    hist->add(L"echo exit");
+    do_test(!on_disk(L"echo exit"));
+    hist->save();
+    do_test(on_disk(L"echo exit"));
+
+    // With immediate, an executed command is on disk even while its file detection runs, i.e.
+    // automatic saving is disabled.
+    history_set_save_policy(history_save_policy_t::immediate);
+    history_t::add_pending_with_file_detection(hist, L"echo immediate some/path", vars);
+    hist->resolve_pending();
+    hist->save_after_exec();
+    do_test(on_disk(L"echo immediate some/path"));
+
+    iothread_drain_all();
+    history_set_save_policy(history_save_policy_t::periodic);
+    hist->clear();
+}
+
 void history_tests_t::test_history_formats() {
     const wchar_t *name;
 
@@ -4654,6 +5687,7 @@ void history_tests_t::test_history_formats() {
         // We don't expect whitespace to be elided (#4908: except for leading/trailing whitespace)
         const wchar_t *expected[] = {L"EOF",
                                      L"sleep 123",
+                                     L"posix_cmd_sub $(is supported)",
                                      L"a && echo valid construct",
                                      L"final line",
                                      L"echo supsup",
@@ -4686,6 +5720,51 @@ void history_tests_t::test_history_formats() {
     }
 }
 
+static void test_job_journal() {
+    say(L"Testing job journal encoding");
+    job_journal_entry_t entry;
+    entry.timestamp = 1700000000;
+    entry.command = L"make all";
+    entry.status = 0;
+    entry.duration_ms = 4210;
+    std::string line = job_journal_encode(entry);
+    do_test(line == R"({"ts":1700000000,"cmd":"make all","status":0,"duration_ms":4210})"
+                    "\n");
+
+    // Quotes, backslashes, control characters and non-ASCII survive the round trip.
+    entry.command = L"echo \"a\\b\"\nprintf '\t\x01' # \u00e9\U0001F41F";
+    entry.status = 127;
+    line = job_journal_encode(entry);
+    do_test(line.find('\n') == line.size() - 1);
+    do_test(line.find("\\u0001") != std::string::npos);
+    line.pop_back();
+    auto decoded = job_journal_decode(line);
+    do_test(decoded.has_value());
+    if (decoded) {
+        do_test(decoded->timestamp == entry.timestamp);
+        do_test(decoded->command == entry.command);
+        do_test(decoded->status == 127);
+        do_test(decoded->duration_ms == 4210);
+    }
+
+    // Other writers may order keys differently, add keys and escape differently.
+    decoded = job_journal_decode(
+        R"({ "status": 1, "host": "x", "cmd": "\u00e9\ud83d\udc1f\/", "duration_ms": 5, "ts": 3})");
+    do_test(decoded.has_value());
+    if (decoded) {
+        do_test(decoded->command == L"\u00e9\U0001F41F/");
+        do_test(decoded->status == 1);
+        do_test(decoded->timestamp == 3);
+    }
+
+    do_test(!job_journal_decode(""));
+    do_test(!job_journal_decode("{}"));
+    do_test(!job_journal_decode(R"({"ts":1,"cmd":"x","status":0})"));
+    do_test(!job_journal_decode(R"({"ts":1,"cmd":"x","status":0,"duration_ms":1)"));
+    do_test(!job_journal_decode(R"({"ts":1 "cmd":"x","status":0,"duration_ms":1})"));
+    do_test(!job_journal_decode(R"({"ts":1,"cmd":"x\q","status":0,"duration_ms":1})"));
+}
+
 #if 0
 // This test isn't run at this time. It was added by commit b9283d48 but not actually enabled.
 void history_tests_t::test_history_speed(void)
@@ -5103,8 +6182,7 @@ static void test_error_messages() {
                        {L"echo foo\"$\"bar", ERROR_NO_VAR_NAME},
                        {L"echo \"foo\"$\"bar\"", ERROR_NO_VAR_NAME},
                        {L"echo foo $ bar", ERROR_NO_VAR_NAME},
-                       {L"echo foo$(foo)bar", ERROR_BAD_VAR_SUBCOMMAND1},
-                       {L"echo \"foo$(foo)bar\"", ERROR_BAD_VAR_SUBCOMMAND1}};
+                       {L"echo foo$$(foo)", ERROR_NO_VAR_NAME}};
 
     parse_error_list_t errors;
     for (const auto &test : error_tests) {
@@ -5195,6 +6273,18 @@ static void test_highlighting() {
         {L"cat", highlight_role_t::command},
     });
 
+    highlight_tests.push_back({
+        {L"echo", highlight_role_t::command},
+        {L"$(", highlight_role_t::operat},
+        {L"ls", highlight_role_t::command, ns},
+        {L")", highlight_role_t::operat, ns},
+        {L"\"a", highlight_role_t::quote},
+        {L"$(", highlight_role_t::operat, ns},
+        {L"ls", highlight_role_t::command, ns},
+        {L")", highlight_role_t::operat, ns},
+        {L"b\"", highlight_role_t::quote, ns},
+    });
+
     // Redirections substitutions.
     highlight_tests.push_back({
         {L"echo", highlight_role_t::command},
@@ -5330,6 +6420,21 @@ static void test_highlighting() {
         {L"[3]", highlight_role_t::param},  // two dollar signs, so last one is not an expansion
     });
 
+    // Noclobber redirections are errors if the file exists.
+    highlight_tests.push_back({
+        {L"echo", highlight_role_t::command},
+        {L">?", highlight_role_t::redirection},
+        {L"foo", highlight_role_t::error},
+        {L"2>?", highlight_role_t::redirection},
+        {L"newfile", highlight_role_t::redirection},
+    });
+
+    highlight_tests.push_back({
+        {L"echo", highlight_role_t::command},
+        {L">>?", highlight_role_t::error},
+        {L"newfile", highlight_role_t::error},
+    });
+
     highlight_tests.push_back({
         {L"cat", highlight_role_t::command},
         {L"/dev/null", param_valid_path},
@@ -5466,6 +6571,23 @@ static void test_highlighting() {
     }
     vars.remove(L"VARIABLE_IN_COMMAND", ENV_DEFAULT);
     vars.remove(L"VARIABLE_IN_COMMAND2", ENV_DEFAULT);
+
+    // fish_highlight_command may override the built-in roles.
+    auto &parser = parser_t::principal_parser();
+    parser.eval(
+        L"function fish_highlight_command; set -l roles (string split ' ' -- $argv[2]); "
+        L"set roles[1] error; string replace param keyword -- $roles; echo nonsense; end",
+        io_chain_t{});
+    const wcstring text = L"echo abc";
+    std::vector<highlight_spec_t> colors;
+    highlight_shell(text, colors, operation_context_t{vars});
+    do_test(colors.at(5) == highlight_role_t::param);
+    highlight_run_user_hook(parser, text, colors);
+    do_test(colors.at(0) == highlight_role_t::error);
+    do_test(colors.at(1) == highlight_role_t::command);
+    do_test(colors.at(5) == highlight_role_t::keyword);
+    do_test(colors.at(7) == highlight_role_t::keyword);
+    parser.eval(L"functions --erase fish_highlight_command", io_chain_t{});
 }
 
 static void test_split_string_tok() {
@@ -5985,10 +7107,49 @@ static void test_env_snapshot() {
     do_test(snapshot->get(L"test_env_snapshot_var")->as_string() == L"before");
     do_test(snapshot->get(L"test_env_snapshot_var_2") == none());
 
-    // snapshots see global var changes except for perproc like PWD
+    // snapshots don't see global var changes either
     vars.set(L"test_env_snapshot_var_3", ENV_GLOBAL, {L"reallyglobal"});
     do_test(vars.get(L"test_env_snapshot_var_3")->as_string() == L"reallyglobal");
-    do_test(snapshot->get(L"test_env_snapshot_var_3")->as_string() == L"reallyglobal");
+    do_test(snapshot->get(L"test_env_snapshot_var_3") == none());
+
+    // or the status, which is frozen when snapshotting
+    vars.set_last_statuses(statuses_t::just(0));
+    vars.set(L"test_env_snapshot_global", ENV_GLOBAL, {L"before"});
+    const auto snapshot2 = vars.snapshot();
+    vars.set_last_statuses(statuses_t::just(42));
+    vars.set(L"test_env_snapshot_global", ENV_GLOBAL, {L"after"});
+    vars.set(L"test_env_snapshot_var", 0, {L"after", L"again"});
+    do_test(snapshot2->get(L"status")->as_string() == L"0");
+    do_test(snapshot2->get(L"test_env_snapshot_global")->as_string() == L"before");
+    do_test(snapshot2->get(L"test_env_snapshot_var")->as_string() == L"after");
+    do_test(snapshot->get(L"test_env_snapshot_var")->as_string() == L"before");
+    vars.remove(L"test_env_snapshot_global", ENV_GLOBAL);
+    do_test(!vars.get(L"test_env_snapshot_global"));
+    do_test(snapshot2->get(L"test_env_snapshot_global")->as_string() == L"before");
+    auto names = snapshot2->get_names(ENV_GLOBAL);
+    do_test(contains(names, L"test_env_snapshot_global"));
+    do_test(contains(names, L"test_env_snapshot_var_3"));
+
+    // Snapshots may be read from other threads while the environment changes.
+    const auto snapshot3 = vars.snapshot();
+    std::atomic<bool> snapshot_changed{false};
+    std::thread reader([&] {
+        for (int i = 0; i < 10000; i++) {
+            auto var = snapshot3->get(L"test_env_snapshot_var_3");
+            if (!var || var->as_string() != L"reallyglobal") snapshot_changed = true;
+        }
+    });
+    for (int i = 0; i < 1000; i++) {
+        vars.set(L"test_env_snapshot_var_3", ENV_GLOBAL, {to_string(i)});
+        vars.set(L"test_env_snapshot_churn_" + to_string(i), ENV_GLOBAL, {L"x"});
+    }
+    reader.join();
+    do_test(!snapshot_changed);
+    for (int i = 0; i < 1000; i++) {
+        vars.remove(L"test_env_snapshot_churn_" + to_string(i), ENV_GLOBAL);
+    }
+    vars.remove(L"test_env_snapshot_var_3", ENV_GLOBAL);
+    vars.set_last_statuses(statuses_t::just(0));
 
     vars.pop();
     popd();
@@ -6318,6 +7479,102 @@ static void test_topic_monitor() {
     do_test(gens.sigchld == 2);
 }
 
+static void test_topic_monitor_timeout() {
+    say(L"Testing topic monitor timeouts");
+    using namespace std::chrono;
+    topic_monitor_t monitor;
+    constexpr auto t = topic_t::sigchld;
+    generation_list_t gens = monitor.current_generations();
+
+    // Nothing is posted, so we time out.
+    auto start = steady_clock::now();
+    bool changed = monitor.check(&gens, true /* wait */, 20 * 1000);
+    auto elapsed = steady_clock::now() - start;
+    do_test(!changed);
+    do_test(elapsed >= milliseconds(20));
+    do_test(gens.at(t) == 0);
+
+    // A post from another thread while we wait is seen before the timeout.
+    std::thread poster([&] {
+        std::this_thread::sleep_for(milliseconds(10));
+        monitor.post(t);
+    });
+    changed = monitor.check(&gens, true /* wait */, 10 * 1000 * 1000);
+    poster.join();
+    do_test(changed);
+    do_test(gens.at(t) == 1);
+
+    // A timed out wait doesn't eat a later post.
+    changed = monitor.check(&gens, true /* wait */, 1000);
+    do_test(!changed);
+    monitor.post(t);
+    changed = monitor.check(&gens, true /* wait */);
+    do_test(changed);
+    do_test(gens.at(t) == 2);
+}
+
+static void test_wait_for_stopped_job() {
+    say(L"Testing waiting for jobs with a timeout");
+    using namespace std::chrono;
+    parser_t &parser = parser_t::principal_parser();
+    // We need SIGCHLD to find out about the job.
+    signal_set_handlers(true);
+    cleanup_t reset_handlers([] { signal_reset_handlers(); });
+    parser.eval(L"sleep 0.5 &", io_chain_t{});
+    shared_ptr<job_t> job;
+    for (const auto &j : parser.jobs()) {
+        if (j->command() == L"sleep 0.5 &") job = j;
+    }
+    if (!job) {
+        err(L"Background job not found");
+        return;
+    }
+
+    // The job is still running.
+    auto start = steady_clock::now();
+    do_test(!wait_for_stopped_job(parser, job.get(), 20 * 1000));
+    do_test(steady_clock::now() - start >= milliseconds(20));
+
+    // But it completes within a generous timeout.
+    do_test(wait_for_stopped_job(parser, job.get(), 10 * 1000 * 1000));
+    do_test(job->is_completed());
+    job_reap(parser, false);
+}
+
+static void test_job_group_orphan() {
+    say(L"Testing orphaned job groups");
+    job_t::properties_t props{};
+    props.initial_background = true;
+    props.job_control = true;
+    job_t job(props, L"orphan_test");
+    job.processes.push_back(make_unique<process_t>());
+
+    // Without a pgid, there is nothing to orphan.
+    auto group = job_group_t::resolve_group_for_job(job, cancellation_group_t::create(), nullptr);
+    do_test(!group->is_orphan());
+
+    // A group in a session of its own is an orphan as far as we are concerned.
+    auto pipes = make_autoclose_pipes().acquire();
+    pid_t pid = fork();
+    if (pid == 0) {
+        char c = 0;
+        if (setsid() == -1) _exit(1);
+        (void)write_loop(pipes.write.fd(), &c, 1);
+        pause();
+        _exit(0);
+    }
+    pipes.write.close();
+    char c;
+    do_test(read_loop(pipes.read.fd(), &c, 1) == 1);
+    group->set_pgid(pid);
+    do_test(group->is_orphan());
+
+    // Once it is gone, it's not.
+    kill(pid, SIGKILL);
+    waitpid(pid, nullptr, 0);
+    do_test(!group->is_orphan());
+}
+
 static void test_topic_monitor_torture() {
     say(L"Torture-testing topic monitor");
     topic_monitor_t monitor;
@@ -6373,11 +7630,26 @@ static void test_pipes() {
     for (const auto &pipe : pipes) {
         for (int fd : {pipe.read.fd(), pipe.write.fd()}) {
             do_test(fd >= k_first_high_fd);
-            int flags = fcntl(fd, F_GETFD, 0);
-            do_test(flags >= 0);
-            do_test(bool(flags & FD_CLOEXEC));
+            do_test(get_cloexec(fd) == maybe_t<bool>{true});
         }
     }
+
+    // Clearing and setting CLOEXEC round-trips, and closed fds report EBADF.
+    if (!pipes.empty()) {
+        int fd = pipes.front().read.fd();
+        do_test(set_cloexec(fd, false) == 0);
+        do_test(get_cloexec(fd) == maybe_t<bool>{false});
+        do_test(set_cloexec(fd, true) == 0);
+        do_test(get_cloexec(fd) == maybe_t<bool>{true});
+        int closed_fd = pipes.back().write.fd();
+        pipes.back().write.close();
+        errno = 0;
+        do_test(!get_cloexec(closed_fd));
+        do_test(errno == EBADF);
+        errno = 0;
+        do_test(set_cloexec(closed_fd) == -1);
+        do_test(errno == EBADF);
+    }
 }
 
 static void test_fd_event_signaller() {
@@ -6602,11 +7874,15 @@ int main(int argc, char **argv) {
     if (should_test_function("format")) test_format();
     if (should_test_function("convert")) test_convert();
     if (should_test_function("convert")) test_convert_private_use();
+    if (should_test_function("convert")) test_convert_utf8();
     if (should_test_function("convert_ascii")) test_convert_ascii();
     if (should_test_function("perf_convert_ascii", false)) perf_convert_ascii();
     if (should_test_function("convert_nulls")) test_convert_nulls();
     if (should_test_function("tokenizer")) test_tokenizer();
+    if (should_test_function("proc_status")) test_proc_status();
+    if (should_test_function("terminal_reclaim")) test_terminal_reclaim();
     if (should_test_function("fd_monitor")) test_fd_monitor();
+    if (should_test_function("fd_monitor")) test_fd_monitor_many();
     if (should_test_function("iothread")) test_iothread();
     if (should_test_function("pthread")) test_pthread();
     if (should_test_function("debounce")) test_debounce();
@@ -6621,6 +7897,7 @@ int main(int argc, char **argv) {
     if (should_test_function("lru")) test_lru();
     if (should_test_function("expand")) test_expand();
     if (should_test_function("expand")) test_expand_overflow();
+    if (should_test_function("expand")) test_home_directory_cache();
     if (should_test_function("fuzzy_match")) test_fuzzy_match();
     if (should_test_function("ifind")) test_ifind();
     if (should_test_function("ifind_fuzzy")) test_ifind_fuzzy();
@@ -6638,6 +7915,9 @@ int main(int argc, char **argv) {
     if (should_test_function("complete")) test_complete();
     if (should_test_function("autoload")) test_autoload();
     if (should_test_function("input")) test_input();
+    if (should_test_function("input")) test_input_mode_inheritance();
+    if (should_test_function("input")) test_input_ignored();
+    if (should_test_function("input")) test_input_timeout();
     if (should_test_function("line_iterator")) test_line_iterator();
     if (should_test_function("undo")) test_undo();
     if (should_test_function("universal")) test_universal();
@@ -6645,8 +7925,11 @@ int main(int argc, char **argv) {
     if (should_test_function("universal")) test_universal_parsing();
     if (should_test_function("universal")) test_universal_parsing_legacy();
     if (should_test_function("universal")) test_universal_callbacks();
+    if (should_test_function("universal")) test_universal_concurrent();
     if (should_test_function("universal")) test_universal_formats();
+    if (should_test_function("universal")) test_universal_migration();
     if (should_test_function("universal")) test_universal_ok_to_save();
+    if (should_test_function("universal")) test_universal_recovery();
     if (should_test_function("notifiers")) test_universal_notifiers();
     if (should_test_function("wait_handles")) test_wait_handles();
     if (should_test_function("completion_insertions")) test_completion_insertions();
@@ -6661,6 +7944,10 @@ int main(int argc, char **argv) {
         if (should_test_function("history_races")) history_tests_t::test_history_races();
     }
     if (should_test_function("history_formats")) history_tests_t::test_history_formats();
+    if (should_test_function("history_deduplicate")) history_tests_t::test_history_deduplicate();
+    if (should_test_function("history_dedup_policy")) history_tests_t::test_history_dedup_policy();
+    if (should_test_function("history_save_policy")) history_tests_t::test_history_save_policy();
+    if (should_test_function("job_journal")) test_job_journal();
     if (should_test_function("string")) test_string();
     if (should_test_function("illegal_command_exit_code")) test_illegal_command_exit_code();
     if (should_test_function("maybe")) test_maybe();
@@ -6669,7 +7956,10 @@ int main(int argc, char **argv) {
     if (should_test_function("normalize")) test_normalize_path();
     if (should_test_function("dirname")) test_dirname_basename();
     if (should_test_function("topics")) test_topic_monitor();
+    if (should_test_function("topics")) test_topic_monitor_timeout();
     if (should_test_function("topics")) test_topic_monitor_torture();
+    if (should_test_function("wait_for_stopped_job")) test_wait_for_stopped_job();
+    if (should_test_function("job_group_orphan")) test_job_group_orphan();
     if (should_test_function("pipes")) test_pipes();
     if (should_test_function("fd_event")) test_fd_event_signaller();
     if (should_test_function("timer_format")) test_timer_format();
@@ -93,11 +93,15 @@ class category_list_t {
 
     category_t history{L"history", L"Command history events"};
     category_t history_file{L"history-file", L"Reading/Writing the history file"};
+    category_t job_journal{L"job-journal", L"Writing the job journal"};
 
     category_t profile_history{L"profile-history", L"History performance measurements"};
 
     category_t iothread{L"iothread", L"Background IO thread events"};
     category_t fd_monitor{L"fd-monitor", L"FD monitor events"};
+#ifdef FISH_CHECK_FD_LEAKS
+    category_t fd_leaks{L"fd-leaks", L"File descriptors leaking into child processes"};
+#endif
 
     category_t term_support{L"term-support", L"Terminal feature detection"};
 
@@ -105,6 +109,8 @@ class category_list_t {
     category_t reader_render{L"reader-render", L"Rendering the command line"};
     category_t complete{L"complete", L"The completion system"};
     category_t path{L"path", L"Searching/using paths"};
+    category_t path_check{L"path-check", L"Problems with directories in $PATH"};
+    category_t glob{L"glob", L"Wildcard expansion"};
 
     category_t screen{L"screen", L"Screen repaints"};
 };
@@ -319,8 +319,7 @@ int function_get_definition_lineno(const wcstring &name) {
     return 1 + std::count(source.begin(), source.begin() + func_start, L'\n');
 }
 
-void function_invalidate_path() {
-    // Remove all autoloaded functions and update the autoload path.
+void function_remove_autoloaded() {
     // Note we don't want to risk removal during iteration; we expect this to be called
     // infrequently.
     auto funcset = function_set.acquire();
@@ -333,9 +332,15 @@ void function_invalidate_path() {
     for (const wcstring &name : autoloadees) {
         funcset->remove(name);
     }
+    // Forget the files, so they are loaded again even if unchanged.
     funcset->autoloader.clear();
 }
 
+void function_invalidate_path() {
+    // Remove all autoloaded functions and update the autoload path.
+    function_remove_autoloaded();
+}
+
 /// Return a definition of the specified function. Used by the functions builtin.
 wcstring functions_def(const wcstring &name) {
     assert(!name.empty() && "Empty name");
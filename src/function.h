@@ -48,6 +48,10 @@ void function_add(wcstring name, wcstring description, function_properties_ref_t
 /// Remove the function with the specified name.
 void function_remove(const wcstring &name);
 
+/// Remove all functions that were autoloaded, so they are loaded again the next time they are used.
+/// Unlike function_remove(), this does not prevent autoloading them.
+void function_remove_autoloaded();
+
 /// Returns the properties for a function, or nullptr if none. This does not trigger autoloading.
 function_properties_ref_t function_get_properties(const wcstring &name);
 
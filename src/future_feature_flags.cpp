@@ -20,6 +20,9 @@ const features_t::metadata_t features_t::metadata[features_t::flag_count] = {
     {qmark_noglob, L"qmark-noglob", L"3.0", L"? no longer globs", false},
     {string_replace_backslash, L"regex-easyesc", L"3.1", L"string replace -r needs fewer \\'s",
      false},
+    {stream_for_cmdsub, L"stream-for-cmdsub", L"3.3",
+     L"for loops start before a command substitution finishes", false},
+    {remove_percent_self, L"remove-percent-self", L"3.4", L"%self is no longer expanded", false},
 };
 
 const struct features_t::metadata_t *features_t::metadata_for(const wchar_t *name) {
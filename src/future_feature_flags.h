@@ -22,6 +22,12 @@ class features_t {
         /// Whether string replace -r double-unescapes the replacement.
         string_replace_backslash,
 
+        /// Whether for loops consume command substitution output while it is produced.
+        stream_for_cmdsub,
+
+        /// Whether %self is an error instead of expanding to fish's pid.
+        remove_percent_self,
+
         /// The number of flags.
         flag_count
     };
@@ -21,6 +21,7 @@
 #include "color.h"
 #include "common.h"
 #include "env.h"
+#include "exec.h"
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "function.h"
@@ -222,6 +223,7 @@ bool is_potential_path(const wcstring &potential_path_fragment, const wcstring_l
             case BRACE_BEGIN:
             case BRACE_END:
             case BRACE_SEP:
+            case BRACE_RANGE:
             case ANY_CHAR:
             case ANY_STRING:
             case ANY_STRING_RECURSIVE: {
@@ -544,7 +546,7 @@ static void color_string_internal(const wcstring &buffstr, highlight_spec_t base
     std::fill(colors, colors + buff_len, base_color);
 
     // Hacky support for %self which must be an unquoted literal argument.
-    if (buffstr == PROCESS_EXPAND_SELF_STR) {
+    if (buffstr == PROCESS_EXPAND_SELF_STR && !feature_test(features_t::remove_percent_self)) {
         std::fill_n(colors, std::wcslen(PROCESS_EXPAND_SELF_STR), highlight_role_t::operat);
         return;
     }
@@ -747,6 +749,22 @@ static void color_string_internal(const wcstring &buffstr, highlight_spec_t base
                         break;
                     }
                     case L'$': {
+                        if (in_pos + 1 < buff_len && buffstr.at(in_pos + 1) == L'(') {
+                            // A command substitution like "$(cmd)". Skip over it; the caller
+                            // colors it. The quoted string resumes after it.
+                            colors[in_pos] = highlight_role_t::operat;
+                            size_t cursor = in_pos + 1, start = 0, end = 0;
+                            if (parse_util_locate_cmdsubst_range(buffstr, &cursor, nullptr, &start,
+                                                                 &end, true) <= 0 ||
+                                end >= buff_len) {
+                                // Incomplete; the missing paren is the problem, not the quote.
+                                unclosed_quote_offset = none();
+                                in_pos = buff_len;
+                            } else {
+                                in_pos = end;
+                            }
+                            break;
+                        }
                         in_pos += color_variable(buffstr.c_str() + in_pos, buff_len - in_pos,
                                                  colors + in_pos);
                         // Subtract one to account for the upcoming increment in the loop.
@@ -881,9 +899,12 @@ void highlighter_t::color_as_argument(const ast::node_t &node) {
     // Now do command substitutions.
     size_t cmdsub_cursor = 0, cmdsub_start = 0, cmdsub_end = 0;
     wcstring cmdsub_contents;
+    bool is_quoted = false;
+    bool has_dollar = false;
     while (parse_util_locate_cmdsubst_range(arg_str, &cmdsub_cursor, &cmdsub_contents,
                                             &cmdsub_start, &cmdsub_end,
-                                            true /* accept incomplete */) > 0) {
+                                            true /* accept incomplete */, &is_quoted,
+                                            &has_dollar) > 0) {
         // The cmdsub_start is the open paren. cmdsub_end is either the close paren or the end of
         // the string. cmdsub_contents extends from one past cmdsub_start to cmdsub_end.
         assert(cmdsub_end > cmdsub_start);
@@ -898,6 +919,7 @@ void highlighter_t::color_as_argument(const ast::node_t &node) {
         // incomplete.
         assert(cmdsub_start < arg_str.size());
         this->color_array.at(arg_subcmd_start) = highlight_role_t::operat;
+        if (has_dollar) this->color_array.at(arg_subcmd_start - 1) = highlight_role_t::operat;
         if (arg_subcmd_end < this->buff.size())
             this->color_array.at(arg_subcmd_end) = highlight_role_t::operat;
 
@@ -1360,3 +1382,67 @@ void highlight_shell(const wcstring &buff, std::vector<highlight_spec_t> &color,
     highlighter_t highlighter(buff, ctx, working_directory, io_ok);
     color = highlighter.highlight();
 }
+
+/// The prefix of the variables for roles that the commandline can use.
+static const wchar_t *const COMMANDLINE_COLOR_PREFIX = L"fish_color_";
+
+/// \return the name a user hook sees for \p role, e.g. "command" for fish_color_command.
+static const wchar_t *user_hook_role_name(highlight_role_t role) {
+    return get_highlight_var_name(role) + std::wcslen(COMMANDLINE_COLOR_PREFIX);
+}
+
+/// \return the role a user hook means by \p name, if it names one for the commandline.
+static maybe_t<highlight_role_t> user_hook_role_from_name(const wcstring &name) {
+    for (auto role = static_cast<uint8_t>(highlight_role_t::normal);
+         role <= static_cast<uint8_t>(highlight_role_t::selection); role++) {
+        auto result = static_cast<highlight_role_t>(role);
+        if (name == user_hook_role_name(result)) return result;
+    }
+    return none();
+}
+
+void highlight_run_user_hook(parser_t &parser, const wcstring &buff,
+                             std::vector<highlight_spec_t> &color) {
+    ASSERT_IS_MAIN_THREAD();
+    assert(buff.size() == color.size());
+    const wcstring hook_name = L"fish_highlight_command";
+    // The hook may edit the commandline, which would highlight again; don't recurse.
+    static bool running = false;
+    if (running || buff.empty() || !function_exists(hook_name, parser)) return;
+    scoped_push<bool> running_push(&running, true);
+
+    // Pass one role per character, separated by spaces.
+    wcstring roles;
+    for (const highlight_spec_t &spec : color) {
+        if (!roles.empty()) roles.push_back(L' ');
+        roles.append(user_hook_role_name(spec.foreground));
+    }
+    wcstring cmd = hook_name;
+    cmd.push_back(L' ');
+    cmd.append(escape_string(buff, ESCAPE_ALL));
+    cmd.push_back(L' ');
+    cmd.append(escape_string(roles, ESCAPE_ALL));
+
+    // Status is ignored.
+    wcstring_list_t lines;
+    {
+        scoped_push<bool> no_trace(&parser.libdata().suppress_fish_trace, true);
+        scoped_push<bool> noninteractive{&parser.libdata().is_interactive, false};
+        exec_subshell(cmd, parser, lines, false);
+    }
+
+    // The hook prints the roles again, separated by spaces or newlines. Its roles win; unknown
+    // names and missing trailing entries keep the built-in role.
+    size_t idx = 0;
+    for (const wcstring &line : lines) {
+        for (const wcstring &name : split_string_tok(line, L" \t")) {
+            if (idx >= color.size()) return;
+            auto role = user_hook_role_from_name(name);
+            if (role) {
+                // Keep the rest of the spec, like the background and valid_path.
+                color[idx].foreground = *role;
+            }
+            idx++;
+        }
+    }
+}
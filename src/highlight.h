@@ -84,6 +84,7 @@ struct hash<highlight_spec_t> {
 
 class history_item_t;
 class operation_context_t;
+class parser_t;
 
 /// Given a string and list of colors of the same size, return the string with ANSI escape sequences
 /// representing the colors.
@@ -102,6 +103,12 @@ std::string colorize(const wcstring &text, const std::vector<highlight_spec_t> &
 void highlight_shell(const wcstring &buffstr, std::vector<highlight_spec_t> &color,
                      const operation_context_t &ctx, bool io_ok = false);
 
+/// Run the fish_highlight_command function, if it exists, on the buffer \p buff with the roles
+/// from \p color, and apply the roles it prints to \p color. This must be called on the main
+/// thread.
+void highlight_run_user_hook(parser_t &parser, const wcstring &buff,
+                             std::vector<highlight_spec_t> &color);
+
 /// highlight_color_resolver_t resolves highlight specs (like "a command") to actual RGB colors.
 /// It maintains a cache with no invalidation mechanism. The lifetime of these should typically be
 /// one screen redraw.
@@ -65,6 +65,24 @@
 // When we rewrite the history, the number of items we keep.
 #define HISTORY_SAVE_MAX (1024 * 256)
 
+// The value of $fish_history_max_size, or 0 if unset. If the history grows beyond this many
+// items, it is deduplicated and trimmed when saving.
+static relaxed_atomic_t<size_t> s_history_max_size{0};
+
+// The value of $fish_history_dedup.
+static relaxed_atomic_t<history_dedup_t> s_history_dedup{history_dedup_t::global};
+
+// The value of $fish_history_save_policy.
+static relaxed_atomic_t<history_save_policy_t> s_history_save_policy{
+    history_save_policy_t::periodic};
+
+/// Make sure what we wrote to \p fd reaches the disk, if the save policy asks for it.
+/// \return 0 on success, or an errno value.
+static int sync_if_immediate(int fd) {
+    if (s_history_save_policy != history_save_policy_t::immediate) return 0;
+    return fsync(fd) == -1 ? errno : 0;
+}
+
 // Default buffer size for flushing to the history file.
 #define HISTORY_OUTPUT_BUFFER_SIZE (64 * 1024)
 
@@ -286,6 +304,13 @@ struct history_impl_t {
     // Deletes duplicates in new_items.
     void compact_new_items();
 
+    // Removes items identical to the item after them, and rewrites the file.
+    // \return the number of items the history shrank by.
+    size_t deduplicate_in_place();
+
+    // The most items we keep when rewriting the file.
+    static size_t max_saved_items();
+
     // Removes trailing ephemeral items.
     // Ephemeral items have leading spaces, and can only be retrieved immediately; adding any item
     // removes them.
@@ -293,10 +318,12 @@ struct history_impl_t {
 
     // Attempts to rewrite the existing file to a target temporary file
     // Returns false on error, true on success
-    bool rewrite_to_temporary_file(int existing_fd, int dst_fd) const;
+    // If collapse_repeats is set, an item repeating the one before it is dropped, and other
+    // duplicates are kept regardless of $fish_history_dedup.
+    bool rewrite_to_temporary_file(int existing_fd, int dst_fd, bool collapse_repeats) const;
 
     // Saves history by rewriting the file.
-    bool save_internal_via_rewrite();
+    bool save_internal_via_rewrite(bool collapse_repeats = false);
 
     // Saves history by appending to the file.
     bool save_internal_via_appending();
@@ -401,12 +428,25 @@ void history_impl_t::add(history_item_t &&item, bool pending, bool do_save) {
     }
 
     // Try merging with the last item.
-    if (!new_items.empty() && new_items.back().merge(item)) {
+    const history_dedup_t dedup = s_history_dedup;
+    if (dedup != history_dedup_t::none && !new_items.empty() && new_items.back().merge(item)) {
         // We merged, so we don't have to add anything. Maybe this item was pending, but it just got
         // merged with an item that is not pending, so pending just becomes false.
         this->has_pending_item = false;
     } else {
         // We have to add a new item.
+        if (dedup == history_dedup_t::global) {
+            // Forget earlier occurrences from this session. If one was already saved, the file
+            // must be rewritten without it; our new item is unwritten, so it is kept.
+            for (size_t idx = new_items.size(); idx--;) {
+                if (new_items[idx].str() != item.str()) continue;
+                new_items.erase(new_items.begin() + idx);
+                if (idx < first_unwritten_new_item_index) {
+                    first_unwritten_new_item_index--;
+                    deleted_items.insert(item.str());
+                }
+            }
+        }
         new_items.push_back(item);
         this->has_pending_item = pending;
         if (do_save) save_unless_disabled();
@@ -414,8 +454,9 @@ void history_impl_t::add(history_item_t &&item, bool pending, bool do_save) {
 }
 
 void history_impl_t::save_unless_disabled() {
-    // Respect disable_automatic_save_counter.
-    if (disable_automatic_save_counter > 0) {
+    // Respect disable_automatic_save_counter, and leave saving to the end if asked to.
+    if (disable_automatic_save_counter > 0 ||
+        s_history_save_policy == history_save_policy_t::on_exit) {
         return;
     }
 
@@ -440,6 +481,16 @@ void history_impl_t::save_unless_disabled() {
         vacuum = true;
     }
 
+    // If we have grown past the user's limit, deduplicate and trim right away.
+    size_t max_size = s_history_max_size;
+    if (max_size > 0) this->load_old_if_needed();
+    if (max_size > 0 && old_item_offsets.size() + new_items.size() > max_size) {
+        this->deduplicate_in_place();
+        assert(countdown_to_vacuum > 0);
+        countdown_to_vacuum--;
+        return;
+    }
+
     // This might be a good candidate for moving to a background thread.
     time_profiler_t profiler(vacuum ? "save vacuum"       //!OCLINT(unused var)
                                     : "save no vacuum");  //!OCLINT(side-effect)
@@ -672,7 +723,9 @@ void history_impl_t::clear_file_state() {
 }
 
 void history_impl_t::compact_new_items() {
-    // Keep only the most recent items with the given contents.
+    // Keep only the most recent items with the given contents. Other policies already drop
+    // duplicates as items are added.
+    if (s_history_dedup != history_dedup_t::global) return;
     std::unordered_set<wcstring> seen;
     size_t idx = new_items.size();
     while (idx--) {
@@ -695,6 +748,60 @@ void history_impl_t::compact_new_items() {
     }
 }
 
+size_t history_impl_t::deduplicate_in_place() {
+    const size_t old_size = this->size();
+
+    // Only look at resolved items; a pending item is not yet part of our history.
+    size_t new_item_count = new_items.size();
+    if (this->has_pending_item && new_item_count > 0) new_item_count -= 1;
+
+    // Old items come before new items. In a run of identical items, keep the last (most recent)
+    // one.
+    std::deque<size_t> kept_offsets;
+    maybe_t<wcstring> prev;
+    size_t prev_offset = 0;
+    for (size_t offset : old_item_offsets) {
+        wcstring contents = file_contents->decode_item(offset).str();
+        if (prev && *prev != contents) {
+            kept_offsets.push_back(prev_offset);
+        }
+        prev = std::move(contents);
+        prev_offset = offset;
+    }
+    if (prev) {
+        if (new_item_count == 0 || new_items.front().str() != *prev) {
+            kept_offsets.push_back(prev_offset);
+        }
+    }
+    old_item_offsets = std::move(kept_offsets);
+
+    for (size_t idx = 0; idx + 1 < new_item_count;) {
+        if (new_items[idx].str() == new_items[idx + 1].str()) {
+            new_items.erase(new_items.begin() + idx);
+            new_item_count--;
+            if (idx < first_unwritten_new_item_index) first_unwritten_new_item_index--;
+        } else {
+            idx++;
+        }
+    }
+
+    // Rewriting the file drops the removed old items. Duplicates which are not next to each other
+    // are kept, whatever $fish_history_dedup says.
+    // Without a file there is nothing else to do.
+    if (history_filename(name).has_value()) {
+        this->save_internal_via_rewrite(true /* collapse_repeats */);
+    }
+    return old_size - this->size();
+}
+
+size_t history_impl_t::max_saved_items() {
+    size_t max_size = s_history_max_size;
+    if (max_size == 0) return HISTORY_SAVE_MAX;
+    // Trim a tenth below the limit, so we don't rewrite the file for every command once the
+    // history is full.
+    return std::min(max_size - max_size / 10, size_t(HISTORY_SAVE_MAX));
+}
+
 void history_impl_t::remove_ephemeral_items() {
     while (!new_items.empty() &&
            new_items.back().persist_mode == history_persistence_mode_t::ephemeral) {
@@ -706,13 +813,27 @@ void history_impl_t::remove_ephemeral_items() {
 // Given the fd of an existing history file, or -1 if none, write
 // a new history file to temp_fd. Returns true on success, false
 // on error
-bool history_impl_t::rewrite_to_temporary_file(int existing_fd, int dst_fd) const {
+bool history_impl_t::rewrite_to_temporary_file(int existing_fd, int dst_fd,
+                                               bool collapse_repeats) const {
     // We are reading FROM existing_fd and writing TO dst_fd
     // dst_fd must be valid; existing_fd does not need to be
     assert(dst_fd >= 0);
 
-    // Make an LRU cache to save only the last N elements.
-    history_lru_cache_t lru(HISTORY_SAVE_MAX);
+    // Make an LRU cache to save only the last N distinct elements. Unless we deduplicate globally,
+    // we collect all items instead, and trim them afterwards. Each one remembers whether it is
+    // new, so that a changed policy does not rewrite what is already on disk.
+    const history_dedup_t dedup =
+        collapse_repeats ? history_dedup_t::consecutive : history_dedup_t(s_history_dedup);
+    history_lru_cache_t lru(max_saved_items());
+    using item_pair_t = std::pair<history_item_t, bool>;
+    std::vector<item_pair_t> items;
+    auto add_item = [&](history_item_t item, bool is_new) {
+        if (dedup == history_dedup_t::global) {
+            lru.add_item(std::move(item));
+        } else if (!item.empty()) {
+            items.emplace_back(std::move(item), is_new);
+        }
+    };
 
     // Read in existing items (which may have changed out from underneath us, so don't trust our
     // old file contents).
@@ -726,7 +847,7 @@ bool history_impl_t::rewrite_to_temporary_file(int existing_fd, int dst_fd) cons
                 continue;
             }
             // Add this old item.
-            lru.add_item(std::move(old_item));
+            add_item(std::move(old_item), false);
         }
     }
 
@@ -734,29 +855,56 @@ bool history_impl_t::rewrite_to_temporary_file(int existing_fd, int dst_fd) cons
     for (auto iter = new_items.cbegin() + this->first_unwritten_new_item_index;
          iter != new_items.cend(); ++iter) {
         if (iter->should_write_to_disk()) {
-            lru.add_item(*iter);
+            add_item(*iter, true);
         }
     }
 
     // Stable-sort our items by timestamp
     // This is because we may have read "old" items with a later timestamp than our "new" items
     // This is the essential step that roughly orders items by history
-    lru.stable_sort([](const history_item_t &item1, const history_item_t &item2) {
-        return item1.timestamp() < item2.timestamp();
-    });
+    if (dedup == history_dedup_t::global) {
+        lru.stable_sort([](const history_item_t &item1, const history_item_t &item2) {
+            return item1.timestamp() < item2.timestamp();
+        });
+        for (const auto key_item : lru) items.emplace_back(key_item.second, true);
+    } else {
+        std::stable_sort(items.begin(), items.end(),
+                         [](const item_pair_t &pair1, const item_pair_t &pair2) {
+                             return pair1.first.timestamp() < pair2.first.timestamp();
+                         });
+        if (dedup == history_dedup_t::consecutive) {
+            // A new item repeating the one before it replaces it.
+            decltype(items) kept;
+            for (auto &pair : items) {
+                if ((pair.second || collapse_repeats) && !kept.empty() &&
+                    kept.back().first.str() == pair.first.str()) {
+                    kept.pop_back();
+                }
+                kept.push_back(std::move(pair));
+            }
+            items = std::move(kept);
+        }
+        size_t max_items = max_saved_items();
+        if (items.size() > max_items) {
+            items.erase(items.begin(), items.end() - max_items);
+        }
+    }
 
     // Write them out.
     int err = 0;
     std::string buffer;
     buffer.reserve(HISTORY_OUTPUT_BUFFER_SIZE + 128);
-    for (const auto key_item : lru) {
-        append_history_item_to_buffer(key_item.second, &buffer);
+    for (const auto &pair : items) {
+        append_history_item_to_buffer(pair.first, &buffer);
         err = flush_to_fd(&buffer, dst_fd, HISTORY_OUTPUT_BUFFER_SIZE);
         if (err) break;
     }
     if (!err) {
         err = flush_to_fd(&buffer, dst_fd, 0);
     }
+    if (!err) {
+        err = sync_if_immediate(dst_fd);
+    }
     if (err) {
         FLOGF(history_file, L"Error %d when writing to temporary history file", err);
     }
@@ -777,7 +925,7 @@ static autoclose_fd_t create_temporary_file(const wcstring &name_template, wcstr
     return autoclose_fd_t{};
 }
 
-bool history_impl_t::save_internal_via_rewrite() {
+bool history_impl_t::save_internal_via_rewrite(bool collapse_repeats) {
     FLOGF(history, "Saving %lu items via rewrite",
           new_items.size() - first_unwritten_new_item_index);
     bool ok = false;
@@ -813,7 +961,8 @@ bool history_impl_t::save_internal_via_rewrite() {
         autoclose_fd_t target_fd_before{
             wopen_cloexec(target_name, O_RDONLY | O_CREAT, history_file_mode)};
         file_id_t orig_file_id = file_id_for_fd(target_fd_before.fd());  // possibly invalid
-        bool wrote = this->rewrite_to_temporary_file(target_fd_before.fd(), tmp_fd);
+        bool wrote =
+            this->rewrite_to_temporary_file(target_fd_before.fd(), tmp_fd, collapse_repeats);
         target_fd_before.close();
         if (!wrote) {
             // Failed to write, no good
@@ -977,6 +1126,9 @@ bool history_impl_t::save_internal_via_appending() {
         if (!err) {
             err = flush_to_fd(&buffer, history_fd.fd(), 0);
         }
+        if (!err) {
+            err = sync_if_immediate(history_fd.fd());
+        }
 
         // Since we just modified the file, update our mmap_file_id to match its current state
         // Otherwise we'll think the file has been changed by someone else the next time we go to
@@ -1421,6 +1573,10 @@ void history_t::add_pending_with_file_detection(const std::shared_ptr<history_t>
 }
 void history_t::resolve_pending() { impl()->resolve_pending(); }
 
+void history_t::save_after_exec() {
+    if (s_history_save_policy == history_save_policy_t::immediate) impl()->save();
+}
+
 void history_t::save() { impl()->save(); }
 
 /// Perform a search of \p hist for \p search_string. Invoke a function \p func for each match. If
@@ -1502,6 +1658,14 @@ history_item_t history_t::item_at_index(size_t idx) { return impl()->item_at_ind
 
 size_t history_t::size() { return impl()->size(); }
 
+size_t history_t::deduplicate_in_place() { return impl()->deduplicate_in_place(); }
+
+void history_set_max_size(size_t max) { s_history_max_size = max; }
+
+void history_set_dedup(history_dedup_t dedup) { s_history_dedup = dedup; }
+
+void history_set_save_policy(history_save_policy_t policy) { s_history_save_policy = policy; }
+
 /// The set of all histories.
 static owning_lock<std::map<wcstring, std::shared_ptr<history_t>>> s_histories;
 
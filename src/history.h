@@ -188,6 +188,10 @@ class history_t {
     // Resolves any pending history items, so that they may be returned in history searches.
     void resolve_pending();
 
+    // Called after a command from this history was executed. Saves right away if
+    // $fish_history_save_policy asks for it, even if automatic saving is disabled.
+    void save_after_exec();
+
     // Saves history.
     void save();
 
@@ -224,6 +228,11 @@ class history_t {
 
     // Return the number of history entries.
     size_t size();
+
+    // Remove entries identical to the entry right after them, keeping the most recent one, and
+    // rewrite the history file. Duplicates which are not next to each other are kept. Return the
+    // number of removed entries.
+    size_t deduplicate_in_place();
 };
 
 /// Flags for history searching.
@@ -311,6 +320,36 @@ void history_save_all();
 /// Return the prefix for the files to be used for command and read history.
 wcstring history_session_id(const environment_t &vars);
 
+/// Set the number of items ($fish_history_max_size) past which history is deduplicated and
+/// trimmed when saving. 0 means no limit.
+void history_set_max_size(size_t max);
+
+/// How history deduplicates commands as they are added ($fish_history_dedup).
+enum class history_dedup_t {
+    /// Keep every command, even repeats.
+    none,
+    /// Drop a command that repeats the one right before it.
+    consecutive,
+    /// Keep only the most recent occurrence of each command.
+    global,
+};
+
+/// Set how history deduplicates commands as they are added.
+void history_set_dedup(history_dedup_t dedup);
+
+/// When history is written to disk ($fish_history_save_policy).
+enum class history_save_policy_t {
+    /// After each executed command, synced to the disk.
+    immediate,
+    /// As commands are added, without waiting for the disk.
+    periodic,
+    /// Only when the shell exits.
+    on_exit,
+};
+
+/// Set when history is written to disk.
+void history_set_save_policy(history_save_policy_t policy);
+
 /// Given a list of proposed paths and a context, perform variable and home directory expansion,
 /// and detect if the result expands to a value which is also the path to a file.
 /// Wildcard expansions are suppressed - see implementation comments for why.
@@ -49,15 +49,24 @@ struct input_mapping_t {
     wcstring mode;
     /// New mode that should be switched to after command evaluation.
     wcstring sets_mode;
-
-    input_mapping_t(wcstring s, wcstring_list_t c, wcstring m, wcstring sm)
-        : seq(std::move(s)), commands(std::move(c)), mode(std::move(m)), sets_mode(std::move(sm)) {
+    /// How long to wait for the rest of the sequence, or 0 to use the escape delay after escapes.
+    int timeout_ms;
+
+    input_mapping_t(wcstring s, wcstring_list_t c, wcstring m, wcstring sm, int timeout = 0)
+        : seq(std::move(s)),
+          commands(std::move(c)),
+          mode(std::move(m)),
+          sets_mode(std::move(sm)),
+          timeout_ms(timeout) {
         static unsigned int s_last_input_map_spec_order = 0;
         specification_order = ++s_last_input_map_spec_order;
     }
 
     /// \return true if this is a generic mapping, i.e. acts as a fallback.
     bool is_generic() const { return seq.empty(); }
+
+    /// \return true if this mapping only swallows its sequence, as created by `bind --ignore`.
+    bool is_ignored() const { return commands.empty(); }
 };
 
 /// A struct representing the mapping from a terminfo key name to a terminfo character sequence.
@@ -242,7 +251,7 @@ static void input_mapping_insert_sorted(mapping_list_t &ml, input_mapping_t new_
 /// Adds an input mapping.
 void input_mapping_set_t::add(wcstring sequence, const wchar_t *const *commands,
                               size_t commands_len, const wchar_t *mode, const wchar_t *sets_mode,
-                              bool user) {
+                              bool user, int timeout_ms) {
     assert(commands && mode && sets_mode && "Null parameter");
 
     // Clear cached mappings.
@@ -257,13 +266,14 @@ void input_mapping_set_t::add(wcstring sequence, const wchar_t *const *commands,
         if (m.seq == sequence && m.mode == mode) {
             m.commands = commands_vector;
             m.sets_mode = sets_mode;
+            m.timeout_ms = timeout_ms;
             return;
         }
     }
 
     // Add a new mapping, using the next order.
     input_mapping_t new_mapping =
-        input_mapping_t(std::move(sequence), commands_vector, mode, sets_mode);
+        input_mapping_t(std::move(sequence), commands_vector, mode, sets_mode, timeout_ms);
     input_mapping_insert_sorted(ml, std::move(new_mapping));
 }
 
@@ -325,6 +335,7 @@ void inputter_t::prepare_to_select() /* override */ {
     // Fire any pending events and reap stray processes, including printing exit status messages.
     auto &parser = *this->parser_;
     event_fire_delayed(parser);
+    event_fire_queued(parser);
     if (job_reap(parser, true)) reader_schedule_prompt_repaint();
 }
 
@@ -458,10 +469,17 @@ class event_queue_peeker_t {
 
     /// Check if the next event is the given character. This advances the index on success only.
     /// If \p timed is set, then return false if this (or any other) character had a timeout.
-    bool next_is_char(wchar_t c, bool timed = false) {
+    /// \p timeout_ms is how long to wait in that case; 0 means the escape delay.
+    bool next_is_char(wchar_t c, bool timed = false, int timeout_ms = 0) {
+        return next_is_char_in(c, c, timed, timeout_ms);
+    }
+
+    /// Like next_is_char(), but accept any character from \p lo to \p hi inclusive.
+    bool next_is_char_in(wchar_t lo, wchar_t hi, bool timed = false, int timeout_ms = 0) {
         assert(idx_ <= peeked_.size() && "Index must not be larger than dequeued event count");
-        // See if we had a timeout already.
-        if (timed && had_timeout_) {
+        if (timed && timeout_ms <= 0) timeout_ms = get_wait_on_escape_ms();
+        // See if we had a timeout already, and have waited at least as long as we would now.
+        if (timed && timeout_ms <= timed_out_ms_) {
             return false;
         }
         // Grab a new event if we have exhausted what we have already peeked.
@@ -469,18 +487,22 @@ class event_queue_peeker_t {
         if (idx_ == peeked_.size()) {
             char_event_t newevt{L'\0'};
             if (!timed) {
+                // Once something timed out, don't block waiting for it after all.
+                if (timed_out_ms_ > 0) return false;
                 newevt = event_queue_.readch();
-            } else if (auto mevt = event_queue_.readch_timed()) {
+            } else if (auto mevt = event_queue_.readch_timed(timeout_ms - timed_out_ms_)) {
+                // We already waited timed_out_ms_ for this event, so only wait for the rest.
                 newevt = mevt.acquire();
             } else {
-                had_timeout_ = true;
+                timed_out_ms_ = timeout_ms;
                 return false;
             }
             peeked_.push_back(newevt);
         }
         // Now we have peeked far enough; check the event.
         // If it matches the char, then increment the index.
-        if (peeked_.at(idx_).maybe_char() == c) {
+        auto c = peeked_.at(idx_).maybe_char();
+        if (c && *c >= lo && *c <= hi) {
             idx_++;
             return true;
         }
@@ -510,8 +532,8 @@ class event_queue_peeker_t {
     /// The list of events which have been dequeued.
     std::vector<char_event_t> peeked_{};
 
-    /// If set, then some previous timed event timed out.
-    bool had_timeout_{false};
+    /// If positive, a previous timed event timed out after waiting this many milliseconds.
+    int timed_out_ms_{0};
 
     /// The current index. This never exceeds peeked_.size().
     size_t idx_{0};
@@ -572,42 +594,79 @@ static bool have_mouse_tracking_csi(event_queue_peeker_t *peeker) {
 }
 
 /// \return true if a given \p peeker matches a given sequence of char events given by \p str.
-static bool try_peek_sequence(event_queue_peeker_t *peeker, const wcstring &str) {
+/// If \p timeout_ms is positive, give up if any character after the first takes longer than that.
+static bool try_peek_sequence(event_queue_peeker_t *peeker, const wcstring &str,
+                              int timeout_ms = 0) {
     assert(!str.empty() && "Empty string passed to try_peek_sequence");
     wchar_t prev = L'\0';
+    bool first = true;
     for (wchar_t c : str) {
         // If we just read an escape, we need to add a timeout for the next char,
         // to distinguish between the actual escape key and an "alt"-modifier.
-        bool timed = prev == L'\x1B';
-        if (!peeker->next_is_char(c, timed)) {
+        bool timed = prev == L'\x1B' || (timeout_ms > 0 && !first);
+        if (!peeker->next_is_char(c, timed, timeout_ms)) {
             return false;
         }
         prev = c;
+        first = false;
     }
     return true;
 }
 
+/// Having read a CSI (and maybe more), read the rest of a control sequence: any parameter and
+/// intermediate bytes, followed by a final byte. Stop early if the sequence is malformed.
+static void skip_csi_tail(event_queue_peeker_t *peeker) {
+    while (peeker->next_is_char_in(0x20, 0x3F, true /* timed */)) {
+        // Parameter or intermediate byte.
+    }
+    peeker->next_is_char_in(0x40, 0x7E, true /* timed */);
+}
+
 /// \return the first mapping that matches, walking first over the user's mapping list, then the
 /// preset list. \return null if nothing matches.
 maybe_t<input_mapping_t> inputter_t::find_mapping(event_queue_peeker_t *peeker) {
     const input_mapping_t *generic = nullptr;
+    std::vector<const input_mapping_t *> ignored;
     const auto &vars = parser_->vars();
     const wcstring bind_mode = input_get_bind_mode(vars);
 
-    auto ml = input_mappings()->all_mappings();
-    for (const auto &m : *ml) {
-        if (m.mode != bind_mode) {
-            continue;
-        }
+    std::shared_ptr<const std::vector<input_mapping_t>> ml;
+    wcstring_list_t modes;
+    {
+        auto mappings = input_mappings();
+        ml = mappings->all_mappings();
+        modes = mappings->get_mode_chain(bind_mode);
+    }
+    // If nothing in the current mode matches, fall through to the modes it inherits from.
+    // The nearest generic mapping is only used if no mode has a matching sequence.
+    for (const wcstring &mode : modes) {
+        for (const auto &m : *ml) {
+            if (m.mode != mode) {
+                continue;
+            }
 
-        // Defer generic mappings until the end.
-        if (m.is_generic()) {
-            if (!generic) generic = &m;
-            continue;
-        }
+            // Defer generic mappings until the end.
+            if (m.is_generic()) {
+                if (!generic) generic = &m;
+                continue;
+            }
+            // Ignored sequences are only for input that nothing else binds.
+            if (m.is_ignored()) {
+                ignored.push_back(&m);
+                continue;
+            }
 
-        if (try_peek_sequence(peeker, m.seq)) {
-            return m;
+            if (try_peek_sequence(peeker, m.seq, m.timeout_ms)) {
+                return m;
+            }
+            peeker->restart();
+        }
+    }
+    for (const input_mapping_t *m : ignored) {
+        if (try_peek_sequence(peeker, m->seq, m->timeout_ms)) {
+            // A silenced CSI also silences whatever control sequence it starts.
+            if (string_prefixes_string(L"\x1b[", m->seq)) skip_csi_tail(peeker);
+            return *m;
         }
         peeker->restart();
     }
@@ -777,13 +836,15 @@ bool input_mapping_set_t::erase(const wcstring &sequence, const wcstring &mode,
 }
 
 bool input_mapping_set_t::get(const wcstring &sequence, const wcstring &mode,
-                              wcstring_list_t *out_cmds, bool user, wcstring *out_sets_mode) const {
+                              wcstring_list_t *out_cmds, bool user, wcstring *out_sets_mode,
+                              int *out_timeout_ms) const {
     bool result = false;
     const auto &ml = user ? mapping_list_ : preset_mapping_list_;
     for (const input_mapping_t &m : ml) {
         if (sequence == m.seq && mode == m.mode) {
             *out_cmds = m.commands;
             *out_sets_mode = m.sets_mode;
+            if (out_timeout_ms) *out_timeout_ms = m.timeout_ms;
             result = true;
             break;
         }
@@ -791,6 +852,33 @@ bool input_mapping_set_t::get(const wcstring &sequence, const wcstring &mode,
     return result;
 }
 
+bool input_mapping_set_t::set_mode_parent(const wcstring &mode, const wcstring &parent) {
+    if (parent.empty()) {
+        mode_parents_.erase(mode);
+        return true;
+    }
+    wcstring_list_t chain = get_mode_chain(parent);
+    if (contains(chain, mode)) {
+        return false;
+    }
+    mode_parents_[mode] = parent;
+    return true;
+}
+
+std::vector<std::pair<wcstring, wcstring>> input_mapping_set_t::get_mode_parents() const {
+    return {mode_parents_.begin(), mode_parents_.end()};
+}
+
+wcstring_list_t input_mapping_set_t::get_mode_chain(const wcstring &mode) const {
+    wcstring_list_t result{mode};
+    for (auto it = mode_parents_.find(mode); it != mode_parents_.end();
+         it = mode_parents_.find(it->second)) {
+        // set_mode_parent() rejects cycles, so this terminates.
+        result.push_back(it->second);
+    }
+    return result;
+}
+
 std::shared_ptr<const mapping_list_t> input_mapping_set_t::all_mappings() {
     // Populate the cache if needed.
     if (!all_mappings_cache_) {
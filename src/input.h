@@ -5,6 +5,8 @@
 
 #include <stddef.h>
 
+#include <map>
+#include <utility>
 #include <vector>
 
 #include "common.h"
@@ -89,6 +91,8 @@ class input_mapping_set_t {
     mapping_list_t mapping_list_;
     mapping_list_t preset_mapping_list_;
     std::shared_ptr<const mapping_list_t> all_mappings_cache_;
+    /// Map from a bind mode to the mode it inherits bindings from.
+    std::map<wcstring, wcstring> mode_parents_;
 
     input_mapping_set_t();
 
@@ -105,7 +109,7 @@ class input_mapping_set_t {
     /// Gets the command bound to the specified key sequence in the specified mode. Returns true if
     /// it exists, false if not.
     bool get(const wcstring &sequence, const wcstring &mode, wcstring_list_t *out_cmds, bool user,
-             wcstring *out_sets_mode) const;
+             wcstring *out_sets_mode, int *out_timeout_ms = nullptr) const;
 
     /// Returns all mapping names and modes.
     std::vector<input_mapping_name_t> get_names(bool user = true) const;
@@ -117,9 +121,20 @@ class input_mapping_set_t {
     void add(wcstring sequence, const wchar_t *command, const wchar_t *mode = DEFAULT_BIND_MODE,
              const wchar_t *sets_mode = DEFAULT_BIND_MODE, bool user = true);
 
+    /// If \p timeout_ms is positive, wait at most that long for each character after the first.
     void add(wcstring sequence, const wchar_t *const *commands, size_t commands_len,
              const wchar_t *mode = DEFAULT_BIND_MODE, const wchar_t *sets_mode = DEFAULT_BIND_MODE,
-             bool user = true);
+             bool user = true, int timeout_ms = 0);
+
+    /// Make \p mode fall back to the bindings of \p parent when none of its own match.
+    /// An empty \p parent removes the inheritance. \return false if that would create a cycle.
+    bool set_mode_parent(const wcstring &mode, const wcstring &parent);
+
+    /// \return all modes which inherit from another, as (mode, parent) pairs sorted by mode.
+    std::vector<std::pair<wcstring, wcstring>> get_mode_parents() const;
+
+    /// \return \p mode followed by the modes it inherits from, nearest first.
+    wcstring_list_t get_mode_chain(const wcstring &mode) const;
 
     /// \return a snapshot of the list of input mappings.
     std::shared_ptr<const mapping_list_t> all_mappings();
@@ -142,6 +142,8 @@ void update_wait_on_escape_ms(const environment_t& vars) {
     }
 }
 
+int get_wait_on_escape_ms() { return wait_on_escape_ms; }
+
 maybe_t<char_event_t> input_event_queue_t::try_pop() {
     if (queue_.empty()) {
         return none();
@@ -221,11 +223,15 @@ char_event_t input_event_queue_t::readch() {
 }
 
 maybe_t<char_event_t> input_event_queue_t::readch_timed() {
+    return readch_timed(wait_on_escape_ms);
+}
+
+maybe_t<char_event_t> input_event_queue_t::readch_timed(int wait_ms) {
     if (auto evt = try_pop()) {
         return evt;
     }
     const uint64_t usec_per_msec = 1000;
-    uint64_t timeout_usec = static_cast<uint64_t>(wait_on_escape_ms) * usec_per_msec;
+    uint64_t timeout_usec = static_cast<uint64_t>(wait_ms) * usec_per_msec;
     if (select_wrapper_t::is_fd_readable(in_, timeout_usec)) {
         return readch();
     }
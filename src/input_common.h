@@ -180,6 +180,9 @@ class char_event_t {
 class environment_t;
 void update_wait_on_escape_ms(const environment_t &vars);
 
+/// \return how many milliseconds to wait after an escape, per fish_escape_delay_ms.
+int get_wait_on_escape_ms();
+
 /// A class which knows how to produce a stream of input events.
 /// This is a base class; you may subclass it for its override points.
 class input_event_queue_t {
@@ -197,6 +200,9 @@ class input_event_queue_t {
     /// \return none on timeout, the event on success.
     maybe_t<char_event_t> readch_timed();
 
+    /// Like readch_timed(), but wait at most \p wait_ms milliseconds.
+    maybe_t<char_event_t> readch_timed(int wait_ms);
+
     /// Enqueue a character or a readline function to the queue of unread characters that
     /// readch will return before actually reading from fd 0.
     void push_back(const char_event_t &ch);
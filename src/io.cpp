@@ -7,6 +7,7 @@
 #include <fcntl.h>
 #include <stddef.h>
 #include <stdio.h>
+#include <sys/stat.h>
 #include <unistd.h>
 
 #include <cstring>
@@ -213,8 +214,33 @@ void io_chain_t::append(const io_chain_t &chain) {
     this->insert(this->end(), chain.begin(), chain.end());
 }
 
+bool io_chain_t::check_noclobber_specs(const redirection_spec_list_t &specs,
+                                       const wcstring &pwd) {
+    for (const auto &spec : specs) {
+        if (spec.mode != redirection_mode_t::noclob) continue;
+        // Like O_EXCL, this treats a dangling symlink as an existing file.
+        struct stat buf;
+        wcstring path = path_apply_working_directory(spec.target, pwd);
+        if (lwstat(path, &buf) == 0) {
+            FLOGF(warning, NOCLOB_ERROR, spec.target.c_str());
+            return false;
+        }
+    }
+    return true;
+}
+
+int io_chain_t::first_fd_above(int first_fd) const {
+    int result = first_fd;
+    for (const auto &io : *this) {
+        if (io->fd >= result) result = io->fd + 1;
+    }
+    return result;
+}
+
 bool io_chain_t::append_from_specs(const redirection_spec_list_t &specs, const wcstring &pwd) {
     bool have_error = false;
+    // Files we open must not land on an fd which is redirected before they are used.
+    const int first_file_fd = first_fd_above_specs(specs, this->first_fd_above(k_first_high_fd));
     for (const auto &spec : specs) {
         switch (spec.mode) {
             case redirection_mode_t::fd: {
@@ -248,6 +274,12 @@ bool io_chain_t::append_from_specs(const redirection_spec_list_t &specs, const w
                     have_error = true;
                     break;
                 }
+                file = heightenize_fd(std::move(file), true, first_file_fd);
+                if (!file.valid()) {
+                    this->push_back(make_unique<io_close_t>(spec.fd));
+                    have_error = true;
+                    break;
+                }
                 this->push_back(std::make_shared<io_file_t>(spec.fd, std::move(file)));
                 break;
             }
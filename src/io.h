@@ -348,10 +348,19 @@ class io_chain_t : public std::vector<io_data_ref_t> {
     /// if none.
     io_data_ref_t io_for_fd(int fd) const;
 
+    /// \return the lowest fd at or above \p first_fd which is higher than every fd redirected by
+    /// this chain.
+    int first_fd_above(int first_fd) const;
+
     /// Attempt to resolve a list of redirection specs to IOs, appending to 'this'.
     /// \return true on success, false on error, in which case an error will have been printed.
     bool append_from_specs(const redirection_spec_list_t &specs, const wcstring &pwd);
 
+    /// Check the noclobber redirections in \p specs, without opening anything.
+    /// \return false if any of them targets a file which already exists, in which case an error
+    /// will have been printed. Note the check is advisory; opening the file may still fail later.
+    static bool check_noclobber_specs(const redirection_spec_list_t &specs, const wcstring &pwd);
+
     /// Output debugging information to stderr.
     void print() const;
 };
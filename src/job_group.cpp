@@ -2,6 +2,9 @@
 
 #include "job_group.h"
 
+#include <errno.h>
+#include <unistd.h>
+
 #include "common.h"
 #include "fallback.h"  // IWYU pragma: keep
 #include "flog.h"
@@ -47,6 +50,32 @@ void job_group_t::set_pgid(pid_t pgid) {
 
 maybe_t<pid_t> job_group_t::get_pgid() const { return pgid_; }
 
+bool job_group_t::is_orphan() const {
+    if (!pgid_.has_value()) return false;
+    pid_t pgid = *pgid_;
+    pid_t group_sid = getsid(pgid);
+    if (group_sid == -1) {
+        // The group has no processes left, so there is nothing to orphan.
+        return false;
+    }
+
+    // The controlling terminal is released when the session leader exits. If it still belongs to
+    // the group's session, the leader is around.
+    if (tcgetsid(STDIN_FILENO) == group_sid) return false;
+
+    bool orphan = false;
+    if (group_sid != getsid(0)) {
+        FLOG(proc_pgroup, "Process group", pgid, "is in session", group_sid, "not ours");
+        orphan = true;
+    } else if (getsid(group_sid) == -1 && errno == ESRCH) {
+        // The session ID is the pid of the session leader.
+        FLOG(proc_pgroup, "Process group", pgid, "is orphaned, session leader", group_sid,
+             "has exited");
+        orphan = true;
+    }
+    return orphan;
+}
+
 // static
 job_group_ref_t job_group_t::resolve_group_for_job(const job_t &job,
                                                    const cancellation_group_ref_t &cancel_group,
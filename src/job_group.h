@@ -79,6 +79,11 @@ class job_group_t {
     /// \return whether this is an internal group.
     bool is_internal() const { return props_.is_internal; }
 
+    /// \return whether this group is an orphaned process group: it has left fish's session, or the
+    /// leader of its session has exited. Nothing will continue such a group if it stops, and
+    /// signals sent to it may not behave as expected. Groups without a pgid are never orphans.
+    bool is_orphan() const;
+
     /// \return whether we are currently the foreground group.
     bool is_foreground() const { return is_foreground_; }
 
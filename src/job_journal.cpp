@@ -0,0 +1,251 @@
+// The job journal, a file in the data directory that lists recently completed jobs.
+#include "config.h"  // IWYU pragma: keep
+
+#include "job_journal.h"
+
+#include <fcntl.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#include <cerrno>
+#include <cstdlib>
+#include <cstring>
+
+#include "fds.h"
+#include "flog.h"
+#include "path.h"
+#include "wcstringutil.h"
+#include "wutil.h"  // IWYU pragma: keep
+
+/// \return the path of the journal, with \p suffix appended, or none() if there is no data
+/// directory.
+static maybe_t<wcstring> job_journal_path(const wchar_t *suffix = L"") {
+    wcstring result;
+    if (!path_get_data(result)) return none();
+    result.append(L"/job_journal");
+    result.append(suffix);
+    return result;
+}
+
+std::string job_journal_encode(const job_journal_entry_t &entry) {
+    std::string result = "{\"ts\":";
+    result.append(std::to_string(static_cast<long long>(entry.timestamp)));
+    result.append(",\"cmd\":");
+    result.append(wcs2utf8(json_quote(entry.command)));
+    result.append(",\"status\":");
+    result.append(std::to_string(entry.status));
+    result.append(",\"duration_ms\":");
+    result.append(std::to_string(entry.duration_ms));
+    result.append("}\n");
+    return result;
+}
+
+namespace {
+/// A parser for the subset of JSON that the journal uses: one flat object with string and integer
+/// values.
+class journal_line_parser_t {
+   public:
+    explicit journal_line_parser_t(const std::string &line) : line_(line) {}
+
+    maybe_t<job_journal_entry_t> parse() {
+        job_journal_entry_t entry;
+        bool have_ts = false, have_cmd = false, have_status = false, have_duration = false;
+        if (!consume('{')) return none();
+        for (bool first = true; !consume('}'); first = false) {
+            if (!first && !consume(',')) return none();
+            std::string key;
+            if (!parse_string(&key) || !consume(':')) return none();
+
+            skip_space();
+            if (pos_ < line_.size() && line_[pos_] == '"') {
+                std::string value;
+                if (!parse_string(&value)) return none();
+                if (key == "cmd") {
+                    entry.command = utf8towcs(value);
+                    have_cmd = true;
+                }
+            } else {
+                long long value;
+                if (!parse_integer(&value)) return none();
+                if (key == "ts") {
+                    entry.timestamp = static_cast<time_t>(value);
+                    have_ts = true;
+                } else if (key == "status") {
+                    entry.status = static_cast<int>(value);
+                    have_status = true;
+                } else if (key == "duration_ms") {
+                    entry.duration_ms = value;
+                    have_duration = true;
+                }
+            }
+        }
+        skip_space();
+        if (pos_ != line_.size()) return none();
+        if (!have_ts || !have_cmd || !have_status || !have_duration) return none();
+        return entry;
+    }
+
+   private:
+    const std::string &line_;
+    size_t pos_{0};
+
+    void skip_space() {
+        while (pos_ < line_.size() && (line_[pos_] == ' ' || line_[pos_] == '\t')) pos_++;
+    }
+
+    bool consume(char c) {
+        skip_space();
+        if (pos_ < line_.size() && line_[pos_] == c) {
+            pos_++;
+            return true;
+        }
+        return false;
+    }
+
+    bool parse_integer(long long *out) {
+        skip_space();
+        size_t start = pos_;
+        if (pos_ < line_.size() && line_[pos_] == '-') pos_++;
+        size_t digits = pos_;
+        while (pos_ < line_.size() && line_[pos_] >= '0' && line_[pos_] <= '9') pos_++;
+        if (pos_ == digits) return false;
+        errno = 0;
+        *out = std::strtoll(line_.c_str() + start, nullptr, 10);
+        return errno == 0;
+    }
+
+    /// Parse the four hex digits of a unicode escape.
+    bool parse_hex4(unsigned *out) {
+        if (line_.size() - pos_ < 4) return false;
+        unsigned result = 0;
+        for (size_t i = 0; i < 4; i++) {
+            char c = line_[pos_++];
+            result <<= 4;
+            if (c >= '0' && c <= '9') {
+                result |= c - '0';
+            } else if (c >= 'a' && c <= 'f') {
+                result |= c - 'a' + 10;
+            } else if (c >= 'A' && c <= 'F') {
+                result |= c - 'A' + 10;
+            } else {
+                return false;
+            }
+        }
+        *out = result;
+        return true;
+    }
+
+    bool parse_string(std::string *out) {
+        if (!consume('"')) return false;
+        while (pos_ < line_.size()) {
+            char c = line_[pos_++];
+            if (c == '"') return true;
+            if (c != '\\') {
+                out->push_back(c);
+                continue;
+            }
+            if (pos_ == line_.size()) return false;
+            switch (line_[pos_++]) {
+                case '"':
+                    out->push_back('"');
+                    break;
+                case '\\':
+                    out->push_back('\\');
+                    break;
+                case '/':
+                    out->push_back('/');
+                    break;
+                case 'b':
+                    out->push_back('\b');
+                    break;
+                case 'f':
+                    out->push_back('\f');
+                    break;
+                case 'n':
+                    out->push_back('\n');
+                    break;
+                case 'r':
+                    out->push_back('\r');
+                    break;
+                case 't':
+                    out->push_back('\t');
+                    break;
+                case 'u': {
+                    unsigned cp;
+                    if (!parse_hex4(&cp)) return false;
+                    // Combine a surrogate pair.
+                    if (cp >= 0xD800 && cp <= 0xDBFF && line_.compare(pos_, 2, "\\u") == 0) {
+                        pos_ += 2;
+                        unsigned low;
+                        if (!parse_hex4(&low) || low < 0xDC00 || low > 0xDFFF) return false;
+                        cp = 0x10000 + ((cp - 0xD800) << 10) + (low - 0xDC00);
+                    }
+                    out->append(wcs2utf8(wcstring(1, static_cast<wchar_t>(cp))));
+                    break;
+                }
+                default:
+                    return false;
+            }
+        }
+        return false;
+    }
+};
+}  // namespace
+
+maybe_t<job_journal_entry_t> job_journal_decode(const std::string &line) {
+    return journal_line_parser_t(line).parse();
+}
+
+void job_journal_append(const job_journal_entry_t &entry) {
+    auto path = job_journal_path();
+    if (!path) return;
+
+    struct stat buf {};
+    if (wstat(*path, &buf) == 0 && buf.st_size >= kJobJournalMaxSize) {
+        // Another fish may have rotated it already, so a failure is fine.
+        if (wrename(*path, *job_journal_path(L".old")) == -1 && errno != ENOENT) {
+            FLOGF(job_journal, L"Could not rotate the job journal: %s", std::strerror(errno));
+        }
+    }
+
+    // O_APPEND and a single write keep lines from several fish instances intact.
+    autoclose_fd_t fd{wopen_cloexec(*path, O_WRONLY | O_CREAT | O_APPEND, 0600)};
+    if (!fd.valid()) {
+        FLOGF(job_journal, L"Could not open the job journal: %s", std::strerror(errno));
+        return;
+    }
+    std::string line = job_journal_encode(entry);
+    if (write_loop(fd.fd(), line.data(), line.size()) < 0) {
+        FLOGF(job_journal, L"Could not write the job journal: %s", std::strerror(errno));
+    }
+}
+
+/// Append the decodable lines of the file at \p path to \p entries.
+static void read_journal_file(const wcstring &path, std::vector<job_journal_entry_t> *entries) {
+    autoclose_fd_t fd{wopen_cloexec(path, O_RDONLY)};
+    if (!fd.valid()) return;
+
+    std::string contents;
+    char buf[4096];
+    ssize_t amt;
+    while ((amt = read_loop(fd.fd(), buf, sizeof buf)) > 0) {
+        contents.append(buf, static_cast<size_t>(amt));
+    }
+
+    size_t start = 0;
+    while (start < contents.size()) {
+        size_t end = contents.find('\n', start);
+        if (end == std::string::npos) end = contents.size();
+        if (auto entry = job_journal_decode(contents.substr(start, end - start))) {
+            entries->push_back(entry.acquire());
+        }
+        start = end + 1;
+    }
+}
+
+std::vector<job_journal_entry_t> job_journal_read() {
+    std::vector<job_journal_entry_t> result;
+    if (auto old_path = job_journal_path(L".old")) read_journal_file(*old_path, &result);
+    if (auto path = job_journal_path()) read_journal_file(*path, &result);
+    return result;
+}
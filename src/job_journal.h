@@ -0,0 +1,50 @@
+// The job journal, a file in the data directory that lists recently completed jobs.
+#ifndef FISH_JOB_JOURNAL_H
+#define FISH_JOB_JOURNAL_H
+
+#include <sys/types.h>
+
+#include <ctime>
+#include <string>
+#include <vector>
+
+#include "common.h"
+#include "maybe.h"
+
+/// A completed job, as recorded in the journal.
+struct job_journal_entry_t {
+    /// When the job completed, in seconds since the epoch.
+    time_t timestamp{0};
+
+    /// The command of the job.
+    wcstring command{};
+
+    /// The exit status of the job.
+    int status{0};
+
+    /// How long the job ran, in milliseconds.
+    long long duration_ms{0};
+};
+
+/// When the journal grows past this many bytes, it is moved to "job_journal.old" and a new one is
+/// started.
+constexpr off_t kJobJournalMaxSize = 1024 * 1024;
+
+/// Append \p entry to the journal, rotating it first if it is too big.
+/// Failures are logged, but otherwise ignored.
+void job_journal_append(const job_journal_entry_t &entry);
+
+/// \return the entries of the journal, including the rotated one, oldest first.
+/// Lines that can not be decoded are skipped.
+std::vector<job_journal_entry_t> job_journal_read();
+
+/// \return \p entry as one line of JSON, including the trailing newline. For example:
+/// {"ts":1700000000,"cmd":"make all","status":0,"duration_ms":4210}
+std::string job_journal_encode(const job_journal_entry_t &entry);
+
+/// Decode a line written by job_journal_encode(), without the trailing newline.
+/// Keys may be in any order, and unknown keys with string or integer values are ignored.
+/// \return none() if the line is not a journal entry.
+maybe_t<job_journal_entry_t> job_journal_decode(const std::string &line);
+
+#endif
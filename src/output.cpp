@@ -17,9 +17,14 @@
 #elif HAVE_NCURSES_TERM_H
 #include <ncurses/term.h>
 #endif
+#include <fcntl.h>
 #include <limits.h>
+#include <sys/stat.h>
+#include <unistd.h>
 
+#include <algorithm>
 #include <cwchar>
+#include <map>
 #include <memory>
 #include <string>
 #include <vector>
@@ -28,7 +33,9 @@
 #include "common.h"
 #include "env.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "flog.h"
+#include "maybe.h"
 #include "output.h"
 #include "wcstringutil.h"
 #include "wutil.h"  // IWYU pragma: keep
@@ -438,3 +445,264 @@ void writembs_check(outputter_t &outp, const char *mbs, const char *mbs_name, bo
              PACKAGE_BUGREPORT);
     }
 }
+
+namespace {
+/// A terminal description, keyed by the terminfo short names of its capabilities.
+struct term_description_t {
+    std::map<std::string, bool> flags;
+    std::map<std::string, int> numbers;
+    std::map<std::string, std::string> strings;
+};
+}  // namespace
+
+/// The name under which synthesized terminal descriptions are handed to curses.
+static const char *const k_synthesized_term_name = "fish-fallback";
+
+/// Capabilities of an xterm-compatible terminal, used when the terminfo database does not know
+/// $TERM. Every terminal emulator in common use understands these.
+static const struct {
+    const char *name;
+    const char *value;
+} fallback_strings[] = {
+    // Output.
+    {"bel", "\a"},
+    {"cr", "\r"},
+    {"ind", "\n"},
+    {"home", "\x1B[H"},
+    {"clear", "\x1B[H\x1B[2J"},
+    {"el", "\x1B[K"},
+    {"ed", "\x1B[J"},
+    {"cup", "\x1B[%i%p1%d;%p2%dH"},
+    {"cuu1", "\x1B[A"},
+    {"cud1", "\n"},
+    {"cub1", "\b"},
+    {"cuf1", "\x1B[C"},
+    {"cuu", "\x1B[%p1%dA"},
+    {"cud", "\x1B[%p1%dB"},
+    {"cub", "\x1B[%p1%dD"},
+    {"cuf", "\x1B[%p1%dC"},
+    {"sgr0", "\x1B[m"},
+    {"bold", "\x1B[1m"},
+    {"dim", "\x1B[2m"},
+    {"sitm", "\x1B[3m"},
+    {"ritm", "\x1B[23m"},
+    {"smul", "\x1B[4m"},
+    {"rmul", "\x1B[24m"},
+    {"blink", "\x1B[5m"},
+    {"rev", "\x1B[7m"},
+    {"smso", "\x1B[7m"},
+    {"rmso", "\x1B[27m"},
+    {"op", "\x1B[39;49m"},
+    // Input. These are the sequences sent with the keypad in normal mode, which is where fish
+    // leaves it.
+    {"kbs", "\x7F"},
+    {"kcuu1", "\x1B[A"},
+    {"kcud1", "\x1B[B"},
+    {"kcuf1", "\x1B[C"},
+    {"kcub1", "\x1B[D"},
+    {"kLFT", "\x1B[1;2D"},
+    {"kRIT", "\x1B[1;2C"},
+    {"khome", "\x1B[H"},
+    {"kend", "\x1B[F"},
+    {"kHOM", "\x1B[1;2H"},
+    {"kEND", "\x1B[1;2F"},
+    {"kich1", "\x1B[2~"},
+    {"kdch1", "\x1B[3~"},
+    {"kDC", "\x1B[3;2~"},
+    {"kpp", "\x1B[5~"},
+    {"knp", "\x1B[6~"},
+    {"kcbt", "\x1B[Z"},
+    {"kf1", "\x1BOP"},
+    {"kf2", "\x1BOQ"},
+    {"kf3", "\x1BOR"},
+    {"kf4", "\x1BOS"},
+    {"kf5", "\x1B[15~"},
+    {"kf6", "\x1B[17~"},
+    {"kf7", "\x1B[18~"},
+    {"kf8", "\x1B[19~"},
+    {"kf9", "\x1B[20~"},
+    {"kf10", "\x1B[21~"},
+    {"kf11", "\x1B[23~"},
+    {"kf12", "\x1B[24~"},
+};
+
+static term_description_t fallback_description(bool use_256_colors) {
+    term_description_t result;
+    for (const char *flag : {"am", "xenl", "km", "mir", "msgr"}) {
+        result.flags[flag] = true;
+    }
+    result.numbers["cols"] = 80;
+    result.numbers["lines"] = 24;
+    result.numbers["it"] = 8;
+    for (const auto &cap : fallback_strings) {
+        result.strings[cap.name] = cap.value;
+    }
+    if (use_256_colors) {
+        // These are the same as in xterm-256color: the first 16 colors use the old-style codes.
+        result.numbers["colors"] = 256;
+        result.numbers["pairs"] = 32767;
+        result.strings["setaf"] =
+            "\x1B[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m";
+        result.strings["setab"] =
+            "\x1B[%?%p1%{8}%<%t4%p1%d%e%p1%{16}%<%t10%p1%{8}%-%d%e48;5;%p1%d%;m";
+    } else {
+        result.numbers["colors"] = 8;
+        result.numbers["pairs"] = 64;
+        result.strings["setaf"] = "\x1B[3%p1%dm";
+        result.strings["setab"] = "\x1B[4%p1%dm";
+    }
+    return result;
+}
+
+/// \return the description of the terminal curses is currently set up for.
+static term_description_t loaded_description() {
+    term_description_t result;
+    for (size_t i = 0; boolnames[i]; i++) {
+        if (tigetflag(const_cast<char *>(boolnames[i])) > 0) result.flags[boolnames[i]] = true;
+    }
+    for (size_t i = 0; numnames[i]; i++) {
+        int val = tigetnum(const_cast<char *>(numnames[i]));
+        if (val >= 0) result.numbers[numnames[i]] = val;
+    }
+    for (size_t i = 0; strnames[i]; i++) {
+        const char *val = tigetstr(const_cast<char *>(strnames[i]));
+        if (val && val != reinterpret_cast<const char *>(-1)) result.strings[strnames[i]] = val;
+    }
+    return result;
+}
+
+/// \return the index of capability \p name in the NULL-terminated \p names, or -1 if missing.
+static int capability_index(NCURSES_CONST char *const *names, const std::string &name) {
+    for (int i = 0; names[i]; i++) {
+        if (name == names[i]) return i;
+    }
+    return -1;
+}
+
+/// Apply one entry of $fish_term_capabilities to \p desc. Entries use the terminfo source syntax:
+/// "name" sets a flag, "name#num" a number, "name=str" a string and "name@" cancels a capability.
+/// \return false if the entry is malformed or names an unknown capability.
+static bool apply_capability_override(term_description_t *desc, const wcstring &entry) {
+    size_t sep = entry.find_first_of(L"=#@");
+    if (sep == 0) return false;
+    std::string name = wcs2string(entry.substr(0, sep));
+    if (sep == wcstring::npos) {
+        if (capability_index(boolnames, name) < 0) return false;
+        desc->flags[name] = true;
+    } else if (entry[sep] == L'@') {
+        if (sep + 1 != entry.size()) return false;
+        desc->flags.erase(name);
+        desc->numbers.erase(name);
+        desc->strings.erase(name);
+    } else if (entry[sep] == L'#') {
+        if (capability_index(numnames, name) < 0) return false;
+        int val = fish_wcstoi(entry.c_str() + sep + 1);
+        if (errno || val < 0) return false;
+        desc->numbers[name] = val;
+    } else {
+        if (capability_index(strnames, name) < 0) return false;
+        desc->strings[name] = wcs2string(entry.substr(sep + 1));
+    }
+    return true;
+}
+
+/// Compile \p desc into the (legacy) binary terminfo format described in term(5).
+static std::string compile_description(const term_description_t &desc) {
+    std::vector<char> flags;
+    std::vector<int> numbers;
+    std::vector<int> offsets;
+    std::string table;
+    for (const auto &kv : desc.flags) {
+        int idx = capability_index(boolnames, kv.first);
+        if (idx < 0) continue;
+        if (flags.size() <= static_cast<size_t>(idx)) flags.resize(idx + 1, 0);
+        flags.at(idx) = kv.second;
+    }
+    for (const auto &kv : desc.numbers) {
+        int idx = capability_index(numnames, kv.first);
+        if (idx < 0) continue;
+        if (numbers.size() <= static_cast<size_t>(idx)) numbers.resize(idx + 1, -1);
+        // The legacy format only has room for 16 bit numbers.
+        numbers.at(idx) = std::min(kv.second, 32767);
+    }
+    for (const auto &kv : desc.strings) {
+        int idx = capability_index(strnames, kv.first);
+        if (idx < 0) continue;
+        if (offsets.size() <= static_cast<size_t>(idx)) offsets.resize(idx + 1, -1);
+        offsets.at(idx) = static_cast<int>(table.size());
+        table.append(kv.second);
+        table.push_back('\0');
+    }
+
+    std::string result;
+    auto put_short = [&](int val) {
+        result.push_back(static_cast<char>(val & 0xFF));
+        result.push_back(static_cast<char>((val >> 8) & 0xFF));
+    };
+    std::string names = k_synthesized_term_name;
+    names.append("|terminal description synthesized by fish");
+    put_short(0432);
+    put_short(static_cast<int>(names.size() + 1));
+    put_short(static_cast<int>(flags.size()));
+    put_short(static_cast<int>(numbers.size()));
+    put_short(static_cast<int>(offsets.size()));
+    put_short(static_cast<int>(table.size()));
+    result.append(names);
+    result.push_back('\0');
+    result.append(flags.begin(), flags.end());
+    // The numbers start on an even byte.
+    if (result.size() % 2) result.push_back('\0');
+    for (int val : numbers) put_short(val);
+    for (int val : offsets) put_short(val);
+    result.append(table);
+    return result;
+}
+
+bool setupterm_synthesized(bool from_loaded_entry, bool use_256_colors,
+                           const wcstring_list_t &overrides) {
+    term_description_t desc =
+        from_loaded_entry ? loaded_description() : fallback_description(use_256_colors);
+    for (const wcstring &entry : overrides) {
+        if (!apply_capability_override(&desc, entry)) {
+            FLOGF(warning, _(L"Ignoring invalid terminal capability '%ls' in $%ls"),
+                  entry.c_str(), L"fish_term_capabilities");
+        }
+    }
+    const std::string compiled = compile_description(desc);
+
+    // Curses can only load descriptions from a terminfo directory, so make a private one.
+    char dir_template[] = "/tmp/fish-terminfo.XXXXXX";
+    if (!mkdtemp(dir_template)) {
+        FLOGF(term_support, L"Could not create a directory for the terminal description");
+        return false;
+    }
+    const std::string dir = dir_template;
+    // Entries are filed under their first letter, or its hex code on some systems.
+    const std::string subdirs[] = {dir + "/f", dir + "/66"};
+    for (const std::string &subdir : subdirs) {
+        if (mkdir(subdir.c_str(), 0700) < 0) continue;
+        std::string path = subdir + "/" + k_synthesized_term_name;
+        autoclose_fd_t fd{open(path.c_str(), O_WRONLY | O_CREAT | O_EXCL | O_CLOEXEC, 0600)};
+        if (fd.valid()) write_loop(fd.fd(), compiled.data(), compiled.size());
+    }
+
+    const char *old_terminfo = getenv("TERMINFO");
+    const maybe_t<std::string> saved_terminfo =
+        old_terminfo ? maybe_t<std::string>(old_terminfo) : none();
+    setenv_lock("TERMINFO", dir.c_str(), 1);
+    int err_ret;
+    bool success =
+        setupterm(const_cast<char *>(k_synthesized_term_name), STDOUT_FILENO, &err_ret) == OK;
+    if (saved_terminfo) {
+        setenv_lock("TERMINFO", saved_terminfo->c_str(), 1);
+    } else {
+        unsetenv_lock("TERMINFO");
+    }
+
+    for (const std::string &subdir : subdirs) {
+        unlink((subdir + "/" + k_synthesized_term_name).c_str());
+        rmdir(subdir.c_str());
+    }
+    rmdir(dir.c_str());
+    return success;
+}
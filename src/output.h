@@ -131,4 +131,12 @@ rgb_color_t best_color(const std::vector<rgb_color_t> &candidates, color_support
 
 unsigned char index_for_color(rgb_color_t c);
 
+/// Set up curses with a terminal description synthesized by fish. This starts either from the
+/// entry curses currently has loaded (if \p from_loaded_entry is set), or from built-in
+/// xterm-compatible capabilities, with 256 colors if \p use_256_colors is set. The \p overrides
+/// are entries of $fish_term_capabilities, which are applied on top.
+/// \return true if curses could be set up.
+bool setupterm_synthesized(bool from_loaded_entry, bool use_256_colors,
+                           const wcstring_list_t &overrides);
+
 #endif
@@ -226,6 +226,9 @@ enum class pipeline_position_t {
 /// Error message for wildcards with no matches.
 #define WILDCARD_ERR_MSG _(L"No matches for wildcard '%ls'. See `help expand`.")
 
+/// Error message for an unmatched wildcard in the arguments to a command.
+#define WILDCARD_ARG_ERR_MSG _(L"%ls: No matches for wildcard '%ls'. See `help expand`.")
+
 /// Error message when an expansion produces too many results, e.g. `echo /**`.
 #define EXPAND_OVERFLOW_ERR_MSG _(L"Too many items produced by '%ls'.")
 
@@ -253,6 +256,9 @@ enum class pipeline_position_t {
 /// Error issued on $?.
 #define ERROR_NOT_STATUS _(L"$? is not the exit status. In fish, please use $status.")
 
+/// Error issued on %self, if the remove-percent-self feature is enabled.
+#define ERROR_PERCENT_SELF _(L"%%self is not supported. In fish, please use $fish_pid.")
+
 /// Error issued on $$.
 #define ERROR_NOT_PID _(L"$$ is not the pid. In fish, please use $fish_pid.")
 
@@ -262,9 +268,6 @@ enum class pipeline_position_t {
 /// Error issued on $@.
 #define ERROR_NOT_ARGV_AT _(L"$@ is not supported. In fish, please use $argv.")
 
-/// Error issued on $(...).
-#define ERROR_BAD_VAR_SUBCOMMAND1 _(L"$(...) is not supported. In fish, please use '(%ls)'.")
-
 /// Error issued on $*.
 #define ERROR_NOT_ARGV_STAR _(L"$* is not supported. In fish, please use $argv.")
 
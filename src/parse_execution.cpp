@@ -29,6 +29,7 @@
 #include "expand.h"
 #include "flog.h"
 #include "function.h"
+#include "future_feature_flags.h"
 #include "io.h"
 #include "job_group.h"
 #include "maybe.h"
@@ -384,7 +385,7 @@ end_execution_reason_t parse_execution_context_t::run_function_statement(
     ast_args_list_t arg_nodes = get_argument_nodes(header.args);
     arg_nodes.insert(arg_nodes.begin(), &header.first_arg);
     end_execution_reason_t result =
-        this->expand_arguments_from_nodes(arg_nodes, &arguments, failglob);
+        this->expand_arguments_from_nodes(arg_nodes, &arguments, failglob, L"function");
 
     if (result != end_execution_reason_t::ok) {
         return result;
@@ -428,6 +429,64 @@ end_execution_reason_t parse_execution_context_t::run_block_statement(
     return ret;
 }
 
+/// Return whether \p cmd is a plain command name which will resolve to an external command.
+static bool is_plain_external_command(const wcstring &cmd, parser_t &parser) {
+    if (cmd.empty()) return false;
+    for (wchar_t c : cmd) {
+        if (!iswalnum(c) && !std::wcschr(L"_-./+", c)) return false;
+    }
+    if (builtin_exists(cmd) || function_exists(cmd, parser)) return false;
+    wcstring path;
+    return path_get_path(cmd, &path, parser.vars());
+}
+
+/// If the arguments of a for loop are a single command substitution that can be streamed (see
+/// cmdsub_stream_t), return the source of the job inside it. This is only the case for a single
+/// job made only of external commands, since anything else would run in fish itself and so could
+/// not run alongside the loop.
+static maybe_t<wcstring> get_streamable_cmdsub(
+    const std::vector<const ast::argument_t *> &arg_nodes, const wcstring &src, parser_t &parser) {
+    if (!feature_test(features_t::stream_for_cmdsub)) return none();
+    if (arg_nodes.size() != 1) return none();
+    if (parser.vars().get(L"IFS").missing_or_empty()) return none();
+    if (get_job_control_mode() == job_control_t::all) return none();
+
+    wcstring arg = arg_nodes.front()->source(src);
+    wcstring contents;
+    size_t cursor = 0, start = 0, end = 0;
+    bool is_quoted = false, has_dollar = false;
+    if (parse_util_locate_cmdsubst_range(arg, &cursor, &contents, &start, &end, false, &is_quoted,
+                                         &has_dollar) != 1 ||
+        is_quoted || start != (has_dollar ? 1 : 0) || end + 1 != arg.size()) {
+        return none();
+    }
+
+    auto ast = ast::ast_t::parse(contents);
+    if (ast.errored()) return none();
+    const auto &jobs = *ast.top()->as<ast::job_list_t>();
+    if (jobs.count() != 1) return none();
+    const ast::job_conjunction_t &conj = *jobs.at(0);
+    if (conj.decorator || !conj.continuations.empty()) return none();
+    const ast::job_t &job = conj.job;
+    if (job.time || job.bg) return none();
+
+    std::vector<const ast::statement_t *> statements{&job.statement};
+    for (const ast::job_continuation_t &cont : job.continuation) {
+        statements.push_back(&cont.statement);
+    }
+    for (const ast::statement_t *st : statements) {
+        const auto *ds = st->contents->try_as<ast::decorated_statement_t>();
+        if (!ds) return none();
+        auto decoration = ds->decoration();
+        if (decoration != statement_decoration_t::none &&
+            decoration != statement_decoration_t::command) {
+            return none();
+        }
+        if (!is_plain_external_command(ds->command.source(contents), parser)) return none();
+    }
+    return job.source(contents);
+}
+
 end_execution_reason_t parse_execution_context_t::run_for_statement(
     const ast::for_header_t &header, const ast::job_list_t &block_contents) {
     // Get the variable name: `for var_name in ...`. We expand the variable name. It better result
@@ -438,12 +497,17 @@ end_execution_reason_t parse_execution_context_t::run_for_statement(
                             FAILED_EXPANSION_VARIABLE_NAME_ERR_MSG, for_var_name.c_str());
     }
 
-    // Get the contents to iterate over.
+    // Get the contents to iterate over. If that's a command substitution we can stream, we start
+    // it only after checking the variable, below.
     wcstring_list_t arguments;
     ast_args_list_t arg_nodes = get_argument_nodes(header.args);
-    end_execution_reason_t ret = this->expand_arguments_from_nodes(arg_nodes, &arguments, nullglob);
-    if (ret != end_execution_reason_t::ok) {
-        return ret;
+    maybe_t<wcstring> stream_src = get_streamable_cmdsub(arg_nodes, get_source(), *parser);
+    end_execution_reason_t ret = end_execution_reason_t::ok;
+    if (!stream_src) {
+        ret = this->expand_arguments_from_nodes(arg_nodes, &arguments, nullglob, L"for");
+        if (ret != end_execution_reason_t::ok) {
+            return ret;
+        }
     }
 
     auto var = parser->vars().get(for_var_name, ENV_DEFAULT);
@@ -465,15 +529,37 @@ end_execution_reason_t parse_execution_context_t::run_for_statement(
                             for_var_name.c_str());
     }
 
-    trace_if_enabled(*parser, L"for", arguments);
+    std::unique_ptr<cmdsub_stream_t> stream;
+    if (stream_src) {
+        trace_if_enabled(*parser, L"for", {arg_nodes.front()->source(get_source())});
+        stream = cmdsub_stream_t::launch(*stream_src, *parser);
+    } else {
+        trace_if_enabled(*parser, L"for", arguments);
+    }
     block_t *fb = parser->push_block(block_t::for_block());
+    const uint64_t status_count = parser->libdata().status_count;
 
     // Now drive the for loop.
-    for (const wcstring &val : arguments) {
+    size_t idx = 0;
+    bool exhausted = false;
+    wcstring val;
+    for (;;) {
         if (auto reason = check_end_execution()) {
             ret = *reason;
             break;
         }
+        if (stream_src) {
+            if (!stream || !stream->next_line(&val)) {
+                exhausted = true;
+                break;
+            }
+        } else {
+            if (idx == arguments.size()) {
+                exhausted = true;
+                break;
+            }
+            val = std::move(arguments[idx++]);
+        }
 
         int retval = parser->set_var_and_fire(for_var_name, ENV_DEFAULT | ENV_USER, val);
         assert(retval == ENV_OK && "for loop variable should have been successfully set");
@@ -493,6 +579,15 @@ end_execution_reason_t parse_execution_context_t::run_for_statement(
         }
     }
 
+    if (stream) {
+        // Stop the command if we didn't use up all of its output. As with a buffered command
+        // substitution, its status is only visible if the loop body didn't set one.
+        maybe_t<statuses_t> statuses = stream->finish(*parser, !exhausted);
+        if (statuses && parser->libdata().status_count == status_count) {
+            parser->set_last_statuses(statuses.acquire());
+        }
+    }
+
     parser->pop_block(fb);
     trace_if_enabled(*parser, L"end for");
     return ret;
@@ -555,7 +650,7 @@ end_execution_reason_t parse_execution_context_t::run_switch_statement(
         ast_args_list_t arg_nodes = get_argument_nodes(case_item.arguments);
         wcstring_list_t case_args;
         end_execution_reason_t case_result =
-            this->expand_arguments_from_nodes(arg_nodes, &case_args, failglob);
+            this->expand_arguments_from_nodes(arg_nodes, &case_args, failglob, L"case");
         if (case_result == end_execution_reason_t::ok) {
             for (const wcstring &arg : case_args) {
                 // Unescape wildcards so they can be expanded again.
@@ -739,7 +834,7 @@ end_execution_reason_t parse_execution_context_t::handle_command_not_found(
         {
             ast_args_list_t args = get_argument_nodes(statement.args_or_redirs);
             end_execution_reason_t arg_result =
-                this->expand_arguments_from_nodes(args, &event_args, failglob);
+                this->expand_arguments_from_nodes(args, &event_args, failglob, cmd_str);
 
             if (arg_result != end_execution_reason_t::ok) {
                 return arg_result;
@@ -895,7 +990,7 @@ end_execution_reason_t parse_execution_context_t::populate_plain_process(
 
         ast_args_list_t arg_nodes = get_argument_nodes(statement.args_or_redirs);
         end_execution_reason_t arg_result =
-            this->expand_arguments_from_nodes(arg_nodes, &cmd_args, glob_behavior);
+            this->expand_arguments_from_nodes(arg_nodes, &cmd_args, glob_behavior, cmd);
         if (arg_result != end_execution_reason_t::ok) {
             return arg_result;
         }
@@ -918,11 +1013,16 @@ end_execution_reason_t parse_execution_context_t::populate_plain_process(
     return end_execution_reason_t::ok;
 }
 
+bool parse_execution_context_t::null_glob_requested() const {
+    auto var = parser->vars().get(L"fish_null_glob");
+    return !var.missing_or_empty() && var->as_string() != L"0";
+}
+
 // Determine the list of arguments, expanding stuff. Reports any errors caused by expansion. If we
 // have a wildcard that could not be expanded, report the error and continue.
 end_execution_reason_t parse_execution_context_t::expand_arguments_from_nodes(
     const ast_args_list_t &argument_nodes, wcstring_list_t *out_arguments,
-    globspec_t glob_behavior) {
+    globspec_t glob_behavior, const wcstring &cmd) {
     // Get all argument nodes underneath the statement. We guess we'll have that many arguments (but
     // may have more or fewer, if there are wildcards involved).
     out_arguments->reserve(out_arguments->size() + argument_nodes.size());
@@ -946,12 +1046,12 @@ end_execution_reason_t parse_execution_context_t::expand_arguments_from_nodes(
                 return end_execution_reason_t::cancelled;
             }
             case expand_result_t::wildcard_no_match: {
-                if (glob_behavior == failglob) {
+                if (glob_behavior == failglob && !null_glob_requested()) {
                     // For no_exec, ignore the error - this might work at runtime.
                     if (no_exec()) return end_execution_reason_t::ok;
                     // Report the unmatched wildcard error and stop processing.
-                    return report_error(STATUS_UNMATCHED_WILDCARD, *arg_node, WILDCARD_ERR_MSG,
-                                        get_source(*arg_node).c_str());
+                    return report_error(STATUS_UNMATCHED_WILDCARD, *arg_node, WILDCARD_ARG_ERR_MSG,
+                                        cmd.c_str(), get_source(*arg_node).c_str());
                 }
                 break;
             }
@@ -1214,6 +1314,16 @@ end_execution_reason_t parse_execution_context_t::populate_job_from_job_node(
         // Link up the processes.
         assert(!processes.empty());  //!OCLINT(multiple unary operator)
         j->processes = std::move(processes);
+    } else if (result == end_execution_reason_t::error) {
+        // Nothing in the job gets launched. Like for processes aborted in exec_job, count all other
+        // stages as failed, so $pipestatus still has one entry per stage.
+        size_t stage_count = 1 + job_node.continuation.count();
+        if (stage_count > 1) {
+            statuses_t statuses = parser->get_last_statuses();
+            statuses.pipestatus.assign(stage_count, EXIT_FAILURE);
+            statuses.pipestatus.at(processes.size() - 1) = statuses.status;
+            parser->set_last_statuses(std::move(statuses));
+        }
     }
     return result;
 }
@@ -1351,6 +1461,8 @@ end_execution_reason_t parse_execution_context_t::run_1_job(const ast::job_t &jo
     props.from_event_handler = ld.is_event;
     props.job_control = wants_job_control;
     props.wants_timing = job_node_wants_timing(job_node);
+    props.ephemeral = ld.is_ephemeral_command;
+    props.hidden = ld.is_hidden_job;
 
     // It's an error to have 'time' in a background job.
     if (props.wants_timing && props.initial_background) {
@@ -1412,6 +1524,17 @@ end_execution_reason_t parse_execution_context_t::run_1_job(const ast::job_t &jo
         profile_item->level = parser->eval_level;
         profile_item->cmd = job ? job->command() : wcstring();
         profile_item->skipped = (pop_result != end_execution_reason_t::ok);
+        if (job) {
+            for (const process_ptr_t &p : job->processes) {
+                wcstring_list_t *names = nullptr;
+                if (p->type == process_type_t::function) {
+                    names = &profile_item->functions;
+                } else if (p->type == process_type_t::external) {
+                    names = &profile_item->external_commands;
+                }
+                if (names && !contains(*names, p->argv0())) names->push_back(p->argv0());
+            }
+        }
     }
 
     job_reap(*parser, false);  // clean up jobs
@@ -1479,9 +1602,11 @@ end_execution_reason_t parse_execution_context_t::test_and_run_1_job_conjunction
     // Skipping is treated as success.
     if (skip) {
         return end_execution_reason_t::ok;
-    } else {
-        return this->run_job_conjunction(jc, associated_block);
     }
+    end_execution_reason_t result = this->run_job_conjunction(jc, associated_block);
+    // The command is done, so this is a safe point to run handlers for `emit --async`.
+    event_fire_queued(*parser);
+    return result;
 }
 
 end_execution_reason_t parse_execution_context_t::run_job_list(const ast::job_list_t &job_list_node,
@@ -127,7 +127,11 @@ class parse_execution_context_t {
 
     end_execution_reason_t expand_arguments_from_nodes(const ast_args_list_t &argument_nodes,
                                                        wcstring_list_t *out_arguments,
-                                                       globspec_t glob_behavior);
+                                                       globspec_t glob_behavior,
+                                                       const wcstring &cmd);
+
+    // Whether $fish_null_glob asks for unmatched wildcards to expand to nothing.
+    bool null_glob_requested() const;
 
     // Determines the list of redirections for a node.
     end_execution_reason_t determine_redirections(const ast::argument_or_redirection_list_t &list,
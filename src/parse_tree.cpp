@@ -30,6 +30,7 @@ parse_error_code_t parse_error_from_tokenizer_error(tokenizer_error_t err) {
         case tokenizer_error_t::unterminated_quote:
             return parse_error_tokenizer_unterminated_quote;
         case tokenizer_error_t::unterminated_subshell:
+        case tokenizer_error_t::unterminated_dollar_subshell:
             return parse_error_tokenizer_unterminated_subshell;
         case tokenizer_error_t::unterminated_slice:
             return parse_error_tokenizer_unterminated_slice;
@@ -104,32 +104,70 @@ size_t parse_util_get_offset(const wcstring &str, int line, long line_offset) {
     return off + line_offset;
 }
 
+/// \param inout_is_quoted On input, whether \p in starts inside a double quoted string, just after
+/// a command substitution which interrupted it. On output, whether the located command substitution
+/// is inside double quotes, like "$(cmd)".
+/// \param out_has_dollar On output, whether the located bracket is preceded by an unescaped '$'.
 static int parse_util_locate_brackets_of_type(const wchar_t *in, wchar_t **begin, wchar_t **end,
                                               bool allow_incomplete, wchar_t open_type,
-                                              wchar_t close_type) {
+                                              wchar_t close_type, bool *inout_is_quoted = nullptr,
+                                              bool *out_has_dollar = nullptr) {
     // open_type is typically ( or [, and close type is the corresponding value.
     wchar_t *pos;
     bool escaped = false;
     bool syntax_error = false;
     int paran_count = 0;
+    bool is_quoted = false;
+    bool has_dollar = false;
+    const wchar_t *last_dollar = nullptr;
 
     wchar_t *paran_begin = nullptr, *paran_end = nullptr;
 
     assert(in && "null parameter");
 
-    for (pos = const_cast<wchar_t *>(in); *pos; pos++) {
+    // Return the end of the quoted string which starts after q, or null if it is unterminated. A
+    // command substitution inside double quotes is skipped, unless it is the one we are looking
+    // for, in which case this returns the position of its '$'.
+    auto skip_quoted = [&](const wchar_t *q, wchar_t quote) -> wchar_t * {
+        wchar_t *q_end = quote_end(q, quote);
+        while (q_end && *q_end == L'$') {
+            if (open_type == L'(' && paran_count == 0) {
+                is_quoted = true;
+                last_dollar = q_end;
+                break;
+            }
+            wchar_t *sub_end = nullptr;
+            if (parse_util_locate_brackets_of_type(q_end + 1, nullptr, &sub_end, false, L'(',
+                                                   L')') <= 0) {
+                return nullptr;
+            }
+            q_end = quote_end(sub_end, L'"');
+        }
+        return q_end;
+    };
+
+    pos = const_cast<wchar_t *>(in);
+    if (inout_is_quoted && *inout_is_quoted && *pos) {
+        // Resume the double quoted string. The character before us closed a command substitution.
+        pos = skip_quoted(pos - 1, L'"');
+    }
+
+    for (; pos && *pos; pos++) {
         if (!escaped) {
-            if (std::wcschr(L"'\"", *pos)) {
-                wchar_t *q_end = quote_end(pos);
+            if (*pos == L'\'' || *pos == L'"') {
+                wchar_t *q_end = skip_quoted(pos, *pos);
                 if (q_end && *q_end) {
                     pos = q_end;
                 } else {
                     break;
                 }
+            } else if (*pos == L'$') {
+                last_dollar = pos;
             } else {
                 if (*pos == open_type) {
                     if ((paran_count == 0) && (paran_begin == nullptr)) {
                         paran_begin = pos;
+                        has_dollar = last_dollar && last_dollar == pos - 1;
                     }
 
                     paran_count++;
@@ -174,6 +212,8 @@ static int parse_util_locate_brackets_of_type(const wchar_t *in, wchar_t **begin
         *end = paran_count ? const_cast<wchar_t *>(in) + std::wcslen(in) : paran_end;
     }
 
+    if (inout_is_quoted) *inout_is_quoted = is_quoted;
+    if (out_has_dollar) *out_has_dollar = has_dollar;
     return 1;
 }
 
@@ -185,7 +225,8 @@ int parse_util_locate_slice(const wchar_t *in, wchar_t **begin, wchar_t **end,
 static int parse_util_locate_brackets_range(const wcstring &str, size_t *inout_cursor_offset,
                                             wcstring *out_contents, size_t *out_start,
                                             size_t *out_end, bool accept_incomplete,
-                                            wchar_t open_type, wchar_t close_type) {
+                                            wchar_t open_type, wchar_t close_type,
+                                            bool *inout_is_quoted, bool *out_has_dollar) {
     // Clear the return values.
     if (out_contents != nullptr) out_contents->clear();
     *out_start = 0;
@@ -201,7 +242,7 @@ static int parse_util_locate_brackets_range(const wcstring &str, size_t *inout_c
     wchar_t *bracket_range_begin = nullptr, *bracket_range_end = nullptr;
     int ret = parse_util_locate_brackets_of_type(valid_range_start, &bracket_range_begin,
                                                  &bracket_range_end, accept_incomplete, open_type,
-                                                 close_type);
+                                                 close_type, inout_is_quoted, out_has_dollar);
     if (ret <= 0) {
         return ret;
     }
@@ -231,9 +272,11 @@ static int parse_util_locate_brackets_range(const wcstring &str, size_t *inout_c
 
 int parse_util_locate_cmdsubst_range(const wcstring &str, size_t *inout_cursor_offset,
                                      wcstring *out_contents, size_t *out_start, size_t *out_end,
-                                     bool accept_incomplete) {
+                                     bool accept_incomplete, bool *inout_is_quoted,
+                                     bool *out_has_dollar) {
     return parse_util_locate_brackets_range(str, inout_cursor_offset, out_contents, out_start,
-                                            out_end, accept_incomplete, L'(', L')');
+                                            out_end, accept_incomplete, L'(', L')',
+                                            inout_is_quoted, out_has_dollar);
 }
 
 void parse_util_cmdsubst_extent(const wchar_t *buff, size_t cursor_pos, const wchar_t **a,
@@ -247,9 +290,11 @@ void parse_util_cmdsubst_extent(const wchar_t *buff, size_t cursor_pos, const wc
     // ap and bp are the beginning and end of the tightest command substitution found so far.
     const wchar_t *ap = buff, *bp = buff + bufflen;
     const wchar_t *pos = buff;
+    bool is_quoted = false;
     for (;;) {
         wchar_t *begin = nullptr, *end = nullptr;
-        if (parse_util_locate_brackets_of_type(pos, &begin, &end, true, L'(', L')') <= 0) {
+        if (parse_util_locate_brackets_of_type(pos, &begin, &end, true, L'(', L')', &is_quoted) <=
+            0) {
             // No subshell found, all done.
             break;
         }
@@ -267,6 +312,7 @@ void parse_util_cmdsubst_extent(const wchar_t *buff, size_t cursor_pos, const wc
             // next one.
             if (begin >= end) break;
             pos = begin + 1;
+            is_quoted = false;
         } else if (begin >= cursor) {
             // This command substitution starts at or after the cursor. Since it was the first
             // command substitution in the string, we're done.
@@ -465,7 +511,17 @@ static wchar_t get_quote(const wcstring &cmd_str, size_t len) {
             i++;
         } else {
             if (cmd[i] == L'\'' || cmd[i] == L'\"') {
-                const wchar_t *end = quote_end(&cmd[i]);
+                const wchar_t *end = quote_end(&cmd[i], cmd[i]);
+                // Skip command substitutions inside double quotes, unless they contain the cursor.
+                while (end && *end == L'$' && end < cmd + len) {
+                    wchar_t *sub_end = nullptr;
+                    if (parse_util_locate_brackets_of_type(end + 1, nullptr, &sub_end, false, L'(',
+                                                           L')') <= 0 ||
+                        sub_end >= cmd + len) {
+                        return 0;
+                    }
+                    end = quote_end(sub_end, L'"');
+                }
                 // std::fwprintf( stderr, L"Jump %d\n",  end-cmd );
                 if ((end == nullptr) || (!*end) || (end > cmd + len)) {
                     res = cmd[i];
@@ -888,26 +944,6 @@ void parse_util_expand_variable_error(const wcstring &token, size_t global_token
             append_syntax_error(errors, global_dollar_pos, ERROR_NO_VAR_NAME);
             break;
         }
-        case '(': {
-            // e.g.: 'echo "foo$(bar)baz"
-            // Try to determine what's in the parens.
-            wcstring token_after_parens;
-            wcstring paren_text;
-            size_t open_parens = dollar_pos + 1, cmdsub_start = 0, cmdsub_end = 0;
-            if (parse_util_locate_cmdsubst_range(token, &open_parens, &paren_text, &cmdsub_start,
-                                                 &cmdsub_end, true) > 0) {
-                token_after_parens = tok_first(paren_text);
-            }
-
-            // Make sure we always show something.
-            if (token_after_parens.empty()) {
-                token_after_parens = get_ellipsis_str();
-            }
-
-            append_syntax_error(errors, global_dollar_pos, ERROR_BAD_VAR_SUBCOMMAND1,
-                                truncate(token_after_parens, var_err_len).c_str());
-            break;
-        }
         case L'\0': {
             append_syntax_error(errors, global_dollar_pos, ERROR_NO_VAR_NAME);
             break;
@@ -933,39 +969,6 @@ void parse_util_expand_variable_error(const wcstring &token, size_t global_token
     assert(errors->size() == start_error_count + 1);
 }
 
-/// Detect cases like $(abc). Given an arg like foo(bar), let arg_src be foo and cmdsubst_src be
-/// bar. If arg ends with VARIABLE_EXPAND, then report an error.
-static parser_test_error_bits_t detect_dollar_cmdsub_errors(size_t arg_src_offset,
-                                                            const wcstring &arg_src,
-                                                            const wcstring &cmdsubst_src,
-                                                            parse_error_list_t *out_errors) {
-    parser_test_error_bits_t result_bits = 0;
-    wcstring unescaped_arg_src;
-
-    if (!unescape_string(arg_src, &unescaped_arg_src, UNESCAPE_SPECIAL) ||
-        unescaped_arg_src.empty()) {
-        return result_bits;
-    }
-
-    wchar_t last = unescaped_arg_src.at(unescaped_arg_src.size() - 1);
-    if (last == VARIABLE_EXPAND) {
-        result_bits |= PARSER_TEST_ERROR;
-        if (out_errors != nullptr) {
-            wcstring subcommand_first_token = tok_first(cmdsubst_src);
-            if (subcommand_first_token.empty()) {
-                // e.g. $(). Report somthing.
-                subcommand_first_token = get_ellipsis_str();
-            }
-            append_syntax_error(
-                out_errors,
-                arg_src_offset + arg_src.size() - 1,  // global position of the dollar
-                ERROR_BAD_VAR_SUBCOMMAND1, truncate(subcommand_first_token, var_err_len).c_str());
-        }
-    }
-
-    return result_bits;
-}
-
 /// Test if this argument contains any errors. Detected errors include syntax errors in command
 /// substitutions, improperly escaped characters and improper use of the variable expansion
 /// operator.
@@ -980,13 +983,20 @@ parser_test_error_bits_t parse_util_detect_errors_in_argument(const ast::argumen
 
     size_t cursor = 0;
     wcstring subst;
+    bool is_quoted = false;
+
+    // The argument with its command substitutions (including the $ of any $(...)) cut out, so the
+    // checks below only see the surrounding text.
+    wcstring arg_src_outside_cmdsubs;
+    size_t outside_begin = 0;
 
     bool do_loop = true;
     while (do_loop) {
         size_t paren_begin = 0;
         size_t paren_end = 0;
+        bool has_dollar = false;
         switch (parse_util_locate_cmdsubst_range(arg_src, &cursor, &subst, &paren_begin, &paren_end,
-                                                 false)) {
+                                                 false, &is_quoted, &has_dollar)) {
             case -1: {
                 err |= PARSER_TEST_ERROR;
                 if (out_errors) {
@@ -1011,16 +1021,12 @@ parser_test_error_bits_t parse_util_detect_errors_in_argument(const ast::argumen
 
                 if (out_errors != nullptr) {
                     out_errors->insert(out_errors->end(), subst_errors.begin(), subst_errors.end());
-
-                    // Hackish. Take this opportunity to report $(...) errors. We do this because
-                    // after we've replaced with internal separators, we can't distinguish between
-                    // "" and (), and also we no longer have the source of the command substitution.
-                    // As an optimization, this is only necessary if the last character is a $.
-                    if (paren_begin > 0 && arg_src.at(paren_begin - 1) == L'$') {
-                        err |= detect_dollar_cmdsub_errors(
-                            source_start, arg_src.substr(0, paren_begin), subst, out_errors);
-                    }
                 }
+
+                size_t cmdsub_begin = paren_begin - (has_dollar ? 1 : 0);
+                arg_src_outside_cmdsubs.append(arg_src, outside_begin,
+                                               cmdsub_begin - outside_begin);
+                outside_begin = paren_end + 1;
                 break;
             }
             default: {
@@ -1029,8 +1035,10 @@ parser_test_error_bits_t parse_util_detect_errors_in_argument(const ast::argumen
         }
     }
 
+    arg_src_outside_cmdsubs.append(arg_src, outside_begin, wcstring::npos);
+
     wcstring unesc;
-    if (!unescape_string(arg_src, &unesc, UNESCAPE_SPECIAL)) {
+    if (!unescape_string(arg_src_outside_cmdsubs, &unesc, UNESCAPE_SPECIAL)) {
         if (out_errors) {
             append_syntax_error(out_errors, source_start, L"Invalid token '%ls'", arg_src.c_str());
         }
@@ -28,10 +28,15 @@ int parse_util_locate_slice(const wchar_t *in, wchar_t **begin, wchar_t **end,
 /// \param out_end On output, the offset of the end of the command substitution (close paren), or
 /// the end of the string if it was incomplete
 /// \param accept_incomplete whether to permit missing closing parenthesis
+/// \param inout_is_quoted On input, whether the search begins inside double quotes, just after a
+/// command substitution like "foo$(bar)". On output, whether the command substitution found is
+/// inside double quotes. Pass the same variable when iterating.
+/// \param out_has_dollar On output, whether the command substitution is written as $(...).
 /// \return -1 on syntax error, 0 if no subshells exist and 1 on success
 int parse_util_locate_cmdsubst_range(const wcstring &str, size_t *inout_cursor_offset,
                                      wcstring *out_contents, size_t *out_start, size_t *out_end,
-                                     bool accept_incomplete);
+                                     bool accept_incomplete, bool *inout_is_quoted = nullptr,
+                                     bool *out_has_dollar = nullptr);
 
 /// Find the beginning and end of the command substitution under the cursor. If no subshell is
 /// found, the entire string is returned. If the current command substitution is not ended, i.e. the
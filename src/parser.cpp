@@ -8,6 +8,7 @@
 
 #include <algorithm>
 #include <cwchar>
+#include <map>
 #include <memory>
 #include <utility>
 
@@ -17,6 +18,7 @@
 #include "event.h"
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "flog.h"
 #include "function.h"
 #include "intern.h"
@@ -28,6 +30,7 @@
 #include "reader.h"
 #include "sanity.h"
 #include "signal.h"
+#include "wcstringutil.h"
 #include "wutil.h"  // IWYU pragma: keep
 
 class io_chain_t;
@@ -89,7 +92,8 @@ parser_t::parser_t(std::shared_ptr<env_stack_t> vars) : variables(std::move(vars
         perror("Unable to open the current working directory");
         return;
     }
-    libdata().cwd_fd = std::make_shared<const autoclose_fd_t>(cwd);
+    libdata().cwd_fd =
+        std::make_shared<const autoclose_fd_t>(heightenize_fd(autoclose_fd_t{cwd}, true));
 }
 
 parser_t::parser_t() : parser_t(env_stack_t::principal_ref()) {}
@@ -231,6 +235,24 @@ block_t *parser_t::block_at_index(size_t idx) {
 
 block_t *parser_t::current_block() { return block_at_index(0); }
 
+/// Compute the self time of the item at \p idx as its total time, minus the total time consumed by
+/// subsequent items exactly one eval level deeper.
+static long long profile_self_time(const std::deque<profile_item_t> &items, size_t idx) {
+    const profile_item_t &item = items.at(idx);
+    long long self_time = item.duration;
+    for (size_t i = idx + 1; i < items.size(); i++) {
+        const profile_item_t &nested_item = items.at(i);
+        if (nested_item.skipped) continue;
+
+        // If the eval level is not larger, then we have exhausted nested items.
+        if (nested_item.level <= item.level) break;
+
+        // If the eval level is exactly one more than our level, it is a directly nested item.
+        if (nested_item.level == item.level + 1) self_time -= nested_item.duration;
+    }
+    return self_time;
+}
+
 /// Print profiling information to the specified stream.
 static void print_profile(const std::deque<profile_item_t> &items, FILE *out) {
     for (size_t idx = 0; idx < items.size(); idx++) {
@@ -238,20 +260,7 @@ static void print_profile(const std::deque<profile_item_t> &items, FILE *out) {
         if (item.skipped || item.cmd.empty()) continue;
 
         long long total_time = item.duration;
-
-        // Compute the self time as the total time, minus the total time consumed by subsequent
-        // items exactly one eval level deeper.
-        long long self_time = item.duration;
-        for (size_t i = idx + 1; i < items.size(); i++) {
-            const profile_item_t &nested_item = items.at(i);
-            if (nested_item.skipped) continue;
-
-            // If the eval level is not larger, then we have exhausted nested items.
-            if (nested_item.level <= item.level) break;
-
-            // If the eval level is exactly one more than our level, it is a directly nested item.
-            if (nested_item.level == item.level + 1) self_time -= nested_item.duration;
-        }
+        long long self_time = profile_self_time(items, idx);
 
         if (std::fwprintf(out, L"%lld\t%lld\t", self_time, total_time) < 0) {
             wperror(L"fwprintf");
@@ -272,6 +281,94 @@ static void print_profile(const std::deque<profile_item_t> &items, FILE *out) {
     }
 }
 
+namespace {
+/// Aggregated profiling times of one function or external command.
+struct profile_total_t {
+    long long self_time{0};
+    long long total_time{0};
+    size_t calls{0};
+};
+using profile_totals_t = std::map<wcstring, profile_total_t>;
+}  // namespace
+
+/// Print one section of the profile summary, sorted by total time.
+static bool print_profile_totals(const profile_totals_t &totals, const wchar_t *header, FILE *out) {
+    if (totals.empty()) return true;
+
+    using total_pair_t = std::pair<wcstring, profile_total_t>;
+    std::vector<total_pair_t> sorted(totals.begin(), totals.end());
+    std::stable_sort(sorted.begin(), sorted.end(),
+                     [](const total_pair_t &a, const total_pair_t &b) {
+                         return a.second.total_time > b.second.total_time;
+                     });
+
+    if (std::fwprintf(out, L"\n%ls", header) < 0) return false;
+    for (const auto &kv : sorted) {
+        if (std::fwprintf(out, L"%lld\t%lld\t%lu\t%ls\n", kv.second.self_time,
+                          kv.second.total_time, static_cast<unsigned long>(kv.second.calls),
+                          kv.first.c_str()) < 0) {
+            return false;
+        }
+    }
+    return true;
+}
+
+/// Print the times per function and per external command to the specified stream.
+/// The self time of every item goes to the innermost function it runs in, or that it calls.
+/// A call nested in a call of the same name does not add to the total time again, so recursion is
+/// not counted twice.
+static void print_profile_summary(const std::deque<profile_item_t> &items, FILE *out) {
+    profile_totals_t functions;
+    profile_totals_t commands;
+
+    // The indexes of the items enclosing the current one, outermost first.
+    std::vector<size_t> enclosing;
+    auto is_recursive = [&](const wcstring &name, wcstring_list_t profile_item_t::*names) {
+        for (size_t i : enclosing) {
+            if (contains(items.at(i).*names, name)) return true;
+        }
+        return false;
+    };
+
+    for (size_t idx = 0; idx < items.size(); idx++) {
+        const profile_item_t &item = items.at(idx);
+        if (item.skipped || item.cmd.empty()) continue;
+
+        while (!enclosing.empty() && items.at(enclosing.back()).level >= item.level) {
+            enclosing.pop_back();
+        }
+        long long self_time = profile_self_time(items, idx);
+
+        const profile_item_t *owner = item.functions.empty() ? nullptr : &item;
+        for (auto it = enclosing.rbegin(); !owner && it != enclosing.rend(); ++it) {
+            if (!items.at(*it).functions.empty()) owner = &items.at(*it);
+        }
+        if (owner) {
+            for (const wcstring &name : owner->functions) functions[name].self_time += self_time;
+        }
+
+        for (const wcstring &name : item.functions) {
+            profile_total_t &total = functions[name];
+            total.calls++;
+            if (!is_recursive(name, &profile_item_t::functions)) total.total_time += item.duration;
+        }
+        for (const wcstring &name : item.external_commands) {
+            profile_total_t &total = commands[name];
+            total.calls++;
+            total.self_time += self_time;
+            if (!is_recursive(name, &profile_item_t::external_commands)) {
+                total.total_time += item.duration;
+            }
+        }
+        enclosing.push_back(idx);
+    }
+
+    if (!print_profile_totals(functions, _(L"Time\tSum\tCalls\tFunction\n"), out) ||
+        !print_profile_totals(commands, _(L"Time\tSum\tCalls\tExternal command\n"), out)) {
+        wperror(L"fwprintf");
+    }
+}
+
 void parser_t::clear_profiling() { profile_items.clear(); }
 
 void parser_t::emit_profiling(const char *path) const {
@@ -285,6 +382,7 @@ void parser_t::emit_profiling(const char *path) const {
             wperror(L"fwprintf");
         } else {
             print_profile(profile_items, f);
+            print_profile_summary(profile_items, f);
         }
 
         if (fclose(f)) {
@@ -412,6 +510,75 @@ wcstring parser_t::stack_trace() const {
     return trace;
 }
 
+/// Append a JSON object describing the block \p b to \p frames, if it appears in stack traces.
+static void append_block_frame_json(const parser_t &parser, const block_t &b,
+                                    wcstring_list_t &frames) {
+    wcstring frame;
+    switch (b.type()) {
+        case block_type_t::function_call:
+        case block_type_t::function_call_no_shadow: {
+            frame = L"{\"type\": \"function\", \"name\": " + json_quote(b.function_name);
+            frame.append(L", \"arguments\": [");
+            for (size_t i = 0; i < b.function_args.size(); i++) {
+                if (i > 0) frame.append(L", ");
+                frame.append(json_quote(b.function_args.at(i)));
+            }
+            frame.push_back(L']');
+            break;
+        }
+        case block_type_t::subst: {
+            frame = L"{\"type\": \"command-substitution\"";
+            break;
+        }
+        case block_type_t::source: {
+            frame = L"{\"type\": \"source\", \"source\": " + json_quote(b.sourced_file);
+            break;
+        }
+        case block_type_t::event: {
+            assert(b.event && "Should have an event");
+            frame = L"{\"type\": \"event\", \"event\": " +
+                    json_quote(event_get_desc(parser, *b.event));
+            break;
+        }
+
+        case block_type_t::top:
+        case block_type_t::begin:
+        case block_type_t::switch_block:
+        case block_type_t::while_block:
+        case block_type_t::for_block:
+        case block_type_t::if_block:
+        case block_type_t::breakpoint:
+        case block_type_t::variable_assignment:
+            return;
+    }
+
+    // Where the block was entered, if we know.
+    if (b.src_filename) {
+        frame.append(L", \"file\": " + json_quote(b.src_filename));
+        append_format(frame, L", \"line\": %d", b.src_lineno);
+    }
+    frame.push_back(L'}');
+    frames.push_back(std::move(frame));
+}
+
+wcstring parser_t::stack_trace_json() const {
+    wcstring_list_t frames;
+    for (const auto &b : blocks()) {
+        append_block_frame_json(*this, b, frames);
+        // Stop at event handler, like stack_trace().
+        if (b.type() == block_type_t::event) break;
+    }
+    if (frames.empty()) return L"[]\n";
+    wcstring result = L"[\n";
+    for (size_t i = 0; i < frames.size(); i++) {
+        result.append(L"  ");
+        result.append(frames.at(i));
+        result.append(i + 1 < frames.size() ? L",\n" : L"\n");
+    }
+    result.append(L"]\n");
+    return result;
+}
+
 /// Returns the name of the currently evaluated function if we are currently evaluating a function,
 /// NULL otherwise. This is tested by moving down the block-scope-stack, checking every block if it
 /// is of type FUNCTION_CALL. If the caller doesn't specify a starting position in the stack we
@@ -568,6 +735,7 @@ void parser_t::job_promote(job_t *job) {
 
 const job_t *parser_t::job_with_id(job_id_t id) const {
     for (const auto &job : job_list) {
+        if (job->is_hidden()) continue;
         if (id <= 0 || job->job_id() == id) return job.get();
     }
     return nullptr;
@@ -582,6 +750,7 @@ const job_t *parser_t::job_with_internal_id(internal_job_id_t id) const {
 
 job_t *parser_t::job_get_from_pid(pid_t pid) const {
     for (const auto &job : jobs()) {
+        if (job->is_hidden()) continue;
         for (const process_ptr_t &p : job->processes) {
             if (p->pid == pid) {
                 return job.get();
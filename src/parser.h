@@ -130,12 +130,17 @@ struct profile_item_t {
     /// The command string.
     wcstring cmd{};
 
+    /// The names of the functions and external commands run by this job, for the summary.
+    wcstring_list_t functions{};
+    wcstring_list_t external_commands{};
+
     /// \return the current time as a microsecond timestamp since the epoch.
     static microseconds_t now() { return get_time(); }
 };
 
 class parse_execution_context_t;
 class completion_t;
+class completion_arguments_cache_t;
 struct event_t;
 
 /// Miscellaneous data used to avoid recursion and others.
@@ -182,6 +187,14 @@ struct library_data_t {
     /// Whether we are currently interactive.
     bool is_interactive{false};
 
+    /// Whether the commandline being run starts with a space, which keeps it out of the history
+    /// and the job journal.
+    bool is_ephemeral_command{false};
+
+    /// Whether the jobs being created are run by fish for its own purposes, like the command of a
+    /// streamed command substitution, and so are hidden from the job builtins.
+    bool is_hidden_job{false};
+
     /// Whether to suppress fish_trace output. This occurs in the prompt, event handlers, and key
     /// bindings.
     bool suppress_fish_trace{false};
@@ -210,11 +223,17 @@ struct library_data_t {
     /// List of events that have been sent but have not yet been delivered because they are blocked.
     std::vector<shared_ptr<const event_t>> blocked_events{};
 
+    /// List of events from `emit --async`, which are delivered once the current command is done.
+    std::vector<shared_ptr<const event_t>> queued_events{};
+
     /// A stack of fake values to be returned by builtin_commandline. This is used by the completion
     /// machinery when wrapping: e.g. if `tig` wraps `git` then git completions need to see git on
     /// the command line.
     wcstring_list_t transient_commandlines{};
 
+    /// Completion argument lists expanded by earlier completion requests, see complete.cpp.
+    std::shared_ptr<completion_arguments_cache_t> completion_arguments_cache{};
+
     /// A file descriptor holding the current working directory, for use in openat().
     /// This is never null and never invalid.
     std::shared_ptr<const autoclose_fd_t> cwd_fd{};
@@ -400,12 +419,13 @@ class parser_t : public std::enable_shared_from_this<parser_t> {
     void job_promote(job_t *job);
 
     /// Return the job with the specified job id. If id is 0 or less, return the last job used.
+    /// Hidden jobs are skipped.
     const job_t *job_with_id(job_id_t job_id) const;
 
     /// Return the job with the specified internal job id.
     const job_t *job_with_internal_id(internal_job_id_t job_id) const;
 
-    /// Returns the job with the given pid.
+    /// Returns the job with the given pid. Hidden jobs are skipped.
     job_t *job_get_from_pid(pid_t pid) const;
 
     /// Returns a new profile item if profiling is active. The caller should fill it in.
@@ -434,6 +454,9 @@ class parser_t : public std::enable_shared_from_this<parser_t> {
     /// Return a string representing the current stack trace.
     wcstring stack_trace() const;
 
+    /// Return the current stack trace as a JSON array of frames, innermost first.
+    wcstring stack_trace_json() const;
+
     /// \return whether the number of functions in the stack exceeds our stack depth limit.
     bool function_stack_is_overflowing() const;
 
@@ -19,6 +19,8 @@
 #include <memory>
 #include <string>
 #include <type_traits>
+#include <unordered_map>
+#include <unordered_set>
 #include <vector>
 
 #include "common.h"
@@ -94,6 +96,42 @@ bool path_get_path(const wcstring &cmd, wcstring *out_path, const environment_t
     return path_get_path_core(cmd, out_path, vars.get(L"PATH"));
 }
 
+void path_check_dirs(const wcstring &var_name, const wcstring_list_t &dirs) {
+    if (!should_flog(path_check)) return;
+
+    // Only check again if the value actually changed, so that e.g. `set PATH $PATH` doesn't stat
+    // every directory again.
+    static owning_lock<std::unordered_map<wcstring, wcstring_list_t>> s_last_checked;
+    {
+        auto last_checked = s_last_checked.acquire();
+        auto iter = last_checked->find(var_name);
+        if (iter != last_checked->end() && iter->second == dirs) return;
+        (*last_checked)[var_name] = dirs;
+    }
+
+    std::unordered_set<wcstring> seen;
+    for (const wcstring &dir : dirs) {
+        if (!seen.insert(dir).second) {
+            FLOGF(path_check, L"$%ls entry '%ls' is a duplicate", var_name.c_str(), dir.c_str());
+            continue;
+        }
+        if (dir.empty()) {
+            FLOGF(path_check, L"$%ls has an empty entry", var_name.c_str());
+            continue;
+        }
+        if (dir.front() != L'/') {
+            FLOGF(path_check, L"$%ls entry '%ls' is relative", var_name.c_str(), dir.c_str());
+        }
+        struct stat buf;
+        if (wstat(dir, &buf) == -1) {
+            FLOGF(path_check, L"$%ls entry '%ls' does not exist", var_name.c_str(), dir.c_str());
+        } else if (!S_ISDIR(buf.st_mode)) {
+            FLOGF(path_check, L"$%ls entry '%ls' is not a directory", var_name.c_str(),
+                  dir.c_str());
+        }
+    }
+}
+
 bool path_is_executable(const std::string &path) {
     if (access(path.c_str(), X_OK)) return false;
     struct stat buff;
@@ -105,8 +143,7 @@ bool path_is_executable(const std::string &path) {
     return true;
 }
 
-/// \return 1 if the path is remote, 0 if local, -1 if unknown.
-static int path_is_remote(const wcstring &path) {
+int path_is_remote(const wcstring &path) {
     std::string narrow = wcs2string(path);
 #if defined(__linux__)
     struct statfs buf {};
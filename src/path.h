@@ -36,6 +36,9 @@ int path_get_data_is_remote();
 /// Like path_get_data_is_remote but for config directory.
 int path_get_config_is_remote();
 
+/// \return 1 if \p path is on a remote filesystem (eg. NFS), 0 if local, -1 if unknown.
+int path_is_remote(const wcstring &path);
+
 /// Emit any errors if config directories are missing.
 /// Use the given environment stack to ensure this only occurs once.
 class env_stack_t;
@@ -56,6 +59,11 @@ bool path_get_path(const wcstring &cmd, wcstring *out_path, const environment_t
 /// Return all the paths that match the given command.
 wcstring_list_t path_get_paths(const wcstring &cmd, const environment_t &vars);
 
+/// Check the directories of a PATH-like variable named \p var_name, and report duplicate entries,
+/// relative entries and entries which are not existing directories in the path-check debug
+/// category. The value is not modified. This does nothing unless that category is enabled.
+void path_check_dirs(const wcstring &var_name, const wcstring_list_t &dirs);
+
 /// Returns the full path of the specified directory, using the CDPATH variable as a list of base
 /// directories for relative paths.
 ///
@@ -116,9 +116,27 @@ int execute_setpgid(pid_t pid, pid_t pgroup, bool is_parent) {
     }
 }
 
-int child_setup_process(pid_t new_termowner, pid_t fish_pgrp, const job_t &job, bool is_forked,
-                        const dup2_list_t &dup2s) {
-    // Note we are called in a forked child.
+/// Report that applying the redirection \p act failed with \p err.
+/// This runs in a forked child, so it must only use async-signal-safe functions.
+static void safe_report_redirection_error(int err, const dup2_list_t::action_t &act) {
+    char src_buff[64], target_buff[64];
+    format_long_safe(src_buff, act.src);
+    format_long_safe(target_buff, act.target);
+    const char *reason = safe_strerror(err);
+    if (act.target < 0) {
+        debug_safe(0, "Could not close file descriptor %s: %s", src_buff, reason);
+    } else if (act.target != act.src) {
+        debug_safe(0, "Could not redirect file descriptor %s to %s: %s", target_buff, src_buff,
+                   reason);
+    } else {
+        debug_safe(0, "Could not pass on file descriptor %s: %s", src_buff, reason);
+    }
+}
+
+/// Apply the redirections in \p dup2s, in order.
+/// \return 0 on success, or the errno of the first redirection that failed, after reporting it if
+/// \p report is set.
+static int child_apply_dup2s(const dup2_list_t &dup2s, bool report) {
     for (const auto &act : dup2s.get_actions()) {
         int err;
         if (act.target < 0) {
@@ -133,13 +151,24 @@ int child_setup_process(pid_t new_termowner, pid_t fish_pgrp, const job_t &job,
             err = set_cloexec(act.src, false);
         }
         if (err < 0) {
-            if (is_forked) {
-                debug_safe(4, "redirect_in_child_after_fork failed in child_setup_process");
-                exit_without_destructors(1);
-            }
-            return err;
+            int saved_errno = errno;
+            if (report) safe_report_redirection_error(saved_errno, act);
+            return saved_errno;
         }
     }
+    return 0;
+}
+
+int child_setup_process(pid_t new_termowner, pid_t fish_pgrp, const job_t &job, bool is_forked,
+                        const dup2_list_t &dup2s) {
+    // Note we are called in a forked child.
+    if (int err = child_apply_dup2s(dup2s, is_forked)) {
+        // Don't return into the rest of fish from a forked child, where we may not even be able to
+        // report errors, since our stdio may be half redirected.
+        if (is_forked) exit_without_destructors(1);
+        errno = err;
+        return -1;
+    }
     if (new_termowner != INVALID_PID && new_termowner != fish_pgrp) {
         // Assign the terminal within the child to avoid the well-known race between tcsetgrp() in
         // the parent and the child executing. We are not interested in error handling here, except
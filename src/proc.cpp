@@ -34,7 +34,12 @@
 #include <sys/wait.h>
 
 #include <algorithm>  // IWYU pragma: keep
+#include <chrono>
+#include <condition_variable>
+#include <ctime>
+#include <functional>
 #include <memory>
+#include <mutex>
 #include <utility>
 #include <vector>
 
@@ -43,8 +48,11 @@
 #include "fallback.h"  // IWYU pragma: keep
 #include "flog.h"
 #include "global_safety.h"
+#include "history.h"
 #include "io.h"
+#include "iothread.h"
 #include "job_group.h"
+#include "job_journal.h"
 #include "output.h"
 #include "parse_tree.h"
 #include "parser.h"
@@ -52,6 +60,7 @@
 #include "reader.h"
 #include "sanity.h"
 #include "signal.h"
+#include "util.h"
 #include "wcstringutil.h"
 #include "wutil.h"  // IWYU pragma: keep
 
@@ -192,7 +201,7 @@ maybe_t<statuses_t> job_t::get_statuses() const {
 
 void internal_proc_t::mark_exited(proc_status_t status) {
     assert(!exited() && "Process is already exited");
-    status_.store(status, std::memory_order_relaxed);
+    status_ = status;
     exited_.store(true, std::memory_order_release);
     topic_monitor_t::principal().post(topic_t::internal_exit);
     FLOG(proc_internal_proc, L"Internal proc", internal_proc_id_, L"exited with status",
@@ -305,15 +314,21 @@ wait_handle_ref_t process_t::get_wait_handle(bool create) {
     return wait_handle_;
 }
 
-static uint64_t next_internal_job_id() {
-    static std::atomic<uint64_t> s_next{};
-    return ++s_next;
+static internal_job_id_t next_internal_job_id() {
+    static std::atomic<internal_job_id_t> s_next{};
+    // 0 means "no job" (e.g. a parser's caller_id), so skip it should the counter ever wrap.
+    internal_job_id_t id;
+    do {
+        id = ++s_next;
+    } while (id == 0);
+    return id;
 }
 
 job_t::job_t(const properties_t &props, wcstring command_str)
     : properties(props),
       command_str(std::move(command_str)),
-      internal_job_id(next_internal_job_id()) {}
+      internal_job_id(next_internal_job_id()),
+      start_time(get_time()) {}
 
 job_t::~job_t() = default;
 
@@ -384,7 +399,9 @@ static void reap_disowned_pids() {
 /// See if any reapable processes have exited, and mark them accordingly.
 /// \param block_ok if no reapable processes have exited, block until one is (or until we receive a
 /// signal).
-static void process_mark_finished_children(parser_t &parser, bool block_ok) {
+/// \param timeout_usec if blocking, the maximum time to block in microseconds.
+static void process_mark_finished_children(
+    parser_t &parser, bool block_ok, uint64_t timeout_usec = topic_monitor_t::kNoTimeout) {
     ASSERT_IS_MAIN_THREAD();
 
     // Get the exit and signal generations of all reapable processes.
@@ -410,7 +427,7 @@ static void process_mark_finished_children(parser_t &parser, bool block_ok) {
     }
 
     // Now check for changes, optionally waiting.
-    if (!topic_monitor_t::principal().check(&reapgens, block_ok)) {
+    if (!topic_monitor_t::principal().check(&reapgens, block_ok, timeout_usec)) {
         // Nothing changed.
         return;
     }
@@ -432,13 +449,23 @@ static void process_mark_finished_children(parser_t &parser, bool block_ok) {
             proc->gens_.sigchld = reapgens.sigchld;
 
             // Ok, we are reapable. Run waitpid()!
+            // If we can, use wait4() instead, to get the process's resource usage for free.
             int statusv = -1;
+#ifdef HAVE_WAIT4
+            struct rusage usage;
+            pid_t pid = wait4(proc->pid, &statusv, WNOHANG | WUNTRACED | WCONTINUED, &usage);
+#else
             pid_t pid = waitpid(proc->pid, &statusv, WNOHANG | WUNTRACED | WCONTINUED);
+#endif
             assert((pid <= 0 || pid == proc->pid) && "Unexpcted waitpid() return");
             if (pid <= 0) continue;
 
             // The process has stopped or exited! Update its status.
+#ifdef HAVE_WAIT4
+            proc_status_t status = proc_status_t::from_rusage_exit(statusv, usage);
+#else
             proc_status_t status = proc_status_t::from_waitpid(statusv);
+#endif
             handle_child_status(j, proc.get(), status);
             if (status.stopped()) {
                 j->group->set_is_foreground(false);
@@ -599,8 +626,8 @@ static bool job_wants_message(const shared_ptr<job_t> &j) {
 static void save_wait_handle_for_completed_job(const shared_ptr<job_t> &job,
                                                wait_handle_store_t &store) {
     assert(job && job->is_completed() && "Job null or not completed");
-    // Are we a background job?
-    if (!job->is_foreground()) {
+    // Are we a background job? Hidden jobs can't be waited on.
+    if (!job->is_foreground() && !job->is_hidden()) {
         for (auto &proc : job->processes) {
             store.add(proc->get_wait_handle(true));
         }
@@ -616,6 +643,23 @@ static void save_wait_handle_for_completed_job(const shared_ptr<job_t> &job,
     }
 }
 
+/// Record a completed job in the job journal, if the user ran it interactively.
+static void add_completed_job_to_journal(const parser_t &parser, const job_t &job) {
+    // This skips jobs in functions, blocks, command substitutions and non-interactive shells.
+    if (job.skip_notification() || job.is_ephemeral()) return;
+    // Like the history, we keep nothing in private mode or with an empty $fish_history.
+    if (in_private_mode(parser.vars())) return;
+    auto history_var = parser.vars().get(L"fish_history");
+    if (history_var && history_var->as_string().empty()) return;
+
+    job_journal_entry_t entry;
+    entry.timestamp = std::time(nullptr);
+    entry.command = job.command();
+    if (auto statuses = job.get_statuses()) entry.status = statuses->status;
+    entry.duration_ms = (get_time() - job.start_time) / 1000;
+    job_journal_append(entry);
+}
+
 /// Remove completed jobs from the job list, printing status messages as appropriate.
 /// \return whether something was printed.
 static bool process_clean_after_marking(parser_t &parser, bool allow_interactive) {
@@ -694,6 +738,7 @@ static bool process_clean_after_marking(parser_t &parser, bool allow_interactive
         if (should_process_job(j) && j->is_completed()) {
             // If this job finished in the background, we have to remember to wait on it.
             save_wait_handle_for_completed_job(j, parser.get_wait_handles());
+            add_completed_job_to_journal(parser, *j);
             iter = jobs.erase(iter);
         } else {
             ++iter;
@@ -920,6 +965,70 @@ int terminal_maybe_give_to_job_group(const job_group_t *jg, bool continuing_from
     return success;
 }
 
+/// How long to wait for tcsetpgrp() when returning the terminal to the shell, before sending
+/// SIGCONT to the job's process group, and then again before giving up.
+static constexpr uint64_t kTerminalReclaimTimeoutUsec = 1000 * 1000;
+
+/// How often to check whether a foreground job has handed the terminal back to the shell.
+static constexpr uint64_t kForegroundTerminalCheckUsec = 500 * 1000;
+
+bool terminal_try_reclaim_with_timeout(pid_t fg_pgid, uint64_t timeout_usec,
+                                       const std::function<int()> &reclaim) {
+    // Usually this returns right away, so don't bother with a thread.
+    if (reclaim() != -1) return true;
+    if (errno != EINTR) return false;
+
+    // We were interrupted while blocked, so try again where we can time out.
+    FLOGF(proc_termowner, L"tcsetpgrp was interrupted, retrying with a timeout");
+    struct reclaim_state_t {
+        std::mutex lock;
+        std::condition_variable cond;
+        bool done{false};
+        bool cancelled{false};
+        bool success{false};
+        int err{0};
+    };
+    // Shared, because the thread may outlive us if we time out.
+    auto state = std::make_shared<reclaim_state_t>();
+    bool spawned = make_detached_pthread([state, reclaim] {
+        {
+            // If we gave up before the thread got to run, leave the terminal alone.
+            std::lock_guard<std::mutex> locker(state->lock);
+            if (state->cancelled) return;
+        }
+        bool success = reclaim() != -1;
+        int err = errno;
+        std::lock_guard<std::mutex> locker(state->lock);
+        state->done = true;
+        state->success = success;
+        state->err = err;
+        state->cond.notify_one();
+    });
+    if (!spawned) {
+        // No thread, so just block.
+        return reclaim() != -1;
+    }
+
+    const auto timeout = std::chrono::microseconds(timeout_usec);
+    auto is_done = [&] { return state->done; };
+    std::unique_lock<std::mutex> locker(state->lock);
+    if (!state->cond.wait_for(locker, timeout, is_done)) {
+        FLOGF(proc_termowner, L"tcsetpgrp timed out, sending SIGCONT to process group %d",
+              fg_pgid);
+        killpg(fg_pgid, SIGCONT);
+        if (!state->cond.wait_for(locker, timeout, is_done)) {
+            FLOGF(warning,
+                  _(L"Timed out returning the terminal to the shell from process group %d"),
+                  fg_pgid);
+            state->cancelled = true;
+            errno = ETIMEDOUT;
+            return false;
+        }
+    }
+    errno = state->err;
+    return state->success;
+}
+
 /// Returns control of the terminal to the shell, and saves the terminal attribute state to the job
 /// group, so that we can restore the terminal ownership to the job at a later time.
 static bool terminal_return_from_job_group(job_group_t *jg) {
@@ -931,7 +1040,9 @@ static bool terminal_return_from_job_group(job_group_t *jg) {
     }
 
     FLOG(proc_pgroup, "fish reclaiming terminal after job pgid", *pgid);
-    if (tcsetpgrp(STDIN_FILENO, getpgrp()) == -1) {
+    auto reclaim = [] { return tcsetpgrp(STDIN_FILENO, getpgrp()); };
+    if (!terminal_try_reclaim_with_timeout(*pgid, kTerminalReclaimTimeoutUsec, reclaim)) {
+        if (errno == ETIMEDOUT) return false;
         if (errno == ENOTTY) redirect_tty_output();
         FLOGF(warning, _(L"Could not return shell to foreground"));
         wperror(L"tcsetpgrp");
@@ -1017,9 +1128,21 @@ void job_t::continue_job(parser_t &parser, bool in_foreground) {
         }
 
         if (in_foreground) {
-            // Wait for the status of our own job to change.
-            while (!check_cancel_from_fish_signal() && !is_stopped() && !is_completed()) {
-                process_mark_finished_children(parser, true);
+            // Wait for the status of our own job to change. If the job hands the terminal back to
+            // us without stopping, nobody would be reading the terminal, so stop waiting and let
+            // it run in the background instead.
+            uint64_t timeout =
+                term_transferred ? kForegroundTerminalCheckUsec : topic_monitor_t::kNoTimeout;
+            while (!wait_for_stopped_job(parser, this, timeout)) {
+                if (check_cancel_from_fish_signal()) break;
+                if (tcgetpgrp(STDIN_FILENO) == getpgrp()) {
+                    FLOGF(warning,
+                          _(L"Job %d, '%ls' returned the terminal without stopping, continuing it "
+                            L"in the background"),
+                          job_id(), command_wcstr());
+                    group->set_is_foreground(false);
+                    break;
+                }
             }
         }
     }
@@ -1043,6 +1166,25 @@ void proc_wait_any(parser_t &parser) {
     process_clean_after_marking(parser, parser.libdata().is_interactive);
 }
 
+bool wait_for_stopped_job(parser_t &parser, const job_t *job, uint64_t timeout_usec) {
+    ASSERT_IS_MAIN_THREAD();
+    const bool forever = timeout_usec == topic_monitor_t::kNoTimeout;
+    const auto deadline =
+        std::chrono::steady_clock::now() + std::chrono::microseconds(forever ? 0 : timeout_usec);
+    while (!job->is_stopped() && !job->is_completed()) {
+        if (check_cancel_from_fish_signal()) return false;
+        uint64_t remaining = topic_monitor_t::kNoTimeout;
+        if (!forever) {
+            auto now = std::chrono::steady_clock::now();
+            if (now >= deadline) return false;
+            remaining = std::chrono::duration_cast<std::chrono::microseconds>(deadline - now)
+                            .count();
+        }
+        process_mark_finished_children(parser, true, remaining);
+    }
+    return true;
+}
+
 void hup_jobs(const job_list_t &jobs) {
     pid_t fish_pgrp = getpgrp();
     for (const auto &j : jobs) {
@@ -7,11 +7,13 @@
 
 #include <signal.h>
 #include <stddef.h>
-#include <sys/time.h>  // IWYU pragma: keep
-#include <sys/wait.h>  // IWYU pragma: keep
+#include <sys/resource.h>  // IWYU pragma: keep
+#include <sys/time.h>      // IWYU pragma: keep
+#include <sys/wait.h>      // IWYU pragma: keep
 #include <unistd.h>
 
 #include <deque>
+#include <functional>
 #include <memory>
 #include <vector>
 
@@ -58,6 +60,9 @@ class proc_status_t {
     /// If set, there is no actual status to report, e.g. background or variable assignment.
     bool empty_{};
 
+    /// The resource usage of the process, if we got it from wait4().
+    maybe_t<struct rusage> rusage_{};
+
     explicit proc_status_t(int status) : status_(status), empty_(false) {}
 
     proc_status_t(int status, bool empty) : status_(status), empty_(empty) {}
@@ -77,6 +82,13 @@ class proc_status_t {
     /// Construct from a status returned from a waitpid call.
     static proc_status_t from_waitpid(int status) { return proc_status_t(status); }
 
+    /// Construct from a status and resource usage returned from a wait4 call.
+    static proc_status_t from_rusage_exit(int status, const struct rusage &usage) {
+        proc_status_t result(status);
+        result.rusage_ = usage;
+        return result;
+    }
+
     /// Construct directly from an exit code.
     static proc_status_t from_exit_code(int ret) {
         // Some paranoia.
@@ -128,6 +140,9 @@ class proc_status_t {
     /// \return if this status is empty.
     bool is_empty() const { return empty_; }
 
+    /// \return the resource usage of the process, or null if we don't know it.
+    const struct rusage *rusage() const { return rusage_ ? &*rusage_ : nullptr; }
+
     /// \return the value appropriate to populate $status.
     int status_value() const {
         if (signal_exited()) {
@@ -151,7 +166,8 @@ class internal_proc_t {
     std::atomic<bool> exited_{};
 
     /// If the process has exited, its status code.
-    std::atomic<proc_status_t> status_{};
+    /// This is written once, before exited_ is set, which publishes it to other threads.
+    proc_status_t status_{};
 
    public:
     /// \return if this process has exited.
@@ -162,7 +178,7 @@ class internal_proc_t {
 
     proc_status_t get_status() const {
         assert(exited() && "Process is not exited");
-        return status_.load(std::memory_order_relaxed);
+        return status_;
     }
 
     uint64_t get_id() const { return internal_proc_id_; }
@@ -321,6 +337,14 @@ class job_t {
 
         /// Whether the job is under job control, i.e. has its own pgrp.
         bool job_control{};
+
+        /// Whether the job comes from a commandline starting with a space, and so should not be
+        /// added to the job journal.
+        bool ephemeral{};
+
+        /// Whether fish runs the job for its own purposes, like the command of a streamed command
+        /// substitution. Such jobs can't be found by `jobs`, `wait`, `fg`, `bg` or `disown`.
+        bool hidden{};
     };
 
    private:
@@ -397,6 +421,9 @@ class job_t {
     /// A non-user-visible, never-recycled job ID.
     const internal_job_id_t internal_job_id;
 
+    /// When this job was created, as a microsecond timestamp. This is used for the job journal.
+    const long long start_time;
+
     /// Flags associated with the job.
     struct flags_t {
         /// Whether the specified job is completely constructed: every process in the job has been
@@ -451,10 +478,12 @@ class job_t {
     bool is_stopped() const;
     /// The job is OK to be externally visible, e.g. to the user via `jobs`
     bool is_visible() const {
-        return !is_completed() && is_constructed() && !flags().disown_requested;
+        return !is_completed() && is_constructed() && !flags().disown_requested && !is_hidden();
     }
     bool skip_notification() const { return properties.skip_notification; }
     bool from_event_handler() const { return properties.from_event_handler; }
+    bool is_ephemeral() const { return properties.ephemeral; }
+    bool is_hidden() const { return properties.hidden; }
 
     /// \return whether this job's group is in the foreground.
     bool is_foreground() const;
@@ -535,6 +564,11 @@ void proc_init();
 /// Wait for any process finishing, or receipt of a signal.
 void proc_wait_any(parser_t &parser);
 
+/// Wait for \p job to either stop or complete, for at most \p timeout_usec microseconds (or forever
+/// if it is topic_monitor_t::kNoTimeout).
+/// \return true if it did, false if we timed out or were cancelled by a signal first.
+bool wait_for_stopped_job(parser_t &parser, const job_t *job, uint64_t timeout_usec);
+
 /// Set and get whether we are in initialization.
 // Hackish. In order to correctly report the origin of code with no associated file, we need to
 // know whether it's run during initialization or not.
@@ -553,6 +587,14 @@ void hup_jobs(const job_list_t &jobs);
 /// \return 1 if transferred, 0 if no transfer was necessary, -1 on error.
 int terminal_maybe_give_to_job_group(const job_group_t *jg, bool continuing_from_stopped);
 
+/// Give the terminal back to the shell by calling \p reclaim, which does what
+/// tcsetpgrp(STDIN_FILENO, getpgrp()) does. If that is interrupted while it blocks, call it again
+/// on a background thread. If it has not finished after \p timeout_usec microseconds, send SIGCONT
+/// to the foreground process group \p fg_pgid to unblock it, and wait as long again.
+/// \return true on success. On failure, errno is that of \p reclaim, or ETIMEDOUT if we gave up.
+bool terminal_try_reclaim_with_timeout(pid_t fg_pgid, uint64_t timeout_usec,
+                                       const std::function<int()> &reclaim);
+
 /// Add a job to the list of PIDs/PGIDs we wait on even though they are not associated with any
 /// jobs. Used to avoid zombie processes after disown.
 void add_disowned_job(const job_t *j);
@@ -37,6 +37,7 @@
 
 #include <algorithm>
 #include <atomic>
+#include <chrono>
 #include <csignal>
 #include <cwchar>
 #include <functional>
@@ -53,6 +54,7 @@
 #include "exec.h"
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "fds.h"
 #include "flog.h"
 #include "function.h"
 #include "global_safety.h"
@@ -164,6 +166,16 @@ static debounce_t &debounce_highlighting() {
     return *res;
 }
 
+/// With $fish_update_title_on_type, this is the minimum time (in milliseconds) between two runs of
+/// fish_title while typing.
+static constexpr long kTitleOnTypeIntervalMs = 100;
+
+/// Get the debouncer for the delayed title update while typing.
+static debounce_t &debounce_title() {
+    static auto res = new debounce_t(kTitleOnTypeIntervalMs);
+    return *res;
+}
+
 bool edit_t::operator==(const edit_t &other) const {
     return cursor_position_before_edit == other.cursor_position_before_edit &&
            offset == other.offset && length == other.length && old == other.old &&
@@ -634,6 +646,13 @@ class reader_data_t : public std::enable_shared_from_this<reader_data_t> {
     wcstring in_flight_highlight_request;
     wcstring in_flight_autosuggest_request;
 
+    /// The command line text the title was last written for, and when that was. This is used by
+    /// $fish_update_title_on_type to run fish_title at most every kTitleOnTypeIntervalMs.
+    wcstring last_title_text;
+    std::chrono::steady_clock::time_point last_title_time{};
+    /// Whether a delayed title update is pending.
+    bool title_update_pending{false};
+
     bool is_navigating_pager_contents() const { return this->pager.is_navigating_contents(); }
 
     /// The line that is currently being edited. Typically the command line, but may be the search
@@ -739,6 +758,11 @@ class reader_data_t : public std::enable_shared_from_this<reader_data_t> {
     void finish_highlighting_before_exec();
 
     void highlight_complete(highlight_result_t result);
+
+    /// If $fish_update_title_on_type is set, rerun fish_title for the current command line.
+    /// This is rate-limited; an update that comes too soon is delayed instead.
+    void update_title_on_type();
+
     void exec_mode_prompt();
     void exec_prompt();
 
@@ -1297,7 +1321,10 @@ void reader_data_t::exec_prompt() {
         }
 
         if (!conf.right_prompt_cmd.empty()) {
-            if (function_exists(conf.right_prompt_cmd, parser())) {
+            // fish_right_prompt is optional, but a right prompt given to e.g. `read -R` can be any
+            // command.
+            if (conf.right_prompt_cmd != RIGHT_PROMPT_FUNCTION_NAME ||
+                function_exists(conf.right_prompt_cmd, parser())) {
                 // Status is ignored.
                 wcstring_list_t prompt_list;
                 exec_subshell(conf.right_prompt_cmd, parser(), prompt_list, false);
@@ -1313,6 +1340,8 @@ void reader_data_t::exec_prompt() {
     // may still be output on the line from the previous command (#2499) and we need our PROMPT_SP
     // hack to work.
     reader_write_title(L"", parser(), false);
+    last_title_text.clear();
+    last_title_time = std::chrono::steady_clock::now();
 
     // Some prompt may have requested an exit (#8033).
     this->exit_loop_requested |= parser().libdata().exit_current_script;
@@ -2082,7 +2111,7 @@ static bool check_for_orphaned_process(unsigned long loop_count, pid_t shell_pgi
         }
 
         // Open the tty. Presumably this is stdin, but maybe not?
-        autoclose_fd_t tty_fd{open(tty, O_RDONLY | O_NONBLOCK)};
+        autoclose_fd_t tty_fd{open_cloexec(tty, O_RDONLY | O_NONBLOCK)};
         if (!tty_fd.valid()) {
             wperror(L"open");
             exit_without_destructors(1);
@@ -2402,8 +2431,12 @@ static eval_res_t reader_run_command(parser_t &parser, const wcstring &cmd) {
 
     gettimeofday(&time_before, nullptr);
 
+    // Like the history, the job journal ignores commands with a leading space.
+    scoped_push<bool> ephemeral(&parser.libdata().is_ephemeral_command,
+                                !cmd.empty() && cmd.front() == L' ');
     auto eval_res = parser.eval(cmd, io_chain_t{});
     job_reap(parser, true);
+    ephemeral.restore();
 
     gettimeofday(&time_after, nullptr);
 
@@ -2460,6 +2493,8 @@ void reader_data_t::highlight_complete(highlight_result_t result) {
     in_flight_highlight_request.clear();
     if (result.text == command_line.text()) {
         assert(result.colors.size() == command_line.size());
+        highlight_run_user_hook(parser(), result.text, result.colors);
+        if (result.text != command_line.text()) return;
         if (this->is_repaint_needed(&result.colors)) {
             this->layout_and_repaint(L"highlight", std::move(result.colors));
         }
@@ -2555,6 +2590,44 @@ static reader_data_t *current_data() {
     return reader_data_stack.back().get();
 }
 
+void reader_data_t::update_title_on_type() {
+    ASSERT_IS_MAIN_THREAD();
+    // Only for the command line itself, not for `read`.
+    if (!conf.syntax_check_ok || conf.in_silent_mode) return;
+    if (command_line.text() == last_title_text) return;
+
+    auto var = parser().vars().get(L"fish_update_title_on_type");
+    if (var.missing_or_empty() || var->as_string() == L"0") return;
+
+    namespace sc = std::chrono;
+    auto now = sc::steady_clock::now();
+    auto next_allowed = last_title_time + sc::milliseconds(kTitleOnTypeIntervalMs);
+    if (now >= next_allowed) {
+        last_title_text = command_line.text();
+        last_title_time = now;
+        reader_write_title(last_title_text, parser(), false);
+        return;
+    }
+
+    // Too soon. Wait on a background thread and try again, so the last edit is not lost.
+    if (title_update_pending) return;
+    title_update_pending = true;
+    auto delay_usec = sc::duration_cast<sc::microseconds>(next_allowed - now).count();
+    auto shared_this = this->shared_from_this();
+    debounce_title().perform(
+        [delay_usec] {
+            usleep(static_cast<useconds_t>(delay_usec));
+            return true;
+        },
+        [shared_this](bool) {
+            shared_this->title_update_pending = false;
+            // Only update if this reader is still the one reading.
+            if (current_data_or_null() == shared_this.get()) {
+                shared_this->update_title_on_type();
+            }
+        });
+}
+
 void reader_change_history(const wcstring &name) {
     // We don't need to _change_ if we're not initialized yet.
     reader_data_t *data = current_data_or_null();
@@ -2701,12 +2774,15 @@ static int read_i(parser_t &parser) {
 
         maybe_t<wcstring> tmp = data->readline(0);
         if (tmp && !tmp->empty()) {
-            const wcstring command = tmp.acquire();
+            // Fire fish_preexec while the commandline still holds the command, so handlers can
+            // inspect it with `commandline` or change what gets run with `commandline --replace`.
+            wcstring_list_t argv{tmp.acquire()};
+            event_fire_generic(parser, L"fish_preexec", &argv);
+            const wcstring command = data->command_line.text();
             data->update_buff_pos(&data->command_line, 0);
             data->command_line.clear();
             data->command_line_changed(&data->command_line);
-            wcstring_list_t argv{command};
-            event_fire_generic(parser, L"fish_preexec", &argv);
+            argv = {command};
             auto eval_res = reader_run_command(parser, command);
             signal_clear_cancel();
             if (!eval_res.no_status) {
@@ -2721,6 +2797,7 @@ static int read_i(parser_t &parser) {
             // Allow any pending history items to be returned in the history array.
             if (data->history) {
                 data->history->resolve_pending();
+                data->history->save_after_exec();
             }
 
             bool already_warned = data->did_warn_for_bg_jobs;
@@ -2992,7 +3069,12 @@ void reader_data_t::handle_readline_command(readline_cmd_t c, readline_loop_stat
         case rl::repaint: {
             parser().libdata().is_repaint = true;
             exec_prompt();
-            s_reset_line(&screen, true /* redraw prompt */);
+            if (c == rl::force_repaint) {
+                // Don't trust what we think is on the screen.
+                screen_force_full_repaint(&screen);
+            } else {
+                s_reset_line(&screen, true /* redraw prompt */);
+            }
             this->layout_and_repaint(L"readline");
             force_exec_prompt_and_repaint = false;
             parser().libdata().is_repaint = false;
@@ -3898,6 +3980,7 @@ maybe_t<wcstring> reader_data_t::readline(int nchars_or_0) {
         if (conf.in == STDIN_FILENO) {
             this->update_autosuggestion();
             this->super_highlight_me_plenty();
+            this->update_title_on_type();
         }
         if (this->is_repaint_needed()) this->layout_and_repaint(L"toplevel");
         this->force_exec_prompt_and_repaint = false;
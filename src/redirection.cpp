@@ -32,6 +32,19 @@ int redirection_spec_t::oflags() const {
     }
 }
 
+int first_fd_above_specs(const redirection_spec_list_t &specs, int first_fd) {
+    int result = first_fd;
+    for (const auto &spec : specs) {
+        if (spec.fd >= result) result = spec.fd + 1;
+        if (spec.mode == redirection_mode_t::fd) {
+            if (auto target = spec.get_target_as_fd()) {
+                if (*target >= result) result = *target + 1;
+            }
+        }
+    }
+    return result;
+}
+
 dup2_list_t dup2_list_t::resolve_chain(const io_chain_t &io_chain) {
     ASSERT_IS_NOT_FORKED_CHILD();
     dup2_list_t result;
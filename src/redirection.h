@@ -48,6 +48,11 @@ struct redirection_spec_t {
 };
 using redirection_spec_list_t = std::vector<redirection_spec_t>;
 
+/// \return the lowest fd at or above \p first_fd which is higher than every fd named in \p specs,
+/// either as the redirected fd or as the target of an fd redirection. Internal fds placed there
+/// cannot be clobbered by the user's redirections.
+int first_fd_above_specs(const redirection_spec_list_t &specs, int first_fd);
+
 /// A class representing a sequence of basic redirections.
 class dup2_list_t {
    public:
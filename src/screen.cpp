@@ -465,8 +465,29 @@ static void s_check_status(screen_t *s) {
     }
 }
 
+/// If the output cursor is at the start of a line that the previous line soft wrapped into, indent
+/// that line to the width of the prompt, so the command line stays aligned with its first line.
+static void s_desired_indent_soft_wrap(screen_t *s, size_t prompt_width) {
+    int line_no = s->desired.cursor.y;
+    if (s->desired.cursor.x != 0 || line_no == 0 || prompt_width == 0) return;
+    if (static_cast<size_t>(line_no) > s->desired.line_count()) return;
+    // Leave room for at least one wide character after the indentation.
+    if (prompt_width + 2 > static_cast<size_t>(s->desired.screen_width)) return;
+    if (!s->desired.line(line_no - 1).is_soft_wrapped) return;
+
+    line_t &line = s->desired.create_line(line_no);
+    if (line.size() > 0) return;
+    // Don't record this as the line's indentation. The spaces need to be output rather than
+    // skipped over, so the terminal still soft wraps into this line.
+    for (size_t i = 0; i < prompt_width; i++) {
+        line.append(L' ', highlight_spec_t{});
+    }
+    s->desired.cursor.x = static_cast<int>(prompt_width);
+}
+
 /// Appends a character to the end of the line that the output cursor is on. This function
-/// automatically handles linebreaks and lines longer than the screen width.
+/// automatically handles linebreaks and lines longer than the screen width. Lines that are soft
+/// wrapped continue at the width of the prompt.
 static void s_desired_append_char(screen_t *s, wchar_t b, highlight_spec_t c, int indent,
                                   size_t prompt_width, size_t bwidth) {
     int line_no = s->desired.cursor.y;
@@ -492,6 +513,8 @@ static void s_desired_append_char(screen_t *s, wchar_t b, highlight_spec_t c, in
         int screen_width = s->desired.screen_width;
         int cw = bwidth;
 
+        // The cursor may have wrapped after the last character.
+        s_desired_indent_soft_wrap(s, prompt_width);
         s->desired.create_line(line_no);
 
         // Check if we are at the end of the line. If so, continue on the next line.
@@ -503,6 +526,7 @@ static void s_desired_append_char(screen_t *s, wchar_t b, highlight_spec_t c, in
             s->desired.add_line();
             s->desired.cursor.y++;
             s->desired.cursor.x = 0;
+            s_desired_indent_soft_wrap(s, prompt_width);
         }
 
         line_t &line = s->desired.line(line_no);
@@ -1172,6 +1196,7 @@ void s_write(screen_t *s, const wcstring &left_prompt, const wcstring &right_pro
     for (i = 0; i < effective_commandline.size(); i++) {
         // Grab the current cursor's x,y position if this character matches the cursor's offset.
         if (!cursor_is_within_pager && i == cursor_pos) {
+            s_desired_indent_soft_wrap(s, first_line_prompt_space);
             cursor_arr = s->desired.cursor;
         }
         s_desired_append_char(s, effective_commandline.at(i), colors[i], indent[i],
@@ -1181,6 +1206,7 @@ void s_write(screen_t *s, const wcstring &left_prompt, const wcstring &right_pro
 
     // Cursor may have been at the end too.
     if (!cursor_is_within_pager && i == cursor_pos) {
+        s_desired_indent_soft_wrap(s, first_line_prompt_space);
         cursor_arr = s->desired.cursor;
     }
 
@@ -1316,6 +1342,16 @@ void s_reset_abandoning_line(screen_t *s, int screen_width) {
     fstat(STDERR_FILENO, &s->prev_buff_2);
 }
 
+void screen_force_full_repaint(screen_t *s) {
+    assert(s && "Null screen");
+    s_reset_line(s, true /* repaint prompt */);
+    // We don't know what is below us anymore.
+    s->need_clear_screen = true;
+    s->last_right_prompt_width = 0;
+    s->soft_wrap_location = none();
+    s->autosuggestion_is_truncated = false;
+}
+
 void screen_force_clear_to_end() {
     if (clr_eos) {
         writembs(outputter_t::stdoutput(), clr_eos);
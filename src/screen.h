@@ -205,6 +205,12 @@ void s_reset_line(screen_t *s, bool repaint_prompt = false);
 /// The screen width must be provided for the PROMPT_SP hack.
 void s_reset_abandoning_line(screen_t *s, int screen_width);
 
+/// Resets the screen buffer's internal knowledge about the contents of the screen entirely, so the
+/// next call to s_write redraws the prompt and the command line from scratch and clears everything
+/// below them. Use this if the terminal may have been changed behind our back, e.g. by a program
+/// that drew on the screen.
+void screen_force_full_repaint(screen_t *s);
+
 /// Stat stdout and stderr and save result as the current timestamp.
 void s_save_status(screen_t *s);
 
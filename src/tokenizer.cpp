@@ -28,6 +28,8 @@ const wchar_t *tokenizer_get_error_message(tokenizer_error_t err) {
             return _(L"Unexpected end of string, quotes are not balanced");
         case tokenizer_error_t::unterminated_subshell:
             return _(L"Unexpected end of string, expecting ')'");
+        case tokenizer_error_t::unterminated_dollar_subshell:
+            return _(L"Unexpected end of string, expecting ')' to close '$('");
         case tokenizer_error_t::unterminated_slice:
             return _(L"Unexpected end of string, square brackets do not match");
         case tokenizer_error_t::unterminated_escape:
@@ -50,6 +52,8 @@ const wchar_t *tokenizer_get_error_message(tokenizer_error_t err) {
             return _(L"Unexpected '}' found, expecting ')'");
         case tokenizer_error_t::expected_bclose_found_pclose:
             return _(L"Unexpected ')' found, expecting '}'");
+        case tokenizer_error_t::append_noclobber:
+            return _(L"'>>?' is not valid. Appending never overwrites a file, so use '>>'.");
     }
     assert(0 && "Unexpected tokenizer error");
     return nullptr;
@@ -80,6 +84,8 @@ tok_t tokenizer_t::call_error(tokenizer_error_t error_type, const wchar_t *token
     // If we are passed a token_length, then use it; otherwise infer it from the buffer.
     result.length = token_length ? *token_length : this->token_cursor - token_start;
     result.error_offset_within_token = error_loc - token_start;
+    result.is_continuation = this->on_continued_line;
+    this->on_continued_line = false;
     return result;
 }
 
@@ -145,10 +151,29 @@ tok_t tokenizer_t::read_string() {
     std::vector<int> paran_offsets;
     std::vector<int> brace_offsets;
     std::vector<char> expecting;
+    // For each open paren, whether it was preceded by an unescaped '$'.
+    std::vector<bool> paran_dollars;
+    // The number of open parens outside of each command substitution which interrupts a double
+    // quoted string, like "foo$(bar)baz".
+    std::vector<size_t> quoted_cmdsubs;
+    const wchar_t *last_dollar = nullptr;
     int slice_offset = 0;
     const wchar_t *const buff_start = this->token_cursor;
     bool is_first = true;
 
+    // Skip to the end of the quoted string at the cursor, or to the start of a command
+    // substitution which interrupts it. Returns false if the quote is not terminated.
+    auto skip_quoted = [&](wchar_t quote) {
+        const wchar_t *end = quote_end(this->token_cursor, quote);
+        if (!end) return false;
+        if (*end == L'$') {
+            quoted_cmdsubs.push_back(paran_offsets.size());
+            last_dollar = end;
+        }
+        this->token_cursor = end;
+        return true;
+    };
+
     while (true) {
         wchar_t c = *this->token_cursor;
 #if false
@@ -173,8 +198,11 @@ tok_t tokenizer_t::read_string() {
         // has been explicitly ignored (escaped).
         else if (c == L'\\') {
             mode |= tok_modes::char_escape;
+        } else if (c == L'$') {
+            last_dollar = this->token_cursor;
         } else if (c == L'(') {
             paran_offsets.push_back(this->token_cursor - this->start);
+            paran_dollars.push_back(last_dollar == this->token_cursor - 1);
             expecting.push_back(L')');
             mode |= tok_modes::subshell;
         } else if (c == L'{') {
@@ -191,10 +219,25 @@ tok_t tokenizer_t::read_string() {
                                         this->token_cursor, this->token_cursor, 1);
             }
             paran_offsets.pop_back();
+            paran_dollars.pop_back();
             if (paran_offsets.empty()) {
                 mode &= ~(tok_modes::subshell);
             }
             expecting.pop_back();
+            // If this closed a command substitution inside double quotes, the quoted string
+            // resumes here.
+            if (!quoted_cmdsubs.empty() && quoted_cmdsubs.back() == paran_offsets.size()) {
+                quoted_cmdsubs.pop_back();
+                if (!skip_quoted(L'"')) {
+                    const wchar_t *error_loc = this->token_cursor;
+                    this->token_cursor += std::wcslen(this->token_cursor);
+                    if ((!this->accept_unfinished)) {
+                        return this->call_error(tokenizer_error_t::unterminated_quote,
+                                                buff_start, error_loc);
+                    }
+                    break;
+                }
+            }
         } else if (c == L'}') {
             if (!expecting.empty() && expecting.back() == L')') {
                 return this->call_error(tokenizer_error_t::expected_pclose_found_bclose,
@@ -225,10 +268,7 @@ tok_t tokenizer_t::read_string() {
         else if (c == L']' && ((mode & tok_modes::array_brackets) == tok_modes::array_brackets)) {
             mode &= ~(tok_modes::array_brackets);
         } else if (c == L'\'' || c == L'"') {
-            const wchar_t *end = quote_end(this->token_cursor);
-            if (end) {
-                this->token_cursor = end;
-            } else {
+            if (!skip_quoted(c)) {
                 const wchar_t *error_loc = this->token_cursor;
                 this->token_cursor += std::wcslen(this->token_cursor);
                 if ((!this->accept_unfinished)) {
@@ -265,8 +305,10 @@ tok_t tokenizer_t::read_string() {
             assert(!paran_offsets.empty());
             size_t offset_of_open_paran = paran_offsets.back();
 
-            return this->call_error(tokenizer_error_t::unterminated_subshell, buff_start,
-                                    this->start + offset_of_open_paran);
+            return this->call_error(paran_dollars.back()
+                                        ? tokenizer_error_t::unterminated_dollar_subshell
+                                        : tokenizer_error_t::unterminated_subshell,
+                                    buff_start, this->start + offset_of_open_paran);
         } else if (mode & tok_modes::curly_braces) {
             assert(!brace_offsets.empty());
             size_t offset_of_open_brace = brace_offsets.back();
@@ -314,7 +356,7 @@ maybe_t<pipe_or_redir_t> pipe_or_redir_t::from_string(const wchar_t *buff) {
         cmd > file       redirection
         cmd >> file      appending redirection
         cmd >? file      noclobber redirection
-        cmd >>? file     appending noclobber redirection
+        cmd >>? file     appending noclobber redirection (an error)
         cmd 2> file      file redirection with explicit fd
         cmd >&2          fd redirection with no explicit src fd (stdout is used)
         cmd 1>&2         fd redirection with an explicit src fd
@@ -386,10 +428,13 @@ maybe_t<pipe_or_redir_t> pipe_or_redir_t::from_string(const wchar_t *buff) {
                                    : STDOUT_FILENO;              // like > file.txt
                 if (result.mode != redirection_mode_t::append)
                     result.mode = redirection_mode_t::overwrite;
-                // Note 'echo abc >>? file' is valid: it means append and noclobber.
-                // But here "noclobber" means the file must not exist, so appending
-                // can be ignored.
-                if (try_consume(L'?')) result.mode = redirection_mode_t::noclob;
+                if (try_consume(L'?')) {
+                    // Note 'echo abc >>? file' is parsed so the tokenizer can report it, but it is
+                    // an error: "noclobber" means the file must not exist, so appending to it is
+                    // meaningless.
+                    result.append_noclobber = (result.mode == redirection_mode_t::append);
+                    result.mode = redirection_mode_t::noclob;
+                }
             }
             break;
         }
@@ -421,7 +466,10 @@ maybe_t<pipe_or_redir_t> pipe_or_redir_t::from_string(const wchar_t *buff) {
                     // This is a redirection to an fd.
                     result.mode = redirection_mode_t::fd;
                 }
-                if (try_consume(L'?')) result.mode = redirection_mode_t::noclob;
+                if (try_consume(L'?')) {
+                    result.append_noclobber = (result.mode == redirection_mode_t::append);
+                    result.mode = redirection_mode_t::noclob;
+                }
                 break;
             }
         }
@@ -437,8 +485,11 @@ maybe_t<pipe_or_redir_t> pipe_or_redir_t::from_string(const wchar_t *buff) {
                 result.stderr_merge = true;
                 result.mode = redirection_mode_t::overwrite;
                 if (try_consume(L'>')) result.mode = redirection_mode_t::append;  // like &>>
-                if (try_consume(L'?'))
-                    result.mode = redirection_mode_t::noclob;  // like &>? or &>>?
+                if (try_consume(L'?')) {
+                    // like &>? or the invalid &>>?
+                    result.append_noclobber = (result.mode == redirection_mode_t::append);
+                    result.mode = redirection_mode_t::noclob;
+                }
             } else {
                 return none();
             }
@@ -502,6 +553,7 @@ maybe_t<tok_t> tokenizer_t::next() {
         if (this->token_cursor[0] == L'\\' && this->token_cursor[1] == L'\n') {
             this->token_cursor += 2;
             this->continue_line_after_comment = true;
+            this->on_continued_line = true;
         } else if (iswspace_not_nl(this->token_cursor[0])) {
             this->token_cursor++;
         } else {
@@ -525,6 +577,7 @@ maybe_t<tok_t> tokenizer_t::next() {
             tok_t result(token_type_t::comment);
             result.offset = comment_start - this->start;
             result.length = comment_len;
+            result.is_continuation = this->on_continued_line;
             return result;
         }
         while (iswspace_not_nl(this->token_cursor[0])) this->token_cursor++;
@@ -569,6 +622,11 @@ maybe_t<tok_t> tokenizer_t::next() {
                 auto redir = pipe_or_redir_t::from_string(this->token_cursor);
                 assert(redir.has_value() &&
                        "Should always succeed to parse a &> or &| redirection");
+                if (redir->append_noclobber) {
+                    return this->call_error(tokenizer_error_t::append_noclobber,
+                                            this->token_cursor, this->token_cursor,
+                                            redir->consumed);
+                }
                 result.emplace(redir->token_type());
                 result->offset = start_pos;
                 result->length = redir->consumed;
@@ -614,6 +672,10 @@ maybe_t<tok_t> tokenizer_t::next() {
                                         this->token_cursor,
                                         redir_or_pipe ? redir_or_pipe->consumed : 0);
             }
+            if (redir_or_pipe->append_noclobber) {
+                return this->call_error(tokenizer_error_t::append_noclobber, this->token_cursor,
+                                        this->token_cursor, redir_or_pipe->consumed);
+            }
             result.emplace(redir_or_pipe->token_type());
             result->offset = start_pos;
             result->length = redir_or_pipe->consumed;
@@ -636,6 +698,10 @@ maybe_t<tok_t> tokenizer_t::next() {
                     return this->call_error(tokenizer_error_t::invalid_pipe, error_location,
                                             error_location, redir_or_pipe->consumed);
                 }
+                if (redir_or_pipe->append_noclobber) {
+                    return this->call_error(tokenizer_error_t::append_noclobber, error_location,
+                                            error_location, redir_or_pipe->consumed);
+                }
                 result.emplace(redir_or_pipe->token_type());
                 result->offset = start_pos;
                 result->length = redir_or_pipe->consumed;
@@ -648,6 +714,8 @@ maybe_t<tok_t> tokenizer_t::next() {
         }
     }
     assert(result.has_value() && "Should have a token");
+    result->is_continuation = this->on_continued_line;
+    this->on_continued_line = false;
     return result;
 }
 
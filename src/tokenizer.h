@@ -43,6 +43,7 @@ enum class tokenizer_error_t {
     none,
     unterminated_quote,
     unterminated_subshell,
+    unterminated_dollar_subshell,
     unterminated_slice,
     unterminated_escape,
     invalid_redirect,
@@ -54,6 +55,7 @@ enum class tokenizer_error_t {
     unterminated_brace,
     expected_pclose_found_bclose,
     expected_bclose_found_pclose,
+    append_noclobber,
 };
 
 /// Get the error message for an error \p err.
@@ -75,6 +77,10 @@ struct tok_t {
     // at 'offset'.
     size_t error_offset_within_token{size_t(-1)};
 
+    // Whether the token starts on a line that a backslash-newline continued from a previous line,
+    // like 'b' in "echo a \\\n b".
+    bool is_continuation{false};
+
     // Construct from a token type.
     explicit tok_t(token_type_t type);
 
@@ -108,6 +114,8 @@ class tokenizer_t {
     bool continue_after_error{false};
     /// Whether to continue the previous line after the comment.
     bool continue_line_after_comment{false};
+    /// Whether the token being read follows an escaped newline.
+    bool on_continued_line{false};
 
     tok_t call_error(tokenizer_error_t error_type, const wchar_t *token_start,
                      const wchar_t *error_loc, maybe_t<size_t> token_length = {});
@@ -162,6 +170,10 @@ struct pipe_or_redir_t {
     // For example &| or &>
     bool stderr_merge{false};
 
+    // Whether this is an appending noclobber redirection like >>?, which is an error: appending
+    // never overwrites a file.
+    bool append_noclobber{false};
+
     // Number of characters consumed when parsing the string.
     size_t consumed{0};
 
@@ -3,6 +3,7 @@
 #include "topic_monitor.h"
 
 #include <limits.h>
+#include <time.h>
 #include <unistd.h>
 
 #include "flog.h"
@@ -75,17 +76,56 @@ void binary_semaphore_t::post() {
 }
 
 void binary_semaphore_t::wait() {
+    bool posted = wait(select_wrapper_t::kNoTimeout);
+    assert(posted && "Should not time out without a timeout");
+    (void)posted;
+}
+
+bool binary_semaphore_t::wait(uint64_t timeout_usec) {
+    const bool forever = timeout_usec == select_wrapper_t::kNoTimeout;
     if (sem_ok_) {
         int res;
-        do {
-            res = sem_wait(&sem_);
-        } while (res < 0 && errno == EINTR);
+        if (forever) {
+            do {
+                res = sem_wait(&sem_);
+            } while (res < 0 && errno == EINTR);
+        } else {
+#ifdef __linux__
+            // sem_timedwait wants an absolute time on the realtime clock.
+            struct timespec deadline;
+            if (clock_gettime(CLOCK_REALTIME, &deadline) < 0) die(L"clock_gettime");
+            deadline.tv_sec += static_cast<time_t>(timeout_usec / 1000000);
+            deadline.tv_nsec += static_cast<long>(timeout_usec % 1000000) * 1000;
+            if (deadline.tv_nsec >= 1000000000) {
+                deadline.tv_sec += 1;
+                deadline.tv_nsec -= 1000000000;
+            }
+            do {
+                res = sem_timedwait(&sem_, &deadline);
+            } while (res < 0 && errno == EINTR);
+            if (res < 0 && errno == ETIMEDOUT) return false;
+#else
+            DIE("sem_t is only used on Linux");
+#endif
+        }
         // Other errors here are very unexpected.
         if (res < 0) die(L"sem_wait");
     } else {
         int fd = pipes_.read.fd();
+        const auto deadline = std::chrono::steady_clock::now() +
+                              std::chrono::microseconds(forever ? 0 : timeout_usec);
         // We must read exactly one byte.
         for (;;) {
+            if (!forever) {
+                auto now = std::chrono::steady_clock::now();
+                if (now >= deadline) return false;
+                auto remaining =
+                    std::chrono::duration_cast<std::chrono::microseconds>(deadline - now).count();
+                // This may also return false on EINTR, in which case we check the time again.
+                if (!select_wrapper_t::is_fd_readable(fd, static_cast<uint64_t>(remaining))) {
+                    continue;
+                }
+            }
 #ifdef FISH_TSAN_WORKAROUNDS
             // Under tsan our notifying pipe is non-blocking, so we would busy-loop on the read()
             // call until data is available (that is, fish would use 100% cpu while waiting for
@@ -99,6 +139,7 @@ void binary_semaphore_t::wait() {
             if (amt < 0 && errno != EINTR && errno != EAGAIN) die(L"read");
         }
     }
+    return true;
 }
 
 /// Implementation of the principal monitor. This uses new (and leaks) to avoid registering a
@@ -182,7 +223,8 @@ generation_list_t topic_monitor_t::updated_gens() {
     return updated_gens_in_data(data);
 }
 
-bool topic_monitor_t::try_update_gens_maybe_becoming_reader(generation_list_t *gens) {
+bool topic_monitor_t::try_update_gens_maybe_becoming_reader(generation_list_t *gens,
+                                                            const deadline_t *deadline) {
     bool become_reader = false;
     auto data = data_.acquire();
     for (;;) {
@@ -200,7 +242,12 @@ bool topic_monitor_t::try_update_gens_maybe_becoming_reader(generation_list_t *g
         // reader.
         if (data->has_reader) {
             // We already have a reader, wait for it to notify us and loop again.
-            data_notifier_.wait(data.get_lock());
+            if (!deadline) {
+                data_notifier_.wait(data.get_lock());
+            } else if (data_notifier_.wait_until(data.get_lock(), *deadline) ==
+                       std::cv_status::timeout) {
+                break;
+            }
             continue;
         } else {
             // We will try to become the reader.
@@ -224,10 +271,11 @@ bool topic_monitor_t::try_update_gens_maybe_becoming_reader(generation_list_t *g
     return become_reader;
 }
 
-generation_list_t topic_monitor_t::await_gens(const generation_list_t &input_gens) {
+generation_list_t topic_monitor_t::await_gens(const generation_list_t &input_gens,
+                                              const deadline_t *deadline) {
     generation_list_t gens = input_gens;
     while (gens == input_gens) {
-        bool become_reader = try_update_gens_maybe_becoming_reader(&gens);
+        bool become_reader = try_update_gens_maybe_becoming_reader(&gens, deadline);
         if (become_reader) {
             // Now we are the reader. Read from the pipe, and then update with any changes.
             // Note we no longer hold the lock.
@@ -235,25 +283,51 @@ generation_list_t topic_monitor_t::await_gens(const generation_list_t &input_gen
                    "Generations should not have changed if we are the reader.");
 
             // Wait to be woken up.
-            sema_.wait();
+            bool posted = true;
+            if (!deadline) {
+                sema_.wait();
+            } else {
+                auto now = std::chrono::steady_clock::now();
+                auto remaining =
+                    *deadline > now
+                        ? std::chrono::duration_cast<std::chrono::microseconds>(*deadline - now)
+                              .count()
+                        : 0;
+                posted = sema_.wait(static_cast<uint64_t>(remaining));
+            }
+            if (!posted) {
+                // We timed out. Stop asking to be woken up. If that fails, then post() has already
+                // cleared the wakeup bit and is committed to posting to the semaphore, so we have
+                // to consume that post.
+                status_bits_t expected = STATUS_NEEDS_WAKEUP;
+                if (!status_.compare_exchange_strong(expected, 0)) {
+                    sema_.wait();
+                }
+            }
 
             // We are finished waiting. We must stop being the reader, and post on the condition
             // variable to wake up any other threads waiting for us to finish reading.
             auto data = data_.acquire();
-            gens = data->current;
+            gens = posted ? data->current : updated_gens_in_data(data);
             FLOG(topic_monitor, "TID", thread_id(), "local", input_gens.describe(),
-                 "read() complete, current is", gens.describe());
+                 posted ? "read() complete, current is" : "read() timed out, current is",
+                 gens.describe());
             assert(data->has_reader && "We should be the reader");
             data->has_reader = false;
             data_notifier_.notify_all();
         }
+        if (deadline && std::chrono::steady_clock::now() >= *deadline) break;
     }
     return gens;
 }
 
-bool topic_monitor_t::check(generation_list_t *gens, bool wait) {
+bool topic_monitor_t::check(generation_list_t *gens, bool wait, uint64_t timeout_usec) {
     if (!gens->any_valid()) return false;
 
+    const bool has_deadline = wait && timeout_usec != kNoTimeout;
+    const deadline_t deadline = std::chrono::steady_clock::now() +
+                                std::chrono::microseconds(has_deadline ? timeout_usec : 0);
+
     generation_list_t current = updated_gens();
     bool changed = false;
     for (;;) {
@@ -269,13 +343,13 @@ bool topic_monitor_t::check(generation_list_t *gens, bool wait) {
             }
         }
 
-        // If we're not waiting, or something changed, then we're done.
-        if (!wait || changed) {
+        // If we're not waiting, or something changed, or we ran out of time, then we're done.
+        if (!wait || changed || (has_deadline && std::chrono::steady_clock::now() >= deadline)) {
             break;
         }
 
         // Wait until our gens change.
-        current = await_gens(current);
+        current = await_gens(current, has_deadline ? &deadline : nullptr);
     }
     return changed;
 }
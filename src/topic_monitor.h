@@ -6,6 +6,7 @@
 #include <array>
 #include <atomic>
 #include <bitset>
+#include <chrono>
 #include <condition_variable>
 #include <limits>
 #include <numeric>
@@ -144,6 +145,10 @@ class binary_semaphore_t {
     /// This loops on EINTR.
     void wait();
 
+    /// Wait for a post, for at most \p timeout_usec microseconds.
+    /// \return true if we were posted to, false if we timed out. This loops on EINTR.
+    bool wait(uint64_t timeout_usec);
+
    private:
     // Print a message and exit.
     void die(const wchar_t *msg) const;
@@ -213,6 +218,8 @@ class topic_monitor_t {
     /// \return the updated generation list.
     generation_list_t updated_gens_in_data(acquired_lock<data_t> &data);
 
+    using deadline_t = std::chrono::steady_clock::time_point;
+
     /// Given a list of input generations, attempt to update them to something newer.
     /// If \p gens is older, then just return those by reference, and directly return false (not
     /// becoming the reader).
@@ -220,11 +227,15 @@ class topic_monitor_t {
     /// indicating we should become the reader. Now it is our responsibility to wait on the
     /// semaphore and notify on a change via the condition variable. If \p gens is current, and
     /// there is already a reader, then wait until the reader notifies us and try again.
-    bool try_update_gens_maybe_becoming_reader(generation_list_t *gens);
+    /// If \p deadline is not null and passes while waiting for the reader, return false without
+    /// updating \p gens.
+    bool try_update_gens_maybe_becoming_reader(generation_list_t *gens,
+                                               const deadline_t *deadline);
 
-    /// Wait for some entry in the list of generations to change.
-    /// \return the new gens.
-    generation_list_t await_gens(const generation_list_t &input_gens);
+    /// Wait for some entry in the list of generations to change, or for \p deadline to pass (if
+    /// not null).
+    /// \return the new gens, which are unchanged if the deadline passed.
+    generation_list_t await_gens(const generation_list_t &input_gens, const deadline_t *deadline);
 
     /// \return the current generation list, opportunistically applying any pending updates.
     generation_list_t updated_gens();
@@ -256,10 +267,14 @@ class topic_monitor_t {
 
     /// For each valid topic in \p gens, check to see if the current topic is larger than
     /// the value in \p gens.
-    /// If \p wait is set, then wait if there are no changes; otherwise return immediately.
+    /// If \p wait is set, then wait if there are no changes, for at most \p timeout_usec
+    /// microseconds; otherwise return immediately.
     /// \return true if some topic changed, false if none did.
     /// On a true return, this updates the generation list \p gens.
-    bool check(generation_list_t *gens, bool wait);
+    bool check(generation_list_t *gens, bool wait, uint64_t timeout_usec = kNoTimeout);
+
+    /// Value for check()'s timeout to wait forever.
+    static constexpr uint64_t kNoTimeout = std::numeric_limits<uint64_t>::max();
 };
 
 #endif
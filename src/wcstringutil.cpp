@@ -5,7 +5,9 @@
 
 #include <wctype.h>
 
+#include <algorithm>
 #include <locale>
+#include <vector>
 
 #include "common.h"
 #include "flog.h"
@@ -58,6 +60,38 @@ size_t count_preceding_backslashes(const wcstring &text, size_t idx) {
     return backslashes;
 }
 
+wcstring json_quote(const wcstring &str) {
+    wcstring result = L"\"";
+    for (wchar_t c : str) {
+        switch (c) {
+            case L'"':
+                result.append(L"\\\"");
+                break;
+            case L'\\':
+                result.append(L"\\\\");
+                break;
+            case L'\n':
+                result.append(L"\\n");
+                break;
+            case L'\t':
+                result.append(L"\\t");
+                break;
+            case L'\r':
+                result.append(L"\\r");
+                break;
+            default:
+                if (c < 0x20) {
+                    append_format(result, L"\\u%04x", static_cast<unsigned>(c));
+                } else {
+                    result.push_back(c);
+                }
+                break;
+        }
+    }
+    result.push_back(L'"');
+    return result;
+}
+
 bool string_prefixes_string(const wchar_t *proposed_prefix, const wcstring &value) {
     return string_prefixes_string(proposed_prefix, value.c_str());
 }
@@ -138,10 +172,43 @@ static bool subsequence_in_string(const wcstring &needle, const wcstring &haysta
     return ni == needle.end();
 }
 
+/// Returns the smallest number of swaps of adjacent characters in needle which make it a
+/// subsequence of haystack, or none if there is no such number. Swapped pairs may not overlap.
+/// For example, "gti" needs one swap to be a subsequence of "git".
+static maybe_t<uint32_t> transposed_subsequence_in_string(const wcstring &needle,
+                                                          const wcstring &haystack) {
+    const size_t n = needle.size(), m = haystack.size();
+    constexpr uint32_t impossible = UINT32_MAX;
+    // swaps[i * (m + 1) + j] is the number of swaps needed to match needle[i..] within
+    // haystack[j..].
+    std::vector<uint32_t> swaps((n + 1) * (m + 1), impossible);
+    auto at = [&](size_t i, size_t j) -> uint32_t & { return swaps.at(i * (m + 1) + j); };
+    for (size_t j = 0; j <= m; j++) at(n, j) = 0;
+    for (size_t i = n; i-- > 0;) {
+        for (size_t j = m; j-- > 0;) {
+            // Skip this haystack character.
+            uint32_t best = at(i, j + 1);
+            // Match it directly.
+            if (needle[i] == haystack[j]) best = std::min(best, at(i + 1, j + 1));
+            // Match it with the next needle character, and the needle character after it.
+            if (i + 1 < n && needle[i] != needle[i + 1] && needle[i + 1] == haystack[j]) {
+                size_t k = haystack.find(needle[i], j + 1);
+                if (k != wcstring::npos && at(i + 2, k + 1) != impossible) {
+                    best = std::min(best, at(i + 2, k + 1) + 1);
+                }
+            }
+            at(i, j) = best;
+        }
+    }
+    if (at(0, 0) == impossible) return none();
+    return at(0, 0);
+}
+
 // static
 maybe_t<string_fuzzy_match_t> string_fuzzy_match_t::try_create(const wcstring &string,
                                                                const wcstring &match_against,
-                                                               bool anchor_start) {
+                                                               bool anchor_start,
+                                                               bool allow_transpositions) {
     // Helper to lazily compute if case insensitive matches should use icase or smartcase.
     // Use icase if the input contains any uppercase characters, smartcase otherwise.
     auto get_case_fold = [&] {
@@ -198,6 +265,13 @@ maybe_t<string_fuzzy_match_t> string_fuzzy_match_t::try_create(const wcstring &s
     }
 
     // We do not currently test subseq icase.
+
+    // transposed samecase
+    if (allow_transpositions) {
+        if (auto swaps = transposed_subsequence_in_string(string, match_against)) {
+            return string_fuzzy_match_t{contain_type_t::transposed, case_fold_t::samecase, *swaps};
+        }
+    }
     return none();
 }
 
@@ -308,6 +382,67 @@ wcstring join_strings(const wcstring_list_t &vals, wchar_t sep) {
     return result;
 }
 
+size_t levenshtein_distance(const wcstring &a, const wcstring &b) {
+    // Keep one row of the table, as long as the shorter string.
+    const wcstring &longer = a.size() >= b.size() ? a : b;
+    const wcstring &shorter = a.size() >= b.size() ? b : a;
+    std::vector<size_t> row(shorter.size() + 1);
+    for (size_t j = 0; j < row.size(); j++) row[j] = j;
+
+    for (size_t i = 0; i < longer.size(); i++) {
+        // The entry diagonally up and to the left, which the previous iteration overwrote.
+        size_t diagonal = row[0];
+        row[0] = i + 1;
+        for (size_t j = 0; j < shorter.size(); j++) {
+            size_t replace = diagonal + (longer[i] == shorter[j] ? 0 : 1);
+            diagonal = row[j + 1];
+            row[j + 1] = std::min({replace, row[j] + 1, row[j + 1] + 1});
+        }
+    }
+    return row.back();
+}
+
+double jaro_winkler_similarity(const wcstring &a, const wcstring &b) {
+    if (a.empty() && b.empty()) return 1;
+    if (a.empty() || b.empty()) return 0;
+
+    // Characters match if they are equal and not too far apart.
+    size_t window = std::max(a.size(), b.size()) / 2;
+    window = window > 0 ? window - 1 : 0;
+    std::vector<bool> a_matched(a.size()), b_matched(b.size());
+    size_t matches = 0;
+    for (size_t i = 0; i < a.size(); i++) {
+        size_t lo = i > window ? i - window : 0;
+        size_t hi = std::min(i + window + 1, b.size());
+        for (size_t j = lo; j < hi; j++) {
+            if (!b_matched[j] && a[i] == b[j]) {
+                a_matched[i] = b_matched[j] = true;
+                matches++;
+                break;
+            }
+        }
+    }
+    if (matches == 0) return 0;
+
+    // Count the matching characters that are in a different order, each pair counting once.
+    size_t out_of_order = 0;
+    for (size_t i = 0, j = 0; i < a.size(); i++) {
+        if (!a_matched[i]) continue;
+        while (!b_matched[j]) j++;
+        if (a[i] != b[j]) out_of_order++;
+        j++;
+    }
+    double m = matches;
+    double jaro = (m / a.size() + m / b.size() + (m - out_of_order / 2.0) / m) / 3;
+
+    // Boost strings with a common prefix of up to 4 characters.
+    size_t prefix = 0;
+    while (prefix < 4 && prefix < a.size() && prefix < b.size() && a[prefix] == b[prefix]) {
+        prefix++;
+    }
+    return jaro + prefix * 0.1 * (1 - jaro);
+}
+
 void wcs2string_bad_char(wchar_t wc) {
     FLOGF(char_encoding, L"Wide character U+%4X has no narrow representation", wc);
 }
@@ -43,6 +43,7 @@ struct string_fuzzy_match_t {
         prefix,  // prefix match: foo matches foobar
         substr,  // substring match: ooba matches foobar
         subseq,  // subsequence match: fbr matches foobar
+        transposed,  // subsequence match after swapping adjacent characters: gti matches git
     };
     contain_type_t type;
 
@@ -54,9 +55,14 @@ struct string_fuzzy_match_t {
     };
     case_fold_t case_fold;
 
+    // For transposed matches, the number of pairs of adjacent characters that had to be swapped.
+    // Fewer is better. This is 0 for all other types.
+    uint32_t transpositions;
+
     // Constructor.
-    constexpr string_fuzzy_match_t(contain_type_t type, case_fold_t case_fold)
-        : type(type), case_fold(case_fold) {}
+    constexpr string_fuzzy_match_t(contain_type_t type, case_fold_t case_fold,
+                                   uint32_t transpositions = 0)
+        : type(type), case_fold(case_fold), transpositions(transpositions) {}
 
     // Helper to return an exact match.
     static constexpr string_fuzzy_match_t exact_match() {
@@ -76,6 +82,7 @@ struct string_fuzzy_match_t {
                 return true;
             case contain_type_t::substr:
             case contain_type_t::subseq:
+            case contain_type_t::transposed:
                 return false;
         }
         DIE("Unreachable");
@@ -92,6 +99,7 @@ struct string_fuzzy_match_t {
                 return false;
             case contain_type_t::substr:
             case contain_type_t::subseq:
+            case contain_type_t::transposed:
                 return true;
         }
         DIE("Unreachable");
@@ -101,9 +109,11 @@ struct string_fuzzy_match_t {
     /// Try creating a fuzzy match for \p string against \p match_against.
     /// \p string is something like "foo" and \p match_against is like "FooBar".
     /// If \p anchor_start is set, then only exact and prefix matches are permitted.
+    /// If \p allow_transpositions is set, then transposed matches are tried last.
     static maybe_t<string_fuzzy_match_t> try_create(const wcstring &string,
                                                     const wcstring &match_against,
-                                                    bool anchor_start);
+                                                    bool anchor_start,
+                                                    bool allow_transpositions = false);
 
     /// \return a rank for filtering matches.
     /// Earlier (smaller) ranks are better matches.
@@ -111,10 +121,11 @@ struct string_fuzzy_match_t {
 };
 
 /// Cover over string_fuzzy_match_t::try_create().
-inline maybe_t<string_fuzzy_match_t> string_fuzzy_match_string(const wcstring &string,
-                                                               const wcstring &match_against,
-                                                               bool anchor_start = false) {
-    return string_fuzzy_match_t::try_create(string, match_against, anchor_start);
+inline maybe_t<string_fuzzy_match_t> string_fuzzy_match_string(
+    const wcstring &string, const wcstring &match_against, bool anchor_start = false,
+    bool allow_transpositions = false) {
+    return string_fuzzy_match_t::try_create(string, match_against, anchor_start,
+                                            allow_transpositions);
 }
 
 /// Split a string by a separator character.
@@ -133,6 +144,14 @@ wcstring_list_t split_string_tok(const wcstring &val, const wcstring &seps,
 /// Join a list of strings by a separator character.
 wcstring join_strings(const wcstring_list_t &vals, wchar_t sep);
 
+/// \return the Levenshtein distance between \p a and \p b: how many characters must be inserted,
+/// deleted or replaced to turn one into the other.
+size_t levenshtein_distance(const wcstring &a, const wcstring &b);
+
+/// \return the Jaro-Winkler similarity of \p a and \p b, from 0 for no similarity to 1 for equal
+/// strings. This favors strings with a common prefix, which makes it good at ranking typos.
+double jaro_winkler_similarity(const wcstring &a, const wcstring &b);
+
 inline wcstring to_string(long x) {
     wchar_t buff[64];
     format_long_safe(buff, x);
@@ -221,6 +240,9 @@ wcstring wcstolower(wcstring input);
 /// \p idx may be "one past the end."
 size_t count_preceding_backslashes(const wcstring &text, size_t idx);
 
+/// \return \p str as a quoted JSON string.
+wcstring json_quote(const wcstring &str);
+
 // Out-of-line helper for wcs2string_callback.
 void wcs2string_bad_char(wchar_t);
 
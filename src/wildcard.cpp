@@ -19,8 +19,10 @@
 
 #include "common.h"
 #include "complete.h"
+#include "env.h"
 #include "expand.h"
 #include "fallback.h"  // IWYU pragma: keep
+#include "flog.h"
 #include "future_feature_flags.h"
 #include "path.h"
 #include "reader.h"
@@ -227,11 +229,12 @@ static wildcard_result_t wildcard_complete_internal(const wchar_t *const str, si
     // Maybe we have no more wildcards at all. This includes the empty string.
     if (next_wc_char_pos == wcstring::npos) {
         // Try matching.
-        maybe_t<string_fuzzy_match_t> match = string_fuzzy_match_string(wc, str);
-        if (!match) return wildcard_result_t::no_match;
-
         // If we're not allowing fuzzy match, then we require a prefix match.
         bool needs_prefix_match = !(params.expand_flags & expand_flag::fuzzy_match);
+        maybe_t<string_fuzzy_match_t> match = string_fuzzy_match_string(
+            wc, str, false, !needs_prefix_match && get_fuzzy_completion_matching());
+        if (!match) return wildcard_result_t::no_match;
+
         if (needs_prefix_match && !match->is_exact_or_prefix()) {
             return wildcard_result_t::no_match;
         }
@@ -526,6 +529,8 @@ static bool wildcard_test_flags_then_complete(const wcstring &filepath, const wc
            wildcard_result_t::match;
 }
 
+relaxed_atomic_t<size_t> glob_max_depth{kDefaultGlobMaxDepth};
+
 class wildcard_expander_t {
     // A function to call to check cancellation.
     cancel_checker_t cancel_checker;
@@ -535,6 +540,14 @@ class wildcard_expander_t {
     std::unordered_set<wcstring> completion_set;
     // The set of file IDs we have visited, used to avoid symlink loops.
     std::unordered_set<file_id_t> visited_files;
+    // How many directories below the start we are, counting those that wildcards descended into.
+    size_t depth{0};
+    // How many directories wildcards may descend into.
+    const size_t max_depth;
+    // Whether we skipped directories because they were deeper than max_depth.
+    bool did_hit_max_depth{false};
+    // How many directories we could not read because we lack permission.
+    size_t unreadable_dirs{0};
     // Flags controlling expansion.
     const expand_flags_t flags;
     // Resolved items get inserted into here. This is transient of course.
@@ -704,6 +717,7 @@ class wildcard_expander_t {
                         completion_receiver_t *r)
         : cancel_checker(std::move(cancel_checker)),
           working_directory(std::move(wd)),
+          max_depth(glob_max_depth),
           flags(f),
           resolved_completions(r) {
         assert(resolved_completions != nullptr);
@@ -717,6 +731,18 @@ class wildcard_expander_t {
     // Do wildcard expansion. This is recursive.
     void expand(const wcstring &base_dir, const wchar_t *wc, const wcstring &prefix);
 
+    /// Log directories we skipped, as they may be why something did not match.
+    void log_skipped_directories() const {
+        if (this->unreadable_dirs > 0) {
+            FLOGF(glob, L"Some directories were unreadable and skipped: %lu",
+                  static_cast<unsigned long>(this->unreadable_dirs));
+        }
+        if (this->did_hit_max_depth) {
+            FLOGF(glob, L"Some directories were deeper than $fish_glob_max_depth (%lu) and skipped",
+                  static_cast<unsigned long>(this->max_depth));
+        }
+    }
+
     wildcard_result_t status_code() const {
         if (this->did_interrupt) {
             return wildcard_result_t::cancel;
@@ -757,6 +783,14 @@ void wildcard_expander_t::expand_intermediate_segment(const wcstring &base_dir,
                                                       const wcstring &wc_segment,
                                                       const wchar_t *wc_remainder,
                                                       const wcstring &prefix) {
+    // Count the directory we read as visited, so a symlink back to it is a loop right away.
+    file_id_t base_file_id = kInvalidFileID;
+    struct stat base_buf;
+    if (fstat(dirfd(base_dir_fp), &base_buf) == 0 &&
+        this->visited_files.insert(file_id_t::from_stat(base_buf)).second) {
+        base_file_id = file_id_t::from_stat(base_buf);
+    }
+
     wcstring name_str;
     while (!interrupted_or_overflowed() && wreaddir_for_dirs(base_dir_fp, &name_str)) {
         // Note that it's critical we ignore leading dots here, else we may descend into . and ..
@@ -764,6 +798,10 @@ void wildcard_expander_t::expand_intermediate_segment(const wcstring &base_dir,
             // Doesn't match the wildcard for this segment, skip it.
             continue;
         }
+        if (this->depth >= this->max_depth) {
+            this->did_hit_max_depth = true;
+            break;
+        }
 
         wcstring full_path = base_dir + name_str;
         struct stat buf;
@@ -781,12 +819,19 @@ void wildcard_expander_t::expand_intermediate_segment(const wcstring &base_dir,
         // We made it through. Perform normal wildcard expansion on this new directory, starting at
         // our tail_wc, which includes the ANY_STRING_RECURSIVE guy.
         full_path.push_back(L'/');
-        this->expand(full_path, wc_remainder, prefix + wc_segment + L'/');
+        {
+            const scoped_push<size_t> deeper(&this->depth, this->depth + 1);
+            this->expand(full_path, wc_remainder, prefix + wc_segment + L'/');
+        }
 
         // Now remove the visited file. This is for #2414: only directories "beneath" us should be
         // considered visited.
         this->visited_files.erase(file_id);
     }
+
+    if (base_file_id != kInvalidFileID) {
+        this->visited_files.erase(base_file_id);
+    }
 }
 
 void wildcard_expander_t::expand_literal_intermediate_segment_with_fuzz(const wcstring &base_dir,
@@ -931,13 +976,15 @@ void wildcard_expander_t::expand(const wcstring &base_dir, const wchar_t *wc,
     } else {
         assert(!wc_segment.empty() && (segment_has_wildcards || is_last_segment));
 
-        if (!is_last_segment && wc_segment == wcstring{ANY_STRING_RECURSIVE}) {
+        if (!is_last_segment && wc_segment == wcstring{ANY_STRING_RECURSIVE} && *wc_remainder) {
             // Hack for #7222. This is an intermediate wc segment that is exactly **. The
             // tail matches in subdirectories as normal, but also the current directory.
             // That is, '**/bar' may match 'bar' and 'foo/bar'.
             // Implement this by matching the wildcard tail only, in this directory.
             // Note if the segment is not exactly ANY_STRING_RECURSIVE then the segment may only
             // match subdirectories.
+            // A trailing '**/' has no tail, and matches the same directories as '**', which do not
+            // include the current one.
             this->expand(base_dir, wc_remainder, effective_prefix);
             if (interrupted_or_overflowed()) {
                 return;
@@ -972,6 +1019,8 @@ void wildcard_expander_t::expand(const wcstring &base_dir, const wchar_t *wc,
                                                   effective_prefix);
             }
             closedir(dir);
+        } else if (errno == EACCES) {
+            this->unreadable_dirs++;
         }
     }
 }
@@ -1025,5 +1074,6 @@ wildcard_result_t wildcard_expand_string(const wcstring &wc, const wcstring &wor
 
     wildcard_expander_t expander(prefix, flags, cancel_checker, output);
     expander.expand(base_dir, effective_wc.c_str(), base_dir);
+    expander.log_skipped_directories();
     return expander.status_code();
 }
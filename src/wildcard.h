@@ -8,6 +8,7 @@
 #include "common.h"
 #include "complete.h"
 #include "expand.h"
+#include "global_safety.h"
 
 // Enumeration of all wildcard types.
 enum {
@@ -45,6 +46,13 @@ enum class wildcard_result_t {
     cancel,    /// Expansion was cancelled (e.g. control-C).
     overflow,  /// Expansion produced too many results.
 };
+/// The default for how many directories deep a recursive ** wildcard descends.
+constexpr size_t kDefaultGlobMaxDepth = 512;
+
+/// How many directories deep a recursive ** wildcard descends. This is set by the
+/// fish_glob_max_depth variable.
+extern relaxed_atomic_t<size_t> glob_max_depth;
+
 wildcard_result_t wildcard_expand_string(const wcstring &wc, const wcstring &working_directory,
                                          expand_flags_t flags,
                                          const cancel_checker_t &cancel_checker,
@@ -8,6 +8,7 @@
 #include <errno.h>
 #include <fcntl.h>
 #include <libgen.h>
+#include <locale.h>
 #include <limits.h>
 #include <stdio.h>
 #include <stdlib.h>
@@ -23,6 +24,7 @@
 #include <cwchar>
 #include <string>
 #include <unordered_map>
+#include <vector>
 
 #include "common.h"
 #include "fallback.h"  // IWYU pragma: keep
@@ -34,8 +36,18 @@ using cstring = std::string;
 
 const file_id_t kInvalidFileID{};
 
-/// Map used as cache by wgettext.
-static owning_lock<std::unordered_map<wcstring, wcstring>> wgettext_map;
+namespace {
+/// Cache used by wgettext.
+struct wgettext_cache_t {
+    using translation_map_t = std::unordered_map<wcstring, wcstring>;
+    /// Translations, by message catalog. Nothing is ever removed, because wgettext hands out
+    /// references into these maps.
+    std::unordered_map<std::string, translation_map_t> catalogs;
+    /// The translations for the current message locale.
+    translation_map_t *current = &catalogs[""];
+};
+}  // namespace
+static owning_lock<wgettext_cache_t> wgettext_cache;
 
 bool wreaddir_resolving(DIR *dir, const wcstring &dir_path, wcstring &out_name, bool *out_is_dir) {
     struct dirent *result = readdir(dir);
@@ -165,6 +177,22 @@ int lwstat(const wcstring &file_name, struct stat *buf) {
     return lstat(tmp.c_str(), buf);
 }
 
+maybe_t<wcstring> wreadlink(const wcstring &file_name, int dir_fd) {
+    const cstring tmp = wcs2string(file_name);
+    // readlink() silently truncates the target to the buffer, so a full buffer means we have to
+    // retry with a larger one. Give up at some point, in case the error is about file_name.
+    std::vector<char> buff(PATH_MAX);
+    for (;;) {
+        ssize_t len = readlinkat(dir_fd, tmp.c_str(), buff.data(), buff.size());
+        if (len < 0 && errno != ENAMETOOLONG) return none();
+        if (len >= 0 && static_cast<size_t>(len) < buff.size()) {
+            return str2wcstring(buff.data(), len);
+        }
+        if (buff.size() >= 16 * PATH_MAX) return none();
+        buff.resize(buff.size() * 2);
+    }
+}
+
 int waccess(const wcstring &file_name, int mode) {
     const cstring tmp = wcs2string(file_name);
     return access(tmp.c_str(), mode);
@@ -460,8 +488,8 @@ const wcstring &wgettext(const wchar_t *in) {
     wcstring key = in;
 
     wgettext_init_if_necessary();
-    auto wmap = wgettext_map.acquire();
-    wcstring &val = (*wmap)[key];
+    auto cache = wgettext_cache.acquire();
+    wcstring &val = (*cache->current)[key];
     if (val.empty()) {
         cstring mbs_in = wcs2string(key);
         char *out = fish_gettext(mbs_in.c_str());
@@ -474,6 +502,17 @@ const wcstring &wgettext(const wchar_t *in) {
     return val;
 }
 
+void wgettext_locale_changed() {
+    // gettext picks the catalog from LANGUAGE as well as the LC_MESSAGES locale.
+    std::string catalog = setlocale(LC_MESSAGES, nullptr);
+    if (const char *language = getenv("LANGUAGE")) {
+        catalog.push_back(':');
+        catalog.append(language);
+    }
+    auto cache = wgettext_cache.acquire();
+    cache->current = &cache->catalogs[catalog];
+}
+
 int wmkdir(const wcstring &name, int mode) {
     cstring name_narrow = wcs2string(name);
     return mkdir(name_narrow.c_str(), mode);
@@ -485,6 +524,11 @@ int wrename(const wcstring &old, const wcstring &newv) {
     return rename(old_narrow.c_str(), new_narrow.c_str());
 }
 
+int wchmod(const wcstring &name, mode_t mode) {
+    cstring name_narrow = wcs2string(name);
+    return chmod(name_narrow.c_str(), mode);
+}
+
 ssize_t wwrite_to_fd(const wchar_t *input, size_t input_len, int fd) {
     // Accumulate data in a local buffer.
     char accum[512];
@@ -535,6 +579,112 @@ ssize_t wwrite_to_fd(const wchar_t *input, size_t input_len, int fd) {
     return success ? total_written : -1;
 }
 
+static bool is_encode_direct(uint32_t cp) {
+    return cp >= static_cast<uint32_t>(ENCODE_DIRECT_BASE) &&
+           cp < static_cast<uint32_t>(ENCODE_DIRECT_END);
+}
+
+std::string wcs2utf8(const wchar_t *in, size_t len) {
+    std::string result;
+    result.reserve(len);
+    for (size_t i = 0; i < len; i++) {
+        auto cp = static_cast<uint32_t>(in[i]);
+        if (sizeof(wchar_t) == 2 && cp >= 0xD800 && cp <= 0xDBFF && i + 1 < len) {
+            // Combine a UTF-16 surrogate pair.
+            auto low = static_cast<uint32_t>(in[i + 1]);
+            if (low >= 0xDC00 && low <= 0xDFFF) {
+                cp = 0x10000 + ((cp - 0xD800) << 10) + (low - 0xDC00);
+                i++;
+            }
+        }
+
+        if (in[i] == INTERNAL_SEPARATOR) {
+            // Like wcs2string, drop it.
+        } else if (is_encode_direct(cp)) {
+            result.push_back(static_cast<char>(cp - ENCODE_DIRECT_BASE));
+        } else if (cp < 0x80) {
+            result.push_back(static_cast<char>(cp));
+        } else if (cp < 0x800) {
+            result.push_back(static_cast<char>(0xC0 | (cp >> 6)));
+            result.push_back(static_cast<char>(0x80 | (cp & 0x3F)));
+        } else if (cp >= 0xD800 && cp <= 0xDFFF) {
+            // Unpaired surrogate, this has no UTF-8 representation.
+            wcs2string_bad_char(in[i]);
+        } else if (cp < 0x10000) {
+            result.push_back(static_cast<char>(0xE0 | (cp >> 12)));
+            result.push_back(static_cast<char>(0x80 | ((cp >> 6) & 0x3F)));
+            result.push_back(static_cast<char>(0x80 | (cp & 0x3F)));
+        } else if (cp <= 0x10FFFF) {
+            result.push_back(static_cast<char>(0xF0 | (cp >> 18)));
+            result.push_back(static_cast<char>(0x80 | ((cp >> 12) & 0x3F)));
+            result.push_back(static_cast<char>(0x80 | ((cp >> 6) & 0x3F)));
+            result.push_back(static_cast<char>(0x80 | (cp & 0x3F)));
+        } else {
+            wcs2string_bad_char(in[i]);
+        }
+    }
+    return result;
+}
+
+std::string wcs2utf8(const wcstring &in) { return wcs2utf8(in.data(), in.size()); }
+
+/// Decode the UTF-8 sequence at the start of \p in, which holds \p len > 0 bytes.
+/// \return the number of bytes in the sequence, storing the code point in \p out_cp,
+/// or 0 if the sequence is invalid, overlong, truncated or encodes a surrogate.
+static size_t decode_utf8_char(const unsigned char *in, size_t len, uint32_t *out_cp) {
+    unsigned char lead = in[0];
+    size_t n;
+    uint32_t cp, min;
+    if (lead < 0x80) {
+        *out_cp = lead;
+        return 1;
+    } else if ((lead & 0xE0) == 0xC0) {
+        n = 2, cp = lead & 0x1F, min = 0x80;
+    } else if ((lead & 0xF0) == 0xE0) {
+        n = 3, cp = lead & 0x0F, min = 0x800;
+    } else if ((lead & 0xF8) == 0xF0) {
+        n = 4, cp = lead & 0x07, min = 0x10000;
+    } else {
+        return 0;
+    }
+    if (len < n) return 0;
+    for (size_t i = 1; i < n; i++) {
+        if ((in[i] & 0xC0) != 0x80) return 0;
+        cp = (cp << 6) | (in[i] & 0x3F);
+    }
+    if (cp < min || cp > 0x10FFFF || (cp >= 0xD800 && cp <= 0xDFFF)) return 0;
+    *out_cp = cp;
+    return n;
+}
+
+wcstring utf8towcs(const char *in, size_t len) {
+    wcstring result;
+    result.reserve(len);
+    const auto *bytes = reinterpret_cast<const unsigned char *>(in);
+    size_t pos = 0;
+    while (pos < len) {
+        uint32_t cp = 0;
+        size_t n = decode_utf8_char(bytes + pos, len - pos, &cp);
+        if (n == 0 || is_encode_direct(cp) || cp == static_cast<uint32_t>(INTERNAL_SEPARATOR)) {
+            // Store the first byte on its own, and try again from the next one.
+            result.push_back(ENCODE_DIRECT_BASE + bytes[pos]);
+            pos++;
+            continue;
+        }
+        if (sizeof(wchar_t) == 2 && cp >= 0x10000) {
+            cp -= 0x10000;
+            result.push_back(static_cast<wchar_t>(0xD800 + (cp >> 10)));
+            result.push_back(static_cast<wchar_t>(0xDC00 + (cp & 0x3FF)));
+        } else {
+            result.push_back(static_cast<wchar_t>(cp));
+        }
+        pos += n;
+    }
+    return result;
+}
+
+wcstring utf8towcs(const std::string &in) { return utf8towcs(in.data(), in.size()); }
+
 /// Return one if the code point is in a Unicode private use area.
 static int fish_is_pua(wint_t wc) {
     if (PUA1_START <= wc && wc < PUA1_END) return 1;
@@ -3,6 +3,7 @@
 #define FISH_WUTIL_H
 
 #include <dirent.h>
+#include <fcntl.h>
 #include <locale.h>
 #include <stddef.h>
 #include <stdio.h>
@@ -53,6 +54,10 @@ wcstring wgetcwd();
 /// \returns the canonicalized path, or none if the path is invalid.
 maybe_t<wcstring> wrealpath(const wcstring &pathname);
 
+/// Wide character version of readlink(). Relative paths are looked up from \p dir_fd.
+/// \returns the target of the symbolic link, or none if it is not one or on error.
+maybe_t<wcstring> wreadlink(const wcstring &file_name, int dir_fd = AT_FDCWD);
+
 /// Given an input path, "normalize" it:
 /// 1. Collapse multiple /s into a single /, except maybe at the beginning.
 /// 2. .. goes up a level.
@@ -88,12 +93,19 @@ std::wstring wbasename(std::wstring path);
 /// will be nothing more than a wrapper around gettext, like all other functions in this file.
 const wcstring &wgettext(const wchar_t *in);
 
+/// Make wgettext look up translations again, because the message locale has changed. Strings it
+/// returned before stay valid.
+void wgettext_locale_changed();
+
 /// Wide character version of mkdir.
 int wmkdir(const wcstring &name, int mode);
 
 /// Wide character version of rename.
 int wrename(const wcstring &oldName, const wcstring &newv);
 
+/// Wide character version of chmod.
+int wchmod(const wcstring &name, mode_t mode);
+
 /// Write a wide string to a file descriptor. This avoids doing any additional allocation.
 /// This does NOT retry on EINTR or EAGAIN, it simply returns.
 /// \return -1 on error in which case errno will have been set. In this event, the number of bytes
@@ -105,6 +117,18 @@ inline ssize_t wwrite_to_fd(const wcstring &s, int fd) {
     return wwrite_to_fd(s.c_str(), s.size(), fd);
 }
 
+/// Convert a wide string to UTF-8, regardless of the locale. Characters in the ENCODE_DIRECT range
+/// are written as the byte they stand for, so invalid input read by utf8towcs() round-trips.
+/// Characters that cannot be encoded, like unpaired surrogates, are dropped.
+std::string wcs2utf8(const wchar_t *in, size_t len);
+std::string wcs2utf8(const wcstring &in);
+
+/// Convert UTF-8 to a wide string, regardless of the locale. Bytes that are not part of a valid
+/// UTF-8 sequence are stored as ENCODE_DIRECT characters, as are sequences that decode to that
+/// range or to INTERNAL_SEPARATOR, so that wcs2utf8() gives back the original bytes.
+wcstring utf8towcs(const char *in, size_t len);
+wcstring utf8towcs(const std::string &in);
+
 #define PUA1_START 0xE000
 #define PUA1_END 0xF900
 #define PUA2_START 0xF0000
@@ -111,4 +111,48 @@ bind --erase --user --preset \t
 bind \t
 # CHECK: bind --preset \t complete
 
+# Mode inheritance
+bind -M inherit_child --inherits inherit_parent
+bind -M inherit_child
+# CHECK: bind -M inherit_child --inherits inherit_parent
+bind -M inherit_parent --inherits inherit_child
+# CHECKERR: bind: Mode 'inherit_parent' can not inherit from 'inherit_child', which inherits from it
+echo $status
+# CHECK: 1
+bind -M inherit_child --inherits inherit_parent \cx true
+# CHECKERR: bind: Expected 0 args, got 2
+bind -M inherit_child --inherits 'not a mode'
+# CHECKERR: bind: mode name 'not a mode' is not valid. See `help identifiers`.
+bind -M inherit_child --inherits ''
+bind -M inherit_child
+echo $status
+# CHECK: 0
+
+# Ignored sequences
+bind -M ignore_mode --ignore \e\[
+bind -M ignore_mode --ignore zq
+bind -M ignore_mode
+# CHECK: bind -M ignore_mode --ignore \e\[
+# CHECK: bind -M ignore_mode --ignore zq
+bind -M ignore_mode zq
+# CHECK: bind -M ignore_mode --ignore zq
+bind -M ignore_mode --erase zq
+bind -M ignore_mode zq
+# CHECKERR: bind: No binding found for sequence 'zq'
+# --silent only hides the error.
+bind -M ignore_mode --silent zq
+echo $status
+# CHECK: 1
+bind -M ignore_mode --ignore zq up-line
+# CHECKERR: bind: Expected 1 args, got 2
+
+# Timeouts
+bind -M timeout_mode --timeout 500 \eq true
+bind -M timeout_mode \eq
+# CHECK: bind -M timeout_mode --timeout 500 \eq true
+bind -M timeout_mode --timeout 5 \eq true
+# CHECKERR: bind: Invalid timeout '5', expected a number of milliseconds from 10 to 4999
+bind -M timeout_mode --timeout soon \eq true
+# CHECKERR: bind: Invalid timeout 'soon', expected a number of milliseconds from 10 to 4999
+
 exit 0
@@ -10,4 +10,17 @@ builtin -nq string
 #CHECKERR: --query and --names are mutually exclusive
 echo $status
 #CHECK: 2
+
+# --names lists every builtin once, one per line, sorted.
+set -l names (builtin --names)
+string join \n -- $names | env LC_ALL=C sort -c; and echo sorted
+#CHECK: sorted
+test (count $names) -eq (count (printf '%s\n' $names | sort -u)); and echo unique
+#CHECK: unique
+contains -- builtin $names; and contains -- string $names; and contains -- [ $names
+and echo found
+#CHECK: found
+for name in $names
+    builtin -q -- $name; or echo "$name is not a builtin"
+end
 exit 0
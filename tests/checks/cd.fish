@@ -130,32 +130,38 @@ echo $status
 set -l old_cdpath $CDPATH
 set -l old_path $PWD
 cd nonexistent
+echo $status
 #CHECKERR: cd: The directory 'nonexistent' does not exist
 #CHECKERR: {{.*}}/cd.fish (line {{\d+}}):
 #CHECKERR: builtin cd $argv
 #CHECKERR: ^
 #CHECKERR: in function 'cd' with arguments 'nonexistent'
 #CHECKERR: called on line {{\d+}} of file {{.*}}/cd.fish
+#CHECK: 3
 
 touch file
 cd file
+echo $status
 #CHECKERR: cd: 'file' is not a directory
 #CHECKERR: {{.*}}/cd.fish (line {{\d+}}):
 #CHECKERR: builtin cd $argv
 #CHECKERR: ^
 #CHECKERR: in function 'cd' with arguments 'file'
 #CHECKERR: called on line {{\d+}} of file {{.*}}/cd.fish
+#CHECK: 1
 
 # a directory that isn't executable
 mkdir bad-perms
 chmod -x bad-perms
 cd bad-perms
+echo $status
 #CHECKERR: cd: Permission denied: 'bad-perms'
 #CHECKERR: {{.*}}/cd.fish (line {{\d+}}):
 #CHECKERR: builtin cd $argv
 #CHECKERR: ^
 #CHECKERR: in function 'cd' with arguments 'bad-perms'
 #CHECKERR: called on line {{\d+}} of file {{.*}}/cd.fish
+#CHECK: 4
 
 cd $old_path
 mkdir -p cdpath-dir/bad-perms
@@ -0,0 +1,64 @@
+# RUN: %fish %s
+
+# $(...) is the same as (...) when unquoted.
+echo $(echo a)
+# CHECK: a
+count $(printf '%s\n' a b c)
+# CHECK: 3
+echo x$(echo a b)y
+# CHECK: xa by
+echo $(printf '%s\n' a b)[2]
+# CHECK: b
+
+# Inside double quotes, it expands to exactly one argument, keeping newlines
+# but dropping the trailing one.
+echo "x$(printf 'a\nb')y"
+# CHECK: xa
+# CHECK: by
+count "$(printf 'a\nb\n')"
+# CHECK: 1
+count "$(true)"
+# CHECK: 1
+set -l empty "$(true)"
+set -q empty[1] && test -z "$empty" && echo empty
+# CHECK: empty
+count "a $(printf '%s\n' 1 2) b $(echo 3) c"
+# CHECK: 1
+echo "a $(printf '%s\n' 1 2) b $(echo 3) c"
+# CHECK: a 1
+# CHECK: 2 b 3 c
+
+# Slices are not applied inside quotes.
+echo "$(echo a)[1]"
+# CHECK: a[1]
+
+# Quotes inside the command substitution start new strings.
+echo "$(echo "a  b")"
+# CHECK: a  b
+set -l var value
+echo "$var $(echo "$var" '$var')"
+# CHECK: value value $var
+
+# Nesting.
+echo $(echo (echo x))
+# CHECK: x
+echo "$(echo "$(echo x)" (echo y))"
+# CHECK: x y
+echo (echo "$(echo z)")
+# CHECK: z
+
+# The $ can be escaped.
+echo \$(echo a)
+# CHECK: $a
+echo "\$(echo a)"
+# CHECK: $(echo a)
+
+# Unbalanced $( gets its own error.
+eval 'echo $(echo a'
+# CHECKERR: {{.*}}checks/cmdsub-dollar.fish (line {{\d+}}): Unexpected end of string, expecting ')' to close '$('
+# CHECKERR: echo $(echo a
+# CHECKERR: ^
+eval 'echo "$(echo a'
+# CHECKERR: {{.*}}checks/cmdsub-dollar.fish (line {{\d+}}): Unexpected end of string, expecting ')' to close '$('
+# CHECKERR: echo "$(echo a
+# CHECKERR: ^
@@ -0,0 +1,33 @@
+#RUN: %fish %s
+
+# --resolve-tilde expands a leading ~ before looking for the file.
+set -l tmpdir (mktemp -d)
+set -gx HOME $tmpdir
+mkdir $HOME/bin
+printf '#!/bin/sh\n' >$HOME/bin/mytool
+chmod +x $HOME/bin/mytool
+
+command -v '~/bin/mytool'
+echo $status
+# CHECK: 1
+command -v --resolve-tilde '~/bin/mytool' | string replace -- $HOME HOME
+# CHECK: HOME/bin/mytool
+
+# It implies --search.
+set -l name '~/bin/mytool'
+command --resolve-tilde $name | string replace -- $HOME HOME
+# CHECK: HOME/bin/mytool
+command --resolve-tilde --all $name | string replace -- $HOME HOME
+# CHECK: HOME/bin/mytool
+
+# Files that don't exist are still not found.
+command -q --resolve-tilde '~/bin/nosuchtool'
+echo $status
+# CHECK: 1
+
+# An unknown ~user is left alone.
+command -v --resolve-tilde '~nosuchuser_fish_test/bin/mytool'
+echo $status
+# CHECK: 1
+
+rm -r $tmpdir
@@ -165,6 +165,19 @@ complete -c complete_test_function_desc -xa '(complete -Csome_function)'
 complete -C'complete_test_function_desc ' | count
 # CHECK: 1
 
+# bind: key names after --key, input functions after the sequence,
+# with option arguments like --mode skipped.
+complete -C'bind -M insert -k ' | string match -q 'home*' && echo key names
+# CHECK: key names
+complete -C'bind -M insert \cx ' | string match -q 'beginning-of-line*' && echo functions
+# CHECK: functions
+complete -C'bind \cx forward-char ' | string match -q 'beginning-of-line*' && echo more functions
+# CHECK: more functions
+complete -C'bind -e -k home ' | string match -q 'end*' && echo erase key names
+# CHECK: erase key names
+complete -C'bind -e -k home ' | string match -q 'beginning-of-line*' || echo no erase functions
+# CHECK: no erase functions
+
 complete -c prev-arg-variable -f
 complete -C'prev-arg-variable $HOME '
 
@@ -385,6 +398,9 @@ set -l dir (mktemp -d)
 echo >$dir/target
 complete -C ': $dir/'
 # CHECK: $dir/target
+# Completions that replace the token, like case-insensitive ones, keep the variable.
+complete -C ': $dir/TAR'
+# CHECK: $dir/target
 rm $dir/target
 
 cd $dir
@@ -423,3 +439,189 @@ begin
 end
 
 rm -r $dir
+
+# Identical argument lists are only expanded once per completion run,
+# even if several completions use them.
+set -g __complete_test_arg_runs 0
+function __complete_test_args
+    set -g __complete_test_arg_runs (math $__complete_test_arg_runs + 1)
+    echo arg-one
+    echo arg-two
+end
+complete -c complete_test_cached -xa '(__complete_test_args)'
+complete -c complete_test_cached -n true -xa '(__complete_test_args)' -d 'with a description'
+complete -c complete_test_cached -n true -xa '(__complete_test_args; echo arg-three)'
+complete -C 'complete_test_cached arg-th'
+# CHECK: arg-three
+echo $__complete_test_arg_runs
+# CHECK: 2
+# They are expanded again once other commands ran, like the `complete` here.
+complete -C 'complete_test_cached arg-o' >/dev/null
+echo $__complete_test_arg_runs
+# CHECK: 4
+
+# --escape prints completions as they would be inserted into the commandline.
+complete -c complete_test_escape -xa "'with space' 'semi;colon' 'dollar\$sign' 'star*' plain"
+complete -C 'complete_test_escape '
+# CHECK: with space
+# CHECK: semi;colon
+# CHECK: dollar$sign
+# CHECK: star*
+# CHECK: plain
+complete -C 'complete_test_escape ' --escape
+# CHECK: with\ space
+# CHECK: semi\;colon
+# CHECK: dollar\$sign
+# CHECK: star\*
+# CHECK: plain
+# The escaped output can be used as fish input again.
+for arg in (complete --escape -C 'complete_test_escape w')
+    eval printf '%s\n' $arg
+end
+# CHECK: with space
+# Quotes the user already typed are kept and closed.
+complete --escape -C "complete_test_escape 'w"
+# CHECK: 'with space'
+
+# With $fish_completion_matching set to fuzzy, candidates typed with characters out of order
+# also match.
+complete -c complete_test_fuzzy -xa 'git gitk tig'
+complete -C 'complete_test_fuzzy gti'
+echo nothing
+# CHECK: nothing
+set -g fish_completion_matching fuzzy
+complete -C 'complete_test_fuzzy gti'
+# CHECK: git
+# CHECK: gitk
+function complete_test_fuzzy_func; end
+complete -C complete_test_fuzzy_fnuc
+# CHECK: complete_test_fuzzy_func
+set -e fish_completion_matching
+complete -C 'complete_test_fuzzy gti'
+echo nothing
+# CHECK: nothing
+
+# With $fish_completion_cache_ttl, argument lists are only expanded again after that many
+# seconds, even if other commands ran, or if the commandline before the cursor changed.
+set -g __complete_test_ttl_runs 0
+function __complete_test_ttl_args
+    set -g __complete_test_ttl_runs (math $__complete_test_ttl_runs + 1)
+    printf '%s\n' pkg-one pkg-two
+end
+complete -c complete_test_ttl -xa '(__complete_test_ttl_args)'
+set -g fish_completion_cache_ttl 60
+complete -C 'complete_test_ttl pkg-on'
+# CHECK: pkg-one
+complete -C 'complete_test_ttl pkg-on' >/dev/null
+complete -C 'complete_test_ttl pkg-on' >/dev/null
+echo $__complete_test_ttl_runs
+# CHECK: 1
+# A different token or earlier token means a different key.
+complete -C 'complete_test_ttl pkg-tw'
+# CHECK: pkg-two
+complete -C 'complete_test_ttl pkg-one pkg-on' >/dev/null
+echo $__complete_test_ttl_runs
+# CHECK: 3
+set -g fish_completion_cache_ttl 0.1
+complete -C 'complete_test_ttl pkg-' >/dev/null
+sleep 0.2
+complete -C 'complete_test_ttl pkg-' >/dev/null
+echo $__complete_test_ttl_runs
+# CHECK: 5
+set -e fish_completion_cache_ttl
+complete -C 'complete_test_ttl pkg-on' >/dev/null
+complete -C 'complete_test_ttl pkg-on' >/dev/null
+echo $__complete_test_ttl_runs
+# CHECK: 7
+
+# --only arguments replace all other completions while their condition is true.
+complete -c complete_test_only -l verbose
+complete -c complete_test_only -n 'not __fish_seen_subcommand_from push pull' -fa 'push pull'
+complete -c complete_test_only -n '__fish_seen_subcommand_from push' --only -a 'origin upstream'
+complete -C 'complete_test_only '
+# CHECK: push
+# CHECK: pull
+complete -C 'complete_test_only push '
+# CHECK: origin
+# CHECK: upstream
+complete -C 'complete_test_only push -'
+complete -C 'complete_test_only -'
+# CHECK: --verbose
+complete -c complete_test_only
+# CHECK: complete --only complete_test_only -a 'origin upstream' -n '__fish_seen_subcommand_from push'
+# CHECK: complete --no-files complete_test_only -a 'push pull' -n 'not __fish_seen_subcommand_from push pull'
+# CHECK: complete complete_test_only -l verbose
+
+# --position restricts arguments to the Nth argument that is not an option, or with N- to it and
+# those after it.
+complete -c complete_test_pos -f
+complete -c complete_test_pos -s o -x -a 'x y'
+complete -c complete_test_pos --position 1 -a 'start stop'
+complete -c complete_test_pos --position 2- -a 'alpha beta'
+complete -C 'complete_test_pos '
+# CHECK: start
+# CHECK: stop
+complete -C 'complete_test_pos st'
+# CHECK: start
+# CHECK: stop
+complete -C 'complete_test_pos start '
+# CHECK: alpha
+# CHECK: beta
+complete -C 'complete_test_pos start alpha '
+# CHECK: alpha
+# CHECK: beta
+# Options and their parameters are not counted, except after --.
+complete -C 'complete_test_pos -o x '
+# CHECK: start
+# CHECK: stop
+complete -C 'complete_test_pos -- -o '
+# CHECK: alpha
+# CHECK: beta
+complete -c complete_test_pos
+# CHECK: complete --position 2- complete_test_pos -a 'alpha beta'
+# CHECK: complete --position 1 complete_test_pos -a 'start stop'
+# CHECK: complete --exclusive complete_test_pos -s o -a 'x y'
+# CHECK: complete --no-files complete_test_pos
+complete -c complete_test_pos --position 0 -a nope
+# CHECKERR: complete: Invalid position '0'
+complete -c complete_test_pos --position two -a nope
+# CHECKERR: complete: Invalid position 'two'
+
+# --short-option-requires-argument completes the parameter only in the same token, like -n5.
+complete -c complete_test_attached -f
+complete -c complete_test_attached -s v
+complete -c complete_test_attached -s n --short-option-requires-argument -a '5 10'
+complete -c complete_test_attached --position 1 -a 'first'
+complete -C'complete_test_attached -n'
+# CHECK: -n5
+# CHECK: -n10
+complete -C'complete_test_attached -n1'
+# CHECK: -n10
+complete -C'complete_test_attached -vn'
+# CHECK: -vn5
+# CHECK: -vn10
+# The next argument is not the parameter, and -n5 is not counted as an argument.
+complete -C'complete_test_attached -n '
+# CHECK: first
+complete -C'complete_test_attached -n5 '
+# CHECK: first
+complete -c complete_test_attached
+# CHECK: complete --position 1 complete_test_attached -a first
+# CHECK: complete --short-option-requires-argument complete_test_attached -s n -a '5 10'
+# CHECK: complete complete_test_attached -s v
+# CHECK: complete --no-files complete_test_attached
+
+# fg and bg complete job specs, described by the job's command.
+sleep 10 &
+set -l first_pid $last_pid
+sleep 11 &
+set -l second_pid $last_pid
+complete -C'fg ' | sort
+# CHECK: %1{{\t}}sleep 10 &
+# CHECK: %2{{\t}}sleep 11 &
+kill $first_pid
+wait $first_pid
+complete -C'bg '
+# CHECK: %2{{\t}}sleep 11 &
+kill $second_pid
+wait $second_pid
@@ -0,0 +1,55 @@
+# RUN: %fish %s
+
+function on_later --on-event later
+    echo handled later: $argv
+end
+
+function emitter
+    emit --async later first
+    emit --async later second
+    echo emitter done
+end
+
+# The handlers run after the function that called emitter returns, in order.
+function caller
+    emitter
+    echo emitter returned
+end
+caller
+echo next command
+# CHECK: emitter done
+# CHECK: emitter returned
+# CHECK: handled later: first
+# CHECK: handled later: second
+# CHECK: next command
+
+# Without --async the handler runs right away.
+function sync_emitter
+    emit later now
+    echo sync emitter done
+end
+sync_emitter
+# CHECK: handled later: now
+# CHECK: sync emitter done
+
+# A handler may emit its own event asynchronously; that runs after the next command.
+function again --on-event again
+    echo again: $argv
+    if test $argv -lt 3
+        emit --async again (math $argv + 1)
+    end
+end
+emit --async again 1
+echo between
+echo end
+# CHECK: again: 1
+# CHECK: between
+# CHECK: again: 2
+# CHECK: end
+# CHECK: again: 3
+
+# Command substitutions do not run the handlers.
+set -l out (emit --async later from-cmdsub; echo in cmdsub)
+echo $out
+# CHECK: handled later: from-cmdsub
+# CHECK: in cmdsub
@@ -1,4 +1,4 @@
-#RUN: %fish %s
+#RUN: %fish -C "set -g fish %fish" %s
 exec cat <nosuchfile
 #CHECKERR: warning: An error occurred while redirecting file 'nosuchfile'
 #CHECKERR: open: No such file or directory
@@ -10,6 +10,48 @@ not exec cat <nosuchfile
 echo "neg failed: $status"
 #CHECK: neg failed: 0
 
+# A file that is executable, but that the OS can't run, and that isn't a shell script either.
+set -l tmpdir (mktemp -d)
+printf '\x7fELF\x00\x00' >$tmpdir/broken
+chmod +x $tmpdir/broken
+echo fd three >$tmpdir/three
+
+# A failed exec returns to the shell, with its fds as they were.
+exec 5<$tmpdir/three
+exec $tmpdir/broken 5</dev/null 2>/dev/null
+echo "broken: $status"
+#CHECK: broken: 126
+read -l line <&5
+echo $line
+#CHECK: fd three
+exec 5<&-
+
+exec --keep-fd 3 nosuchcommand
+#CHECKERR: warning: exec: Unknown command 'nosuchcommand'
+echo "unknown: $status"
+#CHECK: unknown: 127
+exec --keep-fd 7 true
+#CHECKERR: warning: exec: fd 7 is not open
+echo "not open: $status"
+#CHECK: not open: 2
+exec --frobnicate true
+#CHECKERR: warning: exec: Unknown option '--frobnicate'
+echo "bad option: $status"
+#CHECK: bad option: 2
+exec --keep-fd 3 cat <nosuchfile
+#CHECKERR: warning: An error occurred while redirecting file 'nosuchfile'
+#CHECKERR: open: No such file or directory
+echo "bad redirection: $status"
+#CHECK: bad redirection: 1
+
+# --keep-fd passes an fd on that would otherwise be closed, --force exits if the exec fails.
+$fish -c 'exec --keep-fd 3 sh -c "cat <&3"' 3<$tmpdir/three
+#CHECK: fd three
+$fish -c "exec --force $tmpdir/broken 2>/dev/null; echo not reached"
+echo "forced: $status"
+#CHECK: forced: 126
+rm -r $tmpdir
+
 # This needs to be last, because it actually runs exec.
 exec cat </dev/null
 echo "not reached"
@@ -231,6 +231,44 @@ echo $foo[d]
 #CHECKERR: {{.*}}expansion.fish (line {{\d+}}): Invalid index value
 #CHECKERR: echo $foo[d]
 #CHECKERR: ^
+echo "$foo[0]"
+#CHECKERR: {{.*}}expansion.fish (line {{\d+}}): array indices start at 1, not 0.
+#CHECKERR: echo "$foo[0]"
+#CHECKERR: ^
+echo "x$foo[1..d]y"
+#CHECKERR: {{.*}}expansion.fish (line {{\d+}}): Invalid index value
+#CHECKERR: echo "x$foo[1..d]y"
+#CHECKERR: ^
+
+# A slice inside double quotes selects the same elements as without quotes,
+# and only then joins them with spaces.
+set -l foo a "b c" d e
+for slice in 1 -1 2 '2 1' 2..3 3..2 -2..-1 1..-1..2 -1..1..-2 5 1..7
+    set -l quoted (eval echo \"\$foo[$slice]\")
+    set -l unquoted (eval string join "' '" -- \$foo[$slice])
+    test "$quoted" = "$unquoted"
+    or echo "mismatch for [$slice]: '$quoted' vs '$unquoted'"
+    echo "[$slice] $quoted"
+end
+#CHECK: [1] a
+#CHECK: [-1] e
+#CHECK: [2] b c
+#CHECK: [2 1] b c a
+#CHECK: [2..3] b c d
+#CHECK: [3..2] d b c
+#CHECK: [-2..-1] d e
+#CHECK: [1..-1..2] a d
+#CHECK: [-1..1..-2] e b c
+#CHECK: [5] 
+#CHECK: [1..7] a b c d e
+expansion "x$foo[2..3]y" x$foo[2..3]y
+#CHECK: 3 xb c dy xb cy xdy
+
+function last_arg
+    echo "[$argv[-1]]" "$argv[2..-1]" "$argv[-1..1]"
+end
+last_arg 1 "2 3" 4
+#CHECK: [4] 2 3 4 4 2 3 1
 
 echo ()[1]
 # No output
@@ -0,0 +1,10 @@
+# RUN: %fish -C "set helper %fish_test_helper; set fish %fish" %s
+# The fd-leaks debug category is only built into debug builds.
+#REQUIRES: %fish --print-debug-categories | grep -q '^fd-leaks '
+
+# The fd-leaks debug category reports fds which would leak into a child.
+$fish -d fd-leaks -c "$helper print_fds 3</dev/null" 2>&1 | string match -v 'Debug enabled*'
+# CHECK: 0 1 2 3
+# An fd inherited by fish is passed on, so it's reported.
+sh -c "exec 4</dev/null; $fish -d fd-leaks -c 'command true'" 2>&1 | string match -v 'Debug enabled*' | string replace -r "'.*'" CMD
+# CHECK: fd-leaks: fd 4 is not close-on-exec and will leak into CMD
@@ -1,4 +1,4 @@
-# RUN: %fish -C "set helper %fish_test_helper" %s
+# RUN: %fish -C "set helper %fish_test_helper; set fish %fish" %s
 
 # Check that we don't leave stray FDs.
 
@@ -77,3 +77,32 @@ $helper print_fds 19</dev/null
 
 $helper print_fds 20</dev/null
 # CHECK: 0 1 2 20
+
+# Fds opened by fish for its own use don't leak into children,
+# wherever the child is run from.
+set -U __fish_test_fds_uvar 1
+set -e -U __fish_test_fds_uvar
+history search --max=1 >/dev/null
+read -l line </dev/null
+$helper print_fds
+# CHECK: 0 1 2
+
+echo ($helper print_fds)
+# CHECK: 0 1 2
+
+$helper print_fds | cat
+# CHECK: 0 1 2
+
+true | $helper print_fds | cat
+# CHECK: 0 1 2
+
+begin
+    $helper print_fds
+end 5</dev/null
+# CHECK: 0 1 2 5
+
+function print_fds_redirected
+    $helper print_fds
+end
+print_fds_redirected 6</dev/null 3>&-
+# CHECK: 0 1 2 6
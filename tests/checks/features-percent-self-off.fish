@@ -0,0 +1,25 @@
+#RUN: %fish --features no-remove-percent-self %s
+
+status test-feature remove-percent-self
+echo $status
+#CHECK: 1
+
+echo %self | string match -qr '^\d+$'
+echo "%self is all digits: $status"
+#CHECK: %self is all digits: 0
+test %self = $fish_pid
+echo "%self is fish_pid: $status"
+#CHECK: %self is fish_pid: 0
+
+echo %selfNOT NOT%self \%self "%self" '%self' 50% %1
+#CHECK: %selfNOT NOT%self %self %self %self 50% %1
+
+sleep 5 &
+test (jobs -p %1) = $last_pid
+echo "jobs -p %1: $status"
+#CHECK: jobs -p %1: 0
+kill %1
+wait
+jobs -q
+echo "jobs left: $status"
+#CHECK: jobs left: 1
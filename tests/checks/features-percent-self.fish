@@ -0,0 +1,38 @@
+#RUN: %fish --features remove-percent-self %s
+
+status test-feature remove-percent-self
+echo $status
+#CHECK: 0
+
+echo %self
+echo $status
+#CHECKERR: {{.*}}features-percent-self.fish (line {{\d+}}): %self is not supported. In fish, please use $fish_pid.
+#CHECKERR: echo %self
+#CHECKERR:      ^
+#CHECK: 121
+
+# Anything but an unquoted %self is not affected.
+echo %selfNOT NOT%self \%self "%self" '%self' 50% %1
+#CHECK: %selfNOT NOT%self %self %self %self 50% %1
+
+# The replacements.
+echo $fish_pid | string match -qr '^\d+$'
+echo "fish_pid is all digits: $status"
+#CHECK: fish_pid is all digits: 0
+
+# Job expansion with %N is still done by the job commands, and $last_pid is the new job.
+sleep 5 &
+test (jobs -p %1) = $last_pid
+echo "jobs -p %1: $status"
+#CHECK: jobs -p %1: 0
+
+# kill is an external command, so it points to the alternatives instead.
+kill %1
+echo "kill %1: $status"
+#CHECKERR: kill: '%1' is not expanded. Use 'kill (jobs -p %1)', or 'fg %1' to bring the job to the foreground.
+#CHECK: kill %1: 2
+kill (jobs -p %1)
+wait
+jobs -q
+echo "jobs left: $status"
+#CHECK: jobs left: 1
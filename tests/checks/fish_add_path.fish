@@ -22,7 +22,11 @@ and echo Have bin
 
 # Not adding duplicates and not triggering variable handlers
 function checkpath --on-variable PATH --on-variable fish_user_paths; echo CHECKPATH: $argv; end
+# Setting PATH to the value it already has doesn't trigger them either.
 set PATH $PATH
+set PATH $PATH[1] $PATH
+# CHECK: CHECKPATH: VARIABLE SET PATH
+set PATH $PATH[2..-1]
 # CHECK: CHECKPATH: VARIABLE SET PATH
 fish_add_path -v $tmpdir/bin
 # Nothing happened, so the status failed.
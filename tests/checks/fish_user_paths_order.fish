@@ -0,0 +1,51 @@
+# RUN: env FISH=%fish %fish %s
+#
+# $fish_user_paths_order decides where $fish_user_paths ends up in $PATH.
+
+set -l tmpdir (mktemp -d)
+mkdir $tmpdir/{user1,user2,sys1,sys2}
+set -l oldpath $PATH
+
+set -g PATH $tmpdir/sys1 $tmpdir/sys2
+set -g fish_user_paths $tmpdir/user1 $tmpdir/user2
+string replace -- $tmpdir '' $PATH | string join ' '
+# CHECK: /user1 /user2 /sys1 /sys2
+
+set -g fish_user_paths_order append
+string replace -- $tmpdir '' $PATH | string join ' '
+# CHECK: /sys1 /sys2 /user1 /user2
+
+# Changing fish_user_paths keeps the current order.
+set -g fish_user_paths $tmpdir/user2
+string replace -- $tmpdir '' $PATH | string join ' '
+# CHECK: /sys1 /sys2 /user2
+
+# Entries that are already in $PATH stay where they are.
+set -g fish_user_paths $tmpdir/sys1 $tmpdir/user1
+string replace -- $tmpdir '' $PATH | string join ' '
+# CHECK: /sys1 /sys2 /user1
+
+# Switching back and forth doesn't duplicate anything.
+set -g fish_user_paths $tmpdir/user1 $tmpdir/user2
+set -g fish_user_paths_order prepend
+set -g fish_user_paths_order append
+set -g fish_user_paths_order prepend
+string replace -- $tmpdir '' $PATH | string join ' '
+# CHECK: /user1 /user2 /sys1 /sys2
+
+# Erasing the order falls back to prepending.
+set -g fish_user_paths_order append
+set -e fish_user_paths_order
+string replace -- $tmpdir '' $PATH | string join ' '
+# CHECK: /user1 /user2 /sys1 /sys2
+
+# A new session picks up universal variables when it builds $PATH.
+set -e fish_user_paths
+set -g PATH $oldpath
+set -l sys (string join : $tmpdir/sys1 $tmpdir/sys2)
+set -lx XDG_CONFIG_HOME $tmpdir/config
+$FISH -c "set -U fish_user_paths $tmpdir/user1; set -U fish_user_paths_order append"
+env PATH=$sys $FISH -c 'string join " " $PATH' | string replace -a -- $tmpdir ''
+# CHECK: /sys1 /sys2 /user1
+
+rm -rf $tmpdir
@@ -0,0 +1,80 @@
+#RUN: %fish --features stream-for-cmdsub -C 'set -g fish %fish' %s
+
+# The loop and the status should look the same as with a buffered command substitution.
+set -l snippets \
+    'for i in (seq 3); echo got $i; end; echo $status' \
+    'for i in (false); end; echo $status $pipestatus' \
+    'for i in (sh -c "echo a; exit 3" | cat); end; echo $status $pipestatus' \
+    'for i in (sh -c "echo a; echo b; exit 3"); echo $i; end; echo $status' \
+    'for i in (printf "a\n\nb"); echo "[$i]"; end' \
+    'for i in (seq 5); if test $i = 3; break; end; echo $i; end; echo $status' \
+    'for i in (seq 2); set -l x $i; end; echo $status $pipestatus'
+for snippet in $snippets
+    set -l streamed ($fish --features stream-for-cmdsub -c $snippet)
+    set -l buffered ($fish --features no-stream-for-cmdsub -c $snippet)
+    test "$streamed" = "$buffered"
+    or echo "mismatch for $snippet: $streamed vs $buffered"
+end
+$fish --features stream-for-cmdsub -c 'for i in (sh -c "echo a; exit 3" | cat); end; echo $status $pipestatus'
+# CHECK: 0 3 0
+
+# The loop body runs before the command finishes.
+set -l tmpdir (mktemp -d)
+for i in (sh -c 'echo first; while [ ! -e "$1" ]; do sleep .05; done; echo second' sh $tmpdir/flag)
+    touch $tmpdir/flag
+    echo $i
+end
+# CHECK: first
+# CHECK: second
+rm -r $tmpdir
+
+# Leaving the loop early stops the command.
+for i in (yes)
+    break
+end
+echo broke out
+# CHECK: broke out
+set -l start (date +%s)
+for i in (sh -c 'echo x; sleep 10; echo y')
+    echo $i
+    break
+end
+# CHECK: x
+test (math (date +%s) - $start) -lt 5
+and echo stopped quickly
+# CHECK: stopped quickly
+
+# Functions are still buffered, as they run in fish itself.
+function produce
+    echo one
+    echo two
+end
+for i in (produce)
+    echo $i
+end
+# CHECK: one
+# CHECK: two
+
+# The job commands in the loop body don't see the command. Waiting for it would hang, since it
+# writes more than fits into the pipe.
+sleep .1 &
+for i in (seq 20000)
+    if test $i = 1
+        jobs -c
+        wait
+        echo waited
+        fg 2>/dev/null
+        or echo nothing to fg
+    end
+end
+echo $i
+# CHECK: Command
+# CHECK: sleep
+# CHECK: waited
+# CHECK: nothing to fg
+# CHECK: 20000
+
+# The command substitution leaves no job behind.
+jobs -q
+echo $status
+# CHECK: 1
@@ -0,0 +1,56 @@
+#RUN: %fish %s
+# Test how funced picks its editor.
+
+function fake_visual
+    echo visual $argv
+end
+function fake_editor
+    echo editor $argv
+end
+function foo
+    echo foo
+end
+
+set -e VISUAL
+set -e EDITOR
+
+begin
+    set -lx VISUAL fake_visual
+    set -lx EDITOR fake_editor
+    funced foo
+    # CHECK: visual {{.*}}/foo.fish
+    # CHECK: Editor exited but the function was not modified
+end
+
+# An editor that can't be found is skipped.
+begin
+    set -lx VISUAL nonexistent-editor --wait
+    set -lx EDITOR fake_editor
+    funced foo
+    # CHECKERR: funced: The value for $VISUAL 'nonexistent-editor --wait' could not be used because the command could not be found
+    # CHECK: editor {{.*}}/foo.fish
+    # CHECK: Editor exited but the function was not modified
+end
+
+# Empty values are ignored, and arguments are passed along.
+begin
+    set -lx VISUAL ''
+    set -lx EDITOR 'fake_editor --wait'
+    funced foo
+    # CHECK: editor --wait {{.*}}/foo.fish
+    # CHECK: Editor exited but the function was not modified
+end
+
+# --editor overrides the variables.
+begin
+    set -lx VISUAL fake_visual
+    funced --editor 'fake_editor -w' foo
+    # CHECK: editor -w {{.*}}/foo.fish
+    # CHECK: Editor exited but the function was not modified
+end
+
+# An explicit editor has to exist.
+funced --editor 'nonexistent-editor -w' foo
+echo $status
+# CHECKERR: funced: Editor command 'nonexistent-editor -w' could not be found
+# CHECK: 1
@@ -117,3 +117,46 @@ functions --no-details --details t
 # CHECKERR: functions --no-details --details t
 # CHECKERR: ^
 # CHECKERR: (Type 'help functions' for related documentation)
+
+# ==========
+# `functions --erase --all-autoloaded` erases only autoloaded functions, and lets them autoload again.
+set -l autoload_dir (mktemp -d)
+set -l old_function_path $fish_function_path
+set -g fish_function_path $autoload_dir $fish_function_path
+echo 'function autoloaded_fn; echo first version; end' >$autoload_dir/autoloaded_fn.fish
+function session_fn
+    echo session
+end
+autoloaded_fn
+# CHECK: first version
+echo (functions --details --verbose autoloaded_fn)[2]
+# CHECK: autoloaded
+echo (functions --details --verbose session_fn)[2]
+# CHECK: not-autoloaded
+
+echo 'function autoloaded_fn; echo second version; end' >$autoload_dir/autoloaded_fn.fish
+functions --erase --all-autoloaded
+functions --query session_fn
+and echo session_fn kept
+# CHECK: session_fn kept
+autoloaded_fn
+# CHECK: second version
+
+functions --erase --all-autoloaded session_fn
+functions --query session_fn
+or echo session_fn erased
+# CHECK: session_fn erased
+autoloaded_fn
+# CHECK: second version
+
+functions --all-autoloaded
+# CHECKERR: functions: Invalid combination of options,
+# CHECKERR: --all-autoloaded can only be used with --erase
+# CHECKERR:
+# CHECKERR: checks/functions.fish (line {{\d+}}):
+# CHECKERR: functions --all-autoloaded
+# CHECKERR: ^
+# CHECKERR: (Type 'help functions' for related documentation)
+
+set -g fish_function_path $old_function_path
+rm -r $autoload_dir
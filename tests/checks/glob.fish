@@ -75,6 +75,18 @@ string join \n **a2/** | sort
 # CHECK: dir_a1/dir_a2/dir_a3
 # CHECK: dir_a1/dir_a2/dir_a3/file_a
 
+# A trailing **/ matches the same directories as **, not the one it starts in.
+string join \n dir_a1/**/ | sort
+# CHECK: dir_a1/dir_a2/
+# CHECK: dir_a1/dir_a2/dir_a3/
+string join \n $tmpdir/dir_b1/** | string replace $tmpdir/ '' | sort
+# CHECK: dir_b1/dir_b2
+# CHECK: dir_b1/dir_b2/dir_b3
+# CHECK: dir_b1/dir_b2/dir_b3/file_b
+string join \n $tmpdir/dir_b1/**/ | string replace $tmpdir/ '' | sort
+# CHECK: dir_b1/dir_b2/
+# CHECK: dir_b1/dir_b2/dir_b3/
+
 rm -Rf *
 
 # Special behavior for #7222.
@@ -85,6 +97,80 @@ string join \n **/bar | sort
 # CHECK: bar
 # CHECK: foo/bar
 
+rm -Rf *
+
+# A symlink back to the directory the glob started in is a loop too.
+mkdir -p loop/sub
+touch rootfile
+ln -s ../.. loop/sub/top
+string join \n **/rootfile
+# CHECK: rootfile
+rm -Rf *
+
+# Recursive globs descend $fish_glob_max_depth directories deep.
+set -l deep (string repeat -n 30 d/)
+mkdir -p $deep
+touch {$deep}deepfile
+count **/deepfile
+# CHECK: 1
+set -g fish_glob_max_depth 30
+count **/deepfile
+# CHECK: 1
+set -g fish_glob_max_depth 29
+count **/deepfile
+# CHECK: 0
+count d/**/deepfile
+# CHECK: 1
+set -e fish_glob_max_depth
+count **/deepfile
+# CHECK: 1
+rm -Rf *
+
+# Wildcard results are sorted naturally: numbers by value, letters ignoring case, and otherwise by
+# code point. Numbers that are equal in value are ordered by their leading zeros.
+touch file1 file2 file10 file010 file02 file002 file9 file100
+echo file*
+# CHECK: file1 file002 file02 file2 file9 file010 file10 file100
+rm -f file*
+touch a10b a9c a9b B1 a2
+echo *
+# CHECK: a2 a9b a9c a10b B1
+rm -f *
+touch ü10 ü2 z1 日本3 a1
+echo *
+# CHECK: a1 z1 ü2 ü10 日本3
+rm -f *
+
+# Unmatched wildcards are an error, naming the command, unless $fish_null_glob is set.
+ls *.nope
+echo $status
+# CHECKERR: {{.*}}glob.fish (line {{\d+}}): ls: No matches for wildcard '*.nope'. See `help expand`.
+# CHECKERR: ls *.nope
+# CHECKERR:    ^
+# CHECK: 124
+set -g fish_null_glob 1
+ls *.nope
+echo $status
+# CHECK: 0
+echo before *.nope after
+# CHECK: before after
+count *.nope
+# CHECK: 0
+set -g fish_null_glob 0
+ls *.nope
+echo $status
+# CHECKERR: {{.*}}glob.fish (line {{\d+}}): ls: No matches for wildcard '*.nope'. See `help expand`.
+# CHECKERR: ls *.nope
+# CHECKERR:    ^
+# CHECK: 124
+set -e fish_null_glob
+fish_null_glob=1 echo only *.nope
+# CHECK: only
+# set and count still never fail.
+set -l nope *.nope
+count $nope *.nope
+# CHECK: 0
+
 # Clean up.
 cd $oldpwd
 rm -Rf $tmpdir
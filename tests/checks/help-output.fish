@@ -0,0 +1,35 @@
+#RUN: %fish %s
+# Builtin help is printed to the builtin's own output, so it can be piped or captured.
+# Stub the help function, as the man pages may not be installed.
+function __fish_print_help
+    string join \n $argv[2..-1]
+    echo "help for $argv[1]"
+    isatty stdout
+    and echo "on a tty"
+end
+
+set --help | string upper
+# CHECK: HELP FOR SET
+
+set -l captured (status --help)
+echo $captured
+# CHECK: help for status
+
+string --help >/dev/null
+echo $status
+# CHECK: 0
+
+# Errors that print help go to stderr.
+for i in 1
+    break extra
+end 2>&1 | string replace -r '^' 'err: '
+# CHECK: err: break: Unknown option 'extra'
+# CHECK: err: 
+# CHECK: err: help for break
+
+function __fish_print_help
+    return 2
+end
+set --help | string upper
+# CHECKERR: fish: Missing man page for 'set'. Did you install the documentation?
+# CHECKERR: `help 'set'` will open the online version.
@@ -55,6 +55,12 @@ builtin history --contains save
 #CHECKERR: history: you cannot use any options with the save command
 builtin history -t merge
 #CHECKERR: history: you cannot use any options with the merge command
+history deduplicate xyz
+#CHECKERR: history: deduplicate expected 0 args, got 1
+builtin history deduplicate --prefix
+#CHECKERR: history: you cannot use any options with the deduplicate command
+builtin history deduplicate abc
+#CHECKERR: history deduplicate: Expected 0 args, got 1
 
 # Now do a history command that should succeed so we exit with a zero,
 # success, status.
@@ -226,6 +226,11 @@ echo < stdin >>appended yes 2>&1 no > stdout maybe 2>&    4 | cat 2>| cat
 ' | $fish_indent
 #CHECK: echo <stdin >>appended yes 2>&1 no >stdout maybe 2>&4 | cat 2>| cat
 
+echo -n '
+echo >?   noclobber 2>? noclobber2 &>?noclobber3
+' | $fish_indent
+#CHECK: echo >?noclobber 2>?noclobber2 &>?noclobber3
+
 
 # issue 7252
 echo -n '
@@ -362,6 +367,11 @@ command 2' | $fish_indent
 
 echo " foo" | $fish_indent --check
 echo $status
+#CHECK: --- -
+#CHECK: +++ - (formatted)
+#CHECK: @@ -1,1 +1,1 @@
+#CHECK: - foo
+#CHECK: +foo
 #CHECK: 1
 echo foo | $fish_indent --check
 echo $status
@@ -416,3 +426,45 @@ echo "\
 a=1 \\
     a=2 echo" | $fish_indent --check
 echo $status #CHECK: 0
+
+# $(...) command substitutions, also inside double quotes, are kept as they are.
+echo 'echo   $(echo a)   "b$(echo "c d")e";echo "$(echo (echo f))"' | $fish_indent
+#CHECK: echo $(echo a) "b$(echo "c d")e"
+#CHECK: echo "$(echo (echo f))"
+echo 'echo "$(echo a)"' | $fish_indent --check
+echo $status #CHECK: 0
+
+# --check prints a diff of only the lines that would change, and writes nothing.
+set -l tmpdir (mktemp -d)
+printf '%s\n' 'if true' 'echo yes' 'end' 'echo done;echo again' >$tmpdir/bad.fish
+printf '%s\n' 'if true' '    echo yes' 'end' >$tmpdir/good.fish
+$fish_indent --check $tmpdir/good.fish
+echo $status
+#CHECK: 0
+$fish_indent --check $tmpdir/bad.fish $tmpdir/good.fish 2>/dev/null | string replace -r -- '^(---|\+\+\+) .*/' '$1 '
+echo $pipestatus[1]
+#CHECK: --- bad.fish
+#CHECK: +++ bad.fish (formatted)
+#CHECK: @@ -2,1 +2,1 @@
+#CHECK: -echo yes
+#CHECK: +{{    }}echo yes
+#CHECK: @@ -4,1 +4,2 @@
+#CHECK: -echo done;echo again
+#CHECK: +echo done
+#CHECK: +echo again
+#CHECK: 1
+$fish_indent --check $tmpdir/bad.fish >/dev/null 2>&1
+echo $status
+#CHECK: 1
+# -w then formats in place, after which --check passes.
+$fish_indent -w $tmpdir/bad.fish
+$fish_indent --check $tmpdir/bad.fish
+echo $status
+#CHECK: 0
+cat $tmpdir/bad.fish
+#CHECK: if true
+#CHECK: {{^    }}echo yes
+#CHECK: end
+#CHECK: echo done
+#CHECK: echo again
+rm -r $tmpdir
@@ -0,0 +1,13 @@
+#RUN: %fish -C 'echo init-command' -C 'nonexistent-init-command' -C 'echo (' --init-command 'echo 4th init-command $status' -c 'echo command'
+# An error in one init command is reported, and the others still run, in order.
+# The status is that of the last init command, here the syntax error.
+# CHECK: init-command
+# CHECKERR: fish: Unknown command: nonexistent-init-command
+# CHECKERR: fish:
+# CHECKERR: nonexistent-init-command
+# CHECKERR: ^
+# CHECKERR: fish: Unexpected end of string, expecting ')'
+# CHECKERR: echo (
+# CHECKERR:      ^
+# CHECK: 4th init-command 123
+# CHECK: command
@@ -78,3 +78,26 @@ and echo matched
 string match -rq "echo thisshouldneverbeintheconfig" < $tmp/full.prof
 and echo matched
 # CHECK: matched
+
+# The profile ends with the times per function and per external command.
+$fish --profile $tmp/summary.prof -c '
+function inner; command true; end
+function outer; inner; inner; end
+function rec; if test $argv[1] -gt 0; rec (math $argv[1] - 1); end; end
+outer
+rec 2'
+string replace -rf '^\d+\t\d+\t(\d+\t.*)$' '$1' < $tmp/summary.prof | sort
+# CHECK: 1{{\t}}outer
+# CHECK: 2{{\t}}inner
+# CHECK: 2{{\t}}true
+# CHECK: 3{{\t}}rec
+string match -q 'Time*Sum*Calls*Function' < $tmp/summary.prof
+and string match -q 'Time*Sum*Calls*External command' < $tmp/summary.prof
+and echo headers
+# CHECK: headers
+# Recursive calls are not counted twice.
+set -l toplevel (string replace -rf '^\d+\t(\d+)\t> rec 2$' '$1' < $tmp/summary.prof)
+set -l total (string replace -rf '^\d+\t(\d+)\t3\trec$' '$1' < $tmp/summary.prof)
+test $total -le $toplevel
+and echo rec ok
+# CHECK: rec ok
@@ -46,9 +46,25 @@ bg -23 1 2>/dev/null
 or echo bg: invalid option -23 >&2
 #CHECKERR: bg: invalid option -23
 fg 3
+echo $status
 #CHECKERR: fg: No suitable job: 3
+#CHECK: 3
 bg 3
+echo $status
 #CHECKERR: bg: Could not find job '3'
+#CHECK: 3
+# These jobs exist, but are not under job control.
+fg $last_pid
+echo $status
+#CHECKERR: fg: Can't put job {{\d+}}, '{{.*}}' to foreground because it is not under job control
+#CHECK: 5
+bg $last_pid 2>/dev/null
+echo $status
+#CHECK: 5
+# The fg function only passes the last job on, so call the builtin.
+builtin fg 1 2 2>/dev/null
+echo $status
+#CHECK: 2
 sleep 1 &
 disown
 jobs -c
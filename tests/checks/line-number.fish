@@ -22,3 +22,29 @@ end
 
 line-number
 # CHECK: 20
+
+# Line numbers and function names in a sourced file.
+set -l tmpdir (mktemp -d)
+printf '%s\n' 'status line-number' '' 'function sourced-func' '    status function' \
+    '    status line-number' end 'sourced-func' 'status function' 'begin' \
+    '    status line-number' end >$tmpdir/lines.fish
+source $tmpdir/lines.fish
+# CHECK: 1
+# CHECK: sourced-func
+# CHECK: 5
+# CHECK: Not a function
+# CHECK: 10
+
+function sources-file
+    source $argv
+end
+sources-file $tmpdir/lines.fish
+# CHECK: 1
+# CHECK: sourced-func
+# CHECK: 5
+# CHECK: Not a function
+# CHECK: 10
+
+status function
+# CHECK: Not a function
+rm -r $tmpdir
@@ -0,0 +1,25 @@
+#RUN: %fish %s
+# Only run where the German locale and translations are installed.
+#REQUIRES: env LC_ALL=de_DE.UTF-8 %fish -c 'bind --no-such-option' 2>&1 | grep -q Unbekannte
+
+# Changing the message locale changes fish's own messages, without a restart.
+set -e LANGUAGE
+set -gx LC_MESSAGES C
+bind --no-such-option 2>&1 | head -n1
+# CHECK: bind: Unknown option '--no-such-option'
+
+set -gx LC_MESSAGES de_DE.UTF-8
+bind --no-such-option 2>&1 | head -n1
+# CHECK: bind: Unbekannte Option '--no-such-option'
+
+set -gx LC_MESSAGES C
+bind --no-such-option 2>&1 | head -n1
+# CHECK: bind: Unknown option '--no-such-option'
+
+# LC_ALL overrides LC_MESSAGES.
+set -gx LC_ALL de_DE.UTF-8
+bind --no-such-option 2>&1 | head -n1
+# CHECK: bind: Unbekannte Option '--no-such-option'
+set -e LC_ALL
+bind --no-such-option 2>&1 | head -n1
+# CHECK: bind: Unknown option '--no-such-option'
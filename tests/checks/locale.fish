@@ -88,3 +88,11 @@ env LC_ALL=C $fish -c 'echo -n Y\u00FCY' | display_bytes
 env LC_ALL=C $fish -c 'echo -n T\u01FDT' | display_bytes
 #CHECK: 0000000 124 077 124
 #CHECK: 0000003
+
+# An unknown locale falls back to C for that category, with a warning.
+begin
+    set -lx LC_MESSAGES xx_YY.UTF-8
+    # CHECKERR: warning: Could not set LC_MESSAGES from the environment, using 'C' instead.
+    bind --no-such-option 2>&1 | head -n1
+    # CHECK: bind: Unknown option '--no-such-option'
+end
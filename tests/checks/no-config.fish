@@ -1,4 +1,4 @@
-#RUN: %fish --no-config %s
+#RUN: %fish --no-config -C "set fish %fish" %s
 
 functions | string match help
 # CHECK: help
@@ -14,3 +14,34 @@ set -S foo
 set -S fish_function_path fish_complete_path
 # CHECK: $fish_function_path: set in global scope, unexported, with 1 elements
 # CHECK: $fish_function_path[1]: |{{.*}}|
+# CHECK: $fish_complete_path: set in global scope, unexported, with 1 elements
+# CHECK: $fish_complete_path[1]: |{{.*}}|
+
+# Bundled completions are still available.
+complete -C'set --univ'
+# CHECK: --universal{{\t}}Share variable persistently across sessions
+
+# Nothing from the user's configuration directory is used.
+set -l tmpdir (mktemp -d)
+mkdir -p $tmpdir/fish/conf.d $tmpdir/fish/functions $tmpdir/fish/completions
+echo 'echo sourced config.fish' >$tmpdir/fish/config.fish
+echo 'echo sourced conf.d' >$tmpdir/fish/conf.d/snippet.fish
+echo 'function myfunc; end' >$tmpdir/fish/functions/myfunc.fish
+echo 'complete -c echo -l myopt' >$tmpdir/fish/completions/echo.fish
+set -l check 'functions -q myfunc; and echo have myfunc; complete -C"echo --my"'
+
+XDG_CONFIG_HOME=$tmpdir $fish -c $check
+# CHECK: sourced conf.d
+# CHECK: sourced config.fish
+# CHECK: have myfunc
+# CHECK: --myopt
+
+XDG_CONFIG_HOME=$tmpdir $fish --no-config -c $check
+XDG_CONFIG_HOME=$tmpdir $fish -N -c $check
+
+# Interactive sessions still get the preset bindings.
+XDG_CONFIG_HOME=$tmpdir $fish --no-config -i -c 'echo $fish_key_bindings; bind --preset \ca' </dev/null
+# CHECK: fish_default_key_bindings
+# CHECK: bind --preset \ca beginning-of-line
+
+rm -r $tmpdir
@@ -0,0 +1,73 @@
+#RUN: %fish %s
+
+set -l dirs /usr/bin /bin
+path-add dirs /opt/bin
+echo $status $dirs
+# CHECK: 0 /opt/bin /usr/bin /bin
+
+# A directory that is already there is left alone.
+path-add dirs /usr/bin
+echo $status $dirs
+# CHECK: 1 /opt/bin /usr/bin /bin
+path-add dirs /usr/bin/ /usr//bin/../bin
+echo $status $dirs
+# CHECK: 1 /opt/bin /usr/bin /bin
+
+# Several at once keep their order, also when appending.
+path-add dirs /a /b /a
+echo $status $dirs
+# CHECK: 0 /a /b /opt/bin /usr/bin /bin
+path-add --append dirs /c /d /usr/bin
+echo $status $dirs
+# CHECK: 0 /a /b /opt/bin /usr/bin /bin /c /d
+
+# --move relocates existing entries.
+path-add --move dirs /bin /usr/bin
+echo $status $dirs
+# CHECK: 0 /bin /usr/bin /a /b /opt/bin /c /d
+path-add --move dirs /bin /usr/bin
+echo $status $dirs
+# CHECK: 1 /bin /usr/bin /a /b /opt/bin /c /d
+path-add --move --append dirs /a
+echo $status $dirs
+# CHECK: 0 /bin /usr/bin /b /opt/bin /c /d /a
+
+# Relative directories are resolved against $PWD.
+set -l tmpdir (mktemp -d)
+cd $tmpdir
+path-add dirs sub ./sub/../other
+echo $status (string replace -- $tmpdir TMP $dirs[1..2])
+# CHECK: 0 TMP/sub TMP/other
+cd -
+rm -r $tmpdir
+
+# A new variable is created, with the given scope.
+function add_global
+    path-add -g some_global_paths /x
+end
+add_global
+set -S some_global_paths
+# CHECK: $some_global_paths: set in global scope, unexported, with 1 elements
+# CHECK: $some_global_paths[1]: |/x|
+
+# Path variables stay path variables.
+set -l oldpath $PATH
+path-add PATH /nonexistent/bin
+echo $PATH[1]
+# CHECK: /nonexistent/bin
+set PATH $oldpath
+
+path-add dirs
+# CHECKERR: path-add: Expected at least 2 args, got 1
+# CHECKERR: {{.*}}checks/path-add.fish (line {{\d+}}):
+# CHECKERR: path-add dirs
+# CHECKERR: ^
+# CHECKERR: (Type 'help path-add' for related documentation)
+path-add -l -g dirs /x
+# CHECKERR: path-add: Variable scope can only be one of universal, global and local
+# CHECKERR: {{.*}}checks/path-add.fish (line {{\d+}}):
+# CHECKERR: path-add -l -g dirs /x
+# CHECKERR: ^
+# CHECKERR: (Type 'help path-add' for related documentation)
+path-add status /x
+# CHECKERR: path-add: Tried to change the read-only variable 'status'
@@ -0,0 +1,31 @@
+#RUN: %fish -C "set fish %fish" %s
+# The path-check debug category reports problems with $PATH when it changes.
+
+set -l dir (mktemp -d)
+mkdir $dir/bin
+touch $dir/file
+
+set -l script "
+set PATH $dir/bin /usr/bin $dir/bin $dir/nonexistent $dir/file relative/bin
+# Setting the same value again doesn't check again.
+set PATH \$PATH
+set fish_user_paths $dir/bin $dir/bin
+"
+$fish -d path-check -c $script 2>&1 | string match -v 'Debug enabled*' | string replace -a $dir DIR
+# CHECK: path-check: $PATH entry 'DIR/bin' is a duplicate
+# CHECK: path-check: $PATH entry 'DIR/nonexistent' does not exist
+# CHECK: path-check: $PATH entry 'DIR/file' is not a directory
+# CHECK: path-check: $PATH entry 'relative/bin' is relative
+# CHECK: path-check: $PATH entry 'relative/bin' does not exist
+# CHECK: path-check: $fish_user_paths entry 'DIR/bin' is a duplicate
+
+# The value itself is left alone.
+$fish -c "set PATH /usr/bin /usr/bin; echo \$PATH"
+# CHECK: /usr/bin /usr/bin
+
+# Without the debug category, nothing is printed.
+$fish -c "set PATH /usr/bin /usr/bin $dir/nonexistent" 2>&1
+echo $status
+# CHECK: 0
+
+rm -r $dir
@@ -0,0 +1,247 @@
+#RUN: %fish %s
+# The path builtin.
+
+path basename /usr/bin/ /usr/local/share/fish.txt foo / a//b//
+# CHECK: bin
+# CHECK: fish.txt
+# CHECK: foo
+# CHECK: /
+# CHECK: b
+
+# Empty arguments aren't paths.
+path basename ""
+echo $status
+# CHECK: 1
+
+path dirname /usr/bin/ /usr/local/share/fish.txt foo / a//b// //x
+# CHECK: /usr
+# CHECK: /usr/local/share
+# CHECK: .
+# CHECK: /
+# CHECK: a
+# CHECK: /
+
+# One line per path, so the output lines up with the input.
+path extension foo.txt .bashrc foo. dir.d/ a.b/c foo.tar.gz
+echo $status
+# CHECK: .txt
+# CHECK:
+# CHECK: .
+# CHECK: .d
+# CHECK:
+# CHECK: .gz
+# CHECK: 0
+path extension foo bar/.baz
+echo $status
+# CHECK:
+# CHECK:
+# CHECK: 1
+
+path change-extension md foo.txt .bashrc a.b/c dir.d/ foo.tar.gz
+# CHECK: foo.md
+# CHECK: .bashrc.md
+# CHECK: a.b/c.md
+# CHECK: dir.md
+# CHECK: foo.tar.md
+path change-extension .md foo.txt
+# CHECK: foo.md
+path change-extension '' foo.txt /
+# CHECK: foo
+# CHECK: /
+path change-extension
+echo $status
+# CHECKERR: path change-extension: Expected an argument
+# CHECK: 2
+
+path normalize ./a/../b//c/. /../x a/.. ../../x -- -foo
+# CHECK: b/c
+# CHECK: /x
+# CHECK: .
+# CHECK: ../../x
+# CHECK: ./-foo
+
+set -l tmpdir (mktemp -d)
+cd $tmpdir
+mkdir -p real/dir
+touch real/file
+ln -s real link
+ln -s nowhere broken
+set -l real_tmpdir (builtin realpath $tmpdir)
+path resolve link link/dir/../file link/nonexistent/../x nonexistent/y / |
+    string replace $real_tmpdir TMP
+# CHECK: TMP/real
+# CHECK: TMP/real/file
+# CHECK: TMP/real/x
+# CHECK: TMP/nonexistent/y
+# CHECK: /
+# Links to paths that don't exist are followed too, but not forever.
+ln -s real/nowhere/else broken2
+ln -s loop1 loop2
+ln -s loop2 loop1
+path resolve broken broken2/x loop1 | string replace $real_tmpdir TMP
+# CHECK: TMP/nowhere
+# CHECK: TMP/real/nowhere/else/x
+# CHECK: TMP/loop1
+rm broken2 loop1 loop2
+
+path is real; and echo exists
+# CHECK: exists
+path is nonexistent broken; or echo neither exists
+# CHECK: neither exists
+path is -f real/file; and echo file
+# CHECK: file
+path is -f real; or echo not a file
+# CHECK: not a file
+path is -t file,dir real; and echo file or dir
+# CHECK: file or dir
+path is -l link broken; and echo link
+# CHECK: link
+path is -d link; and echo links are followed
+# CHECK: links are followed
+path is -rw real/file; and echo readable and writable
+# CHECK: readable and writable
+path is -x real/file; or echo not executable
+# CHECK: not executable
+path is -p user real/file; and echo ours
+# CHECK: ours
+path is -v real nonexistent; and echo something does not exist
+# CHECK: something does not exist
+path is --type=thing real
+# CHECKERR: path is: Invalid type 'thing'
+
+chmod 644 real/file
+touch real/script
+chmod 755 real/script
+mkfifo real/fifo
+path filter real real/file real/script real/fifo link broken nonexistent ""
+echo $status
+# CHECK: real
+# CHECK: real/file
+# CHECK: real/script
+# CHECK: real/fifo
+# CHECK: link
+# CHECK: 0
+path filter -d real real/file real/script link broken
+# CHECK: real
+# CHECK: link
+path filter --type=file,fifo real real/file real/fifo
+# CHECK: real/file
+# CHECK: real/fifo
+path filter -l real link broken
+# CHECK: link
+# CHECK: broken
+path filter -f -x real/file real/script real
+# CHECK: real/script
+path filter --perm=read,write real/file broken
+# CHECK: real/file
+path filter --mode=0644 real/file real/script
+# CHECK: real/file
+path filter -m 755 real/file real/script real/fifo
+# CHECK: real/script
+path filter -v -f real real/file nonexistent ""
+# CHECK: real
+# CHECK: nonexistent
+path filter -f real nonexistent
+echo $status
+# CHECK: 1
+path filter -q -f real/file; and echo found a file
+# CHECK: found a file
+printf '%s\0' real/file real/script | path filter -z -Z -x | string split0
+# CHECK: real/script
+path filter --mode=rw real/file
+# CHECKERR: path filter: Invalid mode 'rw'
+path filter --mode=17777 real/file
+# CHECKERR: path filter: Invalid mode '17777'
+# Relative paths are looked up in the current directory.
+cd real
+path filter -f file ../real/script
+# CHECK: file
+# CHECK: ../real/script
+cd ..
+
+path sort b/file10 a/file2 c/file1 a/file10 ""
+# CHECK: a/file10
+# CHECK: a/file2
+# CHECK: b/file10
+# CHECK: c/file1
+path sort --natural file10 file2 file1
+# CHECK: file1
+# CHECK: file2
+# CHECK: file10
+path sort -n --reverse file10 file2 file1
+# CHECK: file10
+# CHECK: file2
+# CHECK: file1
+path sort -r b a c
+# CHECK: c
+# CHECK: b
+# CHECK: a
+# Sorting is stable, so paths with the same basename keep their order.
+path sort --key=basename z/b y/a x/b w/c
+# CHECK: y/a
+# CHECK: z/b
+# CHECK: x/b
+# CHECK: w/c
+path sort -u c a c b a
+# CHECK: a
+# CHECK: b
+# CHECK: c
+path sort -k basename -u z/b y/a x/b
+# CHECK: y/a
+# CHECK: z/b
+touch -t 202001010000 old
+touch -t 202101010000 new
+touch -t 202001010000 old2
+path sort --key=mtime new old nonexistent old2
+# CHECK: nonexistent
+# CHECK: old
+# CHECK: old2
+# CHECK: new
+path sort -k mtime -r new old
+# CHECK: new
+# CHECK: old
+rm old old2 new
+printf '%s\0' b a | path sort -z -Z | string split0
+# CHECK: a
+# CHECK: b
+path sort ""
+echo $status
+# CHECK: 1
+path sort -q b a; and echo sorted
+# CHECK: sorted
+path sort --key=size a
+# CHECKERR: path sort: Invalid sort key 'size'
+
+# Arguments can come from stdin, also NUL-separated.
+printf '%s\n' a/b.c d/e.f | path basename
+# CHECK: b.c
+# CHECK: e.f
+printf '%s\0' 'a/b c' d/e | path basename -z -Z | string split0
+# CHECK: b c
+# CHECK: e
+echo a | path basename b
+# CHECKERR: path basename: Too many arguments
+path basename -q x y; and echo quiet
+# CHECK: quiet
+
+path
+# CHECKERR: path: Expected a subcommand to follow the command
+# CHECKERR: {{.*}}checks/path.fish (line {{\d+}}):
+# CHECKERR: path
+# CHECKERR: ^
+# CHECKERR: (Type 'help path' for related documentation)
+path frobnicate
+# CHECKERR: path: Subcommand 'frobnicate' is not valid
+# CHECKERR: {{.*}}checks/path.fish (line {{\d+}}):
+# CHECKERR: path frobnicate
+# CHECKERR: ^
+# CHECKERR: (Type 'help path' for related documentation)
+path basename -f x
+# CHECKERR: path basename: Unknown option '-f'
+# CHECKERR: {{.*}}checks/path.fish (line {{\d+}}):
+# CHECKERR: path basename -f x
+# CHECKERR: ^
+# CHECKERR: (Type 'help path' for related documentation)
+
+cd /
+rm -r $tmpdir
@@ -175,6 +175,43 @@ end
 echo $pipestatus : $status
 #CHECK: 0 0 : 0
 
+# pipestatus variable - builtins and functions in the middle
+function ret
+    return $argv[1]
+end
+false | true
+echo $pipestatus : $status
+#CHECK: 1 0 : 0
+command true | ret 4 | command true
+echo $pipestatus : $status
+#CHECK: 0 4 0 : 0
+sh -c 'exit 3' | string upper | sh -c 'exit 5'
+echo $pipestatus : $status
+#CHECK: 3 1 5 : 5
+echo foo | count >/dev/null | command false
+echo $pipestatus : $status
+#CHECK: 0 0 {{1|255}} : 1
+
+# pipestatus variable - stages killed by a signal count as 128 + the signal
+command true | sh -c 'kill -9 $$' | command true
+echo $pipestatus : $status
+#CHECK: 0 137 0 : 0
+command true | sh -c 'kill -15 $$' | ret 2
+echo $pipestatus : $status
+#CHECK: 0 143 2 : 2
+sh -c 'kill -1 $$' | ret 6 | sh -c 'kill -9 $$'
+echo $pipestatus : $status
+#CHECK: 129 6 137 : 137
+
+# pipestatus variable - a stage that can't be run still gets an entry, as do the others
+command true | nonexistent-command-1234 | command true
+echo $pipestatus : $status
+#CHECK: 1 127 1 : 127
+#CHECKERR: fish: Unknown command: nonexistent-command-1234
+#CHECKERR: {{.*}}checks/pipestatus.fish (line {{\d+}}):
+#CHECKERR: command true | nonexistent-command-1234 | command true
+#CHECKERR:                ^
+
 # Check that failed redirections correctly handle pipestatus, etc.
 # See #7540.
 command true > /not/a/valid/path
@@ -122,3 +122,23 @@ printf '%d\n' 15.1
 # CHECKERR: 15.1: value not completely converted
 echo $status
 # CHECK: 1
+
+# Tables, one row per argument.
+printf --table name\tvalue longer-name\tx fish\t漢\tlast
+# CHECK: name         value
+# CHECK: longer-name  x
+# CHECK: fish         漢     last
+printf --table --sep , a,bb,c aaa,b
+# CHECK: a    bb  c
+# CHECK: aaa  b
+printf --table --field-separator ' | ' 'one | two' 'three'
+# CHECK: one    two
+# CHECK: three
+printf --table
+echo $status
+# CHECK: 0
+printf --sep , '%s\n' a
+# CHECKERR: printf: Invalid combination of options,
+# CHECKERR: --field-separator can only be used with --table
+printf --table --sep '' a
+# CHECKERR: printf: The field separator must not be empty
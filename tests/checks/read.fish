@@ -144,6 +144,29 @@ echo $bar
 echo test | read -n 1 foo
 echo $foo
 #CHECK: t
+# Stopping at the end of the line is fine, but running out of input means fewer chars.
+echo test | read -n 10 foo
+echo $status
+#CHECK: 0
+echo -n ab | read -n 3 foo
+echo $status $foo
+#CHECK: 1 ab
+echo -n ab | read -n 3 --line foo bar
+echo $status $foo (count $bar)
+#CHECK: 1 ab 0
+echo -n ab | read -n 3
+echo " $status"
+#CHECK: ab 1
+echo -n abc | read -n 3 foo
+echo $status $foo
+#CHECK: 0 abc
+# Characters, not bytes.
+printf 'é€xyz' | read -n 3 foo
+echo $status $foo
+#CHECK: 0 é€x
+printf 'é€' | read -n 3 foo
+echo $status $foo
+#CHECK: 1 é€
 
 # read -z tests
 echo -n testing | read -lz foo
@@ -345,3 +368,65 @@ echo c $c
 # CHECK: a 'afoo barb'
 # CHECK: b
 # CHECK: c
+
+# Prompts are only shown when reading from a terminal.
+echo foo | read -l --right-prompt-str RIGHT var
+echo $var
+# CHECK: foo
+read -R 'echo right' --right-prompt-str RIGHT </dev/null
+# CHECKERR: read: Options -R and --right-prompt-str cannot be used together
+# CHECKERR:
+# CHECKERR: {{.*}}checks/read.fish (line {{\d+}}):
+# CHECKERR: read -R 'echo right' --right-prompt-str RIGHT </dev/null
+# CHECKERR: ^
+# CHECKERR: (Type 'help read' for related documentation)
+
+# --validate only applies when reading from a terminal.
+echo foo | read -l --validate false var
+echo $status $var
+# CHECK: 0 foo
+read --validate true --max-tries nope </dev/null
+# CHECKERR: read: Argument 'nope' is not a valid integer
+# CHECKERR:
+# CHECKERR: {{.*}}checks/read.fish (line {{\d+}}):
+# CHECKERR: read --validate true --max-tries nope </dev/null
+# CHECKERR: ^
+# CHECKERR: (Type 'help read' for related documentation)
+
+# --until reads up to a given character instead of a newline.
+printf 'a b:c d:' | while read -l --until : rec
+    echo "[$rec]"
+end
+# CHECK: [a b]
+# CHECK: [c d]
+echo 'one;two' | read -l --until ';' first
+echo $first
+# CHECK: one
+set -l path (mktemp)
+printf 'x,y,z' >$path
+begin
+    read -l --until , a
+    read -l --until , b
+    echo $a $b
+end <$path
+# CHECK: x y
+rm $path
+# An empty terminator reads until the end of input.
+printf 'line1\nline2\n' | read -l --until '' all
+string escape -- $all
+# CHECK: line1\nline2\n
+# With --list, all input is split on the terminator.
+printf 'f 1|f 2|f 3|' | read -l --until '|' --list files
+print_vars files
+# CHECK: 3 'f 1' 'f 2' 'f 3'
+read -l --until ab var </dev/null
+# CHECKERR: read: Invalid terminator 'ab', expected a single character
+# CHECKERR:
+# CHECKERR: {{.*}}checks/read.fish (line {{\d+}}):
+# CHECKERR: read -l --until ab var </dev/null
+# CHECKERR: ^
+# CHECKERR: (Type 'help read' for related documentation)
+read -l --until x --line var </dev/null
+# CHECKERR: read: Options --until and --line cannot be used together
+read -l --until x -z var </dev/null
+# CHECKERR: read: Options --until and -z cannot be used together
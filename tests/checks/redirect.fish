@@ -1,4 +1,4 @@
-#RUN: %fish %s
+#RUN: %fish -C "set -g fish %fish" %s
 
 function outnerr
     command echo out $argv
@@ -26,9 +26,37 @@ cat $tmpdir/file.txt
 #CHECK: out append
 #CHECK: err append
 
-echo noclobber &>>?$tmpdir/file.txt
+echo noclobber &>?$tmpdir/file.txt
 #CHECKERR: {{.*}} The file {{.*}} already exists
 
+# A noclobber failure anywhere in a pipeline cancels the whole job before anything is launched.
+command echo launched | cat >?$tmpdir/file.txt
+echo $pipestatus
+#CHECKERR: {{.*}} The file {{.*}} already exists
+#CHECK: 1 1
+command echo launched >&2 | cat 2>?$tmpdir/file.txt
+#CHECKERR: {{.*}} The file {{.*}} already exists
+
+echo noclobber >?$tmpdir/newfile.txt
+cat $tmpdir/newfile.txt
+#CHECK: noclobber
+
+# Appending noclobber redirections are rejected at parse time.
+eval "echo noclobber >>?$tmpdir/newfile.txt"
+#CHECKERR: {{.*}} '>>?' is not valid. Appending never overwrites a file, so use '>>'.
+#CHECKERR: echo noclobber >>?{{.*}}
+#CHECKERR:                ^
+eval "echo noclobber &>>?$tmpdir/newfile.txt"
+#CHECKERR: {{.*}} '>>?' is not valid. Appending never overwrites a file, so use '>>'.
+#CHECKERR: echo noclobber &>>?{{.*}}
+#CHECKERR:                ^
+eval "echo noclobber 2>>?$tmpdir/newfile.txt"
+#CHECKERR: {{.*}} '>>?' is not valid. Appending never overwrites a file, so use '>>'.
+#CHECKERR: echo noclobber 2>>?{{.*}}
+#CHECKERR:                ^
+cat $tmpdir/newfile.txt
+#CHECK: noclobber
+
 eval "echo foo |& false"
 #CHECKERR: {{.*}} |& is not valid. In fish, use &| to pipe both stdout and stderr.
 #CHECKERR: echo foo |& false
@@ -126,3 +154,49 @@ echo "/bin/echo pipe 12 <&12 12<&-" | source 12<&0
 #CHECK: pipe 10
 #CHECK: pipe 11
 #CHECK: pipe 12
+
+# User fds are passed to external commands, even if they are in the range fish uses internally.
+set -l tmpdir (mktemp -d)
+for i in (seq 3 22)
+    echo line $i >$tmpdir/fd$i
+end
+$fish -c 'read -l line <&4; echo $line' 4<$tmpdir/fd4
+#CHECK: line 4
+echo piped | $fish -c 'read -l line <&11; echo $line; cat' 11<$tmpdir/fd11 | cat
+#CHECK: line 11
+#CHECK: piped
+# Files opened for redirections don't clobber each other.
+$fish -c 'read -l a <&3; read -l b <&4; echo $a $b' 4<$tmpdir/fd4 3<$tmpdir/fd3
+#CHECK: line 3 line 4
+
+# Twenty user fds at once, with pipes on both sides.
+set -l redirs
+for i in (seq 3 22)
+    set -a redirs $i'<'$tmpdir/fd$i
+end
+echo start | eval $fish -c "'cat; for i in (seq 3 22); read -l line <&\$i; echo \$line; end'" $redirs | string join ' '
+#CHECK: start line 3 line 4 line 5 line 6 line 7 line 8 line 9 line 10 line 11 line 12 line 13 line 14 line 15 line 16 line 17 line 18 line 19 line 20 line 21 line 22
+
+# exec without a command keeps fds open in fish, for later commands.
+exec 5<$tmpdir/fd5
+echo $status
+#CHECK: 0
+$fish -c 'read -l line <&5; echo $line'
+#CHECK: line 5
+exec 6<&5 5<&-
+echo $status
+#CHECK: 0
+$fish -c 'read -l line <&5; or echo fd 5 is closed'
+#CHECK: fd 5 is closed
+#CHECKERR: read: stdin is closed
+exec 6<&-
+exec >$tmpdir/out
+echo $status
+#CHECK: 1
+#CHECKERR: {{.*}}exec: Cannot redirect fd 1 of the shell
+
+# A redirection that fails in a forked child is reported, and the command isn't run.
+$fish -c 'set -g fish_use_posix_spawn 0; command echo unreachable >&7; echo $status'
+#CHECKERR: Could not redirect file descriptor 1 to 7: {{.*}}
+#CHECK: 1
+rm -r $tmpdir
@@ -294,6 +294,43 @@ end
 echo Test 18 $__fish_test_18_status
 # CHECK: Test 18 pass
 
+# The status of a command substitution is that of the last command that ran in it,
+# so a skipped "and" or "or" doesn't change it.
+set x (false; and echo hi)
+echo $status (count $x)
+# CHECK: 1 0
+set x (true; or false)
+echo $status
+# CHECK: 0
+set x (true; and false; or echo hi)
+echo $status $x
+# CHECK: 0 hi
+set x (true | false; and echo hi)
+echo $status $pipestatus
+# CHECK: 1 0 1
+set x (not true; and echo hi)
+echo $status
+# CHECK: 1
+set x (false && echo hi)
+echo $status
+# CHECK: 1
+set x (true || false)
+echo $status
+# CHECK: 0
+if set x (false; and echo hi)
+    echo skipped and is true
+else
+    echo skipped and is false
+end
+# CHECK: skipped and is false
+# With several substitutions, the last one counts.
+set x (false; and echo hi) (true; or echo hi)
+echo $status
+# CHECK: 0
+set x (true; or echo hi) (false; and echo hi)
+echo $status
+# CHECK: 1
+
 # Test that local exported variables are copied to functions (#1091)
 function __fish_test_local_export
     echo $var
@@ -373,6 +410,26 @@ env SHLVL=2 $FISH -c 'echo SHLVL: $SHLVL'
 env SHLVL=banana $FISH -c 'echo SHLVL: $SHLVL'
 # CHECK: SHLVL: banana
 
+# Neither do nested one-shot shells, or an exec chain of them.
+env SHLVL=2 $FISH -c '$FISH -c \'echo SHLVL: $SHLVL\''
+# CHECK: SHLVL: 2
+env SHLVL=2 $FISH -c 'exec $FISH -c \'exec $FISH -c "echo SHLVL: \\$SHLVL"\''
+# CHECK: SHLVL: 2
+
+# Login shells count as a level, and undo it when exec'ing another shell.
+env SHLVL=2 $FISH -l -c 'echo SHLVL: $SHLVL; exec $FISH -c \'echo SHLVL: $SHLVL\''
+# CHECK: SHLVL: 3
+# CHECK: SHLVL: 2
+
+# A garbage SHLVL is noted in the env-export log.
+env SHLVL=banana $FISH -d env-export -ic 'echo SHLVL: $SHLVL' 2>&1 | string match -r 'SHLVL.*'
+# CHECK: SHLVL 'banana' is not a valid level, treating it as 0
+# CHECK: SHLVL: 1
+
+# SHLVL can be changed by the user, and stays exported.
+env SHLVL=3 $FISH -ic 'set SHLVL 7; env | string match "SHLVL=*"'
+# CHECK: SHLVL=7
+
 # Test transformation of inherited variables
 env DISPLAY="localhost:0.0" $FISH -c 'echo Elements in DISPLAY: (count $DISPLAY)'
 # CHECK: Elements in DISPLAY: 1
@@ -435,6 +492,66 @@ echo "$__fish_test_path2" $__fish_test_path2
 
 set -e __fish_test_path2
 
+# Inherited variables ending in PATH are only path variables if they have a colon.
+env FOO_PATH=a:b BAR_PATH=nixpkgs NOT_A_PTH=a:b $FISH -c 'set -S FOO_PATH BAR_PATH NOT_A_PTH'
+# CHECK: $FOO_PATH: set in global scope, exported, a path variable (inferred) with 2 elements
+# CHECK: $FOO_PATH[1]: |a|
+# CHECK: $FOO_PATH[2]: |b|
+# CHECK: $BAR_PATH: set in global scope, exported, with 1 elements
+# CHECK: $BAR_PATH[1]: |nixpkgs|
+# CHECK: $NOT_A_PTH: set in global scope, exported, with 1 elements
+# CHECK: $NOT_A_PTH[1]: |a:b|
+
+# ... and are checked again when they are set.
+env BAR_PATH=nixpkgs $FISH -c 'set -a BAR_PATH other; echo "$BAR_PATH"; env | string match "BAR_PATH=*"'
+# CHECK: nixpkgs:other
+# CHECK: BAR_PATH=nixpkgs:other
+
+# Both kinds survive the round trip through the environment.
+set -gx __fish_test_auto_PATH a b
+set -gx __fish_test_plain a:b
+$FISH -c 'echo (count $__fish_test_auto_PATH) (count $__fish_test_plain)'
+# CHECK: 2 1
+env | string match '__fish_test_auto_PATH=*'
+# CHECK: __fish_test_auto_PATH=a:b
+env | string match '__fish_test_plain=*'
+# CHECK: __fish_test_plain=a:b
+
+# --unpath and --path stick to the name, in all scopes and after erasing.
+set -gx --unpath __fish_test_auto_PATH a:b
+set -S __fish_test_auto_PATH
+# CHECK: $__fish_test_auto_PATH: set in global scope, exported, not a path variable with 1 elements
+# CHECK: $__fish_test_auto_PATH[1]: |a:b|
+set -e __fish_test_auto_PATH
+set -gx __fish_test_auto_PATH c d
+echo "$__fish_test_auto_PATH"
+# CHECK: c d
+env | string match '__fish_test_auto_PATH=*'
+# CHECK: __fish_test_auto_PATH=c d
+function __fish_test_local_path
+    set -l __fish_test_plain x y
+    echo "$__fish_test_plain"
+end
+set -g --path __fish_test_plain $__fish_test_plain
+__fish_test_local_path
+# CHECK: x:y
+set -e __fish_test_plain
+set -g __fish_test_plain 1:2
+set -S __fish_test_plain
+# CHECK: $__fish_test_plain: set in global scope, unexported, a path variable with 2 elements
+# CHECK: $__fish_test_plain[1]: |1|
+# CHECK: $__fish_test_plain[2]: |2|
+set -e __fish_test_auto_PATH __fish_test_plain
+
+# A universal --unpath is saved, and applies to inherited variables in new sessions.
+set -U --unpath __fish_test_uvar_PATH a:b
+$FISH -c 'set -S __fish_test_uvar_PATH'
+# CHECK: $__fish_test_uvar_PATH: set in universal scope, unexported, not a path variable with 1 elements
+# CHECK: $__fish_test_uvar_PATH[1]: |a:b|
+env __fish_test_uvar_PATH=c:d $FISH -c 'set -S __fish_test_uvar_PATH' | string match -e global
+# CHECK: $__fish_test_uvar_PATH: set in global scope, exported, not a path variable with 1 elements
+set -e __fish_test_uvar_PATH
+
 # Test empty uvars (#5992)
 set -Ux __fish_empty_uvar
 set -Uq __fish_empty_uvar
@@ -715,3 +832,71 @@ set --show ""
 #CHECKERR: set --show ""
 #CHECKERR: ^
 #CHECKERR: (Type 'help set' for related documentation)
+
+# `set -q --long` also prints the variables that exist
+set -l qfoo one 'two three'
+set -l qbar 'a b'
+set -q --long qfoo qbar
+echo $status
+#CHECK: qfoo='one'  'two three'
+#CHECK: qbar='a b'
+#CHECK: 0
+set -qL qfoo[2] qbar qnope
+echo $status
+#CHECK: qfoo[2]='two three'
+#CHECK: qbar='a b'
+#CHECK: 1
+set -qL qfoo[5]
+echo $status
+#CHECK: 1
+
+# --dedupe keeps the first occurrence of each element
+set -l dd a b a c b
+set --dedupe dd $dd
+echo $dd
+#CHECK: a b c
+set --dedupe --prepend dd c
+echo $dd
+#CHECK: c a b
+set --dedupe --append dd a d
+echo $dd
+#CHECK: c a b d
+set --dedupe dd[2] c
+echo $dd
+#CHECK: c b d
+set --dedupe -e dd
+#CHECKERR: set: Invalid combination of options
+#CHECKERR: {{.*}}set.fish (line {{\d+}}): 
+#CHECKERR: set --dedupe -e dd
+#CHECKERR: ^
+#CHECKERR: (Type 'help set' for related documentation)
+
+# Variable handlers fire when the variable is set or erased, but not when it is set to the value
+# it already has.
+function watch_bar --on-variable __fish_test_watched_bar
+    echo bar: $argv[2] (set -q __fish_test_watched_bar; and echo $__fish_test_watched_bar)
+end
+set -g __fish_test_watched_bar 1
+#CHECK: bar: SET 1
+set -g __fish_test_watched_bar 1
+set -g __fish_test_watched_bar 1 2
+#CHECK: bar: SET 1 2
+set -gx __fish_test_watched_bar 1 2
+#CHECK: bar: SET 1 2
+set -e __fish_test_watched_bar
+#CHECK: bar: ERASE
+set -U __fish_test_watched_bar u
+#CHECK: bar: SET u
+set -U __fish_test_watched_bar u
+# Changes from another fish session arrive when universal variables are synchronized, which
+# setting any universal variable does.
+$FISH -c 'set -U __fish_test_watched_bar other'
+set -U __fish_test_uvar_sync 1
+#CHECK: bar: SET other
+$FISH -c 'set -U __fish_test_watched_bar other'
+set -U __fish_test_uvar_sync 2
+$FISH -c 'set -eU __fish_test_watched_bar'
+set -U __fish_test_uvar_sync 3
+#CHECK: bar: ERASE
+functions -e watch_bar
+set -eU __fish_test_uvar_sync
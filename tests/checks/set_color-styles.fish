@@ -0,0 +1,12 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+#REQUIRES: infocmp vte-256color
+# vte-256color has blink, and the extended strikethrough (smxx) and overline (Smol) capabilities.
+
+TERM=vte-256color $fish -c 'set_color --blink red; echo blink' | string escape
+# CHECK: \e\[5m\e\[31mblink
+TERM=vte-256color $fish -c 'set_color --strikethrough; echo strike' | string escape
+# CHECK: \e\[9mstrike
+TERM=vte-256color $fish -c 'set_color --overline -b blue; echo over' | string escape
+# CHECK: \e\[53m\e\[44mover
+TERM=vte-256color $fish -c 'set_color --bold --blink --strikethrough --overline green; echo all' | string escape
+# CHECK: \e\[1m\e\[5m\e\[9m\e\[53m\e\[32mall
@@ -78,3 +78,62 @@ echo $list # CHECK: 2 1 3
 set -l list 1 2 3
 set list[2..] $list[-1..2]
 echo $list # CHECK: 1 3 2
+
+# Steps
+echo $test[1..-1..2]
+# CHECK: 1 3 5 7 9
+echo $test[-1..1..-3]
+# CHECK: 10 7 4 1
+echo (seq 5)[2..-1..2]
+# CHECK: 2 4
+# The step decides the direction.
+echo $test[1..3..-1]
+# CHECK:
+# A step larger than the list.
+echo $test[1..-1..20]
+# CHECK: 1
+# The start moves by whole steps when it is clamped.
+echo (seq 3)[-4..-1..2]
+# CHECK: 2
+echo (seq 3)[10..1..-2]
+# CHECK: 2
+
+set -l empty
+echo $empty[1..-1..2]
+# CHECK:
+echo $empty[-1..1..-1]
+# CHECK:
+
+set -l one a
+echo $one[-1..-3]
+# CHECK: a
+echo $one[-3..-1..1]
+# CHECK: a
+echo $one[-1..-3..-1]
+# CHECK: a
+echo $one[2..5..1]
+# CHECK:
+
+echo $test[1..2..0]
+#CHECKERR: {{.*}}: Invalid index value
+#CHECKERR: echo $test[1..2..0]
+#CHECKERR:                ^
+
+set -l list 1 2 3 4 5
+set list[1..-1..2] a b c
+echo $list
+# CHECK: a 2 b 4 c
+set list[-1..1..-2] x y z
+echo $list
+# CHECK: z 2 y 4 x
+set list[2..5..3] p q
+echo $list
+# CHECK: z p y 4 q
+set list[-9..1..1] oops
+# CHECKERR: set: Array index out of bounds
+set list[1..2..] oops
+# CHECKERR: set: Invalid index starting at '..]'
+# CHECKERR: {{.*}}slices.fish (line {{\d+}}):
+# CHECKERR: set list[1..2..] oops
+# CHECKERR: ^
+# CHECKERR: (Type 'help set' for related documentation)
@@ -57,6 +57,8 @@ status features
 #CHECK: stderr-nocaret	on	3.0	^ no longer redirects stderr
 #CHECK: qmark-noglob	off	3.0	? no longer globs
 #CHECK: regex-easyesc	off	3.1	string replace -r needs fewer \'s
+#CHECK: stream-for-cmdsub	off	3.3	for loops start before a command substitution finishes
+#CHECK: remove-percent-self	off	3.4	%self is no longer expanded
 status test-feature stderr-nocaret
 echo $status
 #CHECK: 0
@@ -105,3 +107,59 @@ end
 # CHECK: Failed write tests {{finished|skipped}}
 # CHECKERR: write: {{.*}}
 # CHECKERR: write: {{.*}}
+
+status stack-trace --json
+# CHECK: []
+
+function stack_inner
+    status stack-trace --json
+end
+function stack_outer
+    stack_inner a "b c"
+end
+stack_outer
+# CHECK: [
+# CHECK:   {"type": "function", "name": "stack_inner", "arguments": ["a", "b c"], "file": "{{.*}}status.fish", "line": {{\d+}}},
+# CHECK:   {"type": "function", "name": "stack_outer", "arguments": [], "file": "{{.*}}status.fish", "line": {{\d+}}}
+# CHECK: ]
+
+status current-function --json
+# CHECKERR: status: Invalid combination of options,
+# CHECKERR: --json can only be used with stack-trace
+
+# None of these are terminals in the test harness.
+echo | status is-tty
+echo $status
+# CHECK: 1
+status is-tty stdin </dev/null
+echo $status
+# CHECK: 1
+status is-tty stdout | cat
+echo $pipestatus[1]
+# CHECK: 1
+status is-tty stdout >/dev/null
+echo $status
+# CHECK: 1
+status is-tty stderr 2>&1 | cat
+echo $pipestatus[1]
+# CHECK: 1
+status is-tty 2 2>/dev/null
+echo $status
+# CHECK: 1
+begin
+    status is-tty 1
+end | cat
+echo $pipestatus[1]
+# CHECK: 1
+status is-tty 1 <&-
+echo $status
+# CHECK: 1
+
+status is-tty foo
+echo $status
+# CHECKERR: status: Invalid file descriptor 'foo'
+# CHECK: 2
+status is-tty stdin stdout
+echo $status
+# CHECKERR: status is-tty: Expected 1 args, got 2
+# CHECK: 2
@@ -159,6 +159,33 @@ string split -f1 ' ' 'a b' 'c d'
 string split --allow-empty --fields=2,9 "" abc
 # CHECK: b
 
+# Negative fields count from the end.
+string split -f-1,1 . a.b.c
+# CHECK: c
+# CHECK: a
+
+string split --fields=-2--1 . a.b.c
+# CHECK: b
+# CHECK: c
+
+string split --fields=-1-2 . a.b.c d.e
+# CHECK: c
+# CHECK: b
+# CHECK: e
+
+string split --fields=-4 . a.b.c; or echo "exit 1"
+# CHECK: exit 1
+
+string split --allow-empty --fields=2-5 . a.b.c
+# CHECK: b
+# CHECK: c
+
+string split --fields=0 . a.b.c
+# CHECKERR: string split: Invalid fields value '0'
+
+string split --fields=1- . a.b.c
+# CHECKERR: string split: Argument '1-' is not a valid integer
+
 seq 3 | string join ...
 # CHECK: 1...2...3
 
@@ -215,6 +242,17 @@ string escape --style=var 中 | string unescape --style=var
 # CHECK: aöb
 # CHECK: 中
 
+# These always use UTF-8, whatever the locale.
+begin
+    set -lx LC_ALL C
+    string escape --style=url aöb
+    string escape --style=var aöb
+    string unescape --style=url a%C3%B6b | string escape --style=url
+end
+# CHECK: a%C3%B6b
+# CHECK: a_C3_B6_b
+# CHECK: a%C3%B6b
+
 # test regex escaping
 string escape --style=regex ".ext"
 string escape --style=regex "bonjour, amigo"
@@ -462,6 +500,30 @@ string repeat -n3 ""
 or echo string repeat empty string failed
 # CHECK: string repeat empty string failed
 
+# Output is written as it is made, so a huge repeat stops as soon as the pipe is closed,
+# instead of first building a string of 40 gigabytes.
+string repeat -n 10000000000 x | head -c 3
+echo
+# CHECK: xxx
+string repeat -n 10000000000 x | head -c 3 >/dev/null
+echo $pipestatus
+# CHECK: 1 0
+string repeat -m 10000000000 abc | head -c 5
+echo
+# CHECK: abcab
+
+# And at the limit of what a command substitution may read.
+set -l fish_read_limit 1000
+set -l toomuch (string repeat -n 10000000000 abc)
+echo $status (count $toomuch)
+# CHECK: 122 0
+# CHECKERR: {{.*}}: Too much data emitted by command substitution so it was discarded
+# CHECKERR: set -l toomuch (string repeat -n 10000000000 abc)
+# CHECKERR: {{ *}}^
+set -e fish_read_limit
+string repeat -n 3000 abc | string length
+# CHECK: 9000
+
 # Test equivalent matches with/without the --entire, --regex, and --invert flags.
 string match -e x abc dxf xyz jkx x z
 or echo exit 1
@@ -596,6 +658,71 @@ and echo Split something
 # CHECK: b
 # CHECK: Split something
 
+# Filenames can contain newlines, but not NUL.
+set -l tmpdir (mktemp -d)
+touch $tmpdir/one\ntwo $tmpdir/three
+set -l files (find $tmpdir -type f -print0 | string split0)
+count $files
+# CHECK: 2
+string escape -- $files | string replace -- $tmpdir/ '' | sort
+# CHECK: one\ntwo
+# CHECK: three
+set -l files $(find $tmpdir -type f -print0 | string split0)
+count $files
+# CHECK: 2
+string escape -- $files | string replace -- $tmpdir/ '' | sort
+# CHECK: one\ntwo
+# CHECK: three
+rm -r $tmpdir
+
+# A command substitution that ends in a plain `string split0` splits on NUL itself.
+# That must give the same elements and statuses as actually running `string split0`,
+# which it does with any argument, like `--`.
+function check_split0
+    set -l fast (eval $argv | string split0) $pipestatus
+    set -l slow (eval $argv | string split0 --) $pipestatus
+    set fast (string escape -- $fast)
+    set slow (string escape -- $slow)
+    if test "$fast" = "$slow"
+        echo $fast
+    else
+        echo mismatch: $fast vs $slow
+    end
+end
+check_split0 "printf 'a\\nb\\0c\\0'"
+# CHECK: a\nb c 0 0
+check_split0 "printf 'a\\0\\0b'"
+# CHECK: a '' b 0 0
+check_split0 "printf 'abc\\n'"
+# CHECK: abc\n 0 1
+check_split0 true
+# CHECK: 0 1
+check_split0 "false | printf 'a\\0'"
+# CHECK: a 0 0
+check_split0 "string join0 a b\\nc; echo d"
+# CHECK: a b\nc d\n 0 0
+check_split0 "string collect x; printf 'y\\0z'"
+# CHECK: x\ny z 0 0
+functions -e check_split0
+
+# The options that work on each piece also apply to stdin.
+echo -ne 'a\x00\x00b\x00' | string split0 -n
+# CHECK: a
+# CHECK: b
+echo -ne 'abc\x00' | string split0 -q
+and echo split
+# CHECK: split
+echo -ne 'abc' | string split0 -q
+or echo not split
+# CHECK: not split
+echo -ne 'a\x00b\x00c' | string split0 -m1
+# CHECK: a
+# CHECK: b{{\x00}}c
+
+# Long lines are read in one go.
+string repeat -n 100000 abc | string split x | string length
+# CHECK: 300000
+
 # string join0
 set tmp beta alpha\ngamma
 count (string join \n $tmp)
@@ -690,6 +817,30 @@ echo $status
 echo az | string replace -r -- 'a(b.+)?z' 'a:$1z'
 # CHECK: a:z
 
+# Groups can be referred to by name
+string replace -r '(?<first>\w+) (?<second>\w+)' '${second} $first' 'left right'
+# CHECK: right left
+string replace -r --validate-refs '(?<first>\w+) (\w+)' '$2 ${first}_$$' 'left right'
+# CHECK: right left_$
+
+# Without --validate-refs, an unknown group is only noticed when the pattern matches.
+string replace -r '(a)' '$2' bbb
+# CHECK: bbb
+string replace -r --validate-refs '(a)' '$2' bbb
+echo $status
+# CHECKERR: string replace: Reference to unknown group '$2' in replacement
+# CHECK: 2
+string replace -r --validate-refs '(?<name>a)' 'x${nmae}y' abc
+# CHECKERR: string replace: Reference to unknown group '${nmae}' in replacement
+string replace -r --validate-refs '(a)' '${10:-default}' abc
+# CHECKERR: string replace: Reference to unknown group '${10' in replacement
+# Doubled dollars and $*MARK are not references.
+string replace -r --validate-refs '(*MARK:m)a' '$$2 $*MARK' abc
+# CHECK: $2 mbc
+string replace --validate-refs a b abc
+# CHECKERR: string replace: Invalid combination of options,
+# CHECKERR: --validate-refs can only be used with --regex
+
 # --quiet should quit early
 echo "Checking that --quiet quits early - if this is broken it hangs"
 # CHECK: Checking that --quiet quits early - if this is broken it hangs
@@ -718,3 +869,62 @@ string escape \x7F
 string pad -w 8 he \eh
 # CHECK: he
 # CHECK: {{\x1bh}}
+
+string hex encode hello
+# CHECK: 68656c6c6f
+string hex encode --upper hello "é"
+# CHECK: 68656C6C6F
+# CHECK: C3A9
+string hex decode 68656c6c6f 68656C6C6F
+# CHECK: hello
+# CHECK: hello
+printf '%s\n' hello '' | string hex encode | string hex decode
+# CHECK: hello
+# CHECK:
+# Bytes that aren't valid in the locale survive the round trip.
+string hex decode ff00fe | string hex encode
+# CHECK: ff00fe
+string hex decode abc
+echo $status
+# CHECKERR: string hex: Odd number of hex digits in 'abc'
+# CHECK: 2
+string hex decode zz 6869
+echo $status
+# CHECKERR: string hex: Invalid hex digit in 'zz'
+# CHECK: hi
+# CHECK: 2
+string hex decode --upper 00
+# CHECKERR: string hex: Invalid combination of options,
+# CHECKERR: --upper is only valid when encoding
+string hex frob foo
+# CHECKERR: string hex: Invalid mode 'frob', expected 'encode' or 'decode'
+string hex encode
+echo $status
+# CHECK: 1
+
+string distance kitten sitting
+# CHECK: 3
+string distance fish fish fsh shell ''
+# CHECK: 0
+# CHECK: 1
+# CHECK: 5
+# CHECK: 4
+string distance '' flaw lawn
+# CHECK: 4
+# CHECK: 4
+printf '%s\n' flaw lawn kitten | string distance -
+# CHECK: 2
+# CHECK: 6
+string distance --algorithm=jaro-winkler MARTHA MARHTA MARTHA xyz
+# CHECK: 0.038889
+# CHECK: 0
+# CHECK: 1
+string distance --algorithm=jaro-winkler DIXON DICKSONX
+# CHECK: 0.186667
+string distance --algorithm=hamming a b
+# CHECKERR: string distance: Invalid algorithm 'hamming'
+string distance
+# CHECKERR: string distance: Expected an argument
+string distance foo
+echo $status
+# CHECK: 1
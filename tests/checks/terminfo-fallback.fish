@@ -0,0 +1,42 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+# Terminals that the terminfo database does not know get built-in xterm capabilities.
+set -l empty (mktemp -d)
+
+TERMINFO=$empty TERM=fish-no-such-term $fish -c 'set_color red; echo red; set_color normal' | string escape
+# CHECK: \e\[31mred
+# CHECK: \e\[m
+
+# 256 colors if $TERM or $COLORTERM say so.
+TERMINFO=$empty TERM=fish-no-such-term-256color $fish -c 'set_color 5fafd7; echo blue' | string escape
+# CHECK: \e\[38\;5\;74mblue
+TERMINFO=$empty TERM=fish-no-such-term COLORTERM=24bit fish_term24bit=0 $fish -c 'set_color 5fafd7; echo blue' | string escape
+# CHECK: \e\[38\;5\;74mblue
+
+# The built-in capabilities have blink, but neither strikethrough nor overline, which are skipped.
+TERMINFO=$empty TERM=fish-no-such-term $fish -c 'set_color --blink --strikethrough --overline red; echo styled' | string escape
+# CHECK: \e\[5m\e\[31mstyled
+
+# Key names are available for bind -k.
+TERMINFO=$empty TERM=fish-no-such-term $fish -c 'bind -k home true; and echo bound'
+# CHECK: bound
+
+# No warnings, and a dumb terminal stays dumb.
+TERMINFO=$empty TERM=dumb $fish -c 'set_color red; echo dumb' | string escape
+# CHECK: dumb
+
+# Capabilities can be overridden.
+TERMINFO=$empty TERM=fish-no-such-term $fish -c '
+    set -g fish_term_capabilities "setaf=<%p1%d>" "bold@" bogus
+    set_color -o red; echo overridden
+    set -e fish_term_capabilities
+    set_color red; echo restored
+' | string escape
+# CHECKERR: warning: Ignoring invalid terminal capability 'bogus' in $fish_term_capabilities
+# CHECK: '<1>overridden'
+# CHECK: \e\[31mrestored
+
+# This also works on top of a real terminfo entry.
+TERM=xterm $fish -c 'set -g fish_term_capabilities "setaf=<%p1%d>"; set_color blue; echo' | string escape
+# CHECK: '<4>'
+
+rm -r $empty
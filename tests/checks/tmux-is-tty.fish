@@ -0,0 +1,44 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+#REQUIRES: command -v tmux
+
+# Isolated tmux.
+set -g tmpdir (mktemp -d)
+set -g tmux tmux -S $tmpdir/.tmux-socket -f /dev/null
+
+set -g sleep sleep .1
+set -q CI && set sleep sleep 1
+
+set fish (builtin realpath $fish)
+cd $tmpdir
+
+$tmux new-session -x 80 -y 10 -d $fish -C '
+    # This is similar to "tests/interactive.config".
+    function fish_greeting; end
+    function fish_prompt; printf "prompt $status_generation> "; end
+    # No autosuggestion from older history.
+    set fish_history ""
+'
+$sleep # Let fish draw a prompt.
+
+# All standard fds are the terminal here, unless redirected.
+$tmux send-keys 'status is-tty && status is-tty stdout && status is-tty 2; echo $status' Enter
+$sleep
+$tmux send-keys 'status is-tty stdout >/dev/null; echo $status' Enter
+$sleep
+$tmux send-keys 'status is-tty 1 | cat; echo $pipestatus[1]' Enter
+$sleep
+$tmux send-keys 'status is-tty 2 2>&1; echo $status' Enter
+$sleep
+$tmux capture-pane -p
+# CHECK: prompt 0> status is-tty && status is-tty stdout && status is-tty 2; echo $status
+# CHECK: 0
+# CHECK: prompt 1> status is-tty stdout >/dev/null; echo $status
+# CHECK: 1
+# CHECK: prompt 2> status is-tty 1 | cat; echo $pipestatus[1]
+# CHECK: 1
+# CHECK: prompt 3> status is-tty 2 2>&1; echo $status
+# CHECK: 0
+# CHECK: prompt 4>
+
+$tmux kill-server
+rm -r $tmpdir
@@ -0,0 +1,66 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+#REQUIRES: command -v tmux
+
+# Isolated tmux.
+set -g tmpdir (mktemp -d)
+set -g tmux tmux -S $tmpdir/.tmux-socket -f /dev/null
+
+set -g sleep sleep .1
+set -q CI && set sleep sleep 1
+
+set fish (builtin realpath $fish)
+cd $tmpdir
+set -gx XDG_DATA_HOME $tmpdir/data
+
+$tmux new-session -x 80 -y 10 -d $fish -C '
+    # This is similar to "tests/interactive.config".
+    function fish_greeting; end
+    function fish_prompt; printf "prompt $status_generation> "; end
+    # No autosuggestion from older history.
+    set fish_history journal_test
+    function journal_test; sh -c "exit 3"; end
+'
+$sleep # Let fish draw a prompt.
+
+$tmux send-keys 'true' Enter
+$sleep
+$tmux send-keys 'echo (false)' Enter
+$sleep
+$tmux send-keys 'journal_test' Enter
+$sleep
+$tmux send-keys ' echo ephemeral' Enter
+$sleep
+$tmux send-keys 'set fish_history ""' Enter
+$sleep
+$tmux send-keys 'echo no history' Enter
+$sleep
+$tmux kill-server
+
+# Jobs inside the command substitution and the function are not recorded.
+# Like in the history, neither are commands with a leading space, nor any with an empty
+# $fish_history.
+string replace -r '"ts":\d+,' '"ts":N,' <$XDG_DATA_HOME/fish/job_journal |
+    string replace -r '"duration_ms":\d+' '"duration_ms":N'
+# CHECK: {"ts":N,"cmd":"true","status":0,"duration_ms":N}
+# CHECK: {"ts":N,"cmd":"echo (false)","status":0,"duration_ms":N}
+# CHECK: {"ts":N,"cmd":"journal_test","status":3,"duration_ms":N}
+
+# Newest first, or oldest first with --reverse.
+$fish -c 'history jobs' | string replace -r '^[\d-]+ [\d:]+\t(\d+)\t\d+\.\d{3}s\t' '$1 '
+# CHECK: 3 journal_test
+# CHECK: 0 echo (false)
+# CHECK: 0 true
+$fish -c 'history jobs --reverse --max 2' | string replace -r '^.*\t' ''
+# CHECK: echo (false)
+# CHECK: journal_test
+$fish -c 'history jobs foo'
+# CHECKERR: history: jobs expected 0 args, got 1
+
+# The journal that was rotated away is read as well.
+mv $XDG_DATA_HOME/fish/job_journal $XDG_DATA_HOME/fish/job_journal.old
+echo '{"ts":1,"cmd":"newer","status":0,"duration_ms":2}' >$XDG_DATA_HOME/fish/job_journal
+$fish -c 'builtin history jobs -n 2' | string replace -r '^.*\t' ''
+# CHECK: newer
+# CHECK: journal_test
+
+rm -r $tmpdir
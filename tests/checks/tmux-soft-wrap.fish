@@ -0,0 +1,33 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+#REQUIRES: command -v tmux
+
+# Isolated tmux.
+set -g tmpdir (mktemp -d)
+set -g tmux tmux -S $tmpdir/.tmux-socket -f /dev/null
+
+set -g sleep sleep .1
+set -q CI && set sleep sleep 1
+
+set fish (builtin realpath $fish)
+cd $tmpdir
+
+$tmux new-session -x 20 -y 10 -d $fish -C '
+    # This is similar to "tests/interactive.config".
+    function fish_greeting; end
+    function fish_prompt; printf "prompt> "; end
+    # No autosuggestion from older history.
+    set fish_history ""
+'
+$sleep # Let fish draw a prompt.
+
+# A command line that is too long for the terminal continues at the width of the prompt.
+$tmux send-keys 'echo 0123456789abcdefghij'
+$sleep
+# Quote the lines, so the leading spaces are checked.
+$tmux capture-pane -p | head -n 3 | string escape
+# CHECK: 'prompt> echo 0123456'
+# CHECK: '        789abcdefghi'
+# CHECK: '        j'
+
+$tmux kill-server
+rm -r $tmpdir
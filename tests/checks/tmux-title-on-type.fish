@@ -0,0 +1,48 @@
+#RUN: %fish -C 'set -g fish %fish' %s
+#REQUIRES: command -v tmux
+
+# Isolated tmux.
+set -g tmpdir (mktemp -d)
+set -g tmux tmux -S $tmpdir/.tmux-socket -f /dev/null
+
+set -g sleep sleep .1
+set -q CI && set sleep sleep 1
+
+set fish (builtin realpath $fish)
+cd $tmpdir
+
+# fish_title logs its argument, so we can see when it ran for a command line.
+$tmux new-session -x 80 -y 10 -d $fish -C '
+    # This is similar to "tests/interactive.config".
+    function fish_greeting; end
+    function fish_prompt; printf "prompt $status_generation> "; end
+    # No autosuggestion from older history.
+    set fish_history ""
+    function fish_title
+        set -q argv[1] && echo $argv >>'$tmpdir'/titles
+        echo title
+    end
+'
+$sleep # Let fish draw a prompt.
+
+# Without the variable, typing does not run fish_title.
+$tmux send-keys 'echo before'
+$sleep
+$sleep
+$tmux send-keys C-u
+$sleep
+$tmux send-keys 'set -g fish_update_title_on_type 1' Enter
+$sleep
+$tmux send-keys 'echo after'
+$sleep
+$sleep
+$tmux send-keys C-u
+$sleep
+
+grep -c 'echo before' titles
+# CHECK: 0
+tail -n 1 titles
+# CHECK: echo after
+
+$tmux kill-server
+rm -r $tmpdir
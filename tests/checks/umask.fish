@@ -89,3 +89,21 @@ umask
 umask -S
 #CHECK: 0222
 #CHECK: u=rx,g=rx,o=rx
+
+# Setting the variable directly changes the umask of child processes.
+set umask 0027
+sh -c umask
+#CHECK: 0027
+set umask 22
+sh -c umask
+echo $umask
+#CHECK: 0022
+#CHECK: 0022
+
+# Invalid values are rejected and the umask is unchanged.
+set umask 0999
+#CHECKERR: set: Tried to modify the special variable 'umask' to an invalid value '0999'
+set umask 1 2
+#CHECKERR: set: Tried to modify the special variable 'umask' to an invalid value '1 2'
+sh -c umask
+#CHECK: 0022
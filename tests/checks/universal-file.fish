@@ -0,0 +1,36 @@
+#RUN: %fish -C "set fish %fish" %s
+
+set -l tmpdir (mktemp -d)
+# $fish may be relative, and we change directories below.
+set fish (builtin realpath $fish)
+
+# Universal variables are kept in the file given by $FISH_UNIVERSAL_FILE.
+FISH_UNIVERSAL_FILE=$tmpdir/vars $fish -c 'set -U relocated_var yes; status universal-file'
+# CHECK: {{.*}}/vars
+string match -q '*relocated_var:yes*' <$tmpdir/vars; and echo saved
+# CHECK: saved
+FISH_UNIVERSAL_FILE=$tmpdir/vars $fish -c 'echo $relocated_var'
+# CHECK: yes
+
+# A relative path is relative to the directory fish started in.
+cd $tmpdir
+FISH_UNIVERSAL_FILE=vars $fish -c 'cd /; status universal-file; echo $relocated_var'
+# CHECK: {{.*}}/vars
+# CHECK: yes
+cd -
+
+# Changing the variable later has no effect.
+FISH_UNIVERSAL_FILE=$tmpdir/vars $fish -c 'set -gx FISH_UNIVERSAL_FILE /nonexistent/vars; status universal-file; set -U late_var 1'
+# CHECK: {{.*}}/vars
+string match -q '*late_var:1*' <$tmpdir/vars; and echo still saved
+# CHECK: still saved
+
+# Without it, the file is in the configuration directory.
+FISH_UNIVERSAL_FILE= $fish -c 'status universal-file'
+# CHECK: {{.*}}/fish/fish_variables
+
+# Without universal variables, there is no file.
+$fish --no-config -c 'status universal-file; echo $status'
+# CHECK: 1
+
+rm -r $tmpdir
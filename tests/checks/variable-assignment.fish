@@ -31,6 +31,25 @@ a=a b={$a}2 echo $a $b
 # CHECK: a
 a=a builtin echo $a
 
+# Overrides are visible to functions and removed afterwards.
+function show_a_b
+    echo "a=$a b=$b"
+    set -qx a; and echo exported
+end
+a=1 b=2 show_a_b
+# CHECK: a=1 b=2
+# CHECK: exported
+show_a_b
+# CHECK: a= b=
+
+# Overrides are exported to external commands.
+a=1 b="two words" sh -c 'echo "$a|$b"'
+# CHECK: 1|two words
+set -l c 3
+a=$c{4,5} sh -c 'echo $a'
+# CHECK: 34 35
+functions --erase show_a_b
+
 # CHECK: 0
 a=failing-glob-* count $a
 
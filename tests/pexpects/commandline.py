@@ -2,7 +2,13 @@
 from pexpect_helper import SpawnedProc
 
 sp = SpawnedProc()
-send, sendline, sleep, expect_prompt = sp.send, sp.sendline, sp.sleep, sp.expect_prompt
+send, sendline, sleep, expect_prompt, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_str,
+)
 expect_prompt()
 
 sendline("bind '~' 'handle_tilde'")
@@ -30,3 +36,19 @@ expect_prompt()
 
 sendline("echo foo")
 expect_prompt("foo")
+
+# commandline --is-valid tells complete, incomplete and erroneous commandlines apart.
+sendline(
+    "function handle_tilde; echo; commandline --is-valid; echo '@VALID:'$status'@'; commandline -b ''; end"
+)
+expect_prompt()
+send("echo foo~")
+expect_str("@VALID:0@")
+send("if true~")
+expect_str("@VALID:2@")
+send("echo 'foo~")
+expect_str("@VALID:2@")
+send("echo foo )~")
+expect_str("@VALID:1@")
+sendline("")
+expect_prompt()
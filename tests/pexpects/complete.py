@@ -57,3 +57,20 @@ sendline("echo bar")
 expect_re("\nbar")
 sendline("echo fo\t")
 expect_re("foooo")
+
+# Pressing tab again on the same commandline reuses the expanded arguments,
+# until another command runs.
+sendline("bind \cg cancel")
+sendline(
+    "set -g cache_runs 0; complete -c cachetest -xa '(set -g cache_runs (math $cache_runs + 1); echo pkg-one; echo pkg-two)'"
+)
+send("cachetest pkg-\t")
+send("\x07")
+send("\t")
+send("\x07")
+sendline("\x15echo runs $cache_runs")
+expect_re("\nruns 1")
+send("cachetest pkg-\t")
+send("\x07")
+sendline("\x15echo runs $cache_runs")
+expect_re("\nruns 2")
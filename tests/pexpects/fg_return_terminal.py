@@ -0,0 +1,21 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+
+sp = SpawnedProc()
+sendline, expect_prompt, expect_str = sp.sendline, sp.expect_prompt, sp.expect_str
+
+expect_prompt()
+sendline("status job-control full")
+expect_prompt()
+
+# A job that hands the terminal back without stopping does not keep fish waiting.
+sendline("$fish_test_helper return_terminal_then_sleep")
+expect_str("returned the terminal without stopping, continuing it in the background")
+expect_prompt()
+
+sendline("jobs -c")
+expect_str("fish_test_helper")
+expect_prompt()
+
+sendline("wait; echo waited")
+expect_prompt("waited")
@@ -0,0 +1,33 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+
+sp = SpawnedProc(args=["--features", "stream-for-cmdsub"])
+send, sendline, sleep, expect_prompt, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_str,
+)
+
+expect_prompt()
+
+# The loop body runs while the command substitution is still going.
+sendline("for i in (sh -c 'echo first; sleep 10; echo second'); echo got-$i; end")
+expect_str("got-first")
+
+# Ctrl-C stops both the loop and the command.
+sleep(0.2)
+send("\x03")
+expect_prompt()
+sendline("jobs -q; echo jobs $status")
+expect_str("jobs 1")
+expect_prompt()
+
+sendline("for i in (yes); sleep 0.01; end")
+sleep(0.3)
+send("\x03")
+expect_prompt()
+sendline("echo still here")
+expect_str("still here")
+expect_prompt()
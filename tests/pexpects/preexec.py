@@ -0,0 +1,43 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+
+sp = SpawnedProc()
+sendline, expect_prompt, expect_str = sp.sendline, sp.expect_prompt, sp.expect_str
+
+# Test fish_preexec for interactive shells.
+expect_prompt()
+
+# The commandline is passed as the argument, and is still available via `commandline`.
+sendline(
+    "function test_fish_preexec --on-event fish_preexec; printf 'preexec:%s, commandline:%s\\n' $argv (commandline); end"
+)
+expect_prompt()
+
+sendline("echo hello")
+expect_str("preexec:echo hello, commandline:echo hello")
+expect_str("hello")
+expect_prompt()
+
+sendline("functions --erase test_fish_preexec")
+expect_prompt()
+
+# A handler can replace the command that gets run.
+sendline(
+    "function test_fish_preexec_replace --on-event fish_preexec; string match -q 'rewrite-me*' -- $argv; and commandline --replace 'echo rewritten'; end"
+)
+expect_prompt()
+sendline(
+    "function test_fish_postexec --on-event fish_postexec; printf 'postexec:%s\\n' $argv; end"
+)
+expect_prompt()
+
+sendline("rewrite-me please")
+expect_str("rewritten")
+expect_str("postexec:echo rewritten")
+expect_prompt()
+
+# Other commands are left alone.
+sendline("echo untouched")
+expect_str("untouched")
+expect_str("postexec:echo untouched")
+expect_prompt()
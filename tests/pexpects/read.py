@@ -132,3 +132,24 @@ expect_read_prompt()
 send("jkl\n")
 expect_str("ghi then jkl\r\n")
 expect_prompt()
+
+# read --validate asks again until the code accepts the input.
+sendline("read --validate 'string match -qr \"^[0-9]+\\$\" -- $argv' num; echo num is $num")
+expect_read_prompt()
+sendline("42")
+expect_prompt("num is 42")
+
+sendline("read --validate 'string match -qr \"^[0-9]+\\$\" -- $argv; or begin; echo not a number; false; end' num; echo num is $num")
+expect_read_prompt()
+sendline("abc")
+expect_str("not a number")
+expect_read_prompt()
+sendline("7")
+expect_prompt("num is 7")
+
+sendline("read --validate false --max-tries 2 num; echo status $status num $num")
+expect_read_prompt()
+sendline("one")
+expect_read_prompt()
+sendline("two")
+expect_prompt("status 1 num")
@@ -0,0 +1,48 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import os
+
+env = os.environ.copy()
+env["TERM"] = "xterm"
+
+sp = SpawnedProc(env=env)
+send, sendline, sleep, expect_prompt, expect_re, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.sleep,
+    sp.expect_prompt,
+    sp.expect_re,
+    sp.expect_str,
+)
+expect_prompt()
+
+# A normal repaint only clears the rest of the line (clr_eol is \e[K for xterm).
+sendline("bind \\cg repaint")
+expect_prompt()
+send("echo normal")
+send("\x07")
+expect_re("normal(\x1b\\[[0-9;]*m|\x1b\\(B)*\x1b\\[K")
+sendline("")
+expect_prompt("normal")
+
+# force-repaint doesn't trust what is on the screen, so it redraws the commandline and clears
+# everything below it (clr_eos is \e[J).
+sendline("bind \\cg force-repaint")
+expect_prompt()
+send("echo force")
+send("\x07")
+expect_re("force(\x1b\\[[0-9;]*m|\x1b\\(B)*\x1b\\[J")
+sendline("")
+expect_prompt("force")
+
+# read draws its right prompt, which needs a terminal that can move the cursor.
+sendline("read -R 'echo right-(math 1 + 1)' foo")
+expect_str("right-2")
+sendline("text")
+expect_prompt()
+sendline("set -g rp 'right str'")
+expect_prompt()
+sendline("read --right-prompt-str $rp foo")
+expect_str("right str")
+sendline("text")
+expect_prompt()
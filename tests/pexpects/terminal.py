@@ -44,6 +44,16 @@ expect_prompt("hellohello")
 sendline("stty -a | string match -q '*-ixon -ixoff*'; echo $status")
 expect_prompt("0")
 
+# A failed exec returns to the shell, with the terminal still set up like before.
+sendline("set -g broken (mktemp -d)/broken; printf '\\x7fELF\\x00\\x00' >$broken; chmod +x $broken")
+expect_prompt()
+sendline("exec $broken 2>/dev/null; echo exec status $status; rm -r (dirname $broken)")
+expect_prompt("exec status 126")
+sendline("echo hello\x13hello")
+expect_prompt("hellohello")
+sendline("stty -a | string match -q '*-ixon -ixoff*'; echo $status")
+expect_prompt("0")
+
 # Turn flow control on
 sendline("stty ixon ixoff")
 expect_prompt()